@@ -0,0 +1,385 @@
+// Build script, generate the linker memory regions from a board description, and the kernel
+// config overrides.
+// See documentation: `Documentation/kernel/board.md` and `Documentation/kernel/config.md`
+//
+// The board is selected with the LRNRTOS_BOARD environment variable, it can be a board name from
+// the `boards` directory or a path to a board file. If not set, use `qemu_virt`, or
+// `qemu_virt_test` when the kernel is built in test mode.
+//
+// The kernel config values from `src/config.rs` can be overridden by a config file given with the
+// LRNRTOS_CONFIG environment variable, and by LRNRTOS_CONFIG_<KEY> environment variables.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+// Board used when LRNRTOS_BOARD is not set.
+const DEFAULT_BOARD: &str = "qemu_virt";
+// Board used when LRNRTOS_BOARD is not set and the kernel is built in test mode.
+const DEFAULT_TEST_BOARD: &str = "qemu_virt_test";
+// Extension of the board files in the boards directory.
+const BOARD_EXTENSION: &str = "board";
+
+// Prefix of the environment variables overriding a single config value.
+const CONFIG_ENV_PREFIX: &str = "LRNRTOS_CONFIG_";
+
+/// Type of a config value, used to check the value and generate the Rust code.
+#[derive(Copy, Clone)]
+enum ConfigType {
+    Usize,
+    U32,
+    U64,
+    // A usize or `None`
+    OptionUsize,
+    LogLevel,
+    WatchdogAction,
+}
+
+// All the config values that can be overridden, must match the statics in `src/config.rs`.
+const CONFIG_KEYS: &[(&str, ConfigType)] = &[
+    ("TICK_DURATION", ConfigType::U64),
+    ("TICK_SAFETY_DURATION", ConfigType::U64),
+    ("LOG_LEVEL", ConfigType::LogLevel),
+    ("LOG_BUFFER_SIZE", ConfigType::Usize),
+    ("LOG_LINE_MAX_SIZE", ConfigType::Usize),
+    ("LOG_DRAIN_TASK_PRIORITY", ConfigType::Usize),
+    ("KPRINT_ADDRESS", ConfigType::Usize),
+    ("TASK_MAX_PRIORITY", ConfigType::Usize),
+    ("TASK_MEMORY_QUOTA", ConfigType::OptionUsize),
+    ("SHARED_BUFFER_MAX_SIZE", ConfigType::Usize),
+    ("DMA_BUFFER_MAX_SIZE", ConfigType::Usize),
+    ("DMA_MIN_ALIGN", ConfigType::Usize),
+    ("CPU_INTC_MAX_SIZE", ConfigType::Usize),
+    ("TIMER_MAX_SIZE", ConfigType::Usize),
+    ("SERIAL_MAX_SIZE", ConfigType::Usize),
+    ("IRQ_MAX_SIZE", ConfigType::Usize),
+    ("IRQ_BOTTOM_HALF_TASK_PRIORITY", ConfigType::Usize),
+    ("BLOCK_MAX_SIZE", ConfigType::Usize),
+    ("RTC_MAX_SIZE", ConfigType::Usize),
+    ("GPIO_MAX_SIZE", ConfigType::Usize),
+    ("SPI_MAX_SIZE", ConfigType::Usize),
+    ("CPUFREQ_NOTIFIER_MAX_SIZE", ConfigType::Usize),
+    ("DEVICE_REGISTRY_MAX_SIZE", ConfigType::Usize),
+    ("WATCHDOG_MAX_SIZE", ConfigType::Usize),
+    ("WATCHDOG_DEFAULT_ACTION", ConfigType::WatchdogAction),
+    ("WATCHDOG_MAX_RESTARTS", ConfigType::Usize),
+    ("VIRTIO_CONSOLE_MAX_SIZE", ConfigType::Usize),
+    ("VIRTIO_QUEUE_MAX_SIZE", ConfigType::Usize),
+    ("SERIAL_BAUD_RATE", ConfigType::U32),
+    ("SERIAL_RX_BUFFER_SIZE", ConfigType::Usize),
+    ("CONSOLE_TASK_ROUTE_MAX_SIZE", ConfigType::Usize),
+    ("RAMFB_WIDTH", ConfigType::Usize),
+    ("RAMFB_HEIGHT", ConfigType::Usize),
+    ("FBCON_STATUS_LINES", ConfigType::Usize),
+    ("CMDLINE_MAX_SIZE", ConfigType::Usize),
+    ("FDT_MAX_STACK", ConfigType::Usize),
+    ("FDT_MAX_PROPS", ConfigType::Usize),
+    ("TASK_LIST_MAX_SIZE", ConfigType::Usize),
+    ("RUN_QUEUE_MAX_SIZE", ConfigType::Usize),
+    ("BLOCK_QUEUE_MAX_SIZE", ConfigType::Usize),
+    ("CPU_CORE_NUMBER", ConfigType::Usize),
+    ("KERNEL_STACK_SIZE", ConfigType::Usize),
+    ("TRAP_STACK_SIZE", ConfigType::Usize),
+    ("SCHEDULER_STACK_SIZE", ConfigType::Usize),
+];
+
+/// Memory layout of a board, all values are kept as written in the board file to be written as is
+/// in the generated linker script, and parsed to be checked.
+struct Board {
+    rom_origin: String,
+    rom_length: String,
+    ram_origin: String,
+    ram_length: String,
+    boot_stack_size: String,
+}
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap_or_default());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap_or_default());
+
+    println!("cargo:rerun-if-env-changed=LRNRTOS_BOARD");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=linkers/linker.ld");
+
+    let board_path = board_path(&manifest_dir);
+    println!("cargo:rerun-if-changed={}", board_path.display());
+    let content = match fs::read_to_string(&board_path) {
+        Ok(c) => c,
+        Err(e) => panic!(
+            "Failed to read board description: {}: {}",
+            board_path.display(),
+            e
+        ),
+    };
+    let board = parse_board(&content, &board_path);
+    check_board(&board, &board_path);
+
+    // Write the memory regions in OUT_DIR, the linker script include it with `INCLUDE memory.x`.
+    let memory_x = format!(
+        "/* Generated by build.rs from {}, do not edit. */\n\
+         MEMORY {{\n  \
+         RAM (rwx) : ORIGIN = {}, LENGTH = {}\n  \
+         ROM (rx) : ORIGIN = {}, LENGTH = {}\n\
+         }}\n\
+         \n\
+         BOOT_STACK_SIZE = {};\n",
+        board_path.display(),
+        board.ram_origin,
+        board.ram_length,
+        board.rom_origin,
+        board.rom_length,
+        board.boot_stack_size,
+    );
+    if let Err(e) = fs::write(out_dir.join("memory.x"), memory_x) {
+        panic!("Failed to write memory.x in OUT_DIR: {}", e);
+    }
+    // Allow the linker to find memory.x
+    println!("cargo:rustc-link-search=native={}", out_dir.display());
+
+    // Write the config overrides in OUT_DIR, `src/config.rs` include it.
+    let kconfig = generate_config(&manifest_dir);
+    if let Err(e) = fs::write(out_dir.join("kconfig.rs"), kconfig) {
+        panic!("Failed to write kconfig.rs in OUT_DIR: {}", e);
+    }
+}
+
+/// Generate the config overrides, one `Option` const per config key, `None` when the value is not
+/// overridden. The config file is read first, then the environment variables.
+fn generate_config(manifest_dir: &Path) -> String {
+    let mut values: Vec<Option<String>> = vec![None; CONFIG_KEYS.len()];
+    let index_of = |key: &str| CONFIG_KEYS.iter().position(|(k, _)| *k == key);
+
+    println!("cargo:rerun-if-env-changed=LRNRTOS_CONFIG");
+    if let Ok(config) = env::var("LRNRTOS_CONFIG")
+        && !config.is_empty()
+    {
+        let mut path = PathBuf::from(config);
+        if !path.is_absolute() {
+            path = manifest_dir.join(path);
+        }
+        println!("cargo:rerun-if-changed={}", path.display());
+        let content = match fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) => panic!("Failed to read kernel config: {}: {}", path.display(), e),
+        };
+        for (nb, key, value) in parse_key_values(&content, &path) {
+            match index_of(&key) {
+                Some(i) => values[i] = Some(value),
+                None => panic!("{}:{}: unknown config key: `{}`", path.display(), nb, key),
+            }
+        }
+    }
+    for (i, (key, _)) in CONFIG_KEYS.iter().enumerate() {
+        let var = format!("{}{}", CONFIG_ENV_PREFIX, key);
+        println!("cargo:rerun-if-env-changed={}", var);
+        if let Ok(value) = env::var(&var) {
+            values[i] = Some(value.trim().to_string());
+        }
+    }
+
+    let mut out = String::from("// Generated by build.rs, do not edit.\n");
+    for ((key, ty), value) in CONFIG_KEYS.iter().zip(values) {
+        let ty_name = match ty {
+            ConfigType::Usize => "usize",
+            ConfigType::U32 => "u32",
+            ConfigType::U64 => "u64",
+            ConfigType::OptionUsize => "Option<usize>",
+            ConfigType::LogLevel => "crate::logs::LogLevel",
+            ConfigType::WatchdogAction => "crate::watchdog::WatchdogAction",
+        };
+        let code = match value {
+            Some(v) => format!("Some({})", config_value(key, *ty, &v)),
+            None => String::from("None"),
+        };
+        out.push_str(&format!(
+            "pub const {}: Option<{}> = {};\n",
+            key, ty_name, code
+        ));
+    }
+    out
+}
+
+/// Check a config value and return it as Rust code.
+fn config_value(key: &str, ty: ConfigType, value: &str) -> String {
+    // Used in error messages.
+    let origin = format!("config key {}", key);
+    let origin = Path::new(&origin);
+    match ty {
+        ConfigType::Usize | ConfigType::U32 => {
+            let v = parse_size(value, origin);
+            if v > u32::MAX as u64 {
+                panic!(
+                    "{}: value must fit in 32 bits: `{}`",
+                    origin.display(),
+                    value
+                );
+            }
+            v.to_string()
+        }
+        ConfigType::U64 => parse_size(value, origin).to_string(),
+        ConfigType::OptionUsize => match value {
+            "None" | "none" => String::from("None"),
+            _ => format!("Some({})", config_value(key, ConfigType::Usize, value)),
+        },
+        ConfigType::LogLevel => match value {
+            "Debug" | "Info" | "Warn" | "Error" => format!("crate::logs::LogLevel::{}", value),
+            _ => panic!(
+                "{}: expected one of `Debug`, `Info`, `Warn`, `Error`, got: `{}`",
+                origin.display(),
+                value
+            ),
+        },
+        ConfigType::WatchdogAction => match value {
+            "Panic" | "RestartTask" | "Reset" => {
+                format!("crate::watchdog::WatchdogAction::{}", value)
+            }
+            _ => panic!(
+                "{}: expected one of `Panic`, `RestartTask`, `Reset`, got: `{}`",
+                origin.display(),
+                value
+            ),
+        },
+    }
+}
+
+/// Return the path of the board file to use.
+fn board_path(manifest_dir: &Path) -> PathBuf {
+    let board = match env::var("LRNRTOS_BOARD") {
+        Ok(b) if !b.is_empty() => b,
+        _ => {
+            if env::var_os("CARGO_FEATURE_TEST").is_some() {
+                String::from(DEFAULT_TEST_BOARD)
+            } else {
+                String::from(DEFAULT_BOARD)
+            }
+        }
+    };
+    // A board name, search it in the boards directory.
+    if !board.contains('/') && !board.contains('.') {
+        return manifest_dir
+            .join("boards")
+            .join(format!("{}.{}", board, BOARD_EXTENSION));
+    }
+    let path = PathBuf::from(board);
+    if path.is_absolute() {
+        path
+    } else {
+        manifest_dir.join(path)
+    }
+}
+
+/// Parse a `KEY = VALUE` file, used by board and config files. Empty lines and lines starting
+/// with `#` are ignored. Return the line number, key and value of each line.
+fn parse_key_values(content: &str, path: &Path) -> Vec<(usize, String, String)> {
+    let mut entries = Vec::new();
+    for (nb, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((k, v)) => entries.push((nb + 1, k.trim().to_string(), v.trim().to_string())),
+            None => panic!(
+                "{}:{}: expected `KEY = VALUE`, got: `{}`",
+                path.display(),
+                nb + 1,
+                line
+            ),
+        }
+    }
+    entries
+}
+
+/// Parse a board file.
+fn parse_board(content: &str, path: &Path) -> Board {
+    let mut rom_origin: Option<String> = None;
+    let mut rom_length: Option<String> = None;
+    let mut ram_origin: Option<String> = None;
+    let mut ram_length: Option<String> = None;
+    let mut boot_stack_size: Option<String> = None;
+    for (nb, key, value) in parse_key_values(content, path) {
+        let field = match key.as_str() {
+            "ROM_ORIGIN" => &mut rom_origin,
+            "ROM_LENGTH" => &mut rom_length,
+            "RAM_ORIGIN" => &mut ram_origin,
+            "RAM_LENGTH" => &mut ram_length,
+            "BOOT_STACK_SIZE" => &mut boot_stack_size,
+            _ => panic!("{}:{}: unknown key: `{}`", path.display(), nb, key),
+        };
+        *field = Some(value);
+    }
+    let required = |field: Option<String>, key: &str| -> String {
+        match field {
+            Some(v) => v,
+            None => panic!("{}: missing key: `{}`", path.display(), key),
+        }
+    };
+    Board {
+        rom_origin: required(rom_origin, "ROM_ORIGIN"),
+        rom_length: required(rom_length, "ROM_LENGTH"),
+        ram_origin: required(ram_origin, "RAM_ORIGIN"),
+        ram_length: required(ram_length, "RAM_LENGTH"),
+        boot_stack_size: required(boot_stack_size, "BOOT_STACK_SIZE"),
+    }
+}
+
+/// Parse a linker script number: decimal or hexadecimal, with an optional K or M suffix.
+fn parse_size(value: &str, path: &Path) -> u64 {
+    let (number, multiplier) = if let Some(n) = value.strip_suffix(['K', 'k']) {
+        (n, 1024)
+    } else if let Some(n) = value.strip_suffix(['M', 'm']) {
+        (n, 1024 * 1024)
+    } else {
+        (value, 1)
+    };
+    let parsed = if let Some(hex) = number
+        .strip_prefix("0x")
+        .or_else(|| number.strip_prefix("0X"))
+    {
+        u64::from_str_radix(&hex.replace('_', ""), 16)
+    } else {
+        number.replace('_', "").parse::<u64>()
+    };
+    match parsed {
+        Ok(v) => v * multiplier,
+        Err(_) => panic!("{}: invalid number: `{}`", path.display(), value),
+    }
+}
+
+/// Check the board memory layout before generating the linker script. Better to fail here than
+/// having a kernel linked at a wrong address.
+fn check_board(board: &Board, path: &Path) {
+    let rom_origin = parse_size(&board.rom_origin, path);
+    let rom_length = parse_size(&board.rom_length, path);
+    let ram_origin = parse_size(&board.ram_origin, path);
+    let ram_length = parse_size(&board.ram_length, path);
+    let boot_stack_size = parse_size(&board.boot_stack_size, path);
+    // The kernel is 32 bits, all regions must be addressable.
+    let addr_max: u64 = 1 << 32;
+    if rom_length == 0 || ram_length == 0 {
+        panic!("{}: ROM and RAM length must not be 0", path.display());
+    }
+    if rom_origin + rom_length > addr_max || ram_origin + ram_length > addr_max {
+        panic!(
+            "{}: memory regions must be in the 32 bits address space",
+            path.display()
+        );
+    }
+    if rom_origin < ram_origin + ram_length && ram_origin < rom_origin + rom_length {
+        panic!("{}: ROM and RAM regions overlap", path.display());
+    }
+    if !ram_origin.is_multiple_of(16) || !ram_length.is_multiple_of(16) {
+        panic!(
+            "{}: RAM origin and length must be aligned on 16 bytes",
+            path.display()
+        );
+    }
+    if boot_stack_size == 0 || !boot_stack_size.is_multiple_of(16) || boot_stack_size >= ram_length
+    {
+        panic!(
+            "{}: BOOT_STACK_SIZE must be a non zero multiple of 16 smaller than RAM_LENGTH",
+            path.display()
+        );
+    }
+}
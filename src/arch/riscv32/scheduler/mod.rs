@@ -1,4 +1,7 @@
-use crate::primitives::stack::AlignedStack16;
+use crate::{
+    config::SCHEDULER_STACK_SIZE,
+    primitives::stack::{AlignedStack16, StackUsage},
+};
 
 #[repr(C)]
 pub struct SchedulerCtx {
@@ -10,7 +13,7 @@ pub struct SchedulerCtx {
     sp: *mut u8, // Offset 132
 }
 
-pub static mut SCHEDULER_STACK: AlignedStack16<4098> = AlignedStack16::new();
+pub static mut SCHEDULER_STACK: AlignedStack16<{ SCHEDULER_STACK_SIZE }> = AlignedStack16::new();
 pub static mut SCHEDULER_CTX: SchedulerCtx = unsafe { core::mem::zeroed() };
 
 impl SchedulerCtx {
@@ -19,15 +22,37 @@ impl SchedulerCtx {
             gpr: [0u32; 32],
             ra: func as usize as u32,
             #[allow(static_mut_refs)]
-            sp: unsafe { SCHEDULER_STACK.buf.as_mut_ptr().wrapping_add(4098) },
+            sp: unsafe { SCHEDULER_STACK.top() },
         }
     }
 }
 
+/// Initialize the scheduler context, paint the scheduler stack to detect overflow and measure its
+/// usage.
 pub fn init_sched_ctx(sched_fn: fn()) {
+    #[allow(static_mut_refs)]
+    unsafe {
+        SCHEDULER_STACK.paint()
+    };
     unsafe { SCHEDULER_CTX = SchedulerCtx::init(sched_fn) }
 }
 
+/// Return the scheduler stack usage.
+pub fn sched_stack_usage() -> StackUsage {
+    #[allow(static_mut_refs)]
+    unsafe {
+        SCHEDULER_STACK.usage()
+    }
+}
+
+/// Panic if the scheduler stack canary has been overwritten.
+pub fn sched_stack_check() {
+    #[allow(static_mut_refs)]
+    if !unsafe { SCHEDULER_STACK.canary_intact() } {
+        panic!("Scheduler stack overflow detected, try increasing SCHEDULER_STACK_SIZE");
+    }
+}
+
 unsafe extern "C" {
     // Switch to the scheduler context
     pub fn sched_ctx_restore(context: *mut SchedulerCtx) -> !;
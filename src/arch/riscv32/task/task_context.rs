@@ -43,6 +43,15 @@ impl TaskContext {
         }
     }
 
+    /// Reset the context to start again from func, with an empty stack in the same memory region.
+    pub fn reset(&mut self, func: fn() -> !) {
+        let size = [
+            self.address_space[0] as usize,
+            self.address_space[1] as usize,
+        ];
+        *self = TaskContext::init(size, func);
+    }
+
     /// Trigger a context switch for a task
     pub fn context_switch(&self) {
         // Save the ptr to self struct, use saved registers to preserved it from across
@@ -19,16 +19,9 @@ Tests files:
 - 'src/tests/arch/riscv32/traps/handler.rs'
 */
 
-use crate::{
-    config::TICK_DURATION,
-    ktime::{
-        set_ktime_ms,
-        tick::{get_tick, increment_tick},
-    },
-    task::primitives::task_awake_blocked,
-};
+use crate::irq::irq_handle;
 
-use super::trap_frame::TrapFrame;
+use super::trap_frame::{TrapFrame, trap_stack_check};
 
 /// Trap routines
 /// Enter this function from trap_entry caller
@@ -65,6 +58,8 @@ pub unsafe extern "C" fn trap_handler(
             "Reach unreachable point, last mcause bit has an incorrect value that the kernel cannot handle"
         ),
     }
+    // Check the trap stack after handling the trap, a nested handler may have overflowed it.
+    trap_stack_check();
     return_pc
 }
 
@@ -81,20 +76,9 @@ fn exception_handler(mcause: usize, hart: usize, mtval: usize) {
     }
 }
 
-/// Handle all interrupts, machine, or software interrupt.
+/// Handle all interrupts, dispatch them to the handlers registered in the irq sub-system.
 fn interrupt_handler(mcause: usize, hart: usize) {
-    match mcause {
-        7 => timer_interrupt(hart),
-        _ => panic!("Unhandled async trap CPU#{} -> {}\n", hart, mcause),
-    }
-}
-
-/// Handle timer interrupt, increment global tick and re set the timer for next timer interrupt.
-fn timer_interrupt(hart: usize) {
-    if hart == 0 {
-        increment_tick();
+    if !irq_handle(mcause as u32) {
+        panic!("Unhandled async trap CPU#{} -> {}\n", hart, mcause);
     }
-    let tick = get_tick();
-    task_awake_blocked(tick);
-    set_ktime_ms(TICK_DURATION);
 }
@@ -77,6 +77,25 @@ pub fn disable_mie_msie() {
     unsafe { asm!("csrrc zero, mie, {}", in(reg) MSIE) };
 }
 
+pub fn enable_mie_meie() {
+    // Set the 11 bit to 1
+    const MEIE: u32 = 1 << 11;
+    unsafe { asm!("csrrs zero, mie, {}", in(reg) MEIE) };
+}
+
+pub fn read_mie_meie() -> u32 {
+    let value: u32;
+    let mask = 1 << 11;
+    unsafe { asm!("csrr {}, mie", out(reg) value) };
+    value & mask
+}
+
+pub fn disable_mie_meie() {
+    // Clear the 11 bit to 0
+    const MEIE: u32 = 1 << 11;
+    unsafe { asm!("csrrc zero, mie, {}", in(reg) MEIE) };
+}
+
 // Machine Status CSR
 
 pub fn enable_mstatus_mie() {
@@ -98,6 +117,22 @@ pub fn disable_mstatus_mie() {
     unsafe { asm!("csrrc zero, mstatus, {}", in(reg) MIE) };
 }
 
+/// Disable interrupts and return the previous mstatus.MIE value, to restore it with
+/// restore_mstatus_mie. Used for short critical sections shared with interrupt handlers.
+pub fn save_and_disable_mstatus_mie() -> u32 {
+    let value: u32;
+    const MIE: u32 = 1 << 3;
+    unsafe { asm!("csrrc {}, mstatus, {}", out(reg) value, in(reg) MIE) };
+    value & MIE
+}
+
+/// Restore the mstatus.MIE value returned by save_and_disable_mstatus_mie.
+pub fn restore_mstatus_mie(mie: u32) {
+    if mie != 0 {
+        enable_mstatus_mie();
+    }
+}
+
 pub fn read_mstatus() -> u32 {
     let value: u32;
     unsafe { asm!("csrr {}, mstatus", out(reg) value) };
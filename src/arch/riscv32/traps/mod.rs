@@ -17,8 +17,9 @@ Tests files:
 */
 
 use interrupt::{
-    disable_mie_msie, disable_mie_mtie, disable_mstatus_mie, enable_mie_msie, enable_mie_mtie,
-    enable_mstatus_mie, mscratch_set_trap_frame, mtvec_set_trap_entry, mtvec_switch_to_direct_mode,
+    disable_mie_meie, disable_mie_msie, disable_mie_mtie, disable_mstatus_mie, enable_mie_meie,
+    enable_mie_msie, enable_mie_mtie, enable_mstatus_mie, mscratch_set_trap_frame,
+    mtvec_set_trap_entry, mtvec_switch_to_direct_mode,
 };
 
 pub mod handler;
@@ -38,6 +39,8 @@ pub fn enable_interrupts() {
     enable_mie_mtie();
     // Enable software interrupt
     enable_mie_msie();
+    // Enable external interrupt, raised by the external interrupt-controller
+    enable_mie_meie();
     // Enable interrupt handling in exception handler
     enable_mstatus_mie();
 }
@@ -46,5 +49,6 @@ pub fn enable_interrupts() {
 pub fn disable_interrupts() {
     disable_mie_mtie();
     disable_mie_msie();
+    disable_mie_meie();
     disable_mstatus_mie();
 }
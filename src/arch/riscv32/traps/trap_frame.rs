@@ -5,6 +5,7 @@ Test coverage: All tested
 
 Tested:
 - Structure initialization with all field tested.
+- Trap stack painted at init.
 
 Not tested:
 - ...
@@ -18,7 +19,10 @@ Tests files:
 
 use core::{mem, ptr::null_mut};
 
-use crate::primitives::stack::AlignedStack16;
+use crate::{
+    config::TRAP_STACK_SIZE,
+    primitives::stack::{AlignedStack16, StackUsage},
+};
 
 #[repr(C)]
 // Trap frame structure, used to store all global registers, give a stack for the trap handling
@@ -47,16 +51,35 @@ impl TrapFrame {
 }
 
 // Static buffer used as a stack for trap handling
-pub static mut TRAP_STACK_BUFF: AlignedStack16<1024> = AlignedStack16::new();
+pub static mut TRAP_STACK_BUFF: AlignedStack16<{ TRAP_STACK_SIZE }> = AlignedStack16::new();
 
 // Init TrapFrame with 0 in mem
 pub static mut KERNEL_TRAP_FRAME: TrapFrame = unsafe { mem::zeroed() };
 
 /// Initialize trap frame with TRAP_STACK_BUFF static as TrapFrame.trap_stack
+/// Paint the trap stack to detect overflow and measure its usage.
 pub fn init_trap_frame() {
     // Static mut safe because it's only used in kernel boot
     #[allow(static_mut_refs)]
     unsafe {
-        KERNEL_TRAP_FRAME.trap_stack = TRAP_STACK_BUFF.buf.as_mut_ptr().wrapping_add(1024);
+        TRAP_STACK_BUFF.paint();
+        KERNEL_TRAP_FRAME.trap_stack = TRAP_STACK_BUFF.top();
+    }
+}
+
+/// Return the trap stack usage.
+pub fn trap_stack_usage() -> StackUsage {
+    #[allow(static_mut_refs)]
+    unsafe {
+        TRAP_STACK_BUFF.usage()
+    }
+}
+
+/// Panic if the trap stack canary has been overwritten, the kernel state can't be trusted after a
+/// stack overflow.
+pub fn trap_stack_check() {
+    #[allow(static_mut_refs)]
+    if !unsafe { TRAP_STACK_BUFF.canary_intact() } {
+        panic!("Trap stack overflow detected, try increasing TRAP_STACK_SIZE");
     }
 }
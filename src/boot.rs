@@ -1,5 +1,8 @@
 use crate::{
-    arch::traps::{enable_interrupts, trap_frame::init_trap_frame},
+    arch::{
+        scheduler::init_sched_ctx,
+        traps::{enable_interrupts, trap_frame::init_trap_frame},
+    },
     config::TICK_SAFETY_DURATION,
     drivers::{cpufreq::CpuFreq, init_subsystems},
     info::KERNEL_VERSION,
@@ -9,6 +12,7 @@ use crate::{
     logs::LogLevel,
     mem::{mem_update_kernel_sp, memory_init},
     platform::platform_init,
+    scheduler::scheduler,
 };
 
 #[unsafe(no_mangle)]
@@ -26,6 +30,11 @@ pub fn kernel_early_boot(core: usize, dtb_addr: usize) -> ! {
     log!(LogLevel::Debug, "Initialing trap frame...");
     init_trap_frame();
     log!(LogLevel::Debug, "Successfully initialized trap frame.");
+    init_sched_ctx(scheduler);
+    log!(
+        LogLevel::Debug,
+        "Successfully initialized scheduler context."
+    );
     set_ktime_seconds(TICK_SAFETY_DURATION);
     enable_interrupts();
     log!(
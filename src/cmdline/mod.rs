@@ -0,0 +1,231 @@
+// See documentation in `Documentation/kernel/cmdline.md`
+/*
+File info: Kernel command line, boot options given by the FDT or the host without rebuilding the
+kernel.
+
+Test coverage: Parsing and applying the options.
+
+Tested:
+- Default parameters from the config file.
+- Set the log level, the tick duration, the tracing and the test filter.
+- Unknown options and invalid values ignored.
+- stdout-path read from the FDT /chosen node.
+
+Not tested:
+- Reading the command line from the bootargs and the fw_cfg file.
+
+Reasons:
+- The test machine is not started with bootargs or a command line file.
+
+Tests files:
+- 'src/tests/cmdline/mod.rs'
+*/
+
+use crate::{
+    config::{CMDLINE_MAX_SIZE, LOG_LEVEL, TICK_DURATION},
+    drivers::firmware::{FirmwareError, firmware_read_file},
+    kprint_fmt, log,
+    logs::LogLevel,
+    platform::fdt::helpers::{fdt_get_node_by_path, fdt_get_node_prop, fdt_get_prop_str},
+};
+
+// fw_cfg file of the command line, given to QEMU with
+// `-fw_cfg name=opt/lrnrtos/cmdline,string=...`
+pub const CMDLINE_FW_CFG_FILE: &str = "opt/lrnrtos/cmdline";
+
+/// All errors that can happen when applying a command line option.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CmdlineError {
+    // There's no parameter with this name.
+    UnknownParam,
+    // The value is missing or not valid for the parameter.
+    InvalidValue,
+}
+
+/// Kernel parameters, the config file values overridden by the command line.
+/// log_level: logs below this level are not printed, `loglevel=debug|info|warn|error`.
+/// tick_duration: duration of a tick in ms, `tick=<ms>`.
+/// trace: print the trace! events, `trace` or `trace=on|off`.
+/// test_filter: in test mode, only the test suites with a name containing the filter are run,
+/// the case is ignored, `test=<filter>`.
+/// stdout_path: path or alias of the default console node, from the FDT /chosen stdout-path.
+#[derive(Copy, Clone, PartialEq)]
+pub struct KernelParams<'a> {
+    pub log_level: LogLevel,
+    pub tick_duration: u64,
+    pub trace: bool,
+    pub test_filter: Option<&'a str>,
+    pub stdout_path: Option<&'a str>,
+}
+
+impl<'a> KernelParams<'a> {
+    pub const fn init() -> Self {
+        KernelParams {
+            log_level: LOG_LEVEL,
+            tick_duration: TICK_DURATION,
+            trace: false,
+            test_filter: None,
+            stdout_path: None,
+        }
+    }
+
+    /// Set the parameter from its value, the parameter is unchanged on error.
+    pub fn set(&mut self, name: &str, value: &'a str) -> Result<(), CmdlineError> {
+        match name {
+            "loglevel" => {
+                self.log_level = LogLevel::from_name(value).ok_or(CmdlineError::InvalidValue)?
+            }
+            "tick" => {
+                self.tick_duration = match value.parse::<u64>() {
+                    Ok(ms) if ms > 0 => ms,
+                    _ => return Err(CmdlineError::InvalidValue),
+                }
+            }
+            // A flag without value is enabled.
+            "trace" => {
+                self.trace = match value {
+                    "" | "on" | "1" => true,
+                    "off" | "0" => false,
+                    _ => return Err(CmdlineError::InvalidValue),
+                }
+            }
+            "test" => {
+                if value.is_empty() {
+                    return Err(CmdlineError::InvalidValue);
+                }
+                self.test_filter = Some(value);
+            }
+            _ => return Err(CmdlineError::UnknownParam),
+        }
+        Ok(())
+    }
+
+    /// Apply the options of the command line, `name=value` separated by spaces. The invalid
+    /// options are ignored with a warning, return the number of ignored options.
+    pub fn parse(&mut self, cmdline: &'a str) -> usize {
+        let mut ignored = 0;
+        for option in cmdline.split_ascii_whitespace() {
+            let (name, value) = option.split_once('=').unwrap_or((option, ""));
+            if let Err(e) = self.set(name, value) {
+                log!(
+                    LogLevel::Warn,
+                    "Command line: ignoring option {}: {:?}",
+                    option,
+                    e
+                );
+                ignored += 1;
+            }
+        }
+        ignored
+    }
+}
+
+// Copies of the boot strings, the bootargs, the stdout-path and the fw_cfg command line, one
+// after the other. The kernel parameters point in it, the FDT memory is not kept.
+static mut CMDLINE: [u8; CMDLINE_MAX_SIZE] = [0; CMDLINE_MAX_SIZE];
+// Used size of CMDLINE
+static mut CMDLINE_LEN: usize = 0;
+// Bootargs of the FDT /chosen node, applied before the fw_cfg command line.
+static mut BOOTARGS: Option<&'static str> = None;
+// Parameters of the kernel, the config file values until the command line is read.
+static mut KERNEL_PARAMS: KernelParams<'static> = KernelParams::init();
+
+/// Current kernel parameters.
+pub fn kernel_params() -> KernelParams<'static> {
+    unsafe { KERNEL_PARAMS }
+}
+
+/// Free space of CMDLINE, the string written in it is kept with cmdline_keep.
+fn cmdline_free_space() -> &'static mut [u8] {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    unsafe {
+        &mut CMDLINE[CMDLINE_LEN..]
+    }
+}
+
+/// Keep the len first bytes of the free space, return them as a string.
+fn cmdline_keep(len: usize) -> Option<&'static str> {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let kept = unsafe { &CMDLINE[CMDLINE_LEN..CMDLINE_LEN + len] };
+    let s = core::str::from_utf8(kept).ok()?;
+    unsafe { CMDLINE_LEN += len };
+    Some(s)
+}
+
+/// Copy the string in CMDLINE, None if there's not enough space.
+fn cmdline_copy(s: &str) -> Option<&'static str> {
+    cmdline_free_space()
+        .get_mut(..s.len())?
+        .copy_from_slice(s.as_bytes());
+    cmdline_keep(s.len())
+}
+
+/// Read the bootargs and the stdout-path of the FDT /chosen node, called by the platform layer
+/// once the FDT is parsed. The sub-systems are not initialized yet, nothing is logged, the
+/// bootargs are applied by cmdline_init.
+pub fn cmdline_init_fdt() {
+    let chosen = match fdt_get_node_by_path("/chosen") {
+        Some(node) => node,
+        None => return,
+    };
+    if let Some(prop) = fdt_get_node_prop(chosen, "bootargs") {
+        match cmdline_copy(fdt_get_prop_str(prop)) {
+            Some(bootargs) => unsafe { BOOTARGS = Some(bootargs) },
+            None => kprint_fmt!("Bootargs larger than CMDLINE_MAX_SIZE, ignored.\n"),
+        }
+    }
+    // Linux also reads the older linux,stdout-path property.
+    if let Some(prop) = fdt_get_node_prop(chosen, "stdout-path")
+        .or_else(|| fdt_get_node_prop(chosen, "linux,stdout-path"))
+    {
+        let stdout_path = cmdline_copy(fdt_get_prop_str(prop));
+        unsafe { KERNEL_PARAMS.stdout_path = stdout_path };
+    }
+}
+
+/// Apply the FDT bootargs, then the command line read from the firmware device, to the kernel
+/// parameters. Both are optional, the config file values are kept without them.
+pub fn cmdline_init() {
+    if let Some(bootargs) = unsafe { BOOTARGS } {
+        log!(LogLevel::Info, "Bootargs: {}", bootargs);
+        // Allow static mut refs for now, kernel only run in monocore
+        #[allow(static_mut_refs)]
+        unsafe {
+            KERNEL_PARAMS.parse(bootargs)
+        };
+    }
+    let buf = cmdline_free_space();
+    let len = match firmware_read_file(CMDLINE_FW_CFG_FILE, buf) {
+        Ok(len) => len,
+        Err(FirmwareError::NotFound) | Err(FirmwareError::NoDevice) => return,
+        Err(e) => {
+            log!(
+                LogLevel::Warn,
+                "Command line: failed to read {}: {:?}",
+                CMDLINE_FW_CFG_FILE,
+                e
+            );
+            return;
+        }
+    };
+    let cmdline = match cmdline_keep(len) {
+        // A file given with `file=` instead of `string=` can end with a new line.
+        Some(cmdline) => cmdline.trim_end_matches(['\0', '\n']),
+        None => {
+            log!(
+                LogLevel::Warn,
+                "Command line: {} is not valid UTF-8",
+                CMDLINE_FW_CFG_FILE
+            );
+            return;
+        }
+    };
+    log!(LogLevel::Info, "Command line: {}", cmdline);
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    unsafe {
+        KERNEL_PARAMS.parse(cmdline)
+    };
+}
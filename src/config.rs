@@ -1,56 +1,384 @@
 // Config file where all static is defined.
 // Use to modify the behaviour of the kernel. Like the scheduler time or logs level.
+// The value given here is the default one, it can be overridden by the embedding application
+// without modifying this file, with a config file or environment variables.
+// See documentation: `Documentation/kernel/config.md`
 
-use crate::logs::LogLevel;
+use crate::{logs::LogLevel, watchdog::WatchdogAction};
+
+// Config overrides generated by build.rs, None if the value is not overridden.
+mod kconfig {
+    include!(concat!(env!("OUT_DIR"), "/kconfig.rs"));
+}
 
 // Define the duration between each tick in ms.
 // 1 = 1ms
-pub static TICK_DURATION: u64 = 4;
+pub static TICK_DURATION: u64 = match kconfig::TICK_DURATION {
+    Some(v) => v,
+    None => 4,
+};
 
 // Define the safety tick in kernel boot, used to avoid trigger an interrupt when the kernel is
 // booting
 // 1 = 1 seconds
-pub static TICK_SAFETY_DURATION: u64 = 1;
+pub static TICK_SAFETY_DURATION: u64 = match kconfig::TICK_SAFETY_DURATION {
+    Some(v) => v,
+    None => 1,
+};
 
 // Static for log level, everything equal to this or below will be logged
-pub static LOG_LEVEL: LogLevel = LogLevel::Debug;
+pub static LOG_LEVEL: LogLevel = match kconfig::LOG_LEVEL {
+    Some(v) => v,
+    None => LogLevel::Debug,
+};
+// Size of the log ring buffer in bytes, log! writes in it once the drain task runs.
+// Must be a power of 2, a record is the line and 2 bytes.
+pub static LOG_BUFFER_SIZE: usize = match kconfig::LOG_BUFFER_SIZE {
+    Some(v) => v,
+    None => 2048,
+};
+// Max size of a buffered log line in bytes, the end of a longer line is dropped.
+pub static LOG_LINE_MAX_SIZE: usize = match kconfig::LOG_LINE_MAX_SIZE {
+    Some(v) => v,
+    None => 160,
+};
+// Priority of the kernel task writing the log ring buffer to the consoles, low to keep the logs
+// out of the timing of the other tasks.
+pub static LOG_DRAIN_TASK_PRIORITY: usize = match kconfig::LOG_DRAIN_TASK_PRIORITY {
+    Some(v) => v,
+    None => 1,
+};
 
 // Define the uart address to use in kprint
-pub static KPRINT_ADDRESS: usize = 0x1000_0000;
+pub static KPRINT_ADDRESS: usize = match kconfig::KPRINT_ADDRESS {
+    Some(v) => v,
+    None => 0x1000_0000,
+};
 // ————————————————————————————————————————————————————————————
 // ——————— Define the max priority available for a task ———————
 // ————————————————————————————————————————————————————————————
-pub static TASK_MAX_PRIORITY: usize = 32;
+pub static TASK_MAX_PRIORITY: usize = match kconfig::TASK_MAX_PRIORITY {
+    Some(v) => v,
+    None => 32,
+};
+// ————————————————————————————————————————————————————————————
+// ————————— Define the max memory usable by a task ———————————
+// ————————————————————————————————————————————————————————————
+// Optional per-task memory quota in bytes, task creation fails if the stack size asked is above
+// the quota. None = no quota.
+pub static TASK_MEMORY_QUOTA: Option<usize> = match kconfig::TASK_MEMORY_QUOTA {
+    Some(v) => v,
+    None => None,
+};
+// ————————————————————————————————————————————————————————————
+// ————————— Define the max number of shared buffers ——————————
+// ————————————————————————————————————————————————————————————
+pub static SHARED_BUFFER_MAX_SIZE: usize = match kconfig::SHARED_BUFFER_MAX_SIZE {
+    Some(v) => v,
+    None => 8,
+};
+// Max number of DMA buffers
+pub static DMA_BUFFER_MAX_SIZE: usize = match kconfig::DMA_BUFFER_MAX_SIZE {
+    Some(v) => v,
+    None => 8,
+};
+// Min alignment of a DMA buffer, the data cache line size
+pub static DMA_MIN_ALIGN: usize = match kconfig::DMA_MIN_ALIGN {
+    Some(v) => v,
+    None => 64,
+};
 // ————————————————————————————————————————————————————————————
 // ———————— Define the max size of devices sub-systems ————————
 // ————————————————————————————————————————————————————————————
-pub static CPU_INTC_MAX_SIZE: usize = 2;
-pub static TIMER_MAX_SIZE: usize = 2;
-pub static SERIAL_MAX_SIZE: usize = 4;
+pub static CPU_INTC_MAX_SIZE: usize = match kconfig::CPU_INTC_MAX_SIZE {
+    Some(v) => v,
+    None => 2,
+};
+// Max size of the kernel command line, in bytes. Holds the FDT bootargs and stdout-path, and the
+// command line read from the host.
+pub static CMDLINE_MAX_SIZE: usize = match kconfig::CMDLINE_MAX_SIZE {
+    Some(v) => v,
+    None => 512,
+};
+pub static TIMER_MAX_SIZE: usize = match kconfig::TIMER_MAX_SIZE {
+    Some(v) => v,
+    None => 2,
+};
+pub static SERIAL_MAX_SIZE: usize = match kconfig::SERIAL_MAX_SIZE {
+    Some(v) => v,
+    None => 4,
+};
+// Max number of interrupt handlers, local interrupts and external interrupt sources
+pub static IRQ_MAX_SIZE: usize = match kconfig::IRQ_MAX_SIZE {
+    Some(v) => v,
+    None => 16,
+};
+// Priority of the kernel task running the interrupt bottom halves
+pub static IRQ_BOTTOM_HALF_TASK_PRIORITY: usize = match kconfig::IRQ_BOTTOM_HALF_TASK_PRIORITY {
+    Some(v) => v,
+    None => 30,
+};
+// Max number of block devices
+pub static BLOCK_MAX_SIZE: usize = match kconfig::BLOCK_MAX_SIZE {
+    Some(v) => v,
+    None => 2,
+};
+// Max number of real time clocks
+pub static RTC_MAX_SIZE: usize = match kconfig::RTC_MAX_SIZE {
+    Some(v) => v,
+    None => 1,
+};
+// Max number of GPIO controllers
+pub static GPIO_MAX_SIZE: usize = match kconfig::GPIO_MAX_SIZE {
+    Some(v) => v,
+    None => 1,
+};
+// Max number of SPI controllers
+pub static SPI_MAX_SIZE: usize = match kconfig::SPI_MAX_SIZE {
+    Some(v) => v,
+    None => 1,
+};
+// Max number of cpufreq notifiers
+pub static CPUFREQ_NOTIFIER_MAX_SIZE: usize = match kconfig::CPUFREQ_NOTIFIER_MAX_SIZE {
+    Some(v) => v,
+    None => 8,
+};
+// Max number of devices bound to a driver, all sub-systems together
+pub static DEVICE_REGISTRY_MAX_SIZE: usize = match kconfig::DEVICE_REGISTRY_MAX_SIZE {
+    Some(v) => v,
+    None => 32,
+};
+// Max number of tasks watched by the watchdog
+pub static WATCHDOG_MAX_SIZE: usize = match kconfig::WATCHDOG_MAX_SIZE {
+    Some(v) => v,
+    None => 8,
+};
+// Action of the watchdog when a task misses its deadline, if the task doesn't choose one
+pub static WATCHDOG_DEFAULT_ACTION: WatchdogAction = match kconfig::WATCHDOG_DEFAULT_ACTION {
+    Some(v) => v,
+    None => WatchdogAction::RestartTask,
+};
+// Number of restarts of a stalled task before the watchdog reset the system
+pub static WATCHDOG_MAX_RESTARTS: usize = match kconfig::WATCHDOG_MAX_RESTARTS {
+    Some(v) => v,
+    None => 3,
+};
+// Max number of virtio consoles, each one use 2 virtqueues
+pub static VIRTIO_CONSOLE_MAX_SIZE: usize = match kconfig::VIRTIO_CONSOLE_MAX_SIZE {
+    Some(v) => v,
+    None => 1,
+};
+// Number of virtqueues that can be allocated by the virtio drivers, each one use 256 bytes
+pub static VIRTIO_QUEUE_MAX_SIZE: usize = match kconfig::VIRTIO_QUEUE_MAX_SIZE {
+    Some(v) => v,
+    None => 4,
+};
+// ————————————————————————————————————————————————————————————
+// ——————————————— Define the serial devices config ———————————
+// ————————————————————————————————————————————————————————————
+// Baud rate used when initializing serial devices
+pub static SERIAL_BAUD_RATE: u32 = match kconfig::SERIAL_BAUD_RATE {
+    Some(v) => v,
+    None => 115200,
+};
+// Size of the receive buffer of a serial device, filled by the receive interrupt.
+// The buffer is len - 1, like the run queue.
+pub static SERIAL_RX_BUFFER_SIZE: usize = match kconfig::SERIAL_RX_BUFFER_SIZE {
+    Some(v) => v,
+    None => 64,
+};
+// Max number of tasks with their output routed to another serial device than the default console.
+pub static CONSOLE_TASK_ROUTE_MAX_SIZE: usize = match kconfig::CONSOLE_TASK_ROUTE_MAX_SIZE {
+    Some(v) => v,
+    None => 4,
+};
+// ————————————————————————————————————————————————————————————
+// ——————————————— Define the display config ——————————————————
+// ————————————————————————————————————————————————————————————
+// Resolution of the ramfb framebuffer in pixels, 4 bytes per pixel, 300 KiB by default.
+pub static RAMFB_WIDTH: usize = match kconfig::RAMFB_WIDTH {
+    Some(v) => v,
+    None => 320,
+};
+pub static RAMFB_HEIGHT: usize = match kconfig::RAMFB_HEIGHT {
+    Some(v) => v,
+    None => 240,
+};
+// Number of text lines of the status panel, at the top of the framebuffer console.
+pub static FBCON_STATUS_LINES: usize = match kconfig::FBCON_STATUS_LINES {
+    Some(v) => v,
+    None => 2,
+};
 
 // ————————————————————————————————————————————————————————————
 // ————————————— Define the max size of fdt pool ——————————————
 // ————————————————————————————————————————————————————————————
-pub static FDT_MAX_STACK: usize = 64;
-pub static FDT_MAX_PROPS: usize = 128;
+pub static FDT_MAX_STACK: usize = match kconfig::FDT_MAX_STACK {
+    Some(v) => v,
+    None => 64,
+};
+pub static FDT_MAX_PROPS: usize = match kconfig::FDT_MAX_PROPS {
+    Some(v) => v,
+    None => 128,
+};
 
 // ————————————————————————————————————————————————————————————
 // ————————————— Define the max size of Task list —————————————
 // ————————————————————————————————————————————————————————————
-pub static TASK_LIST_MAX_SIZE: usize = 4;
+// The kernel creates the idle task and the log drain task, and the irq bottom half task once a
+// bottom half is registered, like by the watchdog. The default leaves 3 to 4 tasks to the
+// application.
+pub static TASK_LIST_MAX_SIZE: usize = match kconfig::TASK_LIST_MAX_SIZE {
+    Some(v) => v,
+    None => 6,
+};
 // ————————————————————————————————————————————————————————————
 // ———— Define the max size of the task run/blocked queue —————
 // ————————————————————————————————————————————————————————————
 // The run queue is len - 1, if the size is 4, it will only use 3 slot in the queue.
-pub static RUN_QUEUE_MAX_SIZE: usize = 3;
-pub static BLOCK_QUEUE_MAX_SIZE: usize = 3;
+// The run queue must be able to hold all the tasks except the idle task, else a ready task pushed
+// in a full queue is dropped and never runs again.
+pub static RUN_QUEUE_MAX_SIZE: usize = match kconfig::RUN_QUEUE_MAX_SIZE {
+    Some(v) => v,
+    None => 6,
+};
+pub static BLOCK_QUEUE_MAX_SIZE: usize = match kconfig::BLOCK_QUEUE_MAX_SIZE {
+    Some(v) => v,
+    None => 5,
+};
 // ————————————————————————————————————————————————————————————
 // ————————————— Define the number of CPU core ————————————————
 // ————————————————————————————————————————————————————————————
-pub static CPU_CORE_NUMBER: usize = 1;
+pub static CPU_CORE_NUMBER: usize = match kconfig::CPU_CORE_NUMBER {
+    Some(v) => v,
+    None => 1,
+};
 
 // Kernel stack size
 // WARNING
 // Changing the kernel stack size can cause a lot of error, UB, or just break everything's
 // don't touch this unless you know what you do
-pub static KERNEL_STACK_SIZE: usize = 0x4000;
+pub static KERNEL_STACK_SIZE: usize = match kconfig::KERNEL_STACK_SIZE {
+    Some(v) => v,
+    None => 0x4000,
+};
+
+// Trap stack size, the stack used when handling a trap. Nested drivers interrupt handler will use
+// this stack, increase it if a trap stack overflow is detected.
+// A log from an interrupt handler formats its line on this stack, in a debug build the trap
+// handler and the timer handler use about 450 bytes, and a log with the wall clock about 1850
+// bytes more, core::fmt::write alone is 480 bytes and it's nested for the message and the time.
+// Must be a multiple of 16.
+pub static TRAP_STACK_SIZE: usize = match kconfig::TRAP_STACK_SIZE {
+    Some(v) => v,
+    None => 0xC00,
+};
+// Scheduler stack size.
+// Must be a multiple of 16.
+pub static SCHEDULER_STACK_SIZE: usize = match kconfig::SCHEDULER_STACK_SIZE {
+    Some(v) => v,
+    None => 0x1000,
+};
+
+// ————————————————————————————————————————————————————————————
+// ———————— Check the config values at compile time ———————————
+// ————————————————————————————————————————————————————————————
+const _: () = {
+    assert!(TICK_DURATION > 0, "TICK_DURATION must not be 0");
+    assert!(
+        TASK_MAX_PRIORITY > 0 && TASK_MAX_PRIORITY <= 32,
+        "TASK_MAX_PRIORITY must be in 1..=32, the run queue bitmap is a u32"
+    );
+    assert!(CPU_CORE_NUMBER > 0, "CPU_CORE_NUMBER must not be 0");
+    assert!(
+        TASK_LIST_MAX_SIZE > 2,
+        "TASK_LIST_MAX_SIZE must have space for the idle task, the log drain task and at least one task"
+    );
+    assert!(
+        RUN_QUEUE_MAX_SIZE >= TASK_LIST_MAX_SIZE,
+        "RUN_QUEUE_MAX_SIZE must be at least TASK_LIST_MAX_SIZE, the run queue use len - 1 slots"
+    );
+    assert!(
+        BLOCK_QUEUE_MAX_SIZE >= TASK_LIST_MAX_SIZE - 1,
+        "BLOCK_QUEUE_MAX_SIZE must be at least TASK_LIST_MAX_SIZE - 1"
+    );
+    assert!(
+        CPU_INTC_MAX_SIZE > 0 && TIMER_MAX_SIZE > 0 && SERIAL_MAX_SIZE > 0,
+        "Sub-systems max size must not be 0"
+    );
+    assert!(IRQ_MAX_SIZE > 0, "IRQ_MAX_SIZE must not be 0");
+    assert!(BLOCK_MAX_SIZE > 0, "BLOCK_MAX_SIZE must not be 0");
+    assert!(RTC_MAX_SIZE > 0, "RTC_MAX_SIZE must not be 0");
+    assert!(GPIO_MAX_SIZE > 0, "GPIO_MAX_SIZE must not be 0");
+    assert!(SPI_MAX_SIZE > 0, "SPI_MAX_SIZE must not be 0");
+    assert!(
+        DEVICE_REGISTRY_MAX_SIZE > 0,
+        "DEVICE_REGISTRY_MAX_SIZE must not be 0"
+    );
+    assert!(WATCHDOG_MAX_SIZE > 0, "WATCHDOG_MAX_SIZE must not be 0");
+    assert!(
+        CPUFREQ_NOTIFIER_MAX_SIZE >= 2,
+        "CPUFREQ_NOTIFIER_MAX_SIZE must have space for the timer and serial notifiers"
+    );
+    assert!(DMA_BUFFER_MAX_SIZE > 0, "DMA_BUFFER_MAX_SIZE must not be 0");
+    assert!(
+        DMA_MIN_ALIGN >= 16 && DMA_MIN_ALIGN.is_power_of_two(),
+        "DMA_MIN_ALIGN must be a power of two, at least 16"
+    );
+    assert!(
+        VIRTIO_CONSOLE_MAX_SIZE < SERIAL_MAX_SIZE,
+        "VIRTIO_CONSOLE_MAX_SIZE must leave a serial slot for the boot UART"
+    );
+    assert!(
+        IRQ_BOTTOM_HALF_TASK_PRIORITY > 0 && IRQ_BOTTOM_HALF_TASK_PRIORITY < TASK_MAX_PRIORITY,
+        "IRQ_BOTTOM_HALF_TASK_PRIORITY must be in 1..TASK_MAX_PRIORITY"
+    );
+    assert!(
+        LOG_BUFFER_SIZE.is_power_of_two(),
+        "LOG_BUFFER_SIZE must be a power of 2"
+    );
+    assert!(
+        LOG_LINE_MAX_SIZE > 0
+            && LOG_LINE_MAX_SIZE <= 255
+            && LOG_LINE_MAX_SIZE + 2 <= LOG_BUFFER_SIZE,
+        "LOG_LINE_MAX_SIZE must be in 1..=255 and a record must fit in LOG_BUFFER_SIZE"
+    );
+    assert!(
+        SERIAL_MAX_SIZE <= 128,
+        "SERIAL_MAX_SIZE must be at most 128, a log record keeps the console index in 7 bits"
+    );
+    assert!(
+        LOG_DRAIN_TASK_PRIORITY > 0 && LOG_DRAIN_TASK_PRIORITY < TASK_MAX_PRIORITY,
+        "LOG_DRAIN_TASK_PRIORITY must be in 1..TASK_MAX_PRIORITY"
+    );
+    assert!(SERIAL_BAUD_RATE > 0, "SERIAL_BAUD_RATE must not be 0");
+    assert!(
+        RAMFB_WIDTH >= 8 && RAMFB_HEIGHT >= 8,
+        "RAMFB_WIDTH and RAMFB_HEIGHT must fit at least one character"
+    );
+    assert!(
+        FBCON_STATUS_LINES < RAMFB_HEIGHT / 8,
+        "FBCON_STATUS_LINES must leave at least one console line"
+    );
+    assert!(CMDLINE_MAX_SIZE > 0, "CMDLINE_MAX_SIZE must not be 0");
+    assert!(
+        SERIAL_RX_BUFFER_SIZE > 1,
+        "SERIAL_RX_BUFFER_SIZE must be at least 2, the buffer use len - 1 slots"
+    );
+    assert!(
+        CONSOLE_TASK_ROUTE_MAX_SIZE > 0,
+        "CONSOLE_TASK_ROUTE_MAX_SIZE must not be 0"
+    );
+    assert!(
+        KERNEL_STACK_SIZE > 0 && KERNEL_STACK_SIZE.is_multiple_of(16),
+        "KERNEL_STACK_SIZE must be a non zero multiple of 16"
+    );
+    assert!(
+        TRAP_STACK_SIZE > 16 && TRAP_STACK_SIZE.is_multiple_of(16),
+        "TRAP_STACK_SIZE must be a multiple of 16"
+    );
+    assert!(
+        SCHEDULER_STACK_SIZE > 16 && SCHEDULER_STACK_SIZE.is_multiple_of(16),
+        "SCHEDULER_STACK_SIZE must be a multiple of 16"
+    );
+};
@@ -3,12 +3,17 @@
 use crate::{
     drivers::DriverRegion,
     platform::{
-        DeviceInfo, DeviceType, Devices, DevicesHeader, InterruptExtended, PlatformCpuFreqDevice,
-        PlatformCpuIntCDevice, PlatformSerialDevice, PlatformTimerDevice, mem::MemoryProvider,
+        DeviceInfo, DeviceType, Devices, DevicesHeader, ExtIntCContext, InterruptExtended,
+        PlatformCpuFreqDevice, PlatformCpuIntCDevice, PlatformExtIntCDevice, PlatformPowerDevice,
+        PlatformRtcDevice, PlatformSerialDevice, PlatformTimerDevice, PlatformVirtioDevice,
+        mem::MemoryProvider,
     },
 };
 
-static mut SERIAL_DEVICE: PlatformSerialDevice = PlatformSerialDevice {};
+static mut SERIAL_DEVICE: PlatformSerialDevice = PlatformSerialDevice {
+    clock_frequency: 3686400,
+    irq: 10,
+};
 static mut CLINT_DEVICE: PlatformTimerDevice = PlatformTimerDevice {
     interrupt_extended: [InterruptExtended {
         cpu_intc: 0,
@@ -18,6 +23,58 @@ static mut CLINT_DEVICE: PlatformTimerDevice = PlatformTimerDevice {
 };
 static mut CPU_INTC_DEVICE: PlatformCpuIntCDevice = PlatformCpuIntCDevice { core_id: 0 };
 static mut CPU_FREQ_DEVICE: PlatformCpuFreqDevice = PlatformCpuFreqDevice { freq: 10000000 };
+static mut PLIC_DEVICE: PlatformExtIntCDevice = PlatformExtIntCDevice {
+    ndev: 95,
+    contexts: [
+        ExtIntCContext {
+            hart: 0,
+            context: 0,
+        },
+        ExtIntCContext {
+            hart: u32::MAX,
+            context: 0,
+        },
+        ExtIntCContext {
+            hart: u32::MAX,
+            context: 0,
+        },
+        ExtIntCContext {
+            hart: u32::MAX,
+            context: 0,
+        },
+    ],
+};
+static mut RTC_DEVICE: PlatformRtcDevice = PlatformRtcDevice { irq: 11 };
+// The sifive,test0 protocol doesn't use the syscon values.
+static mut TEST_DEVICE: PlatformPowerDevice = PlatformPowerDevice::init();
+
+// QEMU virt has 8 virtio-mmio slots, 0x1000 bytes each from 0x1000_1000, irq 1 to 8.
+static VIRTIO_MMIO_DEVICES: [PlatformVirtioDevice; 8] = [
+    PlatformVirtioDevice { irq: 1 },
+    PlatformVirtioDevice { irq: 2 },
+    PlatformVirtioDevice { irq: 3 },
+    PlatformVirtioDevice { irq: 4 },
+    PlatformVirtioDevice { irq: 5 },
+    PlatformVirtioDevice { irq: 6 },
+    PlatformVirtioDevice { irq: 7 },
+    PlatformVirtioDevice { irq: 8 },
+];
+
+macro_rules! virtio_mmio_device {
+    ($slot:expr) => {
+        Devices {
+            header: DevicesHeader {
+                device_type: DeviceType::Virtio,
+                compatible: "virtio,mmio",
+                device_addr: DriverRegion {
+                    addr: 0x1000_1000 + $slot * 0x1000,
+                    size: 0x1000,
+                },
+            },
+            info: Some(&VIRTIO_MMIO_DEVICES[$slot] as *const dyn DeviceInfo),
+        }
+    };
+}
 
 pub static MEM: MemoryProvider = MemoryProvider {
     reg: DriverRegion {
@@ -69,4 +126,48 @@ pub static DEVICES: &[Devices] = &[
         #[allow(static_mut_refs)]
         info: Some(unsafe { &CPU_FREQ_DEVICE as *const dyn DeviceInfo }),
     },
+    Devices {
+        header: DevicesHeader {
+            device_type: DeviceType::ExtIntC,
+            compatible: "sifive,plic-1.0.0",
+            device_addr: DriverRegion {
+                addr: 0x0c00_0000,
+                size: 0x60_0000,
+            },
+        },
+        #[allow(static_mut_refs)]
+        info: Some(unsafe { &PLIC_DEVICE as *const dyn DeviceInfo }),
+    },
+    Devices {
+        header: DevicesHeader {
+            device_type: DeviceType::Rtc,
+            compatible: "google,goldfish-rtc",
+            device_addr: DriverRegion {
+                addr: 0x10_1000,
+                size: 0x1000,
+            },
+        },
+        #[allow(static_mut_refs)]
+        info: Some(unsafe { &RTC_DEVICE as *const dyn DeviceInfo }),
+    },
+    Devices {
+        header: DevicesHeader {
+            device_type: DeviceType::Power,
+            compatible: "sifive,test0",
+            device_addr: DriverRegion {
+                addr: 0x10_0000,
+                size: 0x1000,
+            },
+        },
+        #[allow(static_mut_refs)]
+        info: Some(unsafe { &TEST_DEVICE as *const dyn DeviceInfo }),
+    },
+    virtio_mmio_device!(0),
+    virtio_mmio_device!(1),
+    virtio_mmio_device!(2),
+    virtio_mmio_device!(3),
+    virtio_mmio_device!(4),
+    virtio_mmio_device!(5),
+    virtio_mmio_device!(6),
+    virtio_mmio_device!(7),
 ];
@@ -0,0 +1,221 @@
+// See documentation in `Documentation/kernel/subsystems.md`
+/*
+File info: Block devices sub-system.
+
+Test coverage: Request checks on the sub-system.
+
+Tested:
+- Sub-system initialized empty.
+- Request on a missing device.
+
+Not tested:
+- Add devices and requests on a real device.
+
+Reasons:
+- A block device need a virtio-mmio device, and an MMIO emulation to be tested.
+
+Tests files:
+- 'src/tests/drivers/block/subsystem.rs'
+*/
+
+use virtio_blk::VirtioBlk;
+
+use crate::{
+    arch::traps::interrupt::{restore_mstatus_mie, save_and_disable_mstatus_mie},
+    config::BLOCK_MAX_SIZE,
+    drivers::{
+        DriverRegion,
+        model::{DriverClass, driver_probe_class},
+        pool::DevicePool,
+    },
+    irq::irq_ext,
+    log,
+    logs::LogLevel,
+};
+
+pub mod virtio_blk;
+
+// Size of a sector, all block requests are in sectors.
+pub const BLOCK_SECTOR_SIZE: usize = 512;
+
+/// All errors that can happen on a block request.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BlockError {
+    // There's no block device at this index.
+    NoDevice,
+    // The buffer is empty or its size is not a multiple of BLOCK_SECTOR_SIZE.
+    InvalidBuffer,
+    // The request goes past the last sector of the device.
+    OutOfRange,
+    // The device is read only.
+    ReadOnly,
+    // A request is already in flight on the device.
+    Busy,
+    // The device failed to handle the request.
+    IoError,
+    // The device didn't complete the request in time, it's reset.
+    Timeout,
+    // The device doesn't support the request.
+    Unsupported,
+}
+
+#[derive(PartialEq)]
+pub enum BlockDeviceDriver {
+    VirtioBlk(VirtioBlk),
+}
+
+#[derive(PartialEq)]
+pub struct BlockDevice {
+    pub driver: BlockDeviceDriver,
+}
+
+impl BlockDevice {
+    /// Number of sectors of the device.
+    pub fn sector_count(&self) -> u64 {
+        match &self.driver {
+            BlockDeviceDriver::VirtioBlk(virtio_blk) => virtio_blk.capacity,
+        }
+    }
+
+    pub fn read_only(&self) -> bool {
+        match &self.driver {
+            BlockDeviceDriver::VirtioBlk(virtio_blk) => virtio_blk.read_only,
+        }
+    }
+
+    /// Memory region of the device, used to find the device bound in the device registry.
+    pub fn region(&self) -> DriverRegion {
+        match &self.driver {
+            BlockDeviceDriver::VirtioBlk(virtio_blk) => virtio_blk.transport.region,
+        }
+    }
+
+    /// Interrupt source id of the device, 0 if not connected.
+    pub fn irq(&self) -> u32 {
+        match &self.driver {
+            BlockDeviceDriver::VirtioBlk(virtio_blk) => virtio_blk.transport.irq,
+        }
+    }
+
+    pub fn interrupt_handler(&self) {
+        match &self.driver {
+            BlockDeviceDriver::VirtioBlk(virtio_blk) => virtio_blk.interrupt_handler(),
+        }
+    }
+
+    /// Check the request fits in the device.
+    fn check_request(&self, sector: u64, len: usize) -> Result<(), BlockError> {
+        if len == 0 || !len.is_multiple_of(BLOCK_SECTOR_SIZE) {
+            return Err(BlockError::InvalidBuffer);
+        }
+        let count = (len / BLOCK_SECTOR_SIZE) as u64;
+        match sector.checked_add(count) {
+            Some(end) if end <= self.sector_count() => Ok(()),
+            _ => Err(BlockError::OutOfRange),
+        }
+    }
+
+    /// Read sectors from the device in buf, the buffer size must be a multiple of the sector size.
+    /// Block until the request is completed.
+    pub fn read_sectors(&self, sector: u64, buf: &mut [u8]) -> Result<(), BlockError> {
+        self.check_request(sector, buf.len())?;
+        match &self.driver {
+            BlockDeviceDriver::VirtioBlk(virtio_blk) => virtio_blk.read_sectors(sector, buf),
+        }
+    }
+
+    /// Write buf to sectors of the device, the buffer size must be a multiple of the sector size.
+    /// Block until the request is completed.
+    pub fn write_sectors(&self, sector: u64, buf: &[u8]) -> Result<(), BlockError> {
+        self.check_request(sector, buf.len())?;
+        match &self.driver {
+            BlockDeviceDriver::VirtioBlk(virtio_blk) => virtio_blk.write_sectors(sector, buf),
+        }
+    }
+}
+
+pub struct BlockSubSystem {
+    pub devices: DevicePool<BlockDevice, BLOCK_MAX_SIZE>,
+}
+
+impl BlockSubSystem {
+    pub const fn init() -> Self {
+        BlockSubSystem {
+            devices: DevicePool::init("Block"),
+        }
+    }
+
+    /// Add a new block device in the sub-system, the device index is its position in the pool.
+    pub fn add_block(&self, new_block: BlockDevice) -> Option<usize> {
+        self.devices.add(new_block)
+    }
+
+    /// Remove the block device at index, the indexes of the other devices don't change.
+    pub fn remove_block(&self, index: usize) -> Option<BlockDevice> {
+        let mie = save_and_disable_mstatus_mie();
+        let block = self.devices.remove(index);
+        restore_mstatus_mie(mie);
+        block
+    }
+
+    pub fn get_block(&self, index: usize) -> Option<&BlockDevice> {
+        self.devices.get(index)
+    }
+
+    pub fn get_block_array_size(&self) -> usize {
+        self.devices.size()
+    }
+
+    pub fn read_sectors(
+        &self,
+        index: usize,
+        sector: u64,
+        buf: &mut [u8],
+    ) -> Result<(), BlockError> {
+        match self.get_block(index) {
+            Some(device) => device.read_sectors(sector, buf),
+            None => Err(BlockError::NoDevice),
+        }
+    }
+
+    pub fn write_sectors(&self, index: usize, sector: u64, buf: &[u8]) -> Result<(), BlockError> {
+        match self.get_block(index) {
+            Some(device) => device.write_sectors(sector, buf),
+            None => Err(BlockError::NoDevice),
+        }
+    }
+}
+
+pub static BLOCK_SUBSYSTEM: BlockSubSystem = BlockSubSystem::init();
+
+/// Read sectors from the block device at index, see BlockDevice::read_sectors.
+pub fn block_read(index: usize, sector: u64, buf: &mut [u8]) -> Result<(), BlockError> {
+    BLOCK_SUBSYSTEM.read_sectors(index, sector, buf)
+}
+
+/// Write sectors to the block device at index, see BlockDevice::write_sectors.
+pub fn block_write(index: usize, sector: u64, buf: &[u8]) -> Result<(), BlockError> {
+    BLOCK_SUBSYSTEM.write_sectors(index, sector, buf)
+}
+
+/// Return the number of sectors of the block device at index, None if there's no device.
+pub fn block_sector_count(index: usize) -> Option<u64> {
+    BLOCK_SUBSYSTEM.get_block(index).map(|d| d.sector_count())
+}
+
+/// External interrupt handler of all block devices, find the device raising the irq.
+pub fn block_interrupt_handler(irq: u32, _ctx: usize) {
+    for (_, block) in BLOCK_SUBSYSTEM.devices.iter() {
+        if irq_ext(block.irq()) == irq {
+            block.interrupt_handler();
+        }
+    }
+}
+
+/// Block devices are optional, the sub-system stays empty without device.
+pub fn init_block_subsystem() {
+    driver_probe_class(DriverClass::Block);
+    if BLOCK_SUBSYSTEM.get_block_array_size() == 0 {
+        log!(LogLevel::Info, "No block device found.");
+    }
+}
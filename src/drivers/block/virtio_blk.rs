@@ -0,0 +1,349 @@
+// See documentation in `Documentation/hardware/virtio.md`
+/*
+File info: Virtio block device driver.
+
+Test coverage: None.
+
+Tested:
+
+Not tested:
+- Everything.
+
+Reasons:
+- Testing a block driver need to have an MMIO emulation.
+
+Tests files:
+- 'src/tests/drivers/block/subsystem.rs'
+*/
+
+use core::{cell::Cell, ptr};
+
+use crate::{
+    arch::traps::interrupt::{restore_mstatus_mie, save_and_disable_mstatus_mie},
+    config::BLOCK_MAX_SIZE,
+    drivers::{
+        model::{Driver, DriverClass, DriverError, RegisteredDevice},
+        virtio::{
+            VIRTIO_DEVICE_ID_BLOCK, VirtioError, VirtioMmio,
+            queue::{VirtqBuffer, Virtqueue},
+            virtio_mmio_match,
+        },
+    },
+    irq::{IrqError, irq_ext, irq_register},
+    log,
+    logs::LogLevel,
+    platform::{DeviceType, Devices},
+    task::{primitives::sleep, task_current_pid},
+};
+
+use super::{
+    BLOCK_SECTOR_SIZE, BLOCK_SUBSYSTEM, BlockDevice, BlockDeviceDriver, BlockError,
+    block_interrupt_handler,
+};
+
+// Device features
+const VIRTIO_BLK_F_RO: u64 = 1 << 5;
+
+// Request types
+const VIRTIO_BLK_T_IN: u32 = 0;
+const VIRTIO_BLK_T_OUT: u32 = 1;
+
+// Request status, written by the device
+const VIRTIO_BLK_S_OK: u8 = 0;
+const VIRTIO_BLK_S_IOERR: u8 = 1;
+// Written by the driver before the request, never written by the device
+const VIRTIO_BLK_S_PENDING: u8 = 0xFF;
+
+// Config space offset of the capacity, in 512 bytes sectors
+const VIRTIO_BLK_CONFIG_CAPACITY: usize = 0x0;
+
+/// Request header and status shared with the device. The data buffer is the caller buffer.
+#[repr(C)]
+struct VirtioBlkRequest {
+    req_type: u32,
+    reserved: u32,
+    sector: u64,
+    status: u8,
+}
+
+// One request per device, a device only handle one request at a time.
+static mut VIRTIO_BLK_REQUESTS: [VirtioBlkRequest; BLOCK_MAX_SIZE] = [const {
+    VirtioBlkRequest {
+        req_type: 0,
+        reserved: 0,
+        sector: 0,
+        status: 0,
+    }
+}; BLOCK_MAX_SIZE];
+static mut VIRTIO_BLK_REQUESTS_USED: usize = 0;
+
+// Max wait for a request: ticks when the task sleeps between the checks, else checks of the used
+// ring.
+const VIRTIO_BLK_TIMEOUT_TICKS: usize = 500;
+const VIRTIO_BLK_TIMEOUT_SPIN: usize = 1_000_000;
+
+#[derive(PartialEq)]
+pub struct VirtioBlk {
+    pub transport: VirtioMmio,
+    queue: Virtqueue,
+    // Number of 512 bytes sectors
+    pub capacity: u64,
+    pub read_only: bool,
+    // Index in VIRTIO_BLK_REQUESTS
+    request: usize,
+    // The receive interrupt is attached, else the completion is polled
+    irq_attached: bool,
+    // Head descriptor of the request in flight
+    in_flight: Cell<Option<u16>>,
+    done: Cell<bool>,
+    // A request timed out, the device is reset and doesn't handle requests anymore
+    failed: Cell<bool>,
+}
+
+/// Virtio-blk entry of the driver match table, the virtio-mmio slots are shared with the other
+/// virtio drivers.
+pub struct VirtioBlkDriver;
+
+impl Driver for VirtioBlkDriver {
+    fn name(&self) -> &'static str {
+        "virtio-blk"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        &["virtio,mmio"]
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Virtio
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::Block
+    }
+
+    /// Initialize the virtio block device of the slot and add it to the block sub-system.
+    fn probe(&self, device_info: &Devices) -> Result<(), DriverError> {
+        let transport =
+            virtio_mmio_match(device_info, VIRTIO_DEVICE_ID_BLOCK).ok_or(DriverError::NoMatch)?;
+        let virtio_blk = VirtioBlk::init_device(transport).map_err(|e| {
+            log!(
+                LogLevel::Warn,
+                "Virtio-blk: failed to initialize the device at {:#x}: {:?}",
+                transport.region.addr,
+                e
+            );
+            DriverError::InitFailed
+        })?;
+        let device = BlockDevice {
+            driver: BlockDeviceDriver::VirtioBlk(virtio_blk),
+        };
+        BLOCK_SUBSYSTEM
+            .add_block(device)
+            .map(|_| ())
+            .ok_or(DriverError::InitFailed)
+    }
+
+    /// Reset the device and remove it from the block sub-system, refused while a request is in
+    /// flight. The queue and the request slot of the device are not given back.
+    fn remove(&self, device: &RegisteredDevice) -> Result<(), DriverError> {
+        let index = BLOCK_SUBSYSTEM
+            .devices
+            .position(|b| b.region() == device.region)
+            .ok_or(DriverError::NotRegistered)?;
+        let mie = save_and_disable_mstatus_mie();
+        let result = match BLOCK_SUBSYSTEM.get_block(index).map(|b| &b.driver) {
+            Some(BlockDeviceDriver::VirtioBlk(virtio_blk))
+                if virtio_blk.in_flight.get().is_none() =>
+            {
+                virtio_blk.transport.reset();
+                BLOCK_SUBSYSTEM.remove_block(index);
+                Ok(())
+            }
+            Some(_) => Err(DriverError::Busy),
+            None => Err(DriverError::NotRegistered),
+        };
+        restore_mstatus_mie(mie);
+        result
+    }
+}
+
+impl VirtioBlk {
+    fn init_device(transport: VirtioMmio) -> Result<Self, VirtioError> {
+        let request = unsafe { VIRTIO_BLK_REQUESTS_USED };
+        if request == BLOCK_MAX_SIZE {
+            return Err(VirtioError::OutOfQueueMemory);
+        }
+        let features = transport.init(VIRTIO_BLK_F_RO)?;
+        let queue = Virtqueue::alloc()?;
+        if let Err(e) = transport.setup_queue(0, &queue) {
+            transport.fail();
+            return Err(e);
+        }
+        let mut irq_attached = false;
+        if transport.irq != 0 {
+            match irq_register(irq_ext(transport.irq), 1, block_interrupt_handler, 0, None) {
+                Ok(_) | Err(IrqError::AlreadyRegistered) => irq_attached = true,
+                Err(e) => {
+                    log!(
+                        LogLevel::Warn,
+                        "Virtio-blk: failed to attach the interrupt: {:?}, completion by polling only",
+                        e
+                    );
+                }
+            }
+        }
+        transport.driver_ok();
+        unsafe { VIRTIO_BLK_REQUESTS_USED += 1 };
+        Ok(VirtioBlk {
+            transport,
+            queue,
+            capacity: transport.config_read_u64(VIRTIO_BLK_CONFIG_CAPACITY),
+            read_only: features & VIRTIO_BLK_F_RO != 0,
+            request,
+            irq_attached,
+            in_flight: Cell::new(None),
+            done: Cell::new(false),
+            failed: Cell::new(false),
+        })
+    }
+
+    fn request_ptr(&self) -> *mut VirtioBlkRequest {
+        #[allow(static_mut_refs)]
+        unsafe {
+            &mut VIRTIO_BLK_REQUESTS[self.request] as *mut VirtioBlkRequest
+        }
+    }
+
+    /// Read the used ring, mark the request in flight as done if the device used it. The read stops
+    /// at an invalid used buffer.
+    /// Must be called with interrupts disabled.
+    fn complete(&self) {
+        while let Ok(Some((head, _))) = self.queue.pop_used() {
+            if self.in_flight.get() == Some(head) {
+                self.done.set(true);
+            }
+        }
+    }
+
+    /// Called from the block sub-system interrupt handler.
+    pub fn interrupt_handler(&self) {
+        self.transport.ack_interrupt();
+        self.complete();
+    }
+
+    /// Send a request and wait for its completion. From a task with the interrupt attached, the
+    /// task sleeps one tick between each check and the interrupt handler complete the request.
+    /// Else the used ring is polled.
+    /// A request not completed in VIRTIO_BLK_TIMEOUT_TICKS ticks, or VIRTIO_BLK_TIMEOUT_SPIN
+    /// checks when polling, returns Timeout. The device is reset, it cannot write in the buffer
+    /// after the return, and the next requests return IoError.
+    fn request(
+        &self,
+        write: bool,
+        sector: u64,
+        buf: *mut u8,
+        len: usize,
+    ) -> Result<(), BlockError> {
+        if self.failed.get() {
+            return Err(BlockError::IoError);
+        }
+        let mie = save_and_disable_mstatus_mie();
+        if self.in_flight.get().is_some() {
+            restore_mstatus_mie(mie);
+            return Err(BlockError::Busy);
+        }
+        let request = self.request_ptr();
+        unsafe {
+            (*request).req_type = if write {
+                VIRTIO_BLK_T_OUT
+            } else {
+                VIRTIO_BLK_T_IN
+            };
+            (*request).reserved = 0;
+            (*request).sector = sector;
+            ptr::write_volatile(&mut (*request).status, VIRTIO_BLK_S_PENDING);
+        }
+        let buffers = [
+            VirtqBuffer {
+                addr: request as usize,
+                len: 16,
+                device_writable: false,
+            },
+            VirtqBuffer {
+                addr: buf as usize,
+                len: len as u32,
+                device_writable: !write,
+            },
+            VirtqBuffer {
+                addr: unsafe { &raw mut (*request).status } as usize,
+                len: 1,
+                device_writable: true,
+            },
+        ];
+        let head = match self.queue.add_chain(&buffers) {
+            Ok(h) => h,
+            Err(_) => {
+                restore_mstatus_mie(mie);
+                return Err(BlockError::Busy);
+            }
+        };
+        self.done.set(false);
+        self.in_flight.set(Some(head));
+        self.transport.notify(0);
+        restore_mstatus_mie(mie);
+        let by_interrupt = self.irq_attached && task_current_pid().is_some();
+        let max_checks = if by_interrupt {
+            VIRTIO_BLK_TIMEOUT_TICKS
+        } else {
+            VIRTIO_BLK_TIMEOUT_SPIN
+        };
+        let mut done = false;
+        for _ in 0..max_checks {
+            if !by_interrupt {
+                let mie = save_and_disable_mstatus_mie();
+                self.complete();
+                restore_mstatus_mie(mie);
+            }
+            if self.done.get() {
+                done = true;
+                break;
+            }
+            if by_interrupt {
+                unsafe { sleep(1) };
+            }
+        }
+        if !done {
+            // The device still owns the buffers, stop it before giving the buffer back.
+            self.transport.reset();
+            self.failed.set(true);
+            self.in_flight.set(None);
+            log!(
+                LogLevel::Error,
+                "Virtio-blk: request on sector {} timed out, device reset",
+                sector
+            );
+            return Err(BlockError::Timeout);
+        }
+        self.in_flight.set(None);
+        let status = unsafe { ptr::read_volatile(&(*request).status) };
+        match status {
+            VIRTIO_BLK_S_OK => Ok(()),
+            VIRTIO_BLK_S_IOERR => Err(BlockError::IoError),
+            _ => Err(BlockError::Unsupported),
+        }
+    }
+
+    pub fn read_sectors(&self, sector: u64, buf: &mut [u8]) -> Result<(), BlockError> {
+        self.request(false, sector, buf.as_mut_ptr(), buf.len())
+    }
+
+    pub fn write_sectors(&self, sector: u64, buf: &[u8]) -> Result<(), BlockError> {
+        if self.read_only {
+            return Err(BlockError::ReadOnly);
+        }
+        // The device only read the buffer on a write request.
+        self.request(true, sector, buf.as_ptr() as *mut u8, buf.len())
+    }
+}
+
+const _: () = assert!(BLOCK_SECTOR_SIZE == 512, "Virtio-blk sectors are 512 bytes");
@@ -0,0 +1,83 @@
+// See documentation in `Documentation/kernel/bus.md`
+/*
+File info: Bus lock shared by the SPI and I2C controllers.
+
+Test coverage: Lock and unlock outside a task.
+
+Tested:
+- Lock, unlock and lock already taken.
+
+Not tested:
+- Waiting for the lock from a task.
+
+Reasons:
+- The tests don't run inside a task.
+
+Tests files:
+- 'src/tests/drivers/spi/subsystem.rs'
+*/
+
+use core::cell::Cell;
+
+use crate::{
+    arch::traps::interrupt::{restore_mstatus_mie, save_and_disable_mstatus_mie},
+    task::{primitives::sleep, task_current_pid},
+};
+
+/// Lock of a bus controller, held during a whole transaction so several tasks can share the
+/// controller. The owner is the pid of the task holding the lock, None outside a task.
+pub struct BusLock {
+    locked: Cell<bool>,
+    owner: Cell<Option<u16>>,
+}
+
+// The kernel is single-threaded, the lock state is changed with the interrupts disabled.
+unsafe impl Sync for BusLock {}
+
+impl BusLock {
+    pub const fn init() -> Self {
+        BusLock {
+            locked: Cell::new(false),
+            owner: Cell::new(None),
+        }
+    }
+
+    /// Take the lock if it's free, return false if it's already taken.
+    pub fn try_lock(&self) -> bool {
+        let mie = save_and_disable_mstatus_mie();
+        let free = !self.locked.get();
+        if free {
+            self.locked.set(true);
+            self.owner.set(task_current_pid());
+        }
+        restore_mstatus_mie(mie);
+        free
+    }
+
+    /// Take the lock, a task sleeps one tick between each try until the lock is free.
+    /// Return false if the lock cannot be waited: outside a task the owner would never run, and
+    /// the owner itself would wait forever.
+    pub fn lock(&self) -> bool {
+        loop {
+            if self.try_lock() {
+                return true;
+            }
+            let pid = task_current_pid();
+            if pid.is_none() || self.owner.get() == pid {
+                return false;
+            }
+            unsafe { sleep(1) };
+        }
+    }
+
+    pub fn unlock(&self) {
+        let mie = save_and_disable_mstatus_mie();
+        self.locked.set(false);
+        self.owner.set(None);
+        restore_mstatus_mie(mie);
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.get()
+    }
+}
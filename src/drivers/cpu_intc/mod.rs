@@ -18,21 +18,27 @@ Tests files:
 - 'src/tests/drivers/cpu_intc/subsystem.rs'
 */
 
-use core::cell::UnsafeCell;
-
 use riscv_cpu_intc::RiscVCpuIntc;
 
-use crate::{config::CPU_INTC_MAX_SIZE, log, logs::LogLevel};
+use crate::{
+    config::CPU_INTC_MAX_SIZE,
+    drivers::{
+        model::{DriverClass, driver_probe_class},
+        pool::DevicePool,
+    },
+};
 
 pub mod riscv_cpu_intc;
 
 // Unions enum for CpuIntcDriver struct
 // avoid using &'static mut dyn CpuIntc
+#[derive(PartialEq)]
 pub enum CpuIntcDriver {
     #[allow(unused)]
     RiscVCpuIntc(RiscVCpuIntc),
 }
 
+#[derive(PartialEq)]
 pub struct CpuIntcHw {
     #[allow(unused)]
     pub driver: CpuIntcDriver,
@@ -48,16 +54,13 @@ impl CpuIntcHw {
 
 // Structure handling the cpu interrupt-controller initialized drivers
 pub struct CpuIntcSubSystem {
-    // cpu_intc_pool: UnsafeCell<[Option<CpuIntcHw>; CPU_INTC_MAX_SIZE]>,
-    cpu_intc_pool: [UnsafeCell<Option<CpuIntcHw>>; CPU_INTC_MAX_SIZE],
+    cpu_intc_pool: DevicePool<CpuIntcHw, CPU_INTC_MAX_SIZE>,
 }
 
-unsafe impl Sync for CpuIntcSubSystem {}
-
 impl CpuIntcSubSystem {
     pub const fn init() -> Self {
         CpuIntcSubSystem {
-            cpu_intc_pool: [const { UnsafeCell::new(None) }; CPU_INTC_MAX_SIZE],
+            cpu_intc_pool: DevicePool::init("CPU interrupt-controller"),
         }
     }
 
@@ -69,45 +72,16 @@ impl CpuIntcSubSystem {
     /// index: used to represent the CPU interrupt-controller core id, also used as an index in
     /// the sub-system pool. Because there's only one CPU interrupt-controller per CPU core, no
     /// overlap possible.
-    pub fn add_cpu_intc(&self, new_cpu_intc: CpuIntcHw, index: usize) {
-        let size = self.get_cpu_intc_array_size();
-        if size == CPU_INTC_MAX_SIZE {
-            log!(
-                LogLevel::Warn,
-                "CPU interrupt-controller sub-system: sub-system is full, ignoring registration request"
-            );
-            return;
-        }
-        if self.get_cpu_intc(index).is_some() {
-            log!(
-                LogLevel::Warn,
-                "CPU interrupt-controller sub-system: duplicate device detected, ignoring registration request"
-            );
-        } else {
-            unsafe {
-                *self.cpu_intc_pool[index].get() = Some(new_cpu_intc);
-            }
-        }
+    pub fn add_cpu_intc(&self, new_cpu_intc: CpuIntcHw, index: usize) -> bool {
+        self.cpu_intc_pool.insert(index, new_cpu_intc)
     }
 
     pub fn get_cpu_intc_array_size(&self) -> usize {
-        let mut size: usize = 0;
-        for i in 0..CPU_INTC_MAX_SIZE {
-            let present = unsafe { &*self.cpu_intc_pool[i].get() };
-            if present.is_some() {
-                size += 1;
-            }
-        }
-        size
+        self.cpu_intc_pool.size()
     }
 
     pub fn get_cpu_intc(&self, index: usize) -> Option<&CpuIntcHw> {
-        let cpu_intc = unsafe { &*self.cpu_intc_pool[index].get() };
-        if let Some(cpu_intc_driver) = cpu_intc {
-            Some(cpu_intc_driver)
-        } else {
-            None
-        }
+        self.cpu_intc_pool.get(index)
     }
 }
 
@@ -115,7 +89,7 @@ impl CpuIntcSubSystem {
 pub static CPU_INTC_SUBSYSTEM: CpuIntcSubSystem = CpuIntcSubSystem::init();
 
 pub fn init_cpu_intc_subsystem() {
-    RiscVCpuIntc::init();
+    driver_probe_class(DriverClass::CpuIntC);
     let size = CPU_INTC_SUBSYSTEM.get_cpu_intc_array_size();
     if size == 0 {
         panic!("Error while initializing CPU interrupt-controller sub-system, pool is empty.");
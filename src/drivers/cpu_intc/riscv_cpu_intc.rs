@@ -1,20 +1,37 @@
 use crate::{
+    drivers::model::{Driver, DriverClass, DriverError},
     misc::RawTraitObject,
-    platform::{DeviceType, PlatformCpuIntCDevice, platform_get_device_info},
+    platform::{DeviceType, Devices, PlatformCpuIntCDevice},
 };
 
 use super::{CPU_INTC_SUBSYSTEM, CpuIntcDriver, CpuIntcHw};
 
+#[derive(PartialEq)]
 pub struct RiscVCpuIntc {
     pub hart_id: u32,
 }
 
-impl RiscVCpuIntc {
-    pub fn init() {
-        let device_info = match platform_get_device_info("riscv,cpu-intc", DeviceType::CpuIntC) {
-            Some(d) => d,
-            None => return,
-        };
+/// RISC-V CPU interrupt-controller entry of the driver match table, one device per CPU core.
+pub struct RiscVCpuIntcDriver;
+
+impl Driver for RiscVCpuIntcDriver {
+    fn name(&self) -> &'static str {
+        "riscv-cpu-intc"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        &["riscv,cpu-intc"]
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::CpuIntC
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::CpuIntC
+    }
+
+    fn probe(&self, device_info: &Devices) -> Result<(), DriverError> {
         // Allow the use of expect, once we got the device asked, the trait should be working and
         // we should get the trait behind the Option<>
         #[allow(clippy::expect_used)]
@@ -31,6 +48,9 @@ impl RiscVCpuIntc {
             driver: CpuIntcDriver::RiscVCpuIntc(cpu_intc_pool),
         };
         let cpu_core_id = &cpu_intc.get_cpu_intc_core_id();
-        CPU_INTC_SUBSYSTEM.add_cpu_intc(cpu_intc, *cpu_core_id as usize);
+        match CPU_INTC_SUBSYSTEM.add_cpu_intc(cpu_intc, *cpu_core_id as usize) {
+            true => Ok(()),
+            false => Err(DriverError::InitFailed),
+        }
     }
 }
@@ -1,11 +1,81 @@
+// See documentation in `Documentation/kernel/cpufreq.md`
+/*
+File info: CPU frequency, operating point changes and notifier chain.
+
+Test coverage: Notifier chain and time conversions.
+
+Tested:
+- Register, notify and unregister notifiers.
+- Invalid operating point.
+- Time continuity and deadlines scaling across a timebase change.
+
+Not tested:
+- A real timebase change.
+
+Reasons:
+- QEMU virt timebase is fixed, changing it would break the time of the test framework.
+
+Tests files:
+- 'src/tests/drivers/cpufreq.rs'
+*/
+
 use crate::{
+    arch::traps::interrupt::{restore_mstatus_mie, save_and_disable_mstatus_mie},
+    config::CPUFREQ_NOTIFIER_MAX_SIZE,
+    drivers::timer::TIMER_SUBSYSTEM,
+    log,
+    logs::LogLevel,
     misc::RawTraitObject,
     platform::{DeviceType, PlatformCpuFreqDevice, platform_get_device_info},
 };
 
+/// All errors that can happen on a cpufreq request.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CpuFreqError {
+    // The timebase frequency of an operating point cannot be 0.
+    InvalidFrequency,
+    // The notifier chain is full, see CPUFREQ_NOTIFIER_MAX_SIZE in config file.
+    ChainFull,
+    // There's no notifier with this id.
+    InvalidId,
+}
+
+/// Operating point of the CPU.
+/// timebase: frequency of the architectural timer, used by ktime and the tick.
+/// bus: frequency of the peripheral bus clock, 0 if the peripherals have their own fixed clock.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CpuFreqOpp {
+    pub timebase: u32,
+    pub bus: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CpuFreqStage {
+    // Before the switch, the subscribers stop using the old clock, like flushing a UART.
+    PreChange,
+    // After the switch, the subscribers reprogram their device from the new clock.
+    PostChange,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CpuFreqEvent {
+    pub stage: CpuFreqStage,
+    pub old: CpuFreqOpp,
+    pub new: CpuFreqOpp,
+}
+
+/// Notifier called on each stage of an operating point change, with the interrupts disabled.
+pub type CpuFreqNotifier = fn(event: &CpuFreqEvent);
+
 // Struct to handle the CPU frequency
+// frequency: the timebase frequency.
+// bus_frequency: the peripheral bus clock, 0 if unknown.
+// time_offset: added to the timer value so the time keeps counting at the current frequency
+// after a change, see cpufreq_time.
 pub struct CpuFreq {
     pub frequency: u32,
+    pub bus_frequency: u32,
+    time_offset: i64,
 }
 
 impl CpuFreq {
@@ -25,9 +95,120 @@ impl CpuFreq {
         let cpu_freq_device_ref = unsafe { &*cpu_freq_device_ptr };
         let cpu_freq: CpuFreq = CpuFreq {
             frequency: cpu_freq_device_ref.freq,
+            bus_frequency: 0,
+            time_offset: 0,
         };
         unsafe { CPUFREQ = cpu_freq };
     }
 }
 
-pub static mut CPUFREQ: CpuFreq = CpuFreq { frequency: 0 };
+pub static mut CPUFREQ: CpuFreq = CpuFreq {
+    frequency: 0,
+    bus_frequency: 0,
+    time_offset: 0,
+};
+
+static mut CPUFREQ_NOTIFIERS: [Option<CpuFreqNotifier>; CPUFREQ_NOTIFIER_MAX_SIZE] =
+    [None; CPUFREQ_NOTIFIER_MAX_SIZE];
+
+/// Scale a number of timer cycles counted at old_hz to new_hz.
+pub fn cpufreq_scale(cycles: u64, old_hz: u32, new_hz: u32) -> u64 {
+    (cycles as u128 * new_hz as u128 / old_hz as u128) as u64
+}
+
+/// New time offset after a timebase change at the timer value now, the time counted at old_hz
+/// until now is kept and counts at new_hz from now.
+pub fn cpufreq_rebase_offset(offset: i64, now: u64, old_hz: u32, new_hz: u32) -> i64 {
+    let time = (now as i64 + offset) as u64;
+    cpufreq_scale(time, old_hz, new_hz) as i64 - now as i64
+}
+
+/// Time of the timer value, counting at the current timebase frequency since boot. Use it
+/// instead of the raw timer value to convert the time to a duration.
+pub fn cpufreq_time(timer_value: u64) -> u64 {
+    #[allow(static_mut_refs)]
+    let offset = unsafe { CPUFREQ.time_offset };
+    (timer_value as i64 + offset) as u64
+}
+
+/// Return the current operating point.
+pub fn cpufreq_get() -> CpuFreqOpp {
+    #[allow(static_mut_refs)]
+    unsafe {
+        CpuFreqOpp {
+            timebase: CPUFREQ.frequency,
+            bus: CPUFREQ.bus_frequency,
+        }
+    }
+}
+
+/// Add a notifier at the end of the chain, return its id. A notifier already in the chain is not
+/// added twice, its id is returned.
+pub fn cpufreq_notifier_register(notifier: CpuFreqNotifier) -> Result<usize, CpuFreqError> {
+    #[allow(static_mut_refs)]
+    let notifiers = unsafe { &mut CPUFREQ_NOTIFIERS };
+    let addr = notifier as *const () as usize;
+    if let Some(id) = notifiers
+        .iter()
+        .position(|n| n.is_some_and(|n| n as *const () as usize == addr))
+    {
+        return Ok(id);
+    }
+    let id = notifiers
+        .iter()
+        .position(|n| n.is_none())
+        .ok_or(CpuFreqError::ChainFull)?;
+    notifiers[id] = Some(notifier);
+    Ok(id)
+}
+
+pub fn cpufreq_notifier_unregister(id: usize) -> Result<(), CpuFreqError> {
+    #[allow(static_mut_refs)]
+    let notifier = unsafe { CPUFREQ_NOTIFIERS.get_mut(id) }.ok_or(CpuFreqError::InvalidId)?;
+    notifier.take().map(|_| ()).ok_or(CpuFreqError::InvalidId)
+}
+
+fn cpufreq_notify(event: &CpuFreqEvent) {
+    #[allow(static_mut_refs)]
+    for notifier in unsafe { CPUFREQ_NOTIFIERS.iter() }.flatten() {
+        notifier(event);
+    }
+}
+
+/// Switch to the new operating point. The notifiers are called before and after switch, the
+/// board function changing the clocks, with the interrupts disabled for the whole change.
+/// The time read from ktime stays continuous, the time counted at the old timebase is kept.
+pub fn cpufreq_change(new: CpuFreqOpp, switch: fn(&CpuFreqOpp)) -> Result<(), CpuFreqError> {
+    if new.timebase == 0 {
+        return Err(CpuFreqError::InvalidFrequency);
+    }
+    let old = cpufreq_get();
+    let mie = save_and_disable_mstatus_mie();
+    cpufreq_notify(&CpuFreqEvent {
+        stage: CpuFreqStage::PreChange,
+        old,
+        new,
+    });
+    switch(&new);
+    let now = TIMER_SUBSYSTEM.get_primary_timer().read_time();
+    #[allow(static_mut_refs)]
+    unsafe {
+        CPUFREQ.time_offset =
+            cpufreq_rebase_offset(CPUFREQ.time_offset, now, old.timebase, new.timebase);
+        CPUFREQ.frequency = new.timebase;
+        CPUFREQ.bus_frequency = new.bus;
+    }
+    cpufreq_notify(&CpuFreqEvent {
+        stage: CpuFreqStage::PostChange,
+        old,
+        new,
+    });
+    restore_mstatus_mie(mie);
+    log!(
+        LogLevel::Debug,
+        "Cpufreq: timebase {} Hz, bus {} Hz",
+        new.timebase,
+        new.bus
+    );
+    Ok(())
+}
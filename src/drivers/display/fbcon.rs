@@ -0,0 +1,407 @@
+// See documentation in `Documentation/kernel/framebuffer.md`
+/*
+File info: Framebuffer console, text console and status panel drawn on a framebuffer.
+
+Test coverage: Console on a framebuffer in memory.
+
+Tested:
+- Draw a character with the font.
+- Line wrap, new line and scroll.
+- ANSI color sequences.
+- Status lines not scrolled.
+
+Not tested:
+- The console on a display, and its lock.
+
+Reasons:
+- The test machine is not started with a ramfb device.
+
+Tests files:
+- 'src/tests/drivers/display/fbcon.rs'
+*/
+
+use core::fmt::{self, Write};
+
+use crate::{
+    arch::traps::interrupt::read_mstatus_mie, config::FBCON_STATUS_LINES, drivers::bus::BusLock,
+};
+
+use super::{
+    FbRect, Framebuffer,
+    font::{FONT_HEIGHT, FONT_WIDTH, font_glyph},
+};
+
+/// All errors that can happen on a framebuffer console request.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FbConError {
+    // There's no framebuffer console, no display was found.
+    NoDevice,
+    // The status panel has no line at this index.
+    InvalidLine,
+    // The console is drawn by a task, and the lock cannot be waited with the interrupts disabled.
+    Busy,
+}
+
+// Default colors of the console, light grey on black.
+pub const FBCON_FG: u32 = 0x00aa_aaaa;
+pub const FBCON_BG: u32 = 0x0000_0000;
+// Colors of the status panel, white on blue.
+pub const FBCON_STATUS_FG: u32 = 0x00ff_ffff;
+pub const FBCON_STATUS_BG: u32 = 0x0000_3f7f;
+// Colors of the ANSI codes 30 to 37, the bright variants.
+const FBCON_PALETTE: [u32; 8] = [
+    0x0055_5555,
+    0x00ff_5555,
+    0x0055_ff55,
+    0x00ff_ff55,
+    0x0055_55ff,
+    0x00ff_55ff,
+    0x0055_ffff,
+    0x00ff_ffff,
+];
+const TAB_SIZE: usize = 8;
+// Max number of parameters of an ANSI sequence, the others are ignored.
+const ESCAPE_MAX_PARAMS: usize = 4;
+
+/// State of the ANSI escape sequence parser, the logs use colors.
+#[derive(Copy, Clone, PartialEq)]
+enum Escape {
+    None,
+    // ESC received
+    Start,
+    // ESC [ received, reading the parameters
+    Csi,
+}
+
+/// Text console on a framebuffer. The first status_rows text rows are the status panel, set by
+/// line, the other rows are the console, scrolled up when full.
+/// col, row: cursor position in characters, row is a screen row, never in the status panel.
+pub struct FbCon {
+    fb: Framebuffer,
+    cols: usize,
+    rows: usize,
+    status_rows: usize,
+    col: usize,
+    row: usize,
+    fg: u32,
+    escape: Escape,
+    params: [u16; ESCAPE_MAX_PARAMS],
+    nparams: usize,
+    // The print! output is written to the console.
+    print: bool,
+}
+
+impl FbCon {
+    /// New console on the framebuffer, the status panel is reduced to keep at least one console
+    /// row.
+    pub fn new(fb: Framebuffer, status_rows: usize) -> Self {
+        let rows = fb.height / FONT_HEIGHT;
+        let status_rows = status_rows.min(rows.saturating_sub(1));
+        FbCon {
+            fb,
+            cols: fb.width / FONT_WIDTH,
+            rows,
+            status_rows,
+            col: 0,
+            row: status_rows,
+            fg: FBCON_FG,
+            escape: Escape::None,
+            params: [0; ESCAPE_MAX_PARAMS],
+            nparams: 0,
+            print: true,
+        }
+    }
+
+    /// Size of the console in characters, the status panel included.
+    pub fn size(&self) -> (usize, usize) {
+        (self.cols, self.rows)
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.col, self.row)
+    }
+
+    /// Clear the console and the status panel, the cursor goes to the first console row.
+    pub fn clear(&mut self) {
+        let status_height = self.status_rows * FONT_HEIGHT;
+        self.fb.fill_rect(
+            FbRect {
+                x: 0,
+                y: 0,
+                width: self.fb.width,
+                height: status_height,
+            },
+            FBCON_STATUS_BG,
+        );
+        self.fb.fill_rect(
+            FbRect {
+                x: 0,
+                y: status_height,
+                width: self.fb.width,
+                height: self.fb.height - status_height,
+            },
+            FBCON_BG,
+        );
+        self.col = 0;
+        self.row = self.status_rows;
+        self.fg = FBCON_FG;
+    }
+
+    /// Draw the character at col and row, with the foreground and background colors.
+    fn draw_char(&self, col: usize, row: usize, c: u8, (fg, bg): (u32, u32)) {
+        let glyph = font_glyph(c);
+        let x = col * FONT_WIDTH;
+        let y = row * FONT_HEIGHT;
+        for (dy, bits) in glyph.iter().enumerate() {
+            for dx in 0..FONT_WIDTH {
+                let color = match bits >> dx & 1 {
+                    1 => fg,
+                    _ => bg,
+                };
+                self.fb.put_pixel(x + dx, y + dy, color);
+            }
+        }
+    }
+
+    /// Move the console rows up by one row and clear the last row.
+    fn scroll(&mut self) {
+        let top = (self.status_rows + 1) * FONT_HEIGHT;
+        let height = self.rows.saturating_sub(self.status_rows + 1) * FONT_HEIGHT;
+        self.fb.move_rows(top - FONT_HEIGHT, top, height);
+        self.fb.fill_rect(
+            FbRect {
+                x: 0,
+                y: self.rows.saturating_sub(1) * FONT_HEIGHT,
+                width: self.fb.width,
+                height: FONT_HEIGHT,
+            },
+            FBCON_BG,
+        );
+    }
+
+    fn new_line(&mut self) {
+        self.col = 0;
+        if self.row + 1 < self.rows {
+            self.row += 1;
+        } else {
+            self.scroll();
+        }
+    }
+
+    /// Apply a Select Graphic Rendition sequence, only the foreground colors are supported.
+    fn apply_sgr(&mut self) {
+        // ESC [ m is a reset.
+        if self.nparams == 0 {
+            self.fg = FBCON_FG;
+        }
+        for param in &self.params[..self.nparams.min(ESCAPE_MAX_PARAMS)] {
+            match param {
+                0 | 39 => self.fg = FBCON_FG,
+                30..=37 => self.fg = FBCON_PALETTE[(param - 30) as usize],
+                _ => {}
+            }
+        }
+    }
+
+    /// Parse a byte of an escape sequence, the sequence is dropped if not supported.
+    fn escape_byte(&mut self, b: u8) {
+        match (self.escape, b) {
+            (Escape::Start, b'[') => {
+                self.escape = Escape::Csi;
+                self.params = [0; ESCAPE_MAX_PARAMS];
+                self.nparams = 0;
+            }
+            (Escape::Start, _) => self.escape = Escape::None,
+            (Escape::Csi, b'0'..=b'9') => {
+                if self.nparams == 0 {
+                    self.nparams = 1;
+                }
+                if let Some(param) = self.params.get_mut(self.nparams - 1) {
+                    *param = param.saturating_mul(10).saturating_add((b - b'0') as u16);
+                }
+            }
+            // The parameters after ESCAPE_MAX_PARAMS are counted but not stored.
+            (Escape::Csi, b';') => {
+                self.nparams = (self.nparams.max(1) + 1).min(ESCAPE_MAX_PARAMS + 1)
+            }
+            // Final byte of the sequence
+            (Escape::Csi, 0x40..=0x7e) => {
+                if b == b'm' {
+                    self.apply_sgr();
+                }
+                self.escape = Escape::None;
+            }
+            (Escape::Csi, _) => {}
+            (Escape::None, _) => {}
+        }
+    }
+
+    /// Write a character at the cursor, the control characters move the cursor.
+    pub fn put_char(&mut self, c: char) {
+        if self.escape != Escape::None {
+            self.escape_byte(c as u8);
+            return;
+        }
+        match c {
+            '\x1b' => self.escape = Escape::Start,
+            '\n' => self.new_line(),
+            '\r' => self.col = 0,
+            '\t' => {
+                self.col = (self.col / TAB_SIZE + 1) * TAB_SIZE;
+                if self.col >= self.cols {
+                    self.new_line();
+                }
+            }
+            // Backspace
+            '\x08' => self.col = self.col.saturating_sub(1),
+            _ => {
+                if self.col >= self.cols {
+                    self.new_line();
+                }
+                // The characters outside ASCII are drawn with the replacement glyph.
+                let b = match c.is_ascii() {
+                    true => c as u8,
+                    false => 0,
+                };
+                self.draw_char(self.col, self.row, b, (self.fg, FBCON_BG));
+                self.col += 1;
+            }
+        }
+    }
+
+    /// Replace the status panel line with the text, the text is cut at the line width.
+    pub fn set_status(&mut self, line: usize, args: fmt::Arguments) -> Result<(), FbConError> {
+        if line >= self.status_rows {
+            return Err(FbConError::InvalidLine);
+        }
+        let mut writer = StatusWriter {
+            con: self,
+            line,
+            col: 0,
+        };
+        let _ = writer.write_fmt(args);
+        let col = writer.col;
+        self.fb.fill_rect(
+            FbRect {
+                x: col * FONT_WIDTH,
+                y: line * FONT_HEIGHT,
+                width: self.fb.width - col * FONT_WIDTH,
+                height: FONT_HEIGHT,
+            },
+            FBCON_STATUS_BG,
+        );
+        Ok(())
+    }
+}
+
+impl Write for FbCon {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.put_char(c);
+        }
+        Ok(())
+    }
+}
+
+/// Write a status line, the control characters are drawn with the replacement glyph.
+struct StatusWriter<'a> {
+    con: &'a FbCon,
+    line: usize,
+    col: usize,
+}
+
+impl Write for StatusWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if self.col >= self.con.cols {
+                break;
+            }
+            let b = match c.is_ascii() {
+                true => c as u8,
+                false => 0,
+            };
+            self.con
+                .draw_char(self.col, self.line, b, (FBCON_STATUS_FG, FBCON_STATUS_BG));
+            self.col += 1;
+        }
+        Ok(())
+    }
+}
+
+// The console of the display, None without display.
+static mut FBCON: Option<FbCon> = None;
+// Held while drawing on the console. A scroll or a clear writes the whole framebuffer, the
+// interrupts stay enabled, so the console has its own lock like a bus controller.
+static FBCON_LOCK: BusLock = BusLock::init();
+
+/// Take the console lock. A task sleeps until the lock is free, with the interrupts disabled, in
+/// an interrupt handler or a critical section, the lock is only tried.
+fn fbcon_lock() -> bool {
+    match read_mstatus_mie() {
+        0 => FBCON_LOCK.try_lock(),
+        _ => FBCON_LOCK.lock(),
+    }
+}
+
+/// Start the console on the framebuffer, with FBCON_STATUS_LINES lines of status panel. The
+/// print! output is written to the console from now on.
+pub fn fbcon_init(fb: Framebuffer) {
+    let mut con = FbCon::new(fb, FBCON_STATUS_LINES);
+    con.clear();
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    unsafe {
+        FBCON = Some(con)
+    };
+}
+
+/// Write to the console if the print! output is enabled, called by print!.
+/// If the console is being drawn and the lock cannot be waited, the output is only written to the
+/// serial console.
+pub fn fbcon_print(args: fmt::Arguments) {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    if let Some(con) = unsafe { FBCON.as_mut() }
+        && con.print
+        && fbcon_lock()
+    {
+        let _ = con.write_fmt(args);
+        FBCON_LOCK.unlock();
+    }
+}
+
+/// Enable or disable the print! output on the console, the status panel is kept updated.
+pub fn fbcon_set_print(enabled: bool) -> Result<(), FbConError> {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let con = unsafe { FBCON.as_mut() }.ok_or(FbConError::NoDevice)?;
+    con.print = enabled;
+    Ok(())
+}
+
+/// Replace the line of the status panel, like the uptime or the state of a device on a demo
+/// unit.
+pub fn fbcon_status(line: usize, args: fmt::Arguments) -> Result<(), FbConError> {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let con = unsafe { FBCON.as_mut() }.ok_or(FbConError::NoDevice)?;
+    if !fbcon_lock() {
+        return Err(FbConError::Busy);
+    }
+    let res = con.set_status(line, args);
+    FBCON_LOCK.unlock();
+    res
+}
+
+/// Clear the console and the status panel.
+pub fn fbcon_clear() -> Result<(), FbConError> {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let con = unsafe { FBCON.as_mut() }.ok_or(FbConError::NoDevice)?;
+    if !fbcon_lock() {
+        return Err(FbConError::Busy);
+    }
+    con.clear();
+    FBCON_LOCK.unlock();
+    Ok(())
+}
@@ -0,0 +1,166 @@
+// See documentation in `Documentation/kernel/framebuffer.md`
+/*
+File info: Display sub-system and linear framebuffer.
+
+Test coverage: Framebuffer drawing.
+
+Tested:
+- Put, read and fill pixels in a framebuffer in memory.
+- Scroll the pixel rows.
+
+Not tested:
+- A display device.
+
+Reasons:
+- The test machine is not started with a ramfb device.
+
+Tests files:
+- 'src/tests/drivers/display/fbcon.rs'
+*/
+
+use core::{cell::UnsafeCell, ptr};
+
+use ramfb::Ramfb;
+
+use crate::{
+    drivers::model::{DriverClass, driver_probe_class},
+    log,
+    logs::LogLevel,
+};
+
+pub mod fbcon;
+pub mod font;
+pub mod ramfb;
+
+use fbcon::fbcon_init;
+
+// Size of a pixel in bytes, the framebuffers are in XRGB8888, 0x00RRGGBB in a little endian word.
+pub const FRAMEBUFFER_BPP: usize = 4;
+
+/// Linear framebuffer of 32 bits pixels.
+/// addr: address of the first pixel, the top left one.
+/// width, height: size in pixels.
+/// stride: size of a pixel row in bytes, at least width * FRAMEBUFFER_BPP.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Framebuffer {
+    pub addr: usize,
+    pub width: usize,
+    pub height: usize,
+    pub stride: usize,
+}
+
+/// Rectangle in pixels, from its top left pixel.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FbRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Framebuffer {
+    fn pixel_addr(&self, x: usize, y: usize) -> usize {
+        self.addr + y * self.stride + x * FRAMEBUFFER_BPP
+    }
+
+    /// Set the pixel color, the pixels outside the framebuffer are ignored.
+    pub fn put_pixel(&self, x: usize, y: usize, color: u32) {
+        if x < self.width && y < self.height {
+            unsafe { ptr::write_volatile(self.pixel_addr(x, y) as *mut u32, color) };
+        }
+    }
+
+    /// Color of the pixel, 0 outside the framebuffer.
+    pub fn get_pixel(&self, x: usize, y: usize) -> u32 {
+        if x < self.width && y < self.height {
+            return unsafe { ptr::read_volatile(self.pixel_addr(x, y) as *const u32) };
+        }
+        0
+    }
+
+    /// Fill a rectangle, clipped to the framebuffer.
+    pub fn fill_rect(&self, rect: FbRect, color: u32) {
+        for py in rect.y..(rect.y + rect.height).min(self.height) {
+            for px in rect.x..(rect.x + rect.width).min(self.width) {
+                unsafe { ptr::write_volatile(self.pixel_addr(px, py) as *mut u32, color) };
+            }
+        }
+    }
+
+    /// Move height pixel rows from the row src to the row dst, the rows can overlap.
+    pub fn move_rows(&self, dst: usize, src: usize, height: usize) {
+        if dst.max(src) + height > self.height {
+            return;
+        }
+        unsafe {
+            ptr::copy(
+                self.pixel_addr(0, src) as *const u8,
+                self.pixel_addr(0, dst) as *mut u8,
+                height * self.stride,
+            )
+        };
+    }
+}
+
+#[derive(PartialEq)]
+pub enum DisplayDeviceDriver {
+    Ramfb(Ramfb),
+}
+
+#[derive(PartialEq)]
+pub struct DisplayDevice {
+    pub driver: DisplayDeviceDriver,
+}
+
+impl DisplayDevice {
+    pub fn framebuffer(&self) -> Framebuffer {
+        match &self.driver {
+            DisplayDeviceDriver::Ramfb(ramfb) => ramfb.framebuffer,
+        }
+    }
+}
+
+/// Hold the display device, the framebuffer console draws on one display.
+pub struct DisplaySubSystem {
+    pub device: UnsafeCell<Option<DisplayDevice>>,
+}
+
+unsafe impl Sync for DisplaySubSystem {}
+
+impl DisplaySubSystem {
+    pub const fn init() -> Self {
+        DisplaySubSystem {
+            device: UnsafeCell::new(None),
+        }
+    }
+
+    pub fn add_display(&self, new_display: DisplayDevice) {
+        if self.get_display().is_some() {
+            log!(
+                LogLevel::Warn,
+                "Display sub-system: subsystem is full, ignoring registration request"
+            );
+            return;
+        }
+        unsafe { *self.device.get() = Some(new_display) };
+    }
+
+    pub fn get_display(&self) -> Option<&DisplayDevice> {
+        unsafe { (*self.device.get()).as_ref() }
+    }
+}
+
+pub static DISPLAY_SUBSYSTEM: DisplaySubSystem = DisplaySubSystem::init();
+
+/// The display is optional. The framebuffer is a DMA buffer, the sub-system is initialized once
+/// the memory is initialized, after the other sub-systems. The framebuffer console is started on
+/// the display.
+pub fn init_display_subsystem() {
+    driver_probe_class(DriverClass::Display);
+    match DISPLAY_SUBSYSTEM.get_display() {
+        Some(display) => fbcon_init(display.framebuffer()),
+        None => {
+            log!(LogLevel::Info, "No display found.");
+        }
+    }
+}
@@ -0,0 +1,152 @@
+// See documentation in `Documentation/hardware/ramfb.md`
+/*
+File info: QEMU ramfb driver.
+
+Test coverage: Configuration encoding.
+
+Tested:
+- Encode the ramfb configuration.
+
+Not tested:
+- Configure the device.
+
+Reasons:
+- The test machine is not started with a ramfb device.
+
+Tests files:
+- 'src/tests/drivers/display/fbcon.rs'
+*/
+
+use crate::{
+    config::{RAMFB_HEIGHT, RAMFB_WIDTH},
+    drivers::{
+        firmware::{FirmwareError, firmware_find_file, firmware_write_file},
+        model::{Driver, DriverClass, DriverError},
+    },
+    log,
+    logs::LogLevel,
+    mem::{
+        dma::{DmaDirection, dma_alloc, dma_free, dma_share},
+        mem_free_size,
+    },
+    platform::{DeviceType, Devices},
+};
+
+use super::{DISPLAY_SUBSYSTEM, DisplayDevice, DisplayDeviceDriver, FRAMEBUFFER_BPP, Framebuffer};
+
+// fw_cfg file of the device, only present when QEMU is started with `-device ramfb`.
+const RAMFB_FILE: &str = "etc/ramfb";
+// DRM fourcc of XRGB8888, 'XR24'
+pub const RAMFB_FORMAT_XRGB8888: u32 = 0x3432_5258;
+// Size of the configuration: address, fourcc, flags, width, height and stride, all big endian.
+pub const RAMFB_CONFIG_SIZE: usize = 28;
+// The framebuffer is page aligned, like a framebuffer given by a GPU.
+const RAMFB_ALIGN: usize = 4096;
+
+/// Encode the configuration written to the ramfb file.
+pub fn ramfb_config(framebuffer: &Framebuffer) -> [u8; RAMFB_CONFIG_SIZE] {
+    let mut config = [0u8; RAMFB_CONFIG_SIZE];
+    config[0..8].copy_from_slice(&(framebuffer.addr as u64).to_be_bytes());
+    config[8..12].copy_from_slice(&RAMFB_FORMAT_XRGB8888.to_be_bytes());
+    // flags, always 0
+    config[16..20].copy_from_slice(&(framebuffer.width as u32).to_be_bytes());
+    config[20..24].copy_from_slice(&(framebuffer.height as u32).to_be_bytes());
+    config[24..28].copy_from_slice(&(framebuffer.stride as u32).to_be_bytes());
+    config
+}
+
+/// Structure for the QEMU ramfb driver
+/// framebuffer: the framebuffer given to the device, a DMA buffer shared with the device
+/// dma_id: id of the DMA buffer of the framebuffer
+#[derive(PartialEq)]
+pub struct Ramfb {
+    pub framebuffer: Framebuffer,
+    pub dma_id: usize,
+}
+
+/// QEMU ramfb entry of the driver match table. The device has no node, it's a file of the fw_cfg
+/// device, the driver is matched against the fw_cfg node.
+pub struct RamfbDriver;
+
+impl Driver for RamfbDriver {
+    fn name(&self) -> &'static str {
+        "qemu-ramfb"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        &["qemu,fw-cfg-mmio"]
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Firmware
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::Display
+    }
+
+    /// Allocate the framebuffer and give its configuration to the device, the resolution is
+    /// RAMFB_WIDTH and RAMFB_HEIGHT from the config file. QEMU displays the framebuffer once
+    /// configured.
+    /// The probe fails if the framebuffer takes more than half of the free memory, the rest is
+    /// kept for the task stacks.
+    fn probe(&self, _device_info: &Devices) -> Result<(), DriverError> {
+        let file = match firmware_find_file(RAMFB_FILE) {
+            Ok(file) => file,
+            Err(FirmwareError::NotFound) | Err(FirmwareError::NoDevice) => {
+                return Err(DriverError::NoMatch);
+            }
+            Err(_) => return Err(DriverError::InitFailed),
+        };
+        let stride = RAMFB_WIDTH * FRAMEBUFFER_BPP;
+        let size = stride * RAMFB_HEIGHT;
+        let free = mem_free_size();
+        if size > free / 2 {
+            log!(
+                LogLevel::Warn,
+                "QEMU ramfb: the {}x{} framebuffer of {:#x} bytes doesn't fit in the free memory of {:#x} bytes, reduce RAMFB_WIDTH and RAMFB_HEIGHT",
+                RAMFB_WIDTH,
+                RAMFB_HEIGHT,
+                size,
+                free
+            );
+            return Err(DriverError::InitFailed);
+        }
+        // The device reads the framebuffer while the CPU draws in it, the buffer is shared with
+        // the device for the lifetime of the display, the QEMU memory is DMA coherent.
+        let dma_id = dma_alloc(size, RAMFB_ALIGN, DmaDirection::ToDevice)
+            .map_err(|_| DriverError::InitFailed)?;
+        let region = match dma_share(dma_id) {
+            Ok(region) => region,
+            Err(_) => {
+                let _ = dma_free(dma_id);
+                return Err(DriverError::InitFailed);
+            }
+        };
+        let framebuffer = Framebuffer {
+            addr: region.addr,
+            width: RAMFB_WIDTH,
+            height: RAMFB_HEIGHT,
+            stride,
+        };
+        if let Err(e) = firmware_write_file(&file, &ramfb_config(&framebuffer)) {
+            log!(LogLevel::Warn, "QEMU ramfb: failed to configure: {:?}", e);
+            // The framebuffer stays shared with the device, the device may have read the config.
+            return Err(DriverError::InitFailed);
+        }
+        log!(
+            LogLevel::Info,
+            "QEMU ramfb: {}x{} framebuffer at {:#x}",
+            RAMFB_WIDTH,
+            RAMFB_HEIGHT,
+            framebuffer.addr
+        );
+        DISPLAY_SUBSYSTEM.add_display(DisplayDevice {
+            driver: DisplayDeviceDriver::Ramfb(Ramfb {
+                framebuffer,
+                dma_id,
+            }),
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+// See documentation in `Documentation/hardware/soc/riscv/plic.md`
+/*
+File info: External interrupt-controller sub-system.
+
+Test coverage: Source validation.
+
+Tested:
+- Invalid source and priority.
+
+Not tested:
+- Interrupt claim, dispatch and complete.
+
+Reasons:
+- Need an MMIO emulation and a device raising interrupts.
+
+Tests files:
+- 'src/tests/drivers/ext_intc/subsystem.rs'
+*/
+
+use core::cell::UnsafeCell;
+
+use plic::{PLIC_MAX_PRIORITY, Plic};
+
+use crate::{
+    arch::helpers::current_cpu_core,
+    config::CPU_CORE_NUMBER,
+    drivers::model::{DriverClass, driver_probe_class},
+    irq::{IRQ_EXTERNAL, IrqError, irq_enable_ext_sources, irq_ext, irq_handle, irq_register},
+    log,
+    logs::LogLevel,
+};
+
+pub mod plic;
+
+#[derive(PartialEq)]
+pub enum ExtIntCDeviceDriver {
+    Plic(Plic),
+}
+
+#[derive(PartialEq)]
+pub struct ExtIntCDevice {
+    pub driver: ExtIntCDeviceDriver,
+}
+
+impl ExtIntCDevice {
+    /// Number of interrupt sources, valid irq are 1..=ndev.
+    pub fn ndev(&self) -> u32 {
+        match &self.driver {
+            ExtIntCDeviceDriver::Plic(plic) => plic.ndev,
+        }
+    }
+
+    pub fn set_priority(&self, irq: u32, priority: u32) {
+        match &self.driver {
+            ExtIntCDeviceDriver::Plic(plic) => plic.set_priority(irq, priority),
+        }
+    }
+
+    pub fn set_threshold(&self, hart: usize, threshold: u32) {
+        match &self.driver {
+            ExtIntCDeviceDriver::Plic(plic) => plic.set_threshold(hart, threshold),
+        }
+    }
+
+    pub fn set_enable(&self, hart: usize, irq: u32, enable: bool) {
+        match &self.driver {
+            ExtIntCDeviceDriver::Plic(plic) => plic.set_enable(hart, irq, enable),
+        }
+    }
+
+    pub fn claim(&self, hart: usize) -> Option<u32> {
+        match &self.driver {
+            ExtIntCDeviceDriver::Plic(plic) => plic.claim(hart),
+        }
+    }
+
+    pub fn complete(&self, hart: usize, irq: u32) {
+        match &self.driver {
+            ExtIntCDeviceDriver::Plic(plic) => plic.complete(hart, irq),
+        }
+    }
+}
+
+/// All errors that can happen when configuring an external interrupt source.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExtIntCError {
+    // The source is 0, or above the number of sources of the interrupt-controller.
+    InvalidIrq,
+    // The priority must be in 1..=PLIC_MAX_PRIORITY, 0 disable the source.
+    InvalidPriority,
+}
+
+/// Manage the external interrupt-controller. The handlers attached to its sources are kept in the
+/// irq sub-system, the sources are configured when the device is added.
+pub struct ExtIntCSubSystem {
+    device: UnsafeCell<Option<ExtIntCDevice>>,
+}
+
+unsafe impl Sync for ExtIntCSubSystem {}
+
+impl ExtIntCSubSystem {
+    pub const fn init() -> Self {
+        ExtIntCSubSystem {
+            device: UnsafeCell::new(None),
+        }
+    }
+
+    /// Add the external interrupt-controller, only one is supported.
+    pub fn add_ext_intc(&self, new_ext_intc: ExtIntCDevice) {
+        if self.get_ext_intc().is_some() {
+            log!(
+                LogLevel::Warn,
+                "External interrupt-controller sub-system: duplicate device detected, ignoring registration request"
+            );
+            return;
+        }
+        unsafe { *self.device.get() = Some(new_ext_intc) };
+    }
+
+    pub fn get_ext_intc(&self) -> Option<&ExtIntCDevice> {
+        unsafe { (*self.device.get()).as_ref() }
+    }
+
+    fn check_source(&self, source: u32) -> Result<(), ExtIntCError> {
+        if source == 0 {
+            return Err(ExtIntCError::InvalidIrq);
+        }
+        match self.get_ext_intc() {
+            Some(device) if source > device.ndev() => Err(ExtIntCError::InvalidIrq),
+            _ => Ok(()),
+        }
+    }
+
+    /// Set the source priority and enable it on all harts used by the kernel.
+    /// Without device only the parameters are checked, the source is enabled when the device is
+    /// added.
+    pub fn enable_source(&self, source: u32, priority: u32) -> Result<(), ExtIntCError> {
+        self.check_source(source)?;
+        if priority == 0 || priority > PLIC_MAX_PRIORITY {
+            return Err(ExtIntCError::InvalidPriority);
+        }
+        if let Some(device) = self.get_ext_intc() {
+            device.set_priority(source, priority);
+            for hart in 0..CPU_CORE_NUMBER {
+                device.set_enable(hart, source, true);
+            }
+        }
+        Ok(())
+    }
+
+    /// Disable the source on all harts used by the kernel.
+    pub fn disable_source(&self, source: u32) {
+        if self.check_source(source).is_err() {
+            return;
+        }
+        if let Some(device) = self.get_ext_intc() {
+            device.set_priority(source, 0);
+            for hart in 0..CPU_CORE_NUMBER {
+                device.set_enable(hart, source, false);
+            }
+        }
+    }
+
+    /// Handle a machine external interrupt: claim all pending interrupts, dispatch them to the irq
+    /// sub-system and complete them.
+    pub fn handle(&self, hart: usize) {
+        let device = match self.get_ext_intc() {
+            Some(d) => d,
+            None => panic!(
+                "External interrupt raised on CPU#{} without an external interrupt-controller",
+                hart
+            ),
+        };
+        while let Some(source) = device.claim(hart) {
+            if !irq_handle(irq_ext(source)) {
+                // Disable the source to avoid an interrupt storm.
+                log!(
+                    LogLevel::Warn,
+                    "External interrupt-controller sub-system: no handler for source {}, disabling it",
+                    source
+                );
+                device.set_enable(hart, source, false);
+            }
+            device.complete(hart, source);
+        }
+    }
+}
+
+pub static EXT_INTC_SUBSYSTEM: ExtIntCSubSystem = ExtIntCSubSystem::init();
+
+/// Set the priority threshold of a hart, interrupts with a priority less or equal are masked.
+pub fn ext_intc_set_threshold(hart: usize, threshold: u32) {
+    if let Some(device) = EXT_INTC_SUBSYSTEM.get_ext_intc() {
+        device.set_threshold(hart, threshold);
+    }
+}
+
+/// Machine external interrupt handler, registered in the irq sub-system.
+fn ext_intc_irq_handler(_irq: u32, _ctx: usize) {
+    EXT_INTC_SUBSYSTEM.handle(current_cpu_core());
+}
+
+/// The external interrupt-controller is optional, without it only the CPU local interrupts are
+/// available.
+pub fn init_ext_intc_subsystem() {
+    driver_probe_class(DriverClass::ExtIntC);
+    if EXT_INTC_SUBSYSTEM.get_ext_intc().is_none() {
+        log!(
+            LogLevel::Info,
+            "No external interrupt-controller found, external interrupts disabled."
+        );
+        return;
+    }
+    // Don't mask any priority
+    for hart in 0..CPU_CORE_NUMBER {
+        ext_intc_set_threshold(hart, 0);
+    }
+    // Sources registered before the device was found
+    irq_enable_ext_sources();
+    match irq_register(IRQ_EXTERNAL, 1, ext_intc_irq_handler, 0, None) {
+        Ok(_) | Err(IrqError::AlreadyRegistered) => (),
+        Err(e) => panic!(
+            "Failed to register the machine external interrupt handler: {:?}",
+            e
+        ),
+    }
+}
@@ -0,0 +1,169 @@
+// See documentation in `Documentation/hardware/soc/riscv/plic.md`
+/*
+File info: PLIC driver.
+
+Test coverage: None.
+
+Tested:
+
+Not tested:
+- Everything.
+
+Reasons:
+- Testing an interrupt-controller driver need to have an MMIO emulation.
+
+Tests files:
+- 'src/tests/drivers/ext_intc/subsystem.rs'
+*/
+
+use core::ptr;
+
+use crate::{
+    drivers::{
+        DriverRegion,
+        model::{Driver, DriverClass, DriverError},
+    },
+    misc::RawTraitObject,
+    platform::{self, DeviceType, Devices, ExtIntCContext},
+};
+
+use super::{EXT_INTC_SUBSYSTEM, ExtIntCDevice, ExtIntCDeviceDriver};
+
+// Compatible strings of the PLIC, newer QEMU and SoC use the first one.
+const PLIC_COMPATIBLES: &[&str] = &["sifive,plic-1.0.0", "riscv,plic0"];
+
+// Registers offset from `https://github.com/riscv/riscv-plic-spec`
+// Source priority, 4 bytes per source
+const PRIORITY_OFF: usize = 0x0;
+// Enable bits, 0x80 bytes per context, 1 bit per source
+const ENABLE_OFF: usize = 0x2000;
+const ENABLE_CONTEXT_STRIDE: usize = 0x80;
+// Threshold and claim/complete, 0x1000 bytes per context
+const CONTEXT_OFF: usize = 0x20_0000;
+const CONTEXT_STRIDE: usize = 0x1000;
+const CLAIM_OFF: usize = 0x4;
+
+// Max priority supported by the PLIC on QEMU virt and SiFive SoC
+pub const PLIC_MAX_PRIORITY: u32 = 7;
+
+#[derive(PartialEq)]
+pub struct Plic {
+    pub region: DriverRegion,
+    // Number of interrupt sources, source 0 doesn't exist
+    pub ndev: u32,
+    // Machine mode contexts of each hart
+    pub contexts: [ExtIntCContext; 4],
+}
+
+impl Plic {
+    /// Return the machine mode context of the given hart, None if the hart is not connected.
+    fn context(&self, hart: usize) -> Option<usize> {
+        for context in self.contexts {
+            if context.hart as usize == hart {
+                return Some(context.context as usize);
+            }
+        }
+        None
+    }
+
+    /// Set the priority of an interrupt source, 0 disable the source.
+    pub fn set_priority(&self, irq: u32, priority: u32) {
+        let addr = self.region.addr + PRIORITY_OFF + irq as usize * 4;
+        unsafe { ptr::write_volatile(addr as *mut u32, priority) };
+    }
+
+    /// Set the priority threshold of the hart, only interrupts with a priority above the
+    /// threshold are raised.
+    pub fn set_threshold(&self, hart: usize, threshold: u32) {
+        let context = match self.context(hart) {
+            Some(c) => c,
+            None => return,
+        };
+        let addr = self.region.addr + CONTEXT_OFF + context * CONTEXT_STRIDE;
+        unsafe { ptr::write_volatile(addr as *mut u32, threshold) };
+    }
+
+    /// Enable or disable an interrupt source for the hart.
+    pub fn set_enable(&self, hart: usize, irq: u32, enable: bool) {
+        let context = match self.context(hart) {
+            Some(c) => c,
+            None => return,
+        };
+        let addr = self.region.addr
+            + ENABLE_OFF
+            + context * ENABLE_CONTEXT_STRIDE
+            + (irq as usize / 32) * 4;
+        let mask: u32 = 1 << (irq % 32);
+        let value = unsafe { ptr::read_volatile(addr as *const u32) };
+        let value = if enable { value | mask } else { value & !mask };
+        unsafe { ptr::write_volatile(addr as *mut u32, value) };
+    }
+
+    /// Claim the highest priority pending interrupt for the hart, None if there's no pending
+    /// interrupt.
+    pub fn claim(&self, hart: usize) -> Option<u32> {
+        let context = self.context(hart)?;
+        let addr = self.region.addr + CONTEXT_OFF + context * CONTEXT_STRIDE + CLAIM_OFF;
+        match unsafe { ptr::read_volatile(addr as *const u32) } {
+            0 => None,
+            irq => Some(irq),
+        }
+    }
+
+    /// Signal the end of the handling of a claimed interrupt.
+    pub fn complete(&self, hart: usize, irq: u32) {
+        let context = match self.context(hart) {
+            Some(c) => c,
+            None => return,
+        };
+        let addr = self.region.addr + CONTEXT_OFF + context * CONTEXT_STRIDE + CLAIM_OFF;
+        unsafe { ptr::write_volatile(addr as *mut u32, irq) };
+    }
+}
+
+/// PLIC entry of the driver match table.
+pub struct PlicDriver;
+
+impl Driver for PlicDriver {
+    fn name(&self) -> &'static str {
+        "plic"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        PLIC_COMPATIBLES
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::ExtIntC
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::ExtIntC
+    }
+
+    fn probe(&self, device_info: &Devices) -> Result<(), DriverError> {
+        // Only one external interrupt-controller is supported.
+        if EXT_INTC_SUBSYSTEM.get_ext_intc().is_some() {
+            return Err(DriverError::Busy);
+        }
+        // Allow the use of expect, once we got the device asked, the trait should be working and
+        // we should get the trait behind the Option<>
+        #[allow(clippy::expect_used)]
+        let device_info_trait = device_info
+            .info
+            .expect("Error: failed to get device trait behind option.");
+        let raw: RawTraitObject = unsafe { core::mem::transmute(device_info_trait) };
+        let plic_device_ptr = raw.data as *const platform::PlatformExtIntCDevice;
+        let plic_device_ref = unsafe { &*plic_device_ptr };
+        let plic: Plic = Plic {
+            region: device_info.header.device_addr,
+            ndev: plic_device_ref.ndev,
+            contexts: plic_device_ref.contexts,
+        };
+        let device: ExtIntCDevice = ExtIntCDevice {
+            driver: ExtIntCDeviceDriver::Plic(plic),
+        };
+        EXT_INTC_SUBSYSTEM.add_ext_intc(device);
+        Ok(())
+    }
+}
@@ -0,0 +1,306 @@
+// See documentation in `Documentation/hardware/fw_cfg.md`
+/*
+File info: QEMU fw_cfg driver.
+
+Test coverage: File directory entry and DMA access encoding.
+
+Tested:
+- Parse a file directory entry.
+- Encode a DMA access.
+
+Not tested:
+- Listing and reading the files, writing a file.
+
+Reasons:
+- The test machine is not started with a fw_cfg file, the ramfb device is not added.
+
+Tests files:
+- 'src/tests/drivers/firmware/fw_cfg.rs'
+*/
+
+use core::ptr;
+
+use crate::{
+    drivers::{
+        DriverRegion,
+        model::{Driver, DriverClass, DriverError},
+    },
+    log,
+    logs::LogLevel,
+    mem::dma::{DmaDirection, dma_alloc, dma_free, dma_get, dma_to_cpu, dma_to_device},
+    platform::{DeviceType, Devices},
+};
+
+use super::{FIRMWARE_SUBSYSTEM, FirmwareDevice, FirmwareDeviceDriver, FirmwareError};
+
+// Registers offset
+const DATA: usize = 0x00;
+const SELECTOR: usize = 0x08;
+const DMA_ADDR_HI: usize = 0x10;
+const DMA_ADDR_LO: usize = 0x14;
+
+// Selector keys
+const FW_CFG_SIGNATURE: u16 = 0x00;
+const FW_CFG_ID: u16 = 0x01;
+const FW_CFG_FILE_DIR: u16 = 0x19;
+
+// "QEMU" read from the signature key
+const FW_CFG_SIGNATURE_VALUE: [u8; 4] = *b"QEMU";
+// Feature bit of the ID key, the DMA interface is available
+const FW_CFG_ID_DMA: u32 = 1 << 1;
+
+// DMA access control bits
+pub const FW_CFG_DMA_ERROR: u32 = 1 << 0;
+pub const FW_CFG_DMA_SELECT: u32 = 1 << 3;
+pub const FW_CFG_DMA_WRITE: u32 = 1 << 4;
+
+// Size of a DMA access in memory: control, length and address, all big endian.
+pub const FW_CFG_DMA_ACCESS_SIZE: usize = 16;
+// Size of a file directory entry: size, select, reserved and name.
+pub const FW_CFG_FILE_SIZE: usize = 64;
+pub const FW_CFG_FILE_NAME_SIZE: usize = 56;
+// Max number of reads of the control while waiting for a DMA access. QEMU completes the access
+// before returning from the register write, the wait only covers other implementations.
+const DMA_POLL_MAX: usize = 1_000_000;
+
+/// A named file of the fw_cfg file directory.
+/// size: size of the file in bytes.
+/// select: selector key of the file.
+/// name: file name, NUL padded, like "etc/ramfb".
+#[derive(Copy, Clone, PartialEq)]
+pub struct FwCfgFile {
+    pub size: u32,
+    pub select: u16,
+    pub name: [u8; FW_CFG_FILE_NAME_SIZE],
+}
+
+impl FwCfgFile {
+    /// Parse an entry of the file directory, the numbers are big endian.
+    pub fn parse(entry: &[u8; FW_CFG_FILE_SIZE]) -> Self {
+        let mut name = [0u8; FW_CFG_FILE_NAME_SIZE];
+        name.copy_from_slice(&entry[8..]);
+        FwCfgFile {
+            size: u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]),
+            select: u16::from_be_bytes([entry[4], entry[5]]),
+            name,
+        }
+    }
+
+    /// File name without the NUL padding, empty if the name is not valid UTF-8.
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(FW_CFG_FILE_NAME_SIZE);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+}
+
+/// Encode a DMA access, read by the device from memory.
+pub fn fw_cfg_dma_access(control: u32, length: u32, address: u64) -> [u8; FW_CFG_DMA_ACCESS_SIZE] {
+    let mut access = [0u8; FW_CFG_DMA_ACCESS_SIZE];
+    access[0..4].copy_from_slice(&control.to_be_bytes());
+    access[4..8].copy_from_slice(&length.to_be_bytes());
+    access[8..16].copy_from_slice(&address.to_be_bytes());
+    access
+}
+
+/// Structure for the QEMU fw_cfg driver
+/// region: DriverRegion struct to define address memory region to use with the driver and the address size
+/// dma: the DMA interface is available, the files can only be written with it
+#[derive(PartialEq)]
+pub struct FwCfg {
+    pub region: DriverRegion,
+    pub dma: bool,
+}
+
+impl FwCfg {
+    pub const fn new(region: DriverRegion) -> Self {
+        FwCfg { region, dma: false }
+    }
+
+    /// Select the item at key, the data register reads the item from its first byte.
+    fn select(&self, key: u16) {
+        // The selector is big endian on the MMIO interface.
+        unsafe { ptr::write_volatile((self.region.addr + SELECTOR) as *mut u16, key.to_be()) }
+    }
+
+    /// Read the next bytes of the selected item.
+    fn read_data(&self, buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            *b = unsafe { ptr::read_volatile((self.region.addr + DATA) as *const u8) };
+        }
+    }
+
+    /// Read the first bytes of the item at key, the bytes after the end of the item are read
+    /// as 0.
+    pub fn read(&self, key: u16, buf: &mut [u8]) {
+        self.select(key);
+        self.read_data(buf);
+    }
+
+    /// Iterate over the file directory. The directory is read through the data register, no
+    /// other item must be selected until the iteration is done.
+    pub fn files(&self) -> FwCfgFiles<'_> {
+        let mut count = [0u8; 4];
+        self.read(FW_CFG_FILE_DIR, &mut count);
+        FwCfgFiles {
+            fw_cfg: self,
+            remaining: u32::from_be_bytes(count),
+        }
+    }
+
+    /// Find a file in the file directory by its name.
+    pub fn find_file(&self, name: &str) -> Option<FwCfgFile> {
+        self.files().find(|file| file.name() == name)
+    }
+
+    /// Read the whole file in the first bytes of buf, return the size of the file.
+    pub fn read_file(&self, file: &FwCfgFile, buf: &mut [u8]) -> Result<usize, FirmwareError> {
+        let size = file.size as usize;
+        if size > buf.len() {
+            return Err(FirmwareError::InvalidLength);
+        }
+        self.read(file.select, &mut buf[..size]);
+        Ok(size)
+    }
+
+    /// Write data to the file from its first byte, with the DMA interface. The data register is
+    /// read only.
+    pub fn write_file(&self, file: &FwCfgFile, data: &[u8]) -> Result<(), FirmwareError> {
+        if !self.dma {
+            return Err(FirmwareError::NoDma);
+        }
+        if data.len() > file.size as usize {
+            return Err(FirmwareError::InvalidLength);
+        }
+        // The access and the data in the same buffer, the device writes the control back.
+        let id = dma_alloc(
+            FW_CFG_DMA_ACCESS_SIZE + data.len(),
+            16,
+            DmaDirection::Bidirectional,
+        )
+        .map_err(FirmwareError::Dma)?;
+        let res = self.dma_transfer(id, file.select, data);
+        // The buffer is owned by the CPU again once the transfer is done or failed.
+        let _ = dma_free(id);
+        res
+    }
+
+    fn dma_transfer(&self, id: usize, select: u16, data: &[u8]) -> Result<(), FirmwareError> {
+        // The slice is not used once the buffer is given to the device, the device status is read
+        // from the region.
+        let buf = unsafe { dma_get(id) }.map_err(FirmwareError::Dma)?;
+        let addr = buf.as_ptr() as usize;
+        let control = ((select as u32) << 16) | FW_CFG_DMA_SELECT | FW_CFG_DMA_WRITE;
+        buf[..FW_CFG_DMA_ACCESS_SIZE].copy_from_slice(&fw_cfg_dma_access(
+            control,
+            data.len() as u32,
+            (addr + FW_CFG_DMA_ACCESS_SIZE) as u64,
+        ));
+        buf[FW_CFG_DMA_ACCESS_SIZE..].copy_from_slice(data);
+        let region = dma_to_device(id).map_err(FirmwareError::Dma)?;
+        // The address register is big endian, writing the low half starts the access.
+        unsafe {
+            ptr::write_volatile((self.region.addr + DMA_ADDR_HI) as *mut u32, 0);
+            ptr::write_volatile(
+                (self.region.addr + DMA_ADDR_LO) as *mut u32,
+                (region.addr as u32).to_be(),
+            );
+        }
+        // The device clears the control once done, only the error bit can stay set.
+        let mut control = u32::MAX;
+        for _ in 0..DMA_POLL_MAX {
+            control = u32::from_be(unsafe { ptr::read_volatile(region.addr as *const u32) });
+            if control & !FW_CFG_DMA_ERROR == 0 {
+                break;
+            }
+        }
+        dma_to_cpu(id).map_err(FirmwareError::Dma)?;
+        match control {
+            0 => Ok(()),
+            FW_CFG_DMA_ERROR => Err(FirmwareError::DeviceError),
+            _ => Err(FirmwareError::Timeout),
+        }
+    }
+}
+
+/// Iterator over the fw_cfg file directory, from FwCfg::files.
+pub struct FwCfgFiles<'a> {
+    fw_cfg: &'a FwCfg,
+    remaining: u32,
+}
+
+impl Iterator for FwCfgFiles<'_> {
+    type Item = FwCfgFile;
+
+    fn next(&mut self) -> Option<FwCfgFile> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let mut entry = [0u8; FW_CFG_FILE_SIZE];
+        self.fw_cfg.read_data(&mut entry);
+        Some(FwCfgFile::parse(&entry))
+    }
+}
+
+/// QEMU fw_cfg entry of the driver match table.
+pub struct FwCfgDriver;
+
+impl Driver for FwCfgDriver {
+    fn name(&self) -> &'static str {
+        "qemu-fw-cfg"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        &["qemu,fw-cfg-mmio"]
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Firmware
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::Firmware
+    }
+
+    /// Init the fw_cfg device from the platform layer, the signature is checked and the DMA
+    /// interface detected.
+    fn probe(&self, device_info: &Devices) -> Result<(), DriverError> {
+        if device_info.header.device_addr.addr == 0 {
+            panic!(
+                "Encounter a wrong MMIO reg when initializing device. Check the device definition or hardware."
+            );
+        }
+        let mut fw_cfg = FwCfg::new(device_info.header.device_addr);
+        let mut signature = [0u8; 4];
+        fw_cfg.read(FW_CFG_SIGNATURE, &mut signature);
+        if signature != FW_CFG_SIGNATURE_VALUE {
+            return Err(DriverError::InitFailed);
+        }
+        // The ID is the only little endian item.
+        let mut id = [0u8; 4];
+        fw_cfg.read(FW_CFG_ID, &mut id);
+        fw_cfg.dma = u32::from_le_bytes(id) & FW_CFG_ID_DMA != 0;
+        if !fw_cfg.dma {
+            log!(
+                LogLevel::Warn,
+                "QEMU fw_cfg: no DMA interface, the files are read only"
+            );
+        }
+        for file in fw_cfg.files() {
+            log!(
+                LogLevel::Debug,
+                "QEMU fw_cfg: file {} ({} bytes)",
+                file.name(),
+                file.size
+            );
+        }
+        FIRMWARE_SUBSYSTEM.add_firmware(FirmwareDevice {
+            driver: FirmwareDeviceDriver::FwCfg(fw_cfg),
+        });
+        Ok(())
+    }
+}
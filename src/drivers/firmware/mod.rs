@@ -0,0 +1,160 @@
+// See documentation in `Documentation/kernel/subsystems.md`
+/*
+File info: Firmware sub-system, configuration interfaces given by the host or the firmware.
+
+Test coverage: Sub-system without device.
+
+Tested:
+- Sub-system initialized empty.
+
+Not tested:
+- Files of a device.
+
+Reasons:
+- The test machine is not started with a fw_cfg file.
+
+Tests files:
+- 'src/tests/drivers/firmware/fw_cfg.rs'
+*/
+
+use core::cell::UnsafeCell;
+
+use fw_cfg::{FwCfg, FwCfgFile, FwCfgFiles};
+
+use crate::{
+    drivers::model::{DriverClass, driver_probe_class},
+    log,
+    logs::LogLevel,
+    mem::dma::DmaError,
+};
+
+pub mod fw_cfg;
+
+/// All errors that can happen on a firmware request.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FirmwareError {
+    // There's no firmware device.
+    NoDevice,
+    // There's no file with this name.
+    NotFound,
+    // The data is larger than the file, or the buffer smaller than the file.
+    InvalidLength,
+    // The device has no DMA interface, the files cannot be written.
+    NoDma,
+    // The DMA buffer of the request cannot be used.
+    Dma(DmaError),
+    // The device reported an error on the request.
+    DeviceError,
+    // The device didn't complete the request.
+    Timeout,
+}
+
+#[derive(PartialEq)]
+pub enum FirmwareDeviceDriver {
+    FwCfg(FwCfg),
+}
+
+#[derive(PartialEq)]
+pub struct FirmwareDevice {
+    pub driver: FirmwareDeviceDriver,
+}
+
+impl FirmwareDevice {
+    pub fn files(&self) -> FwCfgFiles<'_> {
+        match &self.driver {
+            FirmwareDeviceDriver::FwCfg(fw_cfg) => fw_cfg.files(),
+        }
+    }
+
+    pub fn find_file(&self, name: &str) -> Option<FwCfgFile> {
+        match &self.driver {
+            FirmwareDeviceDriver::FwCfg(fw_cfg) => fw_cfg.find_file(name),
+        }
+    }
+
+    pub fn read_file(&self, file: &FwCfgFile, buf: &mut [u8]) -> Result<usize, FirmwareError> {
+        match &self.driver {
+            FirmwareDeviceDriver::FwCfg(fw_cfg) => fw_cfg.read_file(file, buf),
+        }
+    }
+
+    pub fn write_file(&self, file: &FwCfgFile, data: &[u8]) -> Result<(), FirmwareError> {
+        match &self.driver {
+            FirmwareDeviceDriver::FwCfg(fw_cfg) => fw_cfg.write_file(file, data),
+        }
+    }
+}
+
+/// Hold the firmware device, the host gives one configuration interface.
+pub struct FirmwareSubSystem {
+    pub device: UnsafeCell<Option<FirmwareDevice>>,
+}
+
+unsafe impl Sync for FirmwareSubSystem {}
+
+impl FirmwareSubSystem {
+    pub const fn init() -> Self {
+        FirmwareSubSystem {
+            device: UnsafeCell::new(None),
+        }
+    }
+
+    pub fn add_firmware(&self, new_firmware: FirmwareDevice) {
+        if self.get_firmware().is_some() {
+            log!(
+                LogLevel::Warn,
+                "Firmware sub-system: subsystem is full, ignoring registration request"
+            );
+            return;
+        }
+        unsafe { *self.device.get() = Some(new_firmware) };
+    }
+
+    pub fn get_firmware(&self) -> Option<&FirmwareDevice> {
+        unsafe { (*self.device.get()).as_ref() }
+    }
+}
+
+pub static FIRMWARE_SUBSYSTEM: FirmwareSubSystem = FirmwareSubSystem::init();
+
+/// Find a file of the firmware device by its name.
+pub fn firmware_find_file(name: &str) -> Result<FwCfgFile, FirmwareError> {
+    FIRMWARE_SUBSYSTEM
+        .get_firmware()
+        .ok_or(FirmwareError::NoDevice)?
+        .find_file(name)
+        .ok_or(FirmwareError::NotFound)
+}
+
+/// Iterate over the files of the firmware device.
+pub fn firmware_files() -> Result<FwCfgFiles<'static>, FirmwareError> {
+    Ok(FIRMWARE_SUBSYSTEM
+        .get_firmware()
+        .ok_or(FirmwareError::NoDevice)?
+        .files())
+}
+
+/// Read the file with this name in the first bytes of buf, return the size of the file. The buffer
+/// must hold the whole file.
+pub fn firmware_read_file(name: &str, buf: &mut [u8]) -> Result<usize, FirmwareError> {
+    let firmware = FIRMWARE_SUBSYSTEM
+        .get_firmware()
+        .ok_or(FirmwareError::NoDevice)?;
+    let file = firmware.find_file(name).ok_or(FirmwareError::NotFound)?;
+    firmware.read_file(&file, buf)
+}
+
+/// Write data to the file from its first byte.
+pub fn firmware_write_file(file: &FwCfgFile, data: &[u8]) -> Result<(), FirmwareError> {
+    FIRMWARE_SUBSYSTEM
+        .get_firmware()
+        .ok_or(FirmwareError::NoDevice)?
+        .write_file(file, data)
+}
+
+/// The firmware device is optional, QEMU virt always has a fw_cfg device.
+pub fn init_firmware_subsystem() {
+    if driver_probe_class(DriverClass::Firmware) == 0 {
+        log!(LogLevel::Info, "No firmware device found.");
+    }
+}
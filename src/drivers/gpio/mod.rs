@@ -0,0 +1,266 @@
+// See documentation in `Documentation/kernel/subsystems.md`
+/*
+File info: GPIO sub-system.
+
+Test coverage: Sub-system and pin checks.
+
+Tested:
+- Sub-system initialized empty, add and duplicate device.
+- Invalid pin and missing device errors.
+- Trigger registers selection.
+
+Not tested:
+- Pin read and write, interrupts.
+
+Reasons:
+- QEMU virt has no GPIO controller, a GPIO need an MMIO emulation to be tested.
+
+Tests files:
+- 'src/tests/drivers/gpio/subsystem.rs'
+*/
+
+use crate::{
+    cmdline::kernel_params,
+    config::GPIO_MAX_SIZE,
+    drivers::{
+        DriverRegion,
+        model::{DriverClass, driver_probe_class},
+        pool::DevicePool,
+    },
+    ktime::tick::get_tick,
+    log,
+    logs::LogLevel,
+    task::{primitives::sleep, task_current_pid},
+};
+
+pub mod sifive_gpio;
+
+use sifive_gpio::SifiveGpio;
+
+/// All errors that can happen on a GPIO request.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GpioError {
+    // There's no GPIO controller at this index.
+    NoDevice,
+    // The pin doesn't exist on the controller.
+    InvalidPin,
+    // A task is already waiting on the pin.
+    Busy,
+    // The pin didn't trigger before the timeout.
+    Timeout,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GpioDirection {
+    Input,
+    Output,
+}
+
+/// Event waited on a pin. The edge triggers fire once per edge, the level triggers fire while the
+/// pin stays at the level.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GpioTrigger {
+    RisingEdge,
+    FallingEdge,
+    BothEdges,
+    HighLevel,
+    LowLevel,
+}
+
+#[derive(PartialEq)]
+pub enum GpioDeviceDriver {
+    SifiveGpio(SifiveGpio),
+}
+
+#[derive(PartialEq)]
+pub struct GpioDevice {
+    pub driver: GpioDeviceDriver,
+}
+
+impl GpioDevice {
+    /// Number of pins of the controller.
+    pub fn ngpios(&self) -> u32 {
+        match &self.driver {
+            GpioDeviceDriver::SifiveGpio(sifive_gpio) => sifive_gpio.ngpios,
+        }
+    }
+
+    fn check_pin(&self, pin: u32) -> Result<(), GpioError> {
+        match pin < self.ngpios() {
+            true => Ok(()),
+            false => Err(GpioError::InvalidPin),
+        }
+    }
+
+    pub fn set_direction(&self, pin: u32, direction: GpioDirection) -> Result<(), GpioError> {
+        self.check_pin(pin)?;
+        match &self.driver {
+            GpioDeviceDriver::SifiveGpio(sifive_gpio) => sifive_gpio.set_direction(pin, direction),
+        }
+        Ok(())
+    }
+
+    /// Read the level of the pin, the pin must be an input.
+    pub fn read(&self, pin: u32) -> Result<bool, GpioError> {
+        self.check_pin(pin)?;
+        match &self.driver {
+            GpioDeviceDriver::SifiveGpio(sifive_gpio) => Ok(sifive_gpio.read(pin)),
+        }
+    }
+
+    /// Set the level of the pin, the pin must be an output.
+    pub fn write(&self, pin: u32, value: bool) -> Result<(), GpioError> {
+        self.check_pin(pin)?;
+        match &self.driver {
+            GpioDeviceDriver::SifiveGpio(sifive_gpio) => sifive_gpio.write(pin, value),
+        }
+        Ok(())
+    }
+
+    /// Arm the trigger on the pin, Busy if a trigger is already armed.
+    pub fn arm(&self, pin: u32, trigger: GpioTrigger) -> Result<(), GpioError> {
+        self.check_pin(pin)?;
+        match &self.driver {
+            GpioDeviceDriver::SifiveGpio(sifive_gpio) => sifive_gpio.arm(pin, trigger),
+        }
+    }
+
+    pub fn disarm(&self, pin: u32) {
+        match &self.driver {
+            GpioDeviceDriver::SifiveGpio(sifive_gpio) => sifive_gpio.disarm(pin),
+        }
+    }
+
+    /// Return true once the armed trigger fired, the pin is disarmed. Check the pending
+    /// registers if the pin interrupt is not attached.
+    pub fn fired(&self, pin: u32) -> bool {
+        match &self.driver {
+            GpioDeviceDriver::SifiveGpio(sifive_gpio) => sifive_gpio.fired(pin),
+        }
+    }
+
+    /// Interrupt source id of the pin, 0 if not connected.
+    pub fn irq(&self, pin: u32) -> u32 {
+        match &self.driver {
+            GpioDeviceDriver::SifiveGpio(sifive_gpio) => sifive_gpio.irq(pin),
+        }
+    }
+
+    /// Memory region of the device, used to find the device bound in the device registry.
+    pub fn region(&self) -> DriverRegion {
+        match &self.driver {
+            GpioDeviceDriver::SifiveGpio(sifive_gpio) => sifive_gpio.region,
+        }
+    }
+
+    pub fn interrupt_handler(&self, irq: u32) {
+        match &self.driver {
+            GpioDeviceDriver::SifiveGpio(sifive_gpio) => sifive_gpio.interrupt_handler(irq),
+        }
+    }
+}
+
+/// Define and manage all GPIO controllers.
+pub struct GpioSubSystem {
+    pub devices: DevicePool<GpioDevice, GPIO_MAX_SIZE>,
+}
+
+impl GpioSubSystem {
+    pub const fn init() -> Self {
+        GpioSubSystem {
+            devices: DevicePool::init("GPIO"),
+        }
+    }
+
+    pub fn add_gpio(&self, new_gpio: GpioDevice) -> Option<usize> {
+        self.devices.add(new_gpio)
+    }
+
+    pub fn get_gpio(&self, index: usize) -> Option<&GpioDevice> {
+        self.devices.get(index)
+    }
+
+    pub fn get_gpio_array_size(&self) -> usize {
+        self.devices.size()
+    }
+}
+
+pub static GPIO_SUBSYSTEM: GpioSubSystem = GpioSubSystem::init();
+
+fn gpio_get(index: usize) -> Result<&'static GpioDevice, GpioError> {
+    GPIO_SUBSYSTEM.get_gpio(index).ok_or(GpioError::NoDevice)
+}
+
+pub fn gpio_set_direction(
+    index: usize,
+    pin: u32,
+    direction: GpioDirection,
+) -> Result<(), GpioError> {
+    gpio_get(index)?.set_direction(pin, direction)
+}
+
+pub fn gpio_read(index: usize, pin: u32) -> Result<bool, GpioError> {
+    gpio_get(index)?.read(pin)
+}
+
+pub fn gpio_write(index: usize, pin: u32, value: bool) -> Result<(), GpioError> {
+    gpio_get(index)?.write(pin, value)
+}
+
+/// Block until the trigger fires on the pin of the GPIO controller at index, see
+/// gpio_wait_timeout.
+pub fn gpio_wait(index: usize, pin: u32, trigger: GpioTrigger) -> Result<(), GpioError> {
+    gpio_wait_until(index, pin, trigger, None)
+}
+
+/// Block until the trigger fires on the pin, or until timeout_ms. From a task with the pin
+/// interrupt attached, the task sleeps one tick between each check and the interrupt handler
+/// marks the pin. Else the pending registers are polled.
+/// One task at a time can wait on a pin, Busy if a task is already waiting.
+pub fn gpio_wait_timeout(
+    index: usize,
+    pin: u32,
+    trigger: GpioTrigger,
+    timeout_ms: u64,
+) -> Result<(), GpioError> {
+    let ticks = timeout_ms.div_ceil(kernel_params().tick_duration) as usize;
+    gpio_wait_until(index, pin, trigger, Some(get_tick() + ticks))
+}
+
+fn gpio_wait_until(
+    index: usize,
+    pin: u32,
+    trigger: GpioTrigger,
+    deadline: Option<usize>,
+) -> Result<(), GpioError> {
+    let gpio = gpio_get(index)?;
+    gpio.arm(pin, trigger)?;
+    let by_interrupt = gpio.irq(pin) != 0 && task_current_pid().is_some();
+    loop {
+        if gpio.fired(pin) {
+            return Ok(());
+        }
+        if deadline.is_some_and(|d| get_tick() >= d) {
+            gpio.disarm(pin);
+            return Err(GpioError::Timeout);
+        }
+        if by_interrupt {
+            unsafe { sleep(1) };
+        }
+    }
+}
+
+/// External interrupt handler of all GPIO pins, find the controller raising the irq.
+pub fn gpio_interrupt_handler(irq: u32, _ctx: usize) {
+    for (_, gpio) in GPIO_SUBSYSTEM.devices.iter() {
+        gpio.interrupt_handler(irq);
+    }
+}
+
+/// GPIO controllers are optional, the sub-system stays empty without device.
+pub fn init_gpio_subsystem() {
+    driver_probe_class(DriverClass::Gpio);
+    if GPIO_SUBSYSTEM.get_gpio_array_size() == 0 {
+        log!(LogLevel::Info, "No GPIO controller found.");
+    }
+}
@@ -0,0 +1,276 @@
+// See documentation in `Documentation/hardware/sifive_gpio.md`
+/*
+File info: SiFive GPIO driver.
+
+Test coverage: Trigger registers selection.
+
+Tested:
+- Interrupt enable registers of each trigger.
+
+Not tested:
+- Everything else.
+
+Reasons:
+- Testing a GPIO driver need to have an MMIO emulation.
+
+Tests files:
+- 'src/tests/drivers/gpio/subsystem.rs'
+*/
+
+use core::{cell::Cell, ptr};
+
+use crate::{
+    arch::traps::interrupt::{restore_mstatus_mie, save_and_disable_mstatus_mie},
+    drivers::{
+        DriverRegion,
+        model::{Driver, DriverClass, DriverError},
+    },
+    irq::{IrqError, irq_ext, irq_register},
+    log,
+    logs::LogLevel,
+    misc::RawTraitObject,
+    platform::{self, DeviceType, Devices, GPIO_MAX_PINS},
+};
+
+use super::{
+    GPIO_SUBSYSTEM, GpioDevice, GpioDeviceDriver, GpioDirection, GpioError, GpioTrigger,
+    gpio_interrupt_handler,
+};
+
+// Registers offset, all registers are 4 bytes wide with one bit per pin.
+const INPUT_VAL: usize = 0x00;
+const INPUT_EN: usize = 0x04;
+const OUTPUT_EN: usize = 0x08;
+const OUTPUT_VAL: usize = 0x0c;
+// Each interrupt enable register is followed by its pending register, a pending bit is cleared by
+// writing 1.
+const RISE_IE: usize = 0x18;
+const FALL_IE: usize = 0x20;
+const HIGH_IE: usize = 0x28;
+const LOW_IE: usize = 0x30;
+const IP_OFF: usize = 0x04;
+const ALL_IE: [usize; 4] = [RISE_IE, FALL_IE, HIGH_IE, LOW_IE];
+
+/// Interrupt enable registers to set for the trigger.
+pub fn trigger_ie_offsets(trigger: GpioTrigger) -> &'static [usize] {
+    match trigger {
+        GpioTrigger::RisingEdge => &[RISE_IE],
+        GpioTrigger::FallingEdge => &[FALL_IE],
+        GpioTrigger::BothEdges => &[RISE_IE, FALL_IE],
+        GpioTrigger::HighLevel => &[HIGH_IE],
+        GpioTrigger::LowLevel => &[LOW_IE],
+    }
+}
+
+/// Structure for the SiFive GPIO driver
+/// region: DriverRegion struct to define address memory region to use with the driver and the address size
+/// irqs: interrupt source id of each pin on the external interrupt controller, 0 if none or if the
+/// interrupt could not be attached, the pin is then polled
+#[derive(PartialEq)]
+pub struct SifiveGpio {
+    pub region: DriverRegion,
+    pub ngpios: u32,
+    pub irqs: [u32; GPIO_MAX_PINS],
+    // Pins with a trigger armed, one bit per pin
+    armed: Cell<u32>,
+    // Pins whose trigger fired, cleared when the waiting task sees it
+    fired: Cell<u32>,
+}
+
+impl SifiveGpio {
+    pub fn new(region: DriverRegion, ngpios: u32, irqs: [u32; GPIO_MAX_PINS]) -> Self {
+        SifiveGpio {
+            region,
+            ngpios,
+            irqs,
+            armed: Cell::new(0),
+            fired: Cell::new(0),
+        }
+    }
+
+    pub fn set_direction(&self, pin: u32, direction: GpioDirection) {
+        let mie = save_and_disable_mstatus_mie();
+        // The input stays enabled on an output, reading the pin return the driven level.
+        self.set_bit(INPUT_EN, pin, true);
+        self.set_bit(OUTPUT_EN, pin, direction == GpioDirection::Output);
+        restore_mstatus_mie(mie);
+    }
+
+    pub fn read(&self, pin: u32) -> bool {
+        self.read_reg(INPUT_VAL) & (1 << pin) != 0
+    }
+
+    pub fn write(&self, pin: u32, value: bool) {
+        let mie = save_and_disable_mstatus_mie();
+        self.set_bit(OUTPUT_VAL, pin, value);
+        restore_mstatus_mie(mie);
+    }
+
+    /// Clear the old events of the pin and enable the interrupts of the trigger.
+    pub fn arm(&self, pin: u32, trigger: GpioTrigger) -> Result<(), GpioError> {
+        let mie = save_and_disable_mstatus_mie();
+        if self.armed.get() & (1 << pin) != 0 {
+            restore_mstatus_mie(mie);
+            return Err(GpioError::Busy);
+        }
+        self.armed.set(self.armed.get() | (1 << pin));
+        self.fired.set(self.fired.get() & !(1 << pin));
+        for ie in trigger_ie_offsets(trigger) {
+            self.write_reg(ie + IP_OFF, 1 << pin);
+            self.set_bit(*ie, pin, true);
+        }
+        restore_mstatus_mie(mie);
+        Ok(())
+    }
+
+    /// Disable the interrupts of the pin and clear its pending events.
+    pub fn disarm(&self, pin: u32) {
+        let mie = save_and_disable_mstatus_mie();
+        self.stop(pin);
+        self.armed.set(self.armed.get() & !(1 << pin));
+        restore_mstatus_mie(mie);
+    }
+
+    /// Return true once the trigger of the pin fired, the pin is disarmed.
+    pub fn fired(&self, pin: u32) -> bool {
+        let mie = save_and_disable_mstatus_mie();
+        if self.irq(pin) == 0 {
+            self.check_pending(pin);
+        }
+        let fired = self.fired.get() & (1 << pin) != 0;
+        if fired {
+            self.fired.set(self.fired.get() & !(1 << pin));
+            self.armed.set(self.armed.get() & !(1 << pin));
+        }
+        restore_mstatus_mie(mie);
+        fired
+    }
+
+    pub fn irq(&self, pin: u32) -> u32 {
+        match self.irqs.get(pin as usize) {
+            Some(irq) => *irq,
+            None => 0,
+        }
+    }
+
+    /// Interrupt handler of the pins connected to irq. The interrupts of a fired pin are disabled,
+    /// a level interrupt would be raised again while the pin stays at the level.
+    /// Must be called from the external interrupt handler when a pin irq is raised.
+    pub fn interrupt_handler(&self, irq: u32) {
+        for pin in 0..self.ngpios.min(GPIO_MAX_PINS as u32) {
+            if self.irqs[pin as usize] != 0 && irq_ext(self.irqs[pin as usize]) == irq {
+                self.check_pending(pin);
+            }
+        }
+    }
+
+    /// Mark the armed pin as fired if one of its enabled interrupts is pending.
+    fn check_pending(&self, pin: u32) {
+        if self.armed.get() & (1 << pin) == 0 {
+            return;
+        }
+        let pending = ALL_IE
+            .iter()
+            .any(|ie| self.read_reg(*ie) & self.read_reg(ie + IP_OFF) & (1 << pin) != 0);
+        if pending {
+            self.stop(pin);
+            self.fired.set(self.fired.get() | (1 << pin));
+        }
+    }
+
+    fn stop(&self, pin: u32) {
+        for ie in ALL_IE {
+            self.set_bit(ie, pin, false);
+            self.write_reg(ie + IP_OFF, 1 << pin);
+        }
+    }
+
+    fn set_bit(&self, off: usize, pin: u32, value: bool) {
+        let reg = self.read_reg(off);
+        match value {
+            true => self.write_reg(off, reg | (1 << pin)),
+            false => self.write_reg(off, reg & !(1 << pin)),
+        }
+    }
+
+    fn read_reg(&self, off: usize) -> u32 {
+        unsafe { ptr::read_volatile((self.region.addr + off) as *const u32) }
+    }
+
+    fn write_reg(&self, off: usize, value: u32) {
+        unsafe { ptr::write_volatile((self.region.addr + off) as *mut u32, value) }
+    }
+}
+
+/// SiFive GPIO entry of the driver match table.
+pub struct SifiveGpioDriver;
+
+impl Driver for SifiveGpioDriver {
+    fn name(&self) -> &'static str {
+        "sifive-gpio"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        &["sifive,gpio0"]
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Gpio
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::Gpio
+    }
+
+    /// Init a new SiFive GPIO from the platform layer, all pins are inputs with their interrupts
+    /// disabled.
+    fn probe(&self, device_info: &Devices) -> Result<(), DriverError> {
+        if device_info.header.device_addr.addr == 0 {
+            panic!(
+                "Encounter a wrong MMIO reg when initializing device. Check the device definition or hardware."
+            );
+        }
+        // Allow the use of expect, once we got the device asked, the trait should be working and
+        // we should get the trait behind the Option<>
+        #[allow(clippy::expect_used)]
+        let device_info_trait = device_info
+            .info
+            .expect("Error: failed to get device trait behind option.");
+        let raw: RawTraitObject = unsafe { core::mem::transmute(device_info_trait) };
+        let gpio_device_ptr = raw.data as *const platform::PlatformGpioDevice;
+        let gpio_device_ref = unsafe { &*gpio_device_ptr };
+        let mut gpio = SifiveGpio::new(
+            device_info.header.device_addr,
+            gpio_device_ref.ngpios,
+            gpio_device_ref.irqs,
+        );
+        for ie in ALL_IE {
+            gpio.write_reg(ie, 0);
+            gpio.write_reg(ie + IP_OFF, u32::MAX);
+        }
+        for pin in 0..gpio.ngpios {
+            let irq = gpio.irq(pin);
+            if irq != 0
+                && let Err(e) = irq_register(irq_ext(irq), 1, gpio_interrupt_handler, 0, None)
+                && e != IrqError::AlreadyRegistered
+            {
+                log!(
+                    LogLevel::Warn,
+                    "SiFive GPIO: failed to attach the interrupt of pin {}: {:?}, pin polled only",
+                    pin,
+                    e
+                );
+                // Without handler the pin must be polled, fired and gpio_wait_until only poll the
+                // pins without irq.
+                gpio.irqs[pin as usize] = 0;
+            }
+        }
+        let device = GpioDevice {
+            driver: GpioDeviceDriver::SifiveGpio(gpio),
+        };
+        GPIO_SUBSYSTEM
+            .add_gpio(device)
+            .map(|_| ())
+            .ok_or(DriverError::InitFailed)
+    }
+}
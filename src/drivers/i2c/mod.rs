@@ -0,0 +1,52 @@
+// See documentation in `Documentation/kernel/bus.md`
+/*
+File info: I2C bus trait.
+
+Test coverage: None.
+
+Tested:
+
+Not tested:
+- The I2C bus trait.
+
+Reasons:
+- There's no I2C driver yet.
+
+Tests files:
+*/
+
+/// All errors that can happen on an I2C request.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum I2cError {
+    // There's no I2C controller at this index.
+    NoDevice,
+    // The address is above 7 bits, 10 bits addresses are not supported.
+    InvalidAddress,
+    // The target didn't acknowledge its address or a byte.
+    Nack,
+    // Another controller took the bus during the transfer.
+    ArbitrationLost,
+    // The bus is locked and the lock cannot be waited.
+    Busy,
+    // The controller didn't complete the transfer.
+    Timeout,
+}
+
+/// Common interface of the I2C controllers. A target is selected by its 7 bits address, there's
+/// no chip select.
+/// Like the SPI controllers, an I2C sub-system stores a BusLock with each controller, the bus
+/// must be locked by the caller for the whole transaction.
+pub trait I2cBus {
+    /// Set the bus clock for the next transfers, 100kHz and 400kHz are the standard rates.
+    fn set_frequency(&self, freq_hz: u32) -> Result<(), I2cError>;
+
+    /// Write tx to the target, with a start and a stop condition.
+    fn write(&self, addr: u8, tx: &[u8]) -> Result<(), I2cError>;
+
+    /// Read rx from the target, with a start and a stop condition.
+    fn read(&self, addr: u8, rx: &mut [u8]) -> Result<(), I2cError>;
+
+    /// Write tx then read rx with a repeated start, like a register address followed by its
+    /// value. The bus is not released between both.
+    fn write_read(&self, addr: u8, tx: &[u8], rx: &mut [u8]) -> Result<(), I2cError>;
+}
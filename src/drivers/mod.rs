@@ -1,11 +1,19 @@
 use core::ptr;
 
 use arrayvec::ArrayVec;
+use block::init_block_subsystem;
 use cpu_intc::init_cpu_intc_subsystem;
+use ext_intc::init_ext_intc_subsystem;
+use firmware::init_firmware_subsystem;
+use gpio::init_gpio_subsystem;
+use power::init_power_subsystem;
+use rtc::init_rtc_subsystem;
 use serials::init_serial_subsystem;
+use spi::init_spi_subsystem;
 use timer::init_timer_subsystem;
 
 use crate::{
+    cmdline::cmdline_init,
     kprint, log,
     logs::LogLevel,
     platform::fdt::{
@@ -23,9 +31,48 @@ pub mod timer;
 // Module for cpu core interrupt-controller
 pub mod cpu_intc;
 
+// Module for external interrupt-controller
+pub mod ext_intc;
+
 // Module for cpu frequency
 pub mod cpufreq;
 
+// Module for virtio-mmio transport
+pub mod virtio;
+
+// Module for block devices
+pub mod block;
+
+// Module for real time clocks
+pub mod rtc;
+
+// Module for GPIO controllers
+pub mod gpio;
+
+// Module for the bus lock shared by the SPI and I2C controllers
+pub mod bus;
+
+// Module for SPI controllers
+pub mod spi;
+
+// Module for the I2C bus trait
+pub mod i2c;
+
+// Module for poweroff and reboot devices
+pub mod power;
+
+// Module for the configuration interfaces given by the host or the firmware
+pub mod firmware;
+
+// Module for displays and the framebuffer console
+pub mod display;
+
+// Module for the device pool shared by the sub-systems
+pub mod pool;
+
+// Module for the driver model, match table and device registry
+pub mod model;
+
 /// Public structure used to define device region in memory.
 /// addr: the address to use in drivers.
 /// size: the size of the address.
@@ -36,7 +83,18 @@ pub struct DriverRegion {
 }
 
 impl DriverRegion {
+    /// Region of the node, the last region if the node has multiple regions in reg.
     pub fn new(node: &FdtNode) -> Self {
+        DriverRegion::parse(node, None)
+    }
+
+    /// Region at index in the reg property, for nodes with multiple regions like a SPI flash
+    /// controller with its memory mapped flash.
+    pub fn new_nth(node: &FdtNode, index: usize) -> Self {
+        DriverRegion::parse(node, Some(index))
+    }
+
+    fn parse(node: &FdtNode, index: Option<usize>) -> Self {
         // Get address and size cells
         // Allow use of expect, those node when used should always have the #address-cells and
         // #size-cells props
@@ -67,7 +125,10 @@ impl DriverRegion {
         let reg_size = address_cells_val + size_cells_val;
         // Init a new DriverRegion
         let mut device_addr: DriverRegion = DriverRegion { addr: 0, size: 0 };
-        for addr in reg_buff.chunks(reg_size as usize) {
+        for (i, addr) in reg_buff.chunks(reg_size as usize).enumerate() {
+            if index.is_some_and(|index| i > index) {
+                break;
+            }
             // Build addr from chunk
             let mut device_addr_build: u64 = 0;
             for i in 0..address_cells_val {
@@ -88,8 +149,8 @@ impl DriverRegion {
     }
 }
 
-/// Init all device drivers sub-system, call all init function from device driver structure. The init function
-/// will automatically save the new initialized device drivers it's own sub system static array.
+/// Init all device drivers sub-system, each sub-system probe the drivers of its class from the driver
+/// match table. The drivers save the new initialized devices in their own sub-system pool.
 pub fn init_subsystems() {
     kprint!("Serial sub-system initializing...\n");
     init_serial_subsystem();
@@ -97,6 +158,22 @@ pub fn init_subsystems() {
         LogLevel::Debug,
         "Serial sub-system successfully initialized."
     );
+    // Initialized early, a panic stop the machine once a power device is registered.
+    log!(LogLevel::Debug, "Power sub-system initializing...");
+    init_power_subsystem();
+    log!(
+        LogLevel::Debug,
+        "Power sub-system successfully initialized."
+    );
+    // Initialized early, the kernel command line given by the host is read from the firmware
+    // device, the parameters apply to the next sub-systems.
+    log!(LogLevel::Debug, "Firmware sub-system initializing...");
+    init_firmware_subsystem();
+    log!(
+        LogLevel::Debug,
+        "Firmware sub-system successfully initialized."
+    );
+    cmdline_init();
     log!(
         LogLevel::Debug,
         "Cpu interrupt controller sub-system initializing..."
@@ -106,10 +183,34 @@ pub fn init_subsystems() {
         LogLevel::Debug,
         "Cpu interrupt-controller sub-system successfully initialized."
     );
+    log!(
+        LogLevel::Debug,
+        "External interrupt-controller sub-system initializing..."
+    );
+    init_ext_intc_subsystem();
+    log!(
+        LogLevel::Debug,
+        "External interrupt-controller sub-system successfully initialized."
+    );
     log!(LogLevel::Debug, "Timer sub-system initializing...");
     init_timer_subsystem();
     log!(
         LogLevel::Debug,
         "Timer sub-system successfully initialized."
     );
+    log!(LogLevel::Debug, "Block sub-system initializing...");
+    init_block_subsystem();
+    log!(
+        LogLevel::Debug,
+        "Block sub-system successfully initialized."
+    );
+    log!(LogLevel::Debug, "RTC sub-system initializing...");
+    init_rtc_subsystem();
+    log!(LogLevel::Debug, "RTC sub-system successfully initialized.");
+    log!(LogLevel::Debug, "GPIO sub-system initializing...");
+    init_gpio_subsystem();
+    log!(LogLevel::Debug, "GPIO sub-system successfully initialized.");
+    log!(LogLevel::Debug, "SPI sub-system initializing...");
+    init_spi_subsystem();
+    log!(LogLevel::Debug, "SPI sub-system successfully initialized.");
 }
@@ -0,0 +1,294 @@
+// See documentation in `Documentation/kernel/driver_model.md`
+/*
+File info: Driver model, driver match table and device registry.
+
+Test coverage: Match table and registry.
+
+Tested:
+- Match table order and compatible strings.
+- Registry enumeration after the sub-systems initialization.
+- Bound device detection.
+- Removing a device without remove support.
+
+Not tested:
+- Probing and removing a real device.
+- Unbinding a device when the registry is full.
+
+Reasons:
+- The devices are already probed at boot, probing them again would reset the hardware used by
+  the test framework.
+
+Tests files:
+- 'src/tests/drivers/model.rs'
+*/
+
+use crate::{
+    config::DEVICE_REGISTRY_MAX_SIZE,
+    drivers::{
+        DriverRegion,
+        block::virtio_blk::VirtioBlkDriver,
+        cpu_intc::riscv_cpu_intc::RiscVCpuIntcDriver,
+        display::ramfb::RamfbDriver,
+        ext_intc::plic::PlicDriver,
+        firmware::fw_cfg::FwCfgDriver,
+        gpio::sifive_gpio::SifiveGpioDriver,
+        pool::DevicePool,
+        power::{
+            sifive_test::SifiveTestDriver,
+            syscon::{SysconPoweroffDriver, SysconRebootDriver},
+        },
+        rtc::goldfish::GoldfishRtcDriver,
+        serials::{ns16550a::Ns16550Driver, virtio_console::VirtioConsoleDriver},
+        spi::sifive_spi::SifiveSpiDriver,
+        timer::clint0::Clint0Driver,
+    },
+    log,
+    logs::LogLevel,
+    platform::{DeviceType, Devices, platform_get_device_info_nth},
+    trace,
+};
+
+/// All errors that can happen when probing or removing a device.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DriverError {
+    // The device is not handled by the driver, the next driver of the table is tried.
+    NoMatch,
+    // The driver failed to initialize the device.
+    InitFailed,
+    // The device is in use and cannot be removed.
+    Busy,
+    // The driver doesn't support removing its devices.
+    NotRemovable,
+    // There's no device at this index in the registry.
+    NotRegistered,
+}
+
+/// Sub-system a driver registers its devices in, the drivers are probed by class in the
+/// sub-systems initialization order.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DriverClass {
+    Serial,
+    Power,
+    CpuIntC,
+    ExtIntC,
+    Timer,
+    Block,
+    Rtc,
+    Gpio,
+    Spi,
+    Firmware,
+    Display,
+}
+
+/// Common interface of all drivers. A driver is matched against the devices of the platform by
+/// its compatible strings, each matching device is given to probe.
+/// The driver still registers the device in its sub-system, the sub-systems keep their enum of
+/// drivers.
+pub trait Driver: Sync {
+    /// Driver name, used in the logs and the registry.
+    fn name(&self) -> &'static str;
+
+    /// Compatible strings matched against the platform devices, the first match is used.
+    fn compatible(&self) -> &'static [&'static str];
+
+    /// Type of the platform device information given to probe.
+    fn device_type(&self) -> DeviceType;
+
+    fn class(&self) -> DriverClass;
+
+    /// Initialize the device and register it in the sub-system of the driver.
+    fn probe(&self, device: &Devices) -> Result<(), DriverError>;
+
+    /// Remove the device from the sub-system of the driver and stop it.
+    fn remove(&self, _device: &RegisteredDevice) -> Result<(), DriverError> {
+        Err(DriverError::NotRemovable)
+    }
+}
+
+/// Match table of all drivers. Inside a class, the drivers are probed in the table order, the
+/// first drivers are preferred.
+pub static DRIVER_TABLE: &[&dyn Driver] = &[
+    &Ns16550Driver,
+    &VirtioConsoleDriver,
+    &SifiveTestDriver,
+    &SysconPoweroffDriver,
+    &SysconRebootDriver,
+    &RiscVCpuIntcDriver,
+    &PlicDriver,
+    &Clint0Driver,
+    &VirtioBlkDriver,
+    &GoldfishRtcDriver,
+    &SifiveGpioDriver,
+    &SifiveSpiDriver,
+    &FwCfgDriver,
+    &RamfbDriver,
+];
+
+/// A device bound to a driver.
+/// compatible: the compatible string the device matched.
+/// region: the memory region of the device, addr is 0 for devices without region.
+/// nth: index of the device among the devices with the same compatible.
+#[derive(Copy, Clone)]
+pub struct RegisteredDevice {
+    pub driver: &'static dyn Driver,
+    pub compatible: &'static str,
+    pub region: DriverRegion,
+    pub nth: usize,
+}
+
+impl RegisteredDevice {
+    /// A device is bound once per driver. Devices without region are identified by their
+    /// compatible string and index.
+    fn is_same_device(
+        &self,
+        driver: &str,
+        compatible: &str,
+        region: DriverRegion,
+        nth: usize,
+    ) -> bool {
+        if self.driver.name() != driver {
+            return false;
+        }
+        match region.addr {
+            0 => self.compatible == compatible && self.nth == nth,
+            _ => self.region == region,
+        }
+    }
+}
+
+impl PartialEq for RegisteredDevice {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_same_device(
+            other.driver.name(),
+            other.compatible,
+            other.region,
+            other.nth,
+        )
+    }
+}
+
+/// All devices bound to a driver, in probe order.
+pub static DEVICE_REGISTRY: DevicePool<RegisteredDevice, DEVICE_REGISTRY_MAX_SIZE> =
+    DevicePool::init("Device registry");
+
+/// Check if the device is already bound to the driver.
+pub fn device_is_bound(driver: &str, compatible: &str, region: DriverRegion, nth: usize) -> bool {
+    DEVICE_REGISTRY
+        .position(|each| each.is_same_device(driver, compatible, region, nth))
+        .is_some()
+}
+
+/// Probe the devices matching the drivers of the class, and register the bound devices.
+/// Return the number of devices bound.
+pub fn driver_probe_class(class: DriverClass) -> usize {
+    let mut bound: usize = 0;
+    for driver in DRIVER_TABLE.iter().filter(|d| d.class() == class) {
+        for compatible in driver.compatible() {
+            let mut nth: usize = 0;
+            while let Some(device) =
+                platform_get_device_info_nth(compatible, driver.device_type(), nth)
+            {
+                let region = device.header.device_addr;
+                if !device_is_bound(driver.name(), compatible, region, nth)
+                    && driver_probe_device(*driver, &device, compatible, nth)
+                {
+                    bound += 1;
+                }
+                nth += 1;
+            }
+        }
+    }
+    bound
+}
+
+fn driver_probe_device(
+    driver: &'static dyn Driver,
+    device: &Devices,
+    compatible: &'static str,
+    nth: usize,
+) -> bool {
+    match driver.probe(device) {
+        Ok(()) => {
+            let registered = RegisteredDevice {
+                driver,
+                compatible,
+                region: device.header.device_addr,
+                nth,
+            };
+            if DEVICE_REGISTRY.add(registered).is_none() {
+                // A bound device outside the registry can't be removed and would be probed again,
+                // unbind it.
+                let removed = driver.remove(&registered);
+                log!(
+                    LogLevel::Error,
+                    "Driver {}: failed to register device {} at {:#x}, raise DEVICE_REGISTRY_MAX_SIZE, unbind: {:?}",
+                    driver.name(),
+                    compatible,
+                    registered.region.addr,
+                    removed
+                );
+                return false;
+            }
+            log!(
+                LogLevel::Debug,
+                "Driver {}: bound device {} at {:#x}",
+                driver.name(),
+                compatible,
+                registered.region.addr
+            );
+            true
+        }
+        Err(DriverError::NoMatch) => {
+            trace!(
+                "Driver {}: no match for device {} at {:#x}",
+                driver.name(),
+                compatible,
+                device.header.device_addr.addr
+            );
+            false
+        }
+        Err(e) => {
+            log!(
+                LogLevel::Warn,
+                "Driver {}: failed to probe device {} at {:#x}: {:?}",
+                driver.name(),
+                compatible,
+                device.header.device_addr.addr,
+                e
+            );
+            false
+        }
+    }
+}
+
+/// Number of devices bound to a driver.
+pub fn device_registry_size() -> usize {
+    DEVICE_REGISTRY.size()
+}
+
+/// Get the registered device at index, the index doesn't change until the device is removed.
+pub fn device_registry_get(index: usize) -> Option<&'static RegisteredDevice> {
+    DEVICE_REGISTRY.get(index)
+}
+
+/// Iterate over the registered devices with their index.
+pub fn device_registry_iter() -> impl Iterator<Item = (usize, &'static RegisteredDevice)> {
+    DEVICE_REGISTRY.iter()
+}
+
+/// Remove the registered device at index from its sub-system and from the registry.
+pub fn device_remove(index: usize) -> Result<(), DriverError> {
+    let device = *DEVICE_REGISTRY
+        .get(index)
+        .ok_or(DriverError::NotRegistered)?;
+    device.driver.remove(&device)?;
+    DEVICE_REGISTRY.remove(index);
+    log!(
+        LogLevel::Debug,
+        "Driver {}: removed device {} at {:#x}",
+        device.driver.name(),
+        device.compatible,
+        device.region.addr
+    );
+    Ok(())
+}
@@ -0,0 +1,144 @@
+// See documentation in `Documentation/kernel/driver_model.md`
+/*
+File info: Device pool shared by the sub-systems.
+
+Test coverage: All methods.
+
+Tested:
+- Add, get, remove and size.
+- Duplicate device and full pool.
+- Insert at a given index.
+
+Not tested:
+
+Reasons:
+
+Tests files:
+- 'src/tests/drivers/pool.rs'
+*/
+
+use core::cell::UnsafeCell;
+
+use crate::{log, logs::LogLevel};
+
+/// Fixed size pool of devices, used by the sub-systems to store their devices.
+/// A device is identified by its index in the pool, the index doesn't change until the device is
+/// removed.
+/// name: name of the sub-system, used in the warnings.
+pub struct DevicePool<T, const N: usize> {
+    devices: [UnsafeCell<Option<T>>; N],
+    name: &'static str,
+}
+
+// The kernel is single-threaded, the sub-systems disable the interrupts when needed.
+unsafe impl<T, const N: usize> Sync for DevicePool<T, N> {}
+
+impl<T: PartialEq, const N: usize> DevicePool<T, N> {
+    pub const fn init(name: &'static str) -> Self {
+        DevicePool {
+            devices: [const { UnsafeCell::new(None) }; N],
+            name,
+        }
+    }
+
+    /// Add the device at the first free index and return the index.
+    /// Return None and log a warning if the pool is full or if the device is already in the pool.
+    pub fn add(&self, new_device: T) -> Option<usize> {
+        if self.contains(&new_device) {
+            log!(
+                LogLevel::Warn,
+                "{} sub-system: duplicate device detected, ignoring registration request",
+                self.name
+            );
+            return None;
+        }
+        let index = match (0..N).find(|i| self.get(*i).is_none()) {
+            Some(i) => i,
+            None => {
+                log!(
+                    LogLevel::Warn,
+                    "{} sub-system: subsystem is full, ignoring registration request",
+                    self.name
+                );
+                return None;
+            }
+        };
+        unsafe { *self.devices[index].get() = Some(new_device) };
+        Some(index)
+    }
+
+    /// Add the device at the given index, for the sub-systems using a fixed index like a core id.
+    /// Return false and log a warning if the index is out of the pool or already used.
+    pub fn insert(&self, index: usize, new_device: T) -> bool {
+        if index >= N {
+            log!(
+                LogLevel::Warn,
+                "{} sub-system: index {} out of the pool, ignoring registration request",
+                self.name,
+                index
+            );
+            return false;
+        }
+        if self.get(index).is_some() || self.contains(&new_device) {
+            log!(
+                LogLevel::Warn,
+                "{} sub-system: duplicate device detected, ignoring registration request",
+                self.name
+            );
+            return false;
+        }
+        unsafe { *self.devices[index].get() = Some(new_device) };
+        true
+    }
+
+    /// Remove the device at index from the pool and return it.
+    pub fn remove(&self, index: usize) -> Option<T> {
+        if index >= N {
+            return None;
+        }
+        unsafe { (*self.devices[index].get()).take() }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= N {
+            return None;
+        }
+        unsafe { (*self.devices[index].get()).as_ref() }
+    }
+
+    /// Mutable access to the device at index.
+    ///
+    /// # Safety
+    ///
+    /// - No other reference to this device must be used at the same time.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn get_mut(&self, index: usize) -> Option<&mut T> {
+        if index >= N {
+            return None;
+        }
+        unsafe { (*self.devices[index].get()).as_mut() }
+    }
+
+    /// Return the index of the first device matching f.
+    pub fn position<F: Fn(&T) -> bool>(&self, f: F) -> Option<usize> {
+        (0..N).find(|i| self.get(*i).is_some_and(&f))
+    }
+
+    pub fn contains(&self, device: &T) -> bool {
+        self.position(|each| each == device).is_some()
+    }
+
+    /// Iterate over the devices with their index.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        (0..N).filter_map(|i| self.get(i).map(|device| (i, device)))
+    }
+
+    /// Number of devices in the pool.
+    pub fn size(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
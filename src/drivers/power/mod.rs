@@ -0,0 +1,119 @@
+// See documentation in `Documentation/kernel/subsystems.md`
+/*
+File info: Power sub-system, poweroff and reboot devices.
+
+Test coverage: Sub-system without device.
+
+Tested:
+- Sub-system initialized empty.
+
+Not tested:
+- Poweroff and reboot.
+
+Reasons:
+- Poweroff and reboot stop or reset QEMU.
+
+Tests files:
+- 'src/tests/drivers/power/subsystem.rs'
+*/
+
+use core::cell::UnsafeCell;
+
+use sifive_test::SifiveTest;
+use syscon::Syscon;
+
+use crate::{
+    drivers::model::{DriverClass, driver_probe_class},
+    log,
+    logs::LogLevel,
+};
+
+pub mod sifive_test;
+pub mod syscon;
+
+#[derive(PartialEq)]
+pub enum PowerDeviceDriver {
+    SifiveTest(SifiveTest),
+    Syscon(Syscon),
+}
+
+#[derive(PartialEq)]
+pub struct PowerDevice {
+    pub driver: PowerDeviceDriver,
+}
+
+impl PowerDevice {
+    /// Stop the machine. Return if the device cannot stop the machine.
+    /// The exit code is ignored by a device that doesn't support it, see supports_exit_code.
+    pub fn poweroff(&self, exit_code: u16) {
+        match &self.driver {
+            PowerDeviceDriver::SifiveTest(sifive_test) => sifive_test.poweroff(exit_code),
+            PowerDeviceDriver::Syscon(syscon) => syscon.poweroff(),
+        }
+    }
+
+    /// Reset the machine. Return if the device cannot reset the machine.
+    pub fn reboot(&self) {
+        match &self.driver {
+            PowerDeviceDriver::SifiveTest(sifive_test) => sifive_test.reboot(),
+            PowerDeviceDriver::Syscon(syscon) => syscon.reboot(),
+        }
+    }
+
+    pub fn supports_exit_code(&self) -> bool {
+        match &self.driver {
+            PowerDeviceDriver::SifiveTest(_) => true,
+            PowerDeviceDriver::Syscon(_) => false,
+        }
+    }
+}
+
+/// Hold the power device, only one device control the machine power.
+pub struct PowerSubSystem {
+    pub device: UnsafeCell<Option<PowerDevice>>,
+}
+
+unsafe impl Sync for PowerSubSystem {}
+
+impl PowerSubSystem {
+    pub const fn init() -> Self {
+        PowerSubSystem {
+            device: UnsafeCell::new(None),
+        }
+    }
+
+    pub fn add_power(&self, new_power: PowerDevice) {
+        if self.get_power().is_some() {
+            log!(
+                LogLevel::Warn,
+                "Power sub-system: subsystem is full, ignoring registration request"
+            );
+            return;
+        }
+        unsafe { *self.device.get() = Some(new_power) };
+    }
+
+    /// Set the power device, the previous device is dropped. Used by the drivers completing or
+    /// replacing the device.
+    pub fn replace_power(&self, new_power: PowerDevice) {
+        unsafe { *self.device.get() = Some(new_power) };
+    }
+
+    pub fn get_power(&self) -> Option<&PowerDevice> {
+        unsafe { (*self.device.get()).as_ref() }
+    }
+}
+
+pub static POWER_SUBSYSTEM: PowerSubSystem = PowerSubSystem::init();
+
+/// The power device is optional, without device the kernel halt the CPU instead of stopping or
+/// resetting the machine. The SiFive test device is preferred, it supports exit codes, it's
+/// before the syscon drivers in the driver match table.
+pub fn init_power_subsystem() {
+    if driver_probe_class(DriverClass::Power) == 0 {
+        log!(
+            LogLevel::Info,
+            "No power device found, shutdown and reboot only halt the CPU."
+        );
+    }
+}
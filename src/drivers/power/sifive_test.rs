@@ -0,0 +1,98 @@
+// See documentation in `Documentation/hardware/sifive_test.md`
+/*
+File info: SiFive test device driver.
+
+Test coverage: None.
+
+Tested:
+
+Not tested:
+- Everything.
+
+Reasons:
+- Writing to the device stop or reset QEMU.
+
+Tests files:
+*/
+
+use core::ptr;
+
+use crate::{
+    drivers::{
+        DriverRegion,
+        model::{Driver, DriverClass, DriverError},
+    },
+    platform::{DeviceType, Devices},
+};
+
+use super::{POWER_SUBSYSTEM, PowerDevice, PowerDeviceDriver};
+
+// Values written in the finisher register, at offset 0.
+// Stop with the exit code in the upper 16 bits
+const TEST_FAIL: u32 = 0x3333;
+// Stop with the exit code 0
+const TEST_PASS: u32 = 0x5555;
+const TEST_RESET: u32 = 0x7777;
+
+/// Structure for the SiFive test device, the QEMU virt finisher.
+/// region: DriverRegion struct to define address memory region to use with the driver and the address size
+#[derive(PartialEq)]
+pub struct SifiveTest {
+    pub region: DriverRegion,
+}
+
+impl SifiveTest {
+    /// Stop the machine, the exit code is given to the emulator.
+    pub fn poweroff(&self, exit_code: u16) {
+        let value = match exit_code {
+            0 => TEST_PASS,
+            _ => ((exit_code as u32) << 16) | TEST_FAIL,
+        };
+        self.write(value);
+    }
+
+    pub fn reboot(&self) {
+        self.write(TEST_RESET);
+    }
+
+    fn write(&self, value: u32) {
+        unsafe { ptr::write_volatile(self.region.addr as *mut u32, value) }
+    }
+}
+
+/// SiFive test entry of the driver match table.
+pub struct SifiveTestDriver;
+
+impl Driver for SifiveTestDriver {
+    fn name(&self) -> &'static str {
+        "sifive-test"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        &["sifive,test0"]
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Power
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::Power
+    }
+
+    /// Init the SiFive test device from the platform layer.
+    fn probe(&self, device_info: &Devices) -> Result<(), DriverError> {
+        if device_info.header.device_addr.addr == 0 {
+            panic!(
+                "Encounter a wrong MMIO reg when initializing device. Check the device definition or hardware."
+            );
+        }
+        let device = PowerDevice {
+            driver: PowerDeviceDriver::SifiveTest(SifiveTest {
+                region: device_info.header.device_addr,
+            }),
+        };
+        POWER_SUBSYSTEM.add_power(device);
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+// See documentation in `Documentation/hardware/sifive_test.md`
+/*
+File info: Generic syscon-poweroff and syscon-reboot driver.
+
+Test coverage: None.
+
+Tested:
+
+Not tested:
+- Everything.
+
+Reasons:
+- Writing to the device stop or reset the machine.
+
+Tests files:
+*/
+
+use core::ptr;
+
+use crate::{
+    drivers::model::{Driver, DriverClass, DriverError},
+    misc::RawTraitObject,
+    platform::{self, DeviceType, Devices},
+};
+
+use super::{POWER_SUBSYSTEM, PowerDevice, PowerDeviceDriver};
+
+/// Write value in the register at addr, only the bits in mask are changed.
+#[derive(Copy, Clone, PartialEq)]
+pub struct SysconAction {
+    pub addr: usize,
+    pub value: u32,
+    pub mask: u32,
+}
+
+impl SysconAction {
+    /// Read the action of a syscon-poweroff or syscon-reboot node.
+    fn from_platform(device_info: &Devices) -> Self {
+        // Allow the use of expect, once we got the device asked, the trait should be working and
+        // we should get the trait behind the Option<>
+        #[allow(clippy::expect_used)]
+        let device_info_trait = device_info
+            .info
+            .expect("Error: failed to get device trait behind option.");
+        let raw: RawTraitObject = unsafe { core::mem::transmute(device_info_trait) };
+        let power_device_ptr = raw.data as *const platform::PlatformPowerDevice;
+        // Copy the values now, the platform layer reuse the same instance for each node.
+        let power_device_ref = unsafe { &*power_device_ptr };
+        SysconAction {
+            addr: device_info.header.device_addr.addr + power_device_ref.offset as usize,
+            value: power_device_ref.value,
+            mask: power_device_ref.mask,
+        }
+    }
+
+    fn run(&self) {
+        let reg = self.addr as *mut u32;
+        unsafe {
+            if self.mask == u32::MAX {
+                ptr::write_volatile(reg, self.value);
+            } else {
+                let current = ptr::read_volatile(reg);
+                ptr::write_volatile(reg, (current & !self.mask) | (self.value & self.mask));
+            }
+        }
+    }
+}
+
+/// Structure for the syscon power driver, each action is optional.
+#[derive(PartialEq)]
+pub struct Syscon {
+    pub poweroff: Option<SysconAction>,
+    pub reboot: Option<SysconAction>,
+}
+
+impl Syscon {
+    /// Add the action of a syscon-poweroff or syscon-reboot node to the syscon power device, both
+    /// nodes make a single power device.
+    fn add_action(device_info: &Devices, poweroff: bool) -> Result<(), DriverError> {
+        let mut syscon = match POWER_SUBSYSTEM.get_power().map(|p| &p.driver) {
+            None => Syscon {
+                poweroff: None,
+                reboot: None,
+            },
+            Some(PowerDeviceDriver::Syscon(s)) => Syscon {
+                poweroff: s.poweroff,
+                reboot: s.reboot,
+            },
+            // The SiFive test device is preferred, see init_power_subsystem.
+            Some(_) => return Err(DriverError::NoMatch),
+        };
+        let action = SysconAction::from_platform(device_info);
+        match poweroff {
+            true => syscon.poweroff = Some(action),
+            false => syscon.reboot = Some(action),
+        }
+        POWER_SUBSYSTEM.replace_power(PowerDevice {
+            driver: PowerDeviceDriver::Syscon(syscon),
+        });
+        Ok(())
+    }
+
+    /// Return if the action is not available.
+    pub fn poweroff(&self) {
+        if let Some(action) = self.poweroff {
+            action.run();
+        }
+    }
+
+    /// Return if the action is not available.
+    pub fn reboot(&self) {
+        if let Some(action) = self.reboot {
+            action.run();
+        }
+    }
+}
+
+/// Syscon-poweroff entry of the driver match table.
+pub struct SysconPoweroffDriver;
+
+impl Driver for SysconPoweroffDriver {
+    fn name(&self) -> &'static str {
+        "syscon-poweroff"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        &["syscon-poweroff"]
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Power
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::Power
+    }
+
+    fn probe(&self, device_info: &Devices) -> Result<(), DriverError> {
+        Syscon::add_action(device_info, true)
+    }
+}
+
+/// Syscon-reboot entry of the driver match table.
+pub struct SysconRebootDriver;
+
+impl Driver for SysconRebootDriver {
+    fn name(&self) -> &'static str {
+        "syscon-reboot"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        &["syscon-reboot"]
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Power
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::Power
+    }
+
+    fn probe(&self, device_info: &Devices) -> Result<(), DriverError> {
+        Syscon::add_action(device_info, false)
+    }
+}
@@ -0,0 +1,178 @@
+// See documentation in `Documentation/hardware/goldfish_rtc.md`
+/*
+File info: Goldfish RTC driver.
+
+Test coverage: None.
+
+Tested:
+
+Not tested:
+- Everything.
+
+Reasons:
+- Testing a RTC driver need to have an MMIO emulation.
+
+Tests files:
+- 'src/tests/drivers/rtc/subsystem.rs'
+*/
+
+use core::ptr;
+
+use crate::{
+    drivers::{
+        DriverRegion,
+        model::{Driver, DriverClass, DriverError, RegisteredDevice},
+    },
+    irq::{IrqError, irq_ext, irq_register},
+    log,
+    logs::LogLevel,
+    misc::RawTraitObject,
+    platform::{self, DeviceType, Devices},
+};
+
+use super::{
+    RTC_SUBSYSTEM, RtcDevice, RtcDeviceDriver, rtc_alarm_bottom_half, rtc_interrupt_handler,
+};
+
+// Registers offset, all registers are 4 bytes wide.
+// Reading TIME_LOW latch TIME_HIGH
+const TIME_LOW: usize = 0x00;
+const TIME_HIGH: usize = 0x04;
+// Writing ALARM_LOW arm the alarm with ALARM_HIGH written before
+const ALARM_LOW: usize = 0x08;
+const ALARM_HIGH: usize = 0x0c;
+const IRQ_ENABLED: usize = 0x10;
+const CLEAR_ALARM: usize = 0x14;
+const CLEAR_INTERRUPT: usize = 0x1c;
+
+/// Structure for the goldfish RTC driver
+/// region: DriverRegion struct to define address memory region to use with the driver and the address size
+/// irq: interrupt source id of the device on the external interrupt controller, 0 if none
+#[derive(PartialEq)]
+pub struct GoldfishRtc {
+    pub region: DriverRegion,
+    pub irq: u32,
+}
+
+/// Goldfish RTC entry of the driver match table.
+pub struct GoldfishRtcDriver;
+
+impl Driver for GoldfishRtcDriver {
+    fn name(&self) -> &'static str {
+        "goldfish-rtc"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        &["google,goldfish-rtc"]
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Rtc
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::Rtc
+    }
+
+    /// Init a new goldfish RTC from the platform layer
+    fn probe(&self, device_info: &Devices) -> Result<(), DriverError> {
+        if device_info.header.device_addr.addr == 0 {
+            panic!(
+                "Encounter a wrong MMIO reg when initializing device. Check the device definition or hardware."
+            );
+        }
+        // Allow the use of expect, once we got the device asked, the trait should be working and
+        // we should get the trait behind the Option<>
+        #[allow(clippy::expect_used)]
+        let device_info_trait = device_info
+            .info
+            .expect("Error: failed to get device trait behind option.");
+        let raw: RawTraitObject = unsafe { core::mem::transmute(device_info_trait) };
+        let rtc_device_ptr = raw.data as *const platform::PlatformRtcDevice;
+        let rtc_device_ref = unsafe { &*rtc_device_ptr };
+        let rtc = GoldfishRtc {
+            region: device_info.header.device_addr,
+            irq: rtc_device_ref.irq,
+        };
+        // No alarm left from a previous boot
+        rtc.clear_alarm();
+        if rtc.irq != 0
+            && let Err(e) = irq_register(
+                irq_ext(rtc.irq),
+                1,
+                rtc_interrupt_handler,
+                0,
+                Some(rtc_alarm_bottom_half),
+            )
+            && e != IrqError::AlreadyRegistered
+        {
+            log!(
+                LogLevel::Warn,
+                "Goldfish RTC: failed to attach the interrupt: {:?}, alarms are not available",
+                e
+            );
+        }
+        let device = RtcDevice {
+            driver: RtcDeviceDriver::Goldfish(rtc),
+        };
+        RTC_SUBSYSTEM
+            .add_rtc(device)
+            .map(|_| ())
+            .ok_or(DriverError::InitFailed)
+    }
+
+    /// Clear the alarm of the device and remove it from the RTC sub-system.
+    fn remove(&self, device: &RegisteredDevice) -> Result<(), DriverError> {
+        let index = RTC_SUBSYSTEM
+            .devices
+            .position(|r| r.region() == device.region)
+            .ok_or(DriverError::NotRegistered)?;
+        let rtc = RTC_SUBSYSTEM
+            .remove_rtc(index)
+            .ok_or(DriverError::NotRegistered)?;
+        let RtcDeviceDriver::Goldfish(goldfish) = rtc.driver;
+        goldfish.clear_alarm();
+        Ok(())
+    }
+}
+
+impl GoldfishRtc {
+    /// Nanoseconds since the Unix epoch.
+    pub fn read_time_ns(&self) -> u64 {
+        let low = self.read_reg(TIME_LOW) as u64;
+        let high = self.read_reg(TIME_HIGH) as u64;
+        (high << 32) | low
+    }
+
+    pub fn set_time_ns(&self, time_ns: u64) {
+        self.write_reg(TIME_HIGH, (time_ns >> 32) as u32);
+        self.write_reg(TIME_LOW, time_ns as u32);
+    }
+
+    /// Arm the alarm, the interrupt is raised once the time reach time_ns.
+    pub fn set_alarm_ns(&self, time_ns: u64) {
+        self.write_reg(IRQ_ENABLED, 1);
+        self.write_reg(ALARM_HIGH, (time_ns >> 32) as u32);
+        self.write_reg(ALARM_LOW, time_ns as u32);
+    }
+
+    /// Disarm the alarm and clear a pending interrupt.
+    pub fn clear_alarm(&self) {
+        self.write_reg(IRQ_ENABLED, 0);
+        self.write_reg(CLEAR_ALARM, 1);
+        self.write_reg(CLEAR_INTERRUPT, 1);
+    }
+
+    /// Alarm interrupt handler, acknowledge the interrupt.
+    pub fn interrupt_handler(&self) {
+        self.write_reg(CLEAR_INTERRUPT, 1);
+    }
+
+    fn read_reg(&self, off: usize) -> u32 {
+        unsafe { ptr::read_volatile((self.region.addr + off) as *const u32) }
+    }
+
+    fn write_reg(&self, off: usize, value: u32) {
+        unsafe { ptr::write_volatile((self.region.addr + off) as *mut u32, value) }
+    }
+}
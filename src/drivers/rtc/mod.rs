@@ -0,0 +1,253 @@
+// See documentation in `Documentation/kernel/subsystems.md`
+/*
+File info: Real time clock sub-system.
+
+Test coverage: Sub-system without device.
+
+Tested:
+- Sub-system initialized empty.
+- Read time and set alarm without device.
+
+Not tested:
+- Add devices, alarm interrupt.
+
+Reasons:
+- A RTC need an MMIO emulation to be tested.
+
+Tests files:
+- 'src/tests/drivers/rtc/subsystem.rs'
+*/
+
+use core::cell::UnsafeCell;
+
+use goldfish::GoldfishRtc;
+
+use crate::{
+    arch::traps::interrupt::{restore_mstatus_mie, save_and_disable_mstatus_mie},
+    config::RTC_MAX_SIZE,
+    drivers::{
+        DriverRegion,
+        model::{DriverClass, driver_probe_class},
+        pool::DevicePool,
+    },
+    irq::irq_ext,
+    log,
+    logs::LogLevel,
+};
+
+pub mod goldfish;
+
+/// Called from the interrupt bottom half task when an alarm expire, with the alarm time.
+pub type RtcAlarmHandler = fn(time_ns: u64);
+
+/// All errors that can happen on a RTC request.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RtcError {
+    // There's no RTC.
+    NoDevice,
+    // The RTC interrupt is not attached, alarms cannot be raised.
+    NoInterrupt,
+    // The alarm time is already reached.
+    AlarmInPast,
+}
+
+#[derive(PartialEq)]
+pub enum RtcDeviceDriver {
+    Goldfish(GoldfishRtc),
+}
+
+#[derive(PartialEq)]
+pub struct RtcDevice {
+    pub driver: RtcDeviceDriver,
+}
+
+impl RtcDevice {
+    /// Nanoseconds since the Unix epoch.
+    pub fn read_time_ns(&self) -> u64 {
+        match &self.driver {
+            RtcDeviceDriver::Goldfish(goldfish) => goldfish.read_time_ns(),
+        }
+    }
+
+    pub fn set_time_ns(&self, time_ns: u64) {
+        match &self.driver {
+            RtcDeviceDriver::Goldfish(goldfish) => goldfish.set_time_ns(time_ns),
+        }
+    }
+
+    pub fn set_alarm_ns(&self, time_ns: u64) {
+        match &self.driver {
+            RtcDeviceDriver::Goldfish(goldfish) => goldfish.set_alarm_ns(time_ns),
+        }
+    }
+
+    pub fn clear_alarm(&self) {
+        match &self.driver {
+            RtcDeviceDriver::Goldfish(goldfish) => goldfish.clear_alarm(),
+        }
+    }
+
+    /// Memory region of the device, used to find the device bound in the device registry.
+    pub fn region(&self) -> DriverRegion {
+        match &self.driver {
+            RtcDeviceDriver::Goldfish(goldfish) => goldfish.region,
+        }
+    }
+
+    /// Interrupt source id of the device, 0 if not connected.
+    pub fn irq(&self) -> u32 {
+        match &self.driver {
+            RtcDeviceDriver::Goldfish(goldfish) => goldfish.irq,
+        }
+    }
+
+    pub fn interrupt_handler(&self) {
+        match &self.driver {
+            RtcDeviceDriver::Goldfish(goldfish) => goldfish.interrupt_handler(),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct RtcAlarm {
+    time_ns: u64,
+    handler: RtcAlarmHandler,
+}
+
+/// Define and manage all RTC devices. The first device is the primary RTC, used for the wall
+/// clock and the alarm.
+/// alarm: the alarm armed on the primary RTC, one alarm at a time.
+/// expired: the alarm raised by the interrupt, waiting for the bottom half.
+pub struct RtcSubSystem {
+    pub devices: DevicePool<RtcDevice, RTC_MAX_SIZE>,
+    alarm: UnsafeCell<Option<RtcAlarm>>,
+    expired: UnsafeCell<Option<RtcAlarm>>,
+}
+
+unsafe impl Sync for RtcSubSystem {}
+
+impl RtcSubSystem {
+    pub const fn init() -> Self {
+        RtcSubSystem {
+            devices: DevicePool::init("RTC"),
+            alarm: UnsafeCell::new(None),
+            expired: UnsafeCell::new(None),
+        }
+    }
+
+    pub fn add_rtc(&self, new_rtc: RtcDevice) -> Option<usize> {
+        self.devices.add(new_rtc)
+    }
+
+    /// Remove the RTC at index, the alarm is cancelled if it's the primary RTC.
+    pub fn remove_rtc(&self, index: usize) -> Option<RtcDevice> {
+        if index == 0 {
+            self.cancel_alarm();
+        }
+        let mie = save_and_disable_mstatus_mie();
+        let rtc = self.devices.remove(index);
+        restore_mstatus_mie(mie);
+        rtc
+    }
+
+    pub fn get_rtc(&self, index: usize) -> Option<&RtcDevice> {
+        self.devices.get(index)
+    }
+
+    /// The first RTC registered, None if there's no RTC.
+    pub fn get_primary_rtc(&self) -> Option<&RtcDevice> {
+        self.get_rtc(0)
+    }
+
+    pub fn get_rtc_array_size(&self) -> usize {
+        self.devices.size()
+    }
+
+    /// Arm the alarm of the primary RTC, replace the alarm already armed.
+    pub fn set_alarm(&self, time_ns: u64, handler: RtcAlarmHandler) -> Result<(), RtcError> {
+        let rtc = self.get_primary_rtc().ok_or(RtcError::NoDevice)?;
+        if rtc.irq() == 0 {
+            return Err(RtcError::NoInterrupt);
+        }
+        let mie = save_and_disable_mstatus_mie();
+        if time_ns <= rtc.read_time_ns() {
+            restore_mstatus_mie(mie);
+            return Err(RtcError::AlarmInPast);
+        }
+        unsafe { *self.alarm.get() = Some(RtcAlarm { time_ns, handler }) };
+        rtc.set_alarm_ns(time_ns);
+        restore_mstatus_mie(mie);
+        Ok(())
+    }
+
+    /// Disarm the alarm of the primary RTC, an expired alarm not handled yet is dropped too.
+    pub fn cancel_alarm(&self) {
+        let mie = save_and_disable_mstatus_mie();
+        if let Some(rtc) = self.get_primary_rtc() {
+            rtc.clear_alarm();
+        }
+        unsafe {
+            *self.alarm.get() = None;
+            *self.expired.get() = None;
+        }
+        restore_mstatus_mie(mie);
+    }
+}
+
+pub static RTC_SUBSYSTEM: RtcSubSystem = RtcSubSystem::init();
+
+/// Nanoseconds since the Unix epoch from the primary RTC, None if there's no RTC.
+pub fn rtc_read_ns() -> Option<u64> {
+    RTC_SUBSYSTEM
+        .get_primary_rtc()
+        .map(|rtc| rtc.read_time_ns())
+}
+
+/// Set the time of the primary RTC, in nanoseconds since the Unix epoch.
+pub fn rtc_set_time_ns(time_ns: u64) -> Result<(), RtcError> {
+    let rtc = RTC_SUBSYSTEM.get_primary_rtc().ok_or(RtcError::NoDevice)?;
+    rtc.set_time_ns(time_ns);
+    Ok(())
+}
+
+/// Call handler from the interrupt bottom half task once the primary RTC reach time_ns, see
+/// RtcSubSystem::set_alarm.
+pub fn rtc_set_alarm(time_ns: u64, handler: RtcAlarmHandler) -> Result<(), RtcError> {
+    RTC_SUBSYSTEM.set_alarm(time_ns, handler)
+}
+
+pub fn rtc_cancel_alarm() {
+    RTC_SUBSYSTEM.cancel_alarm();
+}
+
+/// External interrupt handler of all RTC devices, acknowledge the device and move the alarm of
+/// the primary RTC to the expired alarm.
+pub fn rtc_interrupt_handler(irq: u32, _ctx: usize) {
+    for (i, rtc) in RTC_SUBSYSTEM.devices.iter() {
+        if irq_ext(rtc.irq()) == irq {
+            rtc.interrupt_handler();
+            if i == 0 {
+                unsafe { *RTC_SUBSYSTEM.expired.get() = (*RTC_SUBSYSTEM.alarm.get()).take() };
+            }
+        }
+    }
+}
+
+/// Bottom half of the RTC interrupt, call the expired alarm handler outside of the trap.
+pub fn rtc_alarm_bottom_half(_irq: u32, _ctx: usize) {
+    let mie = save_and_disable_mstatus_mie();
+    let expired = unsafe { (*RTC_SUBSYSTEM.expired.get()).take() };
+    restore_mstatus_mie(mie);
+    if let Some(alarm) = expired {
+        (alarm.handler)(alarm.time_ns);
+    }
+}
+
+/// A RTC is optional, the sub-system stays empty without device and the wall clock is not
+/// available.
+pub fn init_rtc_subsystem() {
+    driver_probe_class(DriverClass::Rtc);
+    if RTC_SUBSYSTEM.get_rtc_array_size() == 0 {
+        log!(LogLevel::Info, "No RTC found, wall clock not available.");
+    }
+}
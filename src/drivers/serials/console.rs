@@ -0,0 +1,184 @@
+// See documentation in `Documentation/kernel/subsystems.md`
+/*
+File info: Console routing, select the serial device of the logs and the task output.
+
+Test coverage: Routes and console resolution.
+
+Tested:
+- Route a log level and a task, clear a route.
+- Resolution order: log level, task, default console.
+- Route to a missing device, full task table.
+- Removed device falling back to the default console.
+
+Not tested:
+- The output on a second UART.
+
+Reasons:
+- QEMU virt has only one UART.
+
+Tests files:
+- 'src/tests/drivers/serials/console.rs'
+*/
+
+use core::cell::UnsafeCell;
+
+use crate::{
+    arch::traps::interrupt::{restore_mstatus_mie, save_and_disable_mstatus_mie},
+    config::CONSOLE_TASK_ROUTE_MAX_SIZE,
+    logs::LogLevel,
+    task::task_current_pid,
+};
+
+use super::{SERIAL_SUBSYSTEM, SerialManager};
+
+// Number of log levels, one route per level.
+const LOG_LEVEL_NUMBER: usize = 4;
+
+/// All errors that can happen on a console routing request.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ConsoleError {
+    // There's no serial device at this index.
+    NoDevice,
+    // The task route table is full, see CONSOLE_TASK_ROUTE_MAX_SIZE in config file.
+    TableFull,
+    // The request must be made from a task.
+    NotInTask,
+}
+
+/// Route of the output of a task.
+#[derive(Copy, Clone)]
+struct TaskRoute {
+    pid: u16,
+    console: usize,
+}
+
+/// Serial device of each log level and task, the other output goes to the default console.
+/// levels: serial index of each log level, None for the default console.
+/// tasks: serial index of the output of a task.
+pub struct ConsoleRoutes {
+    levels: UnsafeCell<[Option<usize>; LOG_LEVEL_NUMBER]>,
+    tasks: UnsafeCell<[Option<TaskRoute>; CONSOLE_TASK_ROUTE_MAX_SIZE]>,
+}
+
+unsafe impl Sync for ConsoleRoutes {}
+
+impl ConsoleRoutes {
+    pub const fn init() -> Self {
+        ConsoleRoutes {
+            levels: UnsafeCell::new([None; LOG_LEVEL_NUMBER]),
+            tasks: UnsafeCell::new([None; CONSOLE_TASK_ROUTE_MAX_SIZE]),
+        }
+    }
+
+    /// Send the logs of the level to the serial device at index, None to go back to the default
+    /// console.
+    pub fn route_level(
+        &self,
+        serials: &SerialManager,
+        level: LogLevel,
+        console: Option<usize>,
+    ) -> Result<(), ConsoleError> {
+        if console.is_some_and(|index| serials.get_serial(index).is_none()) {
+            return Err(ConsoleError::NoDevice);
+        }
+        // A print! from an interrupt must not see a route being changed.
+        let mie = save_and_disable_mstatus_mie();
+        unsafe { (*self.levels.get())[level as usize] = console };
+        restore_mstatus_mie(mie);
+        Ok(())
+    }
+
+    /// Send the output of the task to the serial device at index, None to go back to the default
+    /// console.
+    pub fn route_task(
+        &self,
+        serials: &SerialManager,
+        pid: u16,
+        console: Option<usize>,
+    ) -> Result<(), ConsoleError> {
+        if console.is_some_and(|index| serials.get_serial(index).is_none()) {
+            return Err(ConsoleError::NoDevice);
+        }
+        let mie = save_and_disable_mstatus_mie();
+        let tasks = unsafe { &mut *self.tasks.get() };
+        let res = match (
+            tasks.iter().position(|r| r.is_some_and(|r| r.pid == pid)),
+            console,
+        ) {
+            (Some(i), Some(console)) => {
+                tasks[i] = Some(TaskRoute { pid, console });
+                Ok(())
+            }
+            (Some(i), None) => {
+                tasks[i] = None;
+                Ok(())
+            }
+            (None, Some(console)) => match tasks.iter_mut().find(|r| r.is_none()) {
+                Some(free) => {
+                    *free = Some(TaskRoute { pid, console });
+                    Ok(())
+                }
+                None => Err(ConsoleError::TableFull),
+            },
+            // Nothing to clear
+            (None, None) => Ok(()),
+        };
+        restore_mstatus_mie(mie);
+        res
+    }
+
+    /// Serial index of the logs of the level, None if not routed.
+    pub fn level_route(&self, level: LogLevel) -> Option<usize> {
+        unsafe { (*self.levels.get())[level as usize] }
+    }
+
+    /// Serial index of the output of the task, None if not routed.
+    pub fn task_route(&self, pid: u16) -> Option<usize> {
+        let tasks = unsafe { &*self.tasks.get() };
+        tasks
+            .iter()
+            .flatten()
+            .find(|r| r.pid == pid)
+            .map(|r| r.console)
+    }
+
+    /// Serial index of an output: the route of the log level first, then the route of the task,
+    /// then the default console. A route to a removed device is ignored.
+    pub fn console(
+        &self,
+        serials: &SerialManager,
+        level: Option<LogLevel>,
+        pid: Option<u16>,
+    ) -> usize {
+        level
+            .and_then(|level| self.level_route(level))
+            .into_iter()
+            .chain(pid.and_then(|pid| self.task_route(pid)))
+            .find(|index| serials.get_serial(*index).is_some())
+            .unwrap_or(serials.default_console_index())
+    }
+}
+
+pub static CONSOLE_ROUTES: ConsoleRoutes = ConsoleRoutes::init();
+
+/// Send the logs of the level to the serial device at index, None for the default console.
+pub fn console_route_level(level: LogLevel, console: Option<usize>) -> Result<(), ConsoleError> {
+    CONSOLE_ROUTES.route_level(&SERIAL_SUBSYSTEM, level, console)
+}
+
+/// Send the output of the task to the serial device at index, None for the default console.
+pub fn console_route_task(pid: u16, console: Option<usize>) -> Result<(), ConsoleError> {
+    CONSOLE_ROUTES.route_task(&SERIAL_SUBSYSTEM, pid, console)
+}
+
+/// Send the output of the current task to the serial device at index, None for the default
+/// console.
+pub fn console_route_current_task(console: Option<usize>) -> Result<(), ConsoleError> {
+    let pid = task_current_pid().ok_or(ConsoleError::NotInTask)?;
+    console_route_task(pid, console)
+}
+
+/// Serial index of an output of the current task, a log of the level or a print! without level.
+pub fn console_index(level: Option<LogLevel>) -> usize {
+    CONSOLE_ROUTES.console(&SERIAL_SUBSYSTEM, level, task_current_pid())
+}
@@ -7,6 +7,8 @@ Tested:
 - All basic method from implementation.
 - Adding the same device.
 - Overflow in the sub-system pool.
+- Find a device by index and driver name.
+- Change the default console.
 
 Not tested:
 - ...
@@ -19,51 +21,162 @@ Tests files:
 */
 
 use core::{
-    cell::UnsafeCell,
+    cell::Cell,
     fmt::{self, Write},
 };
 
-use crate::{config::SERIAL_MAX_SIZE, log, logs::LogLevel};
+use crate::{
+    arch::traps::interrupt::{restore_mstatus_mie, save_and_disable_mstatus_mie},
+    cmdline::kernel_params,
+    config::SERIAL_MAX_SIZE,
+    drivers::{
+        DriverRegion,
+        cpufreq::{CpuFreqEvent, CpuFreqStage, cpufreq_notifier_register},
+        model::{DriverClass, driver_probe_class},
+        pool::DevicePool,
+    },
+    irq::irq_ext,
+    ktime::ktime_ms,
+    log,
+    logs::LogLevel,
+    platform::fdt::helpers::{fdt_get_node_by_path, fdt_get_node_prop},
+    task::{
+        primitives::{sleep, task_wake},
+        task_current_pid,
+    },
+};
 
+pub mod console;
 pub mod ns16550a;
+pub mod virtio_console;
 
 /// Generic trait to implement in each serial driver
 pub trait SerialDriver: Send + Sync + Write {
-    // Write char at address
-    fn putchar(&self, c: u8);
-    // Get char from address
-    fn getchar(&self) -> u8;
+    // Write char at address, Timeout if the device stays busy.
+    fn putchar(&self, c: u8) -> Result<(), SerialError>;
+    // Get the oldest received char, None if nothing has been received. Never block.
+    fn getchar(&self) -> Option<u8>;
+}
+
+/// All errors that can happen on a serial device.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SerialError {
+    // The baud rate cannot be reached with the input clock of the device.
+    InvalidClock,
+    // The device didn't send or receive in time.
+    Timeout,
 }
 
 #[derive(PartialEq)]
 pub enum SerialDeviceDriver {
     Ns16550(ns16550a::Ns16550),
+    VirtioConsole(virtio_console::VirtioConsole),
 }
 
 /// Generic struct for each serial device
 /// id: the device id for faster access or identification
 /// default_console: if it's the default console to use or not
 /// driver: enum unions with all serial driver structure
-#[derive(PartialEq)]
 pub struct SerialDevice {
     pub driver: SerialDeviceDriver,
     pub _id: usize,
     pub default_console: bool,
 }
 
+/// Two serial devices are the same if they use the same hardware, whatever the console they are.
+impl PartialEq for SerialDevice {
+    fn eq(&self, other: &Self) -> bool {
+        self.driver == other.driver
+    }
+}
+
 impl SerialDevice {
     pub fn write_fmt(&mut self, s: core::fmt::Arguments) -> fmt::Result {
         match &mut self.driver {
             SerialDeviceDriver::Ns16550(ns16550) => ns16550.write_fmt(s),
+            SerialDeviceDriver::VirtioConsole(virtio_console) => virtio_console.write_fmt(s),
+        }
+    }
+
+    /// Write raw bytes to the device, for binary data that doesn't go through core::fmt.
+    /// If the device stays busy, the remaining bytes are dropped.
+    pub fn write_bytes(&self, data: &[u8]) {
+        match &self.driver {
+            SerialDeviceDriver::Ns16550(ns16550) => {
+                for b in data {
+                    if ns16550.putchar(*b).is_err() {
+                        break;
+                    }
+                }
+            }
+            SerialDeviceDriver::VirtioConsole(virtio_console) => virtio_console.write_bytes(data),
+        }
+    }
+
+    pub fn getchar(&self) -> Option<u8> {
+        match &self.driver {
+            SerialDeviceDriver::Ns16550(ns16550) => ns16550.getchar(),
+            SerialDeviceDriver::VirtioConsole(virtio_console) => virtio_console.getchar(),
+        }
+    }
+
+    /// Interrupt source id of the device on the external interrupt-controller, 0 if none.
+    pub fn irq(&self) -> u32 {
+        match &self.driver {
+            SerialDeviceDriver::Ns16550(ns16550) => ns16550.irq,
+            SerialDeviceDriver::VirtioConsole(virtio_console) => virtio_console.transport.irq,
+        }
+    }
+
+    /// Driver name, used to find a device in the sub-system.
+    pub fn name(&self) -> &'static str {
+        match &self.driver {
+            SerialDeviceDriver::Ns16550(_) => "ns16550a",
+            SerialDeviceDriver::VirtioConsole(_) => "virtio-console",
+        }
+    }
+
+    /// Memory region of the device, used to find the device bound in the device registry.
+    pub fn region(&self) -> DriverRegion {
+        match &self.driver {
+            SerialDeviceDriver::Ns16550(ns16550) => ns16550.region,
+            SerialDeviceDriver::VirtioConsole(virtio_console) => virtio_console.transport.region,
+        }
+    }
+
+    /// Wait until all the written bytes are sent, Timeout if the device stays busy.
+    pub fn flush(&self) -> Result<(), SerialError> {
+        match &self.driver {
+            SerialDeviceDriver::Ns16550(ns16550) => ns16550.flush(),
+            // The virtio console has no baud rate, nothing to wait.
+            SerialDeviceDriver::VirtioConsole(_) => Ok(()),
+        }
+    }
+
+    /// Reprogram the baud rate divisor from the new input clock, on error the old divisor is kept.
+    pub fn set_clock(&self, clock_frequency: u32) -> Result<(), SerialError> {
+        match &self.driver {
+            SerialDeviceDriver::Ns16550(ns16550) => ns16550.set_clock(clock_frequency),
+            SerialDeviceDriver::VirtioConsole(_) => Ok(()),
+        }
+    }
+
+    pub fn interrupt_handler(&self) {
+        match &self.driver {
+            SerialDeviceDriver::Ns16550(ns16550) => ns16550.interrupt_handler(),
+            SerialDeviceDriver::VirtioConsole(virtio_console) => virtio_console.interrupt_handler(),
         }
     }
 }
 
 /// Define and manage all serial devices.
-/// Devices: use an UnsafeCell with an array of Option<UartDevice> used to store and retrieve all
-/// device initialized.
+/// devices: pool of all devices initialized.
+/// default: index of the default console, the first device added until another one is selected.
+/// rx_waiters: task sleeping in a read of the device at the same index, woken by its interrupt.
 pub struct SerialManager {
-    pub devices: [UnsafeCell<Option<SerialDevice>>; SERIAL_MAX_SIZE],
+    pub devices: DevicePool<SerialDevice, SERIAL_MAX_SIZE>,
+    default: Cell<usize>,
+    rx_waiters: [Cell<Option<u16>>; SERIAL_MAX_SIZE],
 }
 
 unsafe impl Sync for SerialManager {}
@@ -71,64 +184,61 @@ unsafe impl Sync for SerialManager {}
 impl SerialManager {
     pub const fn init() -> Self {
         SerialManager {
-            devices: [const { UnsafeCell::new(None) }; SERIAL_MAX_SIZE],
+            devices: DevicePool::init("Serial"),
+            default: Cell::new(0),
+            rx_waiters: [const { Cell::new(None) }; SERIAL_MAX_SIZE],
         }
     }
 
-    /// Add a new serial to the device UnsafeCell array at index where there's no device
-    /// By default if there's no device saved in devices, it'll set the first serial saved as
-    /// default console
-    pub fn add_serial(&self, new_serial: SerialDevice) {
-        let mut index_none: Option<usize> = None;
-        for i in 0..SERIAL_MAX_SIZE {
-            let device = unsafe { &*self.devices[i].get() };
-            if let Some(serial) = device {
-                // Avoid duplication and log warning
-                if serial.driver == new_serial.driver {
-                    log!(
-                        LogLevel::Warn,
-                        "Serial sub-system: duplicate device detected, ignoring registration request"
-                    );
-                    return;
-                }
-            } else if device.is_none() {
-                index_none = Some(i);
-                break;
-            }
+    /// Add a new serial to the first free index of the pool and return the index.
+    /// The first device added is the default console.
+    pub fn add_serial(&self, mut new_serial: SerialDevice) -> Option<usize> {
+        new_serial.default_console = self.devices.get(self.default.get()).is_none();
+        let default_console = new_serial.default_console;
+        let index = self.devices.add(new_serial)?;
+        if default_console {
+            self.default.set(index);
+        }
+        Some(index)
+    }
+
+    /// Index of the default console.
+    pub fn default_console_index(&self) -> usize {
+        self.default.get()
+    }
+
+    /// Use the serial at index as the default console, return false if there's no device at
+    /// index.
+    pub fn set_default_console(&self, index: usize) -> bool {
+        if self.devices.get(index).is_none() {
+            return false;
+        }
+        // print! must not see two default consoles or none.
+        let mie = save_and_disable_mstatus_mie();
+        if let Some(old) = unsafe { self.devices.get_mut(self.default.get()) } {
+            old.default_console = false;
         }
-        if index_none.is_none() {
+        if let Some(new) = unsafe { self.devices.get_mut(index) } {
+            new.default_console = true;
+        }
+        self.default.set(index);
+        restore_mstatus_mie(mie);
+        true
+    }
+
+    /// Remove the serial at index, the default console cannot be removed.
+    pub fn remove_serial(&self, index: usize) -> Option<SerialDevice> {
+        if index == self.default.get() {
             log!(
                 LogLevel::Warn,
-                "Serial sub-system: subsystem is full, ignoring registration request"
+                "Serial sub-system: the default console cannot be removed"
             );
-            return;
-        }
-        // Set default console
-        // Allow the use of expect, we check the option before, so if we can't get the value behind
-        // the option, there's a problem, obviously.
-        #[allow(clippy::expect_used)]
-        if index_none
-            .expect("Error: failed to get the index when checking serial index on sub-system")
-            == 0
-        {
-            let update_serial = SerialDevice {
-                _id: new_serial._id,
-                default_console: true,
-                driver: new_serial.driver,
-            };
-            unsafe { *self.devices[0].get() = Some(update_serial) }
-        } else {
-            // Just save the new device
-            // Allow expect, we should always get the value behind the Option, if not, something is
-            // wrong, fail-fast.
-            #[allow(clippy::expect_used)]
-            unsafe {
-                *self.devices[index_none.expect(
-                    "Error: failed to get the index when adding a new serial to sub-system",
-                )]
-                .get() = Some(new_serial)
-            };
+            return None;
         }
+        let mie = save_and_disable_mstatus_mie();
+        let serial = self.devices.remove(index);
+        restore_mstatus_mie(mie);
+        serial
     }
 
     /// Return &mut default_console from subsystem,
@@ -140,7 +250,7 @@ impl SerialManager {
     ///   unsafe function while there's no mutex built
     #[allow(clippy::mut_from_ref)]
     pub unsafe fn get_default_console(&self) -> &mut SerialDevice {
-        let default_console = unsafe { (*self.devices[0].get()).as_mut() };
+        let default_console = unsafe { self.devices.get_mut(self.default.get()) };
         if let Some(serial) = default_console {
             serial
         } else {
@@ -150,24 +260,221 @@ impl SerialManager {
         }
     }
 
+    pub fn get_serial(&self, index: usize) -> Option<&SerialDevice> {
+        self.devices.get(index)
+    }
+
+    /// Return the index of the first device using the driver name, see SerialDevice::name.
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.devices.position(|s| s.name() == name)
+    }
+
+    /// Index of the device using the region.
+    pub fn find_region(&self, region: DriverRegion) -> Option<usize> {
+        self.devices.position(|s| s.region() == region)
+    }
+
     pub fn get_serial_array_size(&self) -> usize {
-        let mut size: usize = 0;
-        for i in 0..SERIAL_MAX_SIZE {
-            let present = unsafe { &*self.devices[i].get() };
-            if present.is_some() {
-                size += 1;
-            }
+        self.devices.size()
+    }
+
+    /// Set the task waiting for a char from the device at index, None when the read is done.
+    /// Must be called with interrupts disabled.
+    pub fn set_rx_waiter(&self, index: usize, pid: Option<u16>) {
+        if let Some(waiter) = self.rx_waiters.get(index) {
+            waiter.set(pid);
         }
-        size
+    }
+
+    /// Take the task waiting for a char from the device at index.
+    /// Must be called with interrupts disabled.
+    pub fn take_rx_waiter(&self, index: usize) -> Option<u16> {
+        self.rx_waiters.get(index).and_then(|waiter| waiter.take())
     }
 }
 
 pub static SERIAL_SUBSYSTEM: SerialManager = SerialManager::init();
 
+/// External interrupt handler of all serial devices, find the device raising the irq and wake the
+/// task reading from it.
+pub fn serial_interrupt_handler(irq: u32, _ctx: usize) {
+    for (index, serial) in SERIAL_SUBSYSTEM.devices.iter() {
+        if irq_ext(serial.irq()) == irq {
+            serial.interrupt_handler();
+            if let Some(pid) = SERIAL_SUBSYSTEM.take_rx_waiter(index) {
+                task_wake(pid);
+            }
+        }
+    }
+}
+
+/// Cpufreq notifier, the UARTs are clocked by the peripheral bus. Flush before the bus clock
+/// change to not send a byte at a wrong baud rate, and reprogram the divisors after.
+/// An operating point without bus clock keeps the UART clocks. A UART whose baud rate cannot be
+/// reached with the new bus clock keeps its old divisor, it sends at a wrong baud rate until the
+/// next change.
+fn serial_cpufreq_notifier(event: &CpuFreqEvent) {
+    if event.new.bus == 0 || event.new.bus == event.old.bus {
+        return;
+    }
+    for (index, serial) in SERIAL_SUBSYSTEM.devices.iter() {
+        match event.stage {
+            CpuFreqStage::PreChange => {
+                if let Err(e) = serial.flush() {
+                    log!(
+                        LogLevel::Warn,
+                        "Serial sub-system: device {} not flushed before the bus clock change: {:?}",
+                        index,
+                        e
+                    );
+                }
+            }
+            CpuFreqStage::PostChange => {
+                if let Err(e) = serial.set_clock(event.new.bus) {
+                    log!(
+                        LogLevel::Warn,
+                        "Serial sub-system: device {} cannot use bus clock {}: {:?}, divisor kept",
+                        index,
+                        event.new.bus,
+                        e
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Select the default console from the FDT /chosen stdout-path, the device of the node must be
+/// bound. Without stdout-path the first device probed stays the default console.
+fn serial_select_stdout() {
+    let stdout_path = match kernel_params().stdout_path {
+        Some(path) => path,
+        None => return,
+    };
+    // A node without reg is not a device, it cannot be bound.
+    let index = fdt_get_node_by_path(stdout_path)
+        .filter(|node| fdt_get_node_prop(node, "reg").is_some())
+        .and_then(|node| SERIAL_SUBSYSTEM.find_region(DriverRegion::new(node)));
+    match index {
+        Some(index) => {
+            SERIAL_SUBSYSTEM.set_default_console(index);
+        }
+        None => {
+            log!(
+                LogLevel::Warn,
+                "Serial sub-system: no serial device for stdout-path {}, keeping the first device",
+                stdout_path
+            );
+        }
+    }
+}
+
 pub fn init_serial_subsystem() {
-    ns16550a::Ns16550::init();
+    // The boot UART is first in the driver match table, it's the default console without
+    // stdout-path.
+    driver_probe_class(DriverClass::Serial);
     let size = SERIAL_SUBSYSTEM.get_serial_array_size();
     if size == 0 {
         panic!("Error while initializing serial sub-system, pool is empty.");
     }
+    serial_select_stdout();
+    if let Err(e) = cpufreq_notifier_register(serial_cpufreq_notifier) {
+        log!(
+            LogLevel::Warn,
+            "Serial sub-system: failed to register the cpufreq notifier: {:?}",
+            e
+        );
+    }
+}
+
+/// Read a char from the default console, wait at most timeout_ms, see serial_getchar_from.
+pub fn serial_getchar(timeout_ms: u64) -> Result<u8, SerialError> {
+    serial_getchar_from(SERIAL_SUBSYSTEM.default_console_index(), timeout_ms)
+}
+
+/// Read received chars from the default console into buf, wait at most timeout_ms for the first
+/// one. Return the number of chars read, never more than buf.len().
+pub fn serial_read(buf: &mut [u8], timeout_ms: u64) -> Result<usize, SerialError> {
+    serial_read_from(SERIAL_SUBSYSTEM.default_console_index(), buf, timeout_ms)
+}
+
+/// Read a char from the serial device at index, wait at most timeout_ms, Timeout if nothing is
+/// received.
+/// From a task, with the receive interrupt attached, the task sleeps until the interrupt handler
+/// wakes it, else it sleeps one tick between each check. Outside of a task, busy wait.
+/// Panic if there's no device at index.
+pub fn serial_getchar_from(index: usize, timeout_ms: u64) -> Result<u8, SerialError> {
+    let serial = match SERIAL_SUBSYSTEM.get_serial(index) {
+        Some(s) => s,
+        None => panic!("Serial sub-system: no serial device at index {}", index),
+    };
+    let deadline = ktime_ms().saturating_add(timeout_ms);
+    let pid = task_current_pid();
+    loop {
+        // The interrupts stay disabled from the check to the sleep, a char received after the
+        // check wakes the task.
+        let mie = save_and_disable_mstatus_mie();
+        if let Some(c) = serial.getchar() {
+            restore_mstatus_mie(mie);
+            return Ok(c);
+        }
+        let now = ktime_ms();
+        if now >= deadline {
+            restore_mstatus_mie(mie);
+            return Err(SerialError::Timeout);
+        }
+        if let Some(pid) = pid {
+            let ticks = match serial.irq() {
+                0 => 1,
+                _ => {
+                    SERIAL_SUBSYSTEM.set_rx_waiter(index, Some(pid));
+                    (deadline - now).div_ceil(kernel_params().tick_duration) as usize
+                }
+            };
+            unsafe { sleep(ticks) };
+            // The task is resumed with the interrupts enabled.
+            save_and_disable_mstatus_mie();
+            SERIAL_SUBSYSTEM.set_rx_waiter(index, None);
+        }
+        restore_mstatus_mie(mie);
+    }
+}
+
+/// Read received chars from the serial device at index into buf, see serial_read.
+/// Panic if there's no device at index.
+pub fn serial_read_from(
+    index: usize,
+    buf: &mut [u8],
+    timeout_ms: u64,
+) -> Result<usize, SerialError> {
+    if buf.is_empty() {
+        return Ok(0);
+    }
+    buf[0] = serial_getchar_from(index, timeout_ms)?;
+    let mut len: usize = 1;
+    // Allow the use of expect, serial_getchar_from already checked the device.
+    #[allow(clippy::expect_used)]
+    let serial = SERIAL_SUBSYSTEM
+        .get_serial(index)
+        .expect("Error: failed to get the serial device after reading from it");
+    while len < buf.len() {
+        match serial.getchar() {
+            Some(c) => buf[len] = c,
+            None => break,
+        }
+        len += 1;
+    }
+    Ok(len)
+}
+
+/// Write raw bytes to the serial device at index.
+/// Return false if there's no device at index.
+pub fn serial_write(index: usize, data: &[u8]) -> bool {
+    match SERIAL_SUBSYSTEM.get_serial(index) {
+        Some(serial) => {
+            serial.write_bytes(data);
+            true
+        }
+        None => false,
+    }
 }
@@ -1,66 +1,158 @@
+// See documentation in `Documentation/hardware/ns16550.md`
 /*
 File info: Ns16550a driver.
 
-Test coverage: None.
+Test coverage: Initialization and baud rate divisor, on registers in memory.
 
 Tested:
+- Hardware init: divisor, frame format, FIFOs, modem control and receive interrupt.
+- Hardware init without clock or with an invalid clock, the firmware divisor kept.
+- Divisor from the clock frequency, clocks too slow for the baud rate refused.
+- Set clock, the old divisor kept on error.
+- Putchar and flush timeout on a transmitter that stays busy.
+- Getchar, the drain bounded by the FIFO size.
 
 Not tested:
-- Everything.
+- The receive interrupt handler and the drop count of the receive buffer.
+- Polled transmit on QEMU, its suite is skipped.
+- Probe and remove.
 
 Reasons:
-- Testing a serial driver need to have an MMIO emulation.
+- Registers in memory don't clear the data ready bit of LSR when RBR is read, every drain reads
+  a full FIFO. The interrupt path needs an MMIO emulation, it's tested by hand in QEMU.
+- The transmit test writes on the UART of QEMU virt at a fixed address.
+- Probe and remove need a device in the FDT and the irq sub-system.
 
 Tests files:
 - 'src/tests/drivers/serials/ns16550a.rs'
 */
 
-use core::fmt::{self, Write};
+use core::{
+    fmt::{self, Write},
+    ptr,
+};
 
 use crate::{
-    drivers::DriverRegion,
-    platform::{DeviceType, platform_get_device_info},
+    arch::traps::interrupt::{restore_mstatus_mie, save_and_disable_mstatus_mie},
+    config::{SERIAL_BAUD_RATE, SERIAL_RX_BUFFER_SIZE},
+    drivers::{
+        DriverRegion,
+        model::{Driver, DriverClass, DriverError, RegisteredDevice},
+    },
+    irq::{IrqError, irq_ext, irq_register},
+    log,
+    logs::LogLevel,
+    misc::RawTraitObject,
+    platform::{self, DeviceType, Devices},
+    primitives::ring_buff::RingBuffer,
+};
+
+use super::{
+    SERIAL_SUBSYSTEM, SerialDevice, SerialDeviceDriver, SerialDriver, SerialError,
+    serial_interrupt_handler,
 };
 
-use super::{SERIAL_SUBSYSTEM, SerialDevice, SerialDeviceDriver, SerialDriver};
+// Registers offset, the registers are 1 byte wide.
+// Receive buffer (read) / Transmit holding (write) / Divisor latch low (DLAB = 1)
+const RBR_THR_DLL: usize = 0;
+// Interrupt enable / Divisor latch high (DLAB = 1)
+const IER_DLM: usize = 1;
+// FIFO control (write)
+const FCR: usize = 2;
+// Line control
+const LCR: usize = 3;
+// Modem control
+const MCR: usize = 4;
+// Line status
+const LSR: usize = 5;
+
+// IER: received data available interrupt
+const IER_ERBFI: u8 = 1 << 0;
+// FCR: enable FIFOs, clear RX and TX FIFOs, RX trigger level at 1 byte
+const FCR_ENABLE_CLEAR: u8 = 0b0000_0111;
+// LCR: 8 data bits, no parity, 1 stop bit
+const LCR_8N1: u8 = 0b0000_0011;
+// LCR: divisor latch access bit
+const LCR_DLAB: u8 = 1 << 7;
+// MCR: DTR, RTS and OUT2, OUT2 gate the interrupt line on most boards
+const MCR_DTR_RTS_OUT2: u8 = 0b0000_1011;
+// LSR: data ready
+const LSR_DR: u8 = 1 << 0;
+// LSR: transmit holding register empty
+const LSR_THRE: u8 = 1 << 5;
+// LSR: transmitter empty, the holding and shift registers are empty
+const LSR_TEMT: u8 = 1 << 6;
+
+// Number of reads of LSR while waiting for the transmitter.
+const NS16550_TX_SPIN: usize = 100_000;
+// Depth of the receive FIFO, a drain reads at most this number of bytes. The bytes received during
+// the drain keep the receive interrupt raised, they're read by the next drain.
+pub const NS16550_RX_FIFO_SIZE: usize = 16;
+
+// Receive buffer, filled by the receive interrupt handler, or by getchar when polling.
+// Only one Ns16550 is initialized by the platform layer, so one buffer is enough.
+static mut NS16550_RX_BUFF: RingBuffer<u8, SERIAL_RX_BUFFER_SIZE> = RingBuffer::init();
+// Number of bytes dropped because the receive buffer was full.
+static mut NS16550_RX_DROPPED: usize = 0;
 
 /// Structure for Ns16550 driver
 /// region: DriverRegion struct to define address memory region to use with the driver and the address size
+/// irq: interrupt source id of the device on the external interrupt controller, 0 if none
 #[derive(PartialEq)]
 pub struct Ns16550 {
     pub region: DriverRegion,
+    pub irq: u32,
 }
 
 /// Implementing the SerialDriver trait for Ns16550 driver
 impl SerialDriver for Ns16550 {
-    fn putchar(&self, c: u8) {
-        unsafe { core::ptr::write_volatile(self.region.addr as *mut u8, c) }
+    /// Wait for the transmit holding register to be empty and write the char, Timeout if it's
+    /// still full after NS16550_TX_SPIN checks, the char is not written.
+    fn putchar(&self, c: u8) -> Result<(), SerialError> {
+        self.wait_lsr(LSR_THRE)?;
+        self.write_reg(RBR_THR_DLL, c);
+        Ok(())
     }
-    // Not implemented yet, do not use
-    fn getchar(&self) -> u8 {
-        0
+
+    /// Return the oldest received char, None if nothing has been received.
+    /// Drain the device first, the receive interrupt may not be routed yet.
+    fn getchar(&self) -> Option<u8> {
+        let mie = save_and_disable_mstatus_mie();
+        self.drain_rx();
+        #[allow(static_mut_refs)]
+        let c = unsafe {
+            match NS16550_RX_BUFF.size() {
+                0 => None,
+                _ => NS16550_RX_BUFF.pop(),
+            }
+        };
+        restore_mstatus_mie(mie);
+        c
     }
 }
 
-/// Implementing Write trait for Ns16550 to be able to format with core::fmt in print
-/// Use the SerialDriver function implemented in Ns16550
-impl Write for Ns16550 {
-    fn write_str(&mut self, s: &str) -> fmt::Result {
-        for b in s.bytes() {
-            self.putchar(b);
-        }
-        Ok(())
+/// Ns16550 entry of the driver match table, one device per UART node of the FDT.
+pub struct Ns16550Driver;
+
+impl Driver for Ns16550Driver {
+    fn name(&self) -> &'static str {
+        "ns16550a"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        &["ns16550a"]
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Serial
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::Serial
     }
-}
 
-/// Implementation of the Ns16550
-impl Ns16550 {
     /// Init a new Ns16550 from the platform layer
-    pub fn init() {
-        let device_info = match platform_get_device_info("ns16550a", DeviceType::Serial) {
-            Some(d) => d,
-            None => return,
-        };
+    fn probe(&self, device_info: &Devices) -> Result<(), DriverError> {
         // Check MMIO reg
         if device_info.header.device_addr.addr == 0 {
             panic!(
@@ -72,14 +164,191 @@ impl Ns16550 {
                 "Encounter a wrong MMIO reg size when initializing device. Check the device definition or hardware."
             );
         }
-        let ns16550: Ns16550 = Ns16550 {
+        // Allow the use of expect, once we got the device asked, the trait should be working and
+        // we should get the trait behind the Option<>
+        #[allow(clippy::expect_used)]
+        let device_info_trait = device_info
+            .info
+            .expect("Error: failed to get device trait behind option.");
+        let raw: RawTraitObject = unsafe { core::mem::transmute(device_info_trait) };
+        let serial_device_ptr = raw.data as *const platform::PlatformSerialDevice;
+        let serial_device_ref = unsafe { &*serial_device_ptr };
+        let mut ns16550: Ns16550 = Ns16550 {
             region: device_info.header.device_addr,
+            irq: serial_device_ref.irq,
         };
+        if let Err(e) = ns16550.hw_init(serial_device_ref.clock_frequency) {
+            log!(
+                LogLevel::Warn,
+                "Ns16550: cannot reach baud rate {} with clock frequency {}: {:?}, keeping the firmware divisor",
+                SERIAL_BAUD_RATE,
+                serial_device_ref.clock_frequency,
+                e
+            );
+        }
+        if ns16550.irq != 0
+            && let Err(e) = irq_register(irq_ext(ns16550.irq), 1, serial_interrupt_handler, 0, None)
+            && e != IrqError::AlreadyRegistered
+        {
+            log!(
+                LogLevel::Warn,
+                "Ns16550: failed to attach the receive interrupt: {:?}, receive by polling only",
+                e
+            );
+            // A reader polls the device without interrupt.
+            ns16550.irq = 0;
+        }
         let device = SerialDevice {
             _id: 0,
             default_console: false,
             driver: SerialDeviceDriver::Ns16550(ns16550),
         };
-        SERIAL_SUBSYSTEM.add_serial(device);
+        SERIAL_SUBSYSTEM
+            .add_serial(device)
+            .map(|_| ())
+            .ok_or(DriverError::InitFailed)
+    }
+
+    /// Stop the device interrupts and remove it from the serial sub-system, the default console
+    /// cannot be removed.
+    fn remove(&self, device: &RegisteredDevice) -> Result<(), DriverError> {
+        let index = SERIAL_SUBSYSTEM
+            .find_region(device.region)
+            .ok_or(DriverError::NotRegistered)?;
+        let serial = SERIAL_SUBSYSTEM
+            .remove_serial(index)
+            .ok_or(DriverError::Busy)?;
+        if let SerialDeviceDriver::Ns16550(ns16550) = serial.driver {
+            ns16550.write_reg(IER_DLM, 0);
+        }
+        Ok(())
+    }
+}
+
+/// Implementing Write trait for Ns16550 to be able to format with core::fmt in print
+/// Use the SerialDriver function implemented in Ns16550
+impl Write for Ns16550 {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for b in s.bytes() {
+            self.putchar(b).map_err(|_| fmt::Error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Implementation of the Ns16550
+impl Ns16550 {
+    /// Initialize the device: 8N1 frame, baud rate divisor from the clock frequency, FIFOs
+    /// enabled and cleared, and received data available interrupt enabled.
+    /// If the clock frequency is unknown, keep the divisor set by the firmware or emulator.
+    /// If the baud rate cannot be reached, the device is still initialized with the divisor set by
+    /// the firmware or emulator, and an error is returned.
+    pub fn hw_init(&self, clock_frequency: u32) -> Result<(), SerialError> {
+        // Disable all interrupts while configuring the device
+        self.write_reg(IER_DLM, 0);
+        let divisor = match clock_frequency {
+            0 => Ok(None),
+            _ => Ns16550::divisor(clock_frequency).map(Some),
+        };
+        if let Ok(Some(divisor)) = divisor {
+            self.write_divisor(divisor);
+        }
+        // Clear DLAB and set the frame format
+        self.write_reg(LCR, LCR_8N1);
+        self.write_reg(FCR, FCR_ENABLE_CLEAR);
+        self.write_reg(MCR, MCR_DTR_RTS_OUT2);
+        self.write_reg(IER_DLM, IER_ERBFI);
+        divisor.map(|_| ())
+    }
+
+    /// Compute the baud rate divisor from the clock frequency, return an error if the baud rate
+    /// cannot be reached.
+    pub fn divisor(clock_frequency: u32) -> Result<u32, SerialError> {
+        let divisor = SERIAL_BAUD_RATE
+            .checked_mul(16)
+            .map(|rate| clock_frequency / rate)
+            .ok_or(SerialError::InvalidClock)?;
+        if divisor == 0 || divisor > u16::MAX as u32 {
+            return Err(SerialError::InvalidClock);
+        }
+        Ok(divisor)
+    }
+
+    fn write_divisor(&self, divisor: u32) {
+        self.write_reg(LCR, LCR_DLAB);
+        self.write_reg(RBR_THR_DLL, (divisor & 0xFF) as u8);
+        self.write_reg(IER_DLM, ((divisor >> 8) & 0xFF) as u8);
+    }
+
+    /// Wait until the transmitter is empty, the last byte is fully sent. Timeout if it's still
+    /// sending after NS16550_TX_SPIN checks.
+    pub fn flush(&self) -> Result<(), SerialError> {
+        self.wait_lsr(LSR_TEMT)
+    }
+
+    /// Wait for the LSR bits to be set, Timeout after NS16550_TX_SPIN checks.
+    fn wait_lsr(&self, bits: u8) -> Result<(), SerialError> {
+        for _ in 0..NS16550_TX_SPIN {
+            if self.read_reg(LSR) & bits == bits {
+                return Ok(());
+            }
+        }
+        Err(SerialError::Timeout)
+    }
+
+    /// Reprogram the baud rate divisor after a change of the input clock, the frame format and
+    /// the interrupts are kept. If the baud rate cannot be reached, the old divisor is kept and an
+    /// error is returned.
+    pub fn set_clock(&self, clock_frequency: u32) -> Result<(), SerialError> {
+        let divisor = Ns16550::divisor(clock_frequency)?;
+        // A transmitter stuck after the timeout loses its byte, the divisor is changed anyway.
+        let _ = self.flush();
+        let mie = save_and_disable_mstatus_mie();
+        // The interrupt enable register is the divisor latch high while DLAB is set.
+        let ier = self.read_reg(IER_DLM);
+        self.write_divisor(divisor);
+        self.write_reg(LCR, LCR_8N1);
+        self.write_reg(IER_DLM, ier);
+        restore_mstatus_mie(mie);
+        Ok(())
+    }
+
+    /// Receive interrupt handler, move all received bytes to the receive buffer.
+    /// Must be called from the external interrupt handler when the device irq is raised.
+    pub fn interrupt_handler(&self) {
+        self.drain_rx();
+    }
+
+    /// Return the number of bytes dropped because the receive buffer was full.
+    pub fn rx_dropped(&self) -> usize {
+        unsafe { NS16550_RX_DROPPED }
+    }
+
+    /// Read the bytes available in the device, at most NS16550_RX_FIFO_SIZE, and push them to
+    /// the receive buffer.
+    /// Must be called with interrupts disabled or from the interrupt handler.
+    fn drain_rx(&self) {
+        #[allow(static_mut_refs)]
+        let rx_buff = unsafe { &mut NS16550_RX_BUFF };
+        for _ in 0..NS16550_RX_FIFO_SIZE {
+            if self.read_reg(LSR) & LSR_DR == 0 {
+                break;
+            }
+            let c = self.read_reg(RBR_THR_DLL);
+            if rx_buff.is_full() {
+                // Don't log here, the log would be written on this device from an interrupt.
+                unsafe { NS16550_RX_DROPPED += 1 };
+                continue;
+            }
+            rx_buff.push(c);
+        }
+    }
+
+    fn read_reg(&self, off: usize) -> u8 {
+        unsafe { ptr::read_volatile((self.region.addr + off) as *const u8) }
+    }
+
+    fn write_reg(&self, off: usize, value: u8) {
+        unsafe { ptr::write_volatile((self.region.addr + off) as *mut u8, value) }
     }
 }
@@ -0,0 +1,355 @@
+// See documentation in `Documentation/hardware/virtio.md`
+/*
+File info: Virtio console driver.
+
+Test coverage: None.
+
+Tested:
+
+Not tested:
+- Everything.
+
+Reasons:
+- Testing a virtio driver need to have an MMIO emulation.
+
+Tests files:
+- 'src/tests/drivers/serials/subsystem.rs'
+*/
+
+use core::{
+    cell::Cell,
+    fmt::{self, Write},
+};
+
+use crate::{
+    arch::traps::interrupt::{restore_mstatus_mie, save_and_disable_mstatus_mie},
+    config::{SERIAL_RX_BUFFER_SIZE, VIRTIO_CONSOLE_MAX_SIZE},
+    drivers::{
+        model::{Driver, DriverClass, DriverError, RegisteredDevice},
+        virtio::{
+            VIRTIO_DEVICE_ID_CONSOLE, VirtioError, VirtioMmio,
+            queue::{VIRTQ_SIZE, VirtqBuffer, Virtqueue},
+            virtio_mmio_match,
+        },
+    },
+    irq::{IrqError, irq_ext, irq_register},
+    log,
+    logs::LogLevel,
+    platform::{DeviceType, Devices},
+    primitives::ring_buff::RingBuffer,
+};
+
+use super::{
+    SERIAL_SUBSYSTEM, SerialDevice, SerialDeviceDriver, SerialDriver, SerialError,
+    serial_interrupt_handler,
+};
+
+// Queues of port 0, the only port without the multiport feature.
+const VIRTIO_CONSOLE_RECEIVEQ: u32 = 0;
+const VIRTIO_CONSOLE_TRANSMITQ: u32 = 1;
+
+// Size of each buffer given to the device, one buffer per descriptor.
+const VIRTIO_CONSOLE_RX_BUF_SIZE: usize = 32;
+const VIRTIO_CONSOLE_TX_BUF_SIZE: usize = 64;
+
+// Number of checks of the used ring while waiting for a free transmit descriptor.
+const VIRTIO_CONSOLE_TX_SPIN: usize = 100_000;
+
+/// Memory shared with the device, and the receive buffer filled from the receive queue.
+struct VirtioConsoleBuffers {
+    rx: [[u8; VIRTIO_CONSOLE_RX_BUF_SIZE]; VIRTQ_SIZE as usize],
+    // Indexed by the head descriptor of the transmit chain
+    tx: [[u8; VIRTIO_CONSOLE_TX_BUF_SIZE]; VIRTQ_SIZE as usize],
+    rx_buff: RingBuffer<u8, SERIAL_RX_BUFFER_SIZE>,
+    // Number of bytes dropped because the receive buffer was full
+    rx_dropped: usize,
+    // Number of bytes dropped because the device didn't free a transmit descriptor in time
+    tx_dropped: usize,
+}
+
+static mut VIRTIO_CONSOLE_BUFFERS: [VirtioConsoleBuffers; VIRTIO_CONSOLE_MAX_SIZE] = [const {
+    VirtioConsoleBuffers {
+        rx: [[0; VIRTIO_CONSOLE_RX_BUF_SIZE]; VIRTQ_SIZE as usize],
+        tx: [[0; VIRTIO_CONSOLE_TX_BUF_SIZE]; VIRTQ_SIZE as usize],
+        rx_buff: RingBuffer::init(),
+        rx_dropped: 0,
+        tx_dropped: 0,
+    }
+};
+    VIRTIO_CONSOLE_MAX_SIZE];
+static mut VIRTIO_CONSOLE_BUFFERS_USED: usize = 0;
+
+/// Structure for the virtio console driver
+/// transport: the virtio-mmio slot of the device
+/// rx, tx: receive and transmit queues of port 0
+/// buffers: index in VIRTIO_CONSOLE_BUFFERS
+/// rx_slot: receive buffer given to the device with each head descriptor
+#[derive(PartialEq)]
+pub struct VirtioConsole {
+    pub transport: VirtioMmio,
+    rx: Virtqueue,
+    tx: Virtqueue,
+    buffers: usize,
+    rx_slot: [Cell<u8>; VIRTQ_SIZE as usize],
+}
+
+// The kernel is single-threaded and the queues are only accessed with interrupts disabled.
+unsafe impl Sync for VirtioConsole {}
+
+/// Implementing the SerialDriver trait for the virtio console driver
+impl SerialDriver for VirtioConsole {
+    /// Timeout if the device didn't free a transmit buffer in time, the char is dropped.
+    fn putchar(&self, c: u8) -> Result<(), SerialError> {
+        let dropped = self.tx_dropped();
+        self.write_bytes(&[c]);
+        if self.tx_dropped() != dropped {
+            return Err(SerialError::Timeout);
+        }
+        Ok(())
+    }
+
+    /// Return the oldest received char, None if nothing has been received.
+    /// Read the receive queue first, the interrupt may not be routed yet.
+    fn getchar(&self) -> Option<u8> {
+        let mie = save_and_disable_mstatus_mie();
+        self.drain_rx();
+        let rx_buff = &mut self.buffers_mut().rx_buff;
+        let c = match rx_buff.size() {
+            0 => None,
+            _ => rx_buff.pop(),
+        };
+        restore_mstatus_mie(mie);
+        c
+    }
+}
+
+/// Virtio-console entry of the driver match table, the virtio-mmio slots are shared with the
+/// other virtio drivers.
+pub struct VirtioConsoleDriver;
+
+impl Driver for VirtioConsoleDriver {
+    fn name(&self) -> &'static str {
+        "virtio-console"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        &["virtio,mmio"]
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Virtio
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::Serial
+    }
+
+    /// Initialize the virtio console of the slot and add it to the serial sub-system.
+    fn probe(&self, device_info: &Devices) -> Result<(), DriverError> {
+        let transport =
+            virtio_mmio_match(device_info, VIRTIO_DEVICE_ID_CONSOLE).ok_or(DriverError::NoMatch)?;
+        let virtio_console = VirtioConsole::init_device(transport).map_err(|e| {
+            log!(
+                LogLevel::Warn,
+                "Virtio-console: failed to initialize the device at {:#x}: {:?}",
+                transport.region.addr,
+                e
+            );
+            DriverError::InitFailed
+        })?;
+        let device = SerialDevice {
+            _id: 0,
+            default_console: false,
+            driver: SerialDeviceDriver::VirtioConsole(virtio_console),
+        };
+        SERIAL_SUBSYSTEM
+            .add_serial(device)
+            .map(|_| ())
+            .ok_or(DriverError::InitFailed)
+    }
+
+    /// Reset the device and remove it from the serial sub-system, the default console cannot be
+    /// removed. The queues and buffers of the device are not given back.
+    fn remove(&self, device: &RegisteredDevice) -> Result<(), DriverError> {
+        let index = SERIAL_SUBSYSTEM
+            .find_region(device.region)
+            .ok_or(DriverError::NotRegistered)?;
+        let serial = SERIAL_SUBSYSTEM
+            .remove_serial(index)
+            .ok_or(DriverError::Busy)?;
+        if let SerialDeviceDriver::VirtioConsole(virtio_console) = serial.driver {
+            virtio_console.transport.reset();
+        }
+        Ok(())
+    }
+}
+
+impl Write for VirtioConsole {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl VirtioConsole {
+    fn init_device(transport: VirtioMmio) -> Result<Self, VirtioError> {
+        let buffers = unsafe { VIRTIO_CONSOLE_BUFFERS_USED };
+        if buffers == VIRTIO_CONSOLE_MAX_SIZE {
+            return Err(VirtioError::OutOfQueueMemory);
+        }
+        // No feature negotiated: a single port, without the console size.
+        transport.init(0)?;
+        let rx = Virtqueue::alloc()?;
+        let tx = Virtqueue::alloc()?;
+        if let Err(e) = transport
+            .setup_queue(VIRTIO_CONSOLE_RECEIVEQ, &rx)
+            .and_then(|_| transport.setup_queue(VIRTIO_CONSOLE_TRANSMITQ, &tx))
+        {
+            transport.fail();
+            return Err(e);
+        }
+        let mut virtio_console = VirtioConsole {
+            transport,
+            rx,
+            tx,
+            buffers,
+            rx_slot: [const { Cell::new(0) }; VIRTQ_SIZE as usize],
+        };
+        // Give all the receive buffers to the device.
+        for slot in 0..VIRTQ_SIZE as usize {
+            virtio_console.post_rx(slot)?;
+        }
+        if transport.irq != 0
+            && let Err(e) =
+                irq_register(irq_ext(transport.irq), 1, serial_interrupt_handler, 0, None)
+            && e != IrqError::AlreadyRegistered
+        {
+            log!(
+                LogLevel::Warn,
+                "Virtio-console: failed to attach the interrupt: {:?}, receive by polling only",
+                e
+            );
+            // A reader polls the device without interrupt.
+            virtio_console.transport.irq = 0;
+        }
+        transport.driver_ok();
+        transport.notify(VIRTIO_CONSOLE_RECEIVEQ);
+        unsafe { VIRTIO_CONSOLE_BUFFERS_USED += 1 };
+        Ok(virtio_console)
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    fn buffers_mut(&self) -> &mut VirtioConsoleBuffers {
+        #[allow(static_mut_refs)]
+        unsafe {
+            &mut VIRTIO_CONSOLE_BUFFERS[self.buffers]
+        }
+    }
+
+    /// Give the receive buffer at slot to the device, the device must be notified after.
+    fn post_rx(&self, slot: usize) -> Result<(), VirtioError> {
+        let buffer = VirtqBuffer {
+            addr: self.buffers_mut().rx[slot].as_ptr() as usize,
+            len: VIRTIO_CONSOLE_RX_BUF_SIZE as u32,
+            device_writable: true,
+        };
+        let head = self.rx.add_chain(&[buffer])?;
+        self.rx_slot[head as usize].set(slot as u8);
+        Ok(())
+    }
+
+    /// Move the bytes received by the device to the receive buffer and give the buffers back to
+    /// the device. The read stops at an invalid used buffer.
+    /// Must be called with interrupts disabled or from the interrupt handler.
+    fn drain_rx(&self) {
+        let mut posted = false;
+        while let Ok(Some((head, len))) = self.rx.pop_used() {
+            let slot = self.rx_slot[head as usize].get() as usize;
+            let buffers = self.buffers_mut();
+            let len = (len as usize).min(VIRTIO_CONSOLE_RX_BUF_SIZE);
+            for i in 0..len {
+                if buffers.rx_buff.is_full() {
+                    // Don't log here, the log could be written on this device from an interrupt.
+                    buffers.rx_dropped += 1;
+                    continue;
+                }
+                buffers.rx_buff.push(buffers.rx[slot][i]);
+            }
+            // A descriptor was just freed, the buffer always fits back.
+            if self.post_rx(slot).is_ok() {
+                posted = true;
+            }
+        }
+        if posted {
+            self.transport.notify(VIRTIO_CONSOLE_RECEIVEQ);
+        }
+    }
+
+    /// Free the transmit descriptors used by the device, until an invalid used buffer.
+    fn reclaim_tx(&self) {
+        while let Ok(Some(_)) = self.tx.pop_used() {}
+    }
+
+    /// Send bytes to the device. The bytes are copied in transmit buffers, the function return as
+    /// soon as the device is notified, without waiting for the transmission.
+    /// If the device doesn't free a transmit buffer in time, the remaining bytes are dropped.
+    pub fn write_bytes(&self, data: &[u8]) {
+        for (i, chunk) in data.chunks(VIRTIO_CONSOLE_TX_BUF_SIZE).enumerate() {
+            let mie = save_and_disable_mstatus_mie();
+            let mut spin: usize = 0;
+            let head = loop {
+                self.reclaim_tx();
+                if let Some(head) = self.tx.next_head() {
+                    break Some(head);
+                }
+                spin += 1;
+                if spin == VIRTIO_CONSOLE_TX_SPIN {
+                    break None;
+                }
+            };
+            let buffers = self.buffers_mut();
+            let head = match head {
+                Some(h) => h,
+                None => {
+                    buffers.tx_dropped += data.len() - i * VIRTIO_CONSOLE_TX_BUF_SIZE;
+                    restore_mstatus_mie(mie);
+                    return;
+                }
+            };
+            let tx_buf = &mut buffers.tx[head as usize];
+            tx_buf[..chunk.len()].copy_from_slice(chunk);
+            let buffer = VirtqBuffer {
+                addr: tx_buf.as_ptr() as usize,
+                len: chunk.len() as u32,
+                device_writable: false,
+            };
+            // next_head returned a free descriptor, the chain of one buffer fits.
+            if self.tx.add_chain(&[buffer]).is_ok() {
+                self.transport.notify(VIRTIO_CONSOLE_TRANSMITQ);
+            }
+            restore_mstatus_mie(mie);
+        }
+    }
+
+    /// Called from the serial sub-system interrupt handler.
+    pub fn interrupt_handler(&self) {
+        self.transport.ack_interrupt();
+        self.drain_rx();
+        self.reclaim_tx();
+    }
+
+    /// Return the number of bytes dropped because the receive buffer was full.
+    pub fn rx_dropped(&self) -> usize {
+        self.buffers_mut().rx_dropped
+    }
+
+    /// Return the number of bytes dropped because no transmit buffer was free.
+    pub fn tx_dropped(&self) -> usize {
+        self.buffers_mut().tx_dropped
+    }
+}
+
+const _: () = assert!(
+    VIRTQ_SIZE as usize <= u8::MAX as usize,
+    "Virtio-console receive slots are stored on a u8"
+);
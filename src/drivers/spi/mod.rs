@@ -0,0 +1,292 @@
+// See documentation in `Documentation/kernel/bus.md`
+/*
+File info: SPI bus trait and SPI sub-system.
+
+Test coverage: Sub-system, bus lock and argument checks.
+
+Tested:
+- Sub-system initialized empty, add and duplicate device.
+- Bus lock taken by the guard and released when dropped.
+- Invalid length, invalid chip select and missing device errors.
+- Clock divider computation.
+
+Not tested:
+- Transfers.
+
+Reasons:
+- QEMU virt has no SPI controller, a SPI controller need an MMIO emulation to be tested.
+
+Tests files:
+- 'src/tests/drivers/spi/subsystem.rs'
+*/
+
+use core::ops::Deref;
+
+use crate::{
+    config::SPI_MAX_SIZE,
+    drivers::{
+        DriverRegion,
+        bus::BusLock,
+        model::{DriverClass, driver_probe_class},
+        pool::DevicePool,
+    },
+    log,
+    logs::LogLevel,
+};
+
+pub mod sifive_spi;
+
+use sifive_spi::SifiveSpi;
+
+/// All errors that can happen on a SPI request.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SpiError {
+    // There's no SPI controller at this index.
+    NoDevice,
+    // The chip select doesn't exist on the controller.
+    InvalidChipSelect,
+    // The tx and rx buffers of a full duplex transfer don't have the same length.
+    InvalidLength,
+    // The bus is locked and the lock cannot be waited.
+    Busy,
+    // The controller didn't complete the frame.
+    Timeout,
+}
+
+/// Clock polarity and phase, CPOL is the high bit and CPHA the low bit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SpiMode {
+    Mode0,
+    Mode1,
+    Mode2,
+    Mode3,
+}
+
+/// A chip on the bus, the mode and clock are set on each select.
+/// cs: chip select of the chip on the controller.
+/// freq_hz: max clock frequency of the chip, 0 to keep the controller clock.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpiChip {
+    pub cs: u32,
+    pub mode: SpiMode,
+    pub freq_hz: u32,
+}
+
+/// Common interface of the SPI controllers. The drivers implement the chip select and the frame
+/// exchange, the transfers are built on them and keep the chip selected for the whole transfer.
+/// The bus must be locked by the caller, see SpiSubSystem::lock.
+pub trait SpiBus {
+    /// Number of chip selects of the controller.
+    fn num_cs(&self) -> u32;
+
+    /// Configure the controller for the chip and assert its chip select, it stays asserted until
+    /// deselect.
+    fn select(&self, chip: &SpiChip) -> Result<(), SpiError>;
+
+    /// Release the chip select.
+    fn deselect(&self);
+
+    /// Send a byte and return the byte received at the same time.
+    fn exchange(&self, byte: u8) -> Result<u8, SpiError>;
+
+    /// Full duplex transfer, each byte of tx is sent while a byte is received in rx.
+    fn transfer(&self, chip: &SpiChip, tx: &[u8], rx: &mut [u8]) -> Result<(), SpiError> {
+        if tx.len() != rx.len() {
+            return Err(SpiError::InvalidLength);
+        }
+        self.select(chip)?;
+        let res = tx.iter().zip(rx.iter_mut()).try_for_each(|(t, r)| {
+            *r = self.exchange(*t)?;
+            Ok(())
+        });
+        self.deselect();
+        res
+    }
+
+    /// Send tx, the received bytes are dropped.
+    fn write(&self, chip: &SpiChip, tx: &[u8]) -> Result<(), SpiError> {
+        self.write_read(chip, tx, &mut [])
+    }
+
+    /// Send tx then receive rx with the chip selected for both, like a command followed by its
+    /// response. 0 is sent while receiving.
+    fn write_read(&self, chip: &SpiChip, tx: &[u8], rx: &mut [u8]) -> Result<(), SpiError> {
+        self.select(chip)?;
+        let res = tx
+            .iter()
+            .try_for_each(|t| self.exchange(*t).map(|_| ()))
+            .and_then(|_| {
+                rx.iter_mut().try_for_each(|r| {
+                    *r = self.exchange(0)?;
+                    Ok(())
+                })
+            });
+        self.deselect();
+        res
+    }
+}
+
+#[derive(PartialEq)]
+pub enum SpiDeviceDriver {
+    SifiveSpi(SifiveSpi),
+}
+
+pub struct SpiDevice {
+    pub driver: SpiDeviceDriver,
+    lock: BusLock,
+}
+
+// Two devices are the same if they use the same controller, the lock state is ignored.
+impl PartialEq for SpiDevice {
+    fn eq(&self, other: &Self) -> bool {
+        self.driver == other.driver
+    }
+}
+
+impl SpiDevice {
+    pub const fn new(driver: SpiDeviceDriver) -> Self {
+        SpiDevice {
+            driver,
+            lock: BusLock::init(),
+        }
+    }
+
+    /// Memory region of the device, used to find the device bound in the device registry.
+    pub fn region(&self) -> DriverRegion {
+        match &self.driver {
+            SpiDeviceDriver::SifiveSpi(sifive_spi) => sifive_spi.region,
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_locked()
+    }
+}
+
+impl SpiBus for SpiDevice {
+    fn num_cs(&self) -> u32 {
+        match &self.driver {
+            SpiDeviceDriver::SifiveSpi(sifive_spi) => sifive_spi.num_cs(),
+        }
+    }
+
+    fn select(&self, chip: &SpiChip) -> Result<(), SpiError> {
+        match &self.driver {
+            SpiDeviceDriver::SifiveSpi(sifive_spi) => sifive_spi.select(chip),
+        }
+    }
+
+    fn deselect(&self) {
+        match &self.driver {
+            SpiDeviceDriver::SifiveSpi(sifive_spi) => sifive_spi.deselect(),
+        }
+    }
+
+    fn exchange(&self, byte: u8) -> Result<u8, SpiError> {
+        match &self.driver {
+            SpiDeviceDriver::SifiveSpi(sifive_spi) => sifive_spi.exchange(byte),
+        }
+    }
+}
+
+/// Locked SPI controller, the bus is unlocked when the guard is dropped.
+pub struct SpiGuard<'a> {
+    device: &'a SpiDevice,
+}
+
+impl Deref for SpiGuard<'_> {
+    type Target = SpiDevice;
+
+    fn deref(&self) -> &SpiDevice {
+        self.device
+    }
+}
+
+impl Drop for SpiGuard<'_> {
+    fn drop(&mut self) {
+        self.device.lock.unlock();
+    }
+}
+
+/// Define and manage all SPI controllers.
+pub struct SpiSubSystem {
+    pub devices: DevicePool<SpiDevice, SPI_MAX_SIZE>,
+}
+
+impl SpiSubSystem {
+    pub const fn init() -> Self {
+        SpiSubSystem {
+            devices: DevicePool::init("SPI"),
+        }
+    }
+
+    pub fn add_spi(&self, new_spi: SpiDevice) -> Option<usize> {
+        self.devices.add(new_spi)
+    }
+
+    pub fn get_spi(&self, index: usize) -> Option<&SpiDevice> {
+        self.devices.get(index)
+    }
+
+    pub fn get_spi_array_size(&self) -> usize {
+        self.devices.size()
+    }
+
+    /// Lock the controller at index, a task waits until the bus is free.
+    /// Busy if the lock cannot be waited, outside a task or if the task already holds it.
+    pub fn lock(&self, index: usize) -> Result<SpiGuard<'_>, SpiError> {
+        let device = self.get_spi(index).ok_or(SpiError::NoDevice)?;
+        match device.lock.lock() {
+            true => Ok(SpiGuard { device }),
+            false => Err(SpiError::Busy),
+        }
+    }
+
+    /// Lock the controller at index without waiting, Busy if already locked.
+    pub fn try_lock(&self, index: usize) -> Result<SpiGuard<'_>, SpiError> {
+        let device = self.get_spi(index).ok_or(SpiError::NoDevice)?;
+        match device.lock.try_lock() {
+            true => Ok(SpiGuard { device }),
+            false => Err(SpiError::Busy),
+        }
+    }
+}
+
+pub static SPI_SUBSYSTEM: SpiSubSystem = SpiSubSystem::init();
+
+/// Lock the SPI controller at index for a transaction of multiple transfers.
+pub fn spi_lock(index: usize) -> Result<SpiGuard<'static>, SpiError> {
+    SPI_SUBSYSTEM.lock(index)
+}
+
+/// Full duplex transfer with the chip, the bus is locked for the transfer.
+pub fn spi_transfer(
+    index: usize,
+    chip: &SpiChip,
+    tx: &[u8],
+    rx: &mut [u8],
+) -> Result<(), SpiError> {
+    spi_lock(index)?.transfer(chip, tx, rx)
+}
+
+pub fn spi_write(index: usize, chip: &SpiChip, tx: &[u8]) -> Result<(), SpiError> {
+    spi_lock(index)?.write(chip, tx)
+}
+
+/// Send tx then receive rx, the chip stays selected and the bus locked for both.
+pub fn spi_write_read(
+    index: usize,
+    chip: &SpiChip,
+    tx: &[u8],
+    rx: &mut [u8],
+) -> Result<(), SpiError> {
+    spi_lock(index)?.write_read(chip, tx, rx)
+}
+
+/// SPI controllers are optional, the sub-system stays empty without device.
+pub fn init_spi_subsystem() {
+    driver_probe_class(DriverClass::Spi);
+    if SPI_SUBSYSTEM.get_spi_array_size() == 0 {
+        log!(LogLevel::Info, "No SPI controller found.");
+    }
+}
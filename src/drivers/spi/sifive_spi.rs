@@ -0,0 +1,226 @@
+// See documentation in `Documentation/hardware/sifive_spi.md`
+/*
+File info: SiFive SPI driver.
+
+Test coverage: Clock divider and chip select check.
+
+Tested:
+- Clock divider computation.
+- Invalid chip select.
+
+Not tested:
+- Everything else.
+
+Reasons:
+- Testing a SPI driver need to have an MMIO emulation.
+
+Tests files:
+- 'src/tests/drivers/spi/subsystem.rs'
+*/
+
+use core::ptr;
+
+use crate::{
+    drivers::{
+        DriverRegion,
+        model::{Driver, DriverClass, DriverError},
+    },
+    log,
+    logs::LogLevel,
+    misc::RawTraitObject,
+    platform::{self, DeviceType, Devices},
+};
+
+use super::{SPI_SUBSYSTEM, SpiBus, SpiChip, SpiDevice, SpiDeviceDriver, SpiError, SpiMode};
+
+// Registers offset
+const SCKDIV: usize = 0x00;
+const SCKMODE: usize = 0x04;
+const CSID: usize = 0x10;
+const CSDEF: usize = 0x14;
+const CSMODE: usize = 0x18;
+const FMT: usize = 0x40;
+const TXDATA: usize = 0x48;
+const RXDATA: usize = 0x4c;
+const IE: usize = 0x70;
+
+// Chip select modes, HOLD keeps the chip select asserted between frames.
+const CSMODE_AUTO: u32 = 0;
+const CSMODE_HOLD: u32 = 2;
+// TXDATA full flag and RXDATA empty flag.
+const FIFO_FLAG: u32 = 1 << 31;
+// Single line protocol, MSB first, 8 bits frames.
+const FMT_8_BITS: u32 = 8 << 16;
+const SCKDIV_MAX: u32 = 0xfff;
+// Max number of register reads while waiting for the FIFO, a frame takes at most a few thousand
+// cycles at the lowest clock.
+const FIFO_POLL_MAX: usize = 1_000_000;
+
+/// Clock divider for the wanted frequency, SCK = clock_frequency / (2 * (div + 1)).
+/// The divider is rounded up, the SCK is never above freq_hz.
+pub fn sifive_spi_sckdiv(clock_frequency: u32, freq_hz: u32) -> u32 {
+    if freq_hz == 0 {
+        return SCKDIV_MAX;
+    }
+    clock_frequency
+        .div_ceil(2 * freq_hz)
+        .saturating_sub(1)
+        .min(SCKDIV_MAX)
+}
+
+/// Structure for the SiFive SPI driver
+/// region: DriverRegion struct to define address memory region to use with the driver and the address size
+/// clock_frequency: input clock of the controller, 0 if unknown and the clock divider is not changed
+/// num_cs: number of chip selects, read from the CSDEF register width
+#[derive(PartialEq)]
+pub struct SifiveSpi {
+    pub region: DriverRegion,
+    pub clock_frequency: u32,
+    pub num_cs: u32,
+}
+
+impl SifiveSpi {
+    pub const fn new(region: DriverRegion, clock_frequency: u32, num_cs: u32) -> Self {
+        SifiveSpi {
+            region,
+            clock_frequency,
+            num_cs,
+        }
+    }
+
+    /// Number of chip selects, the unimplemented CSDEF bits are read as 0.
+    fn probe_num_cs(&self) -> u32 {
+        let csdef = self.read_reg(CSDEF);
+        self.write_reg(CSDEF, u32::MAX);
+        let num_cs = self.read_reg(CSDEF).count_ones();
+        self.write_reg(CSDEF, csdef);
+        num_cs
+    }
+
+    fn read_reg(&self, off: usize) -> u32 {
+        unsafe { ptr::read_volatile((self.region.addr + off) as *const u32) }
+    }
+
+    fn write_reg(&self, off: usize, value: u32) {
+        unsafe { ptr::write_volatile((self.region.addr + off) as *mut u32, value) }
+    }
+}
+
+impl SpiBus for SifiveSpi {
+    fn num_cs(&self) -> u32 {
+        self.num_cs
+    }
+
+    fn select(&self, chip: &SpiChip) -> Result<(), SpiError> {
+        if chip.cs >= self.num_cs {
+            return Err(SpiError::InvalidChipSelect);
+        }
+        if self.clock_frequency != 0 && chip.freq_hz != 0 {
+            self.write_reg(
+                SCKDIV,
+                sifive_spi_sckdiv(self.clock_frequency, chip.freq_hz),
+            );
+        }
+        let sckmode = match chip.mode {
+            SpiMode::Mode0 => 0b00,
+            SpiMode::Mode1 => 0b01,
+            SpiMode::Mode2 => 0b10,
+            SpiMode::Mode3 => 0b11,
+        };
+        self.write_reg(SCKMODE, sckmode);
+        self.write_reg(CSID, chip.cs);
+        // The chip select is asserted with the first frame and held until deselect.
+        self.write_reg(CSMODE, CSMODE_HOLD);
+        Ok(())
+    }
+
+    fn deselect(&self) {
+        self.write_reg(CSMODE, CSMODE_AUTO);
+    }
+
+    fn exchange(&self, byte: u8) -> Result<u8, SpiError> {
+        let mut sent = false;
+        for _ in 0..FIFO_POLL_MAX {
+            if self.read_reg(TXDATA) & FIFO_FLAG == 0 {
+                self.write_reg(TXDATA, byte as u32);
+                sent = true;
+                break;
+            }
+        }
+        if !sent {
+            return Err(SpiError::Timeout);
+        }
+        // Reading RXDATA pop the FIFO, the value is valid when the empty flag is clear.
+        for _ in 0..FIFO_POLL_MAX {
+            let rx = self.read_reg(RXDATA);
+            if rx & FIFO_FLAG == 0 {
+                return Ok(rx as u8);
+            }
+        }
+        Err(SpiError::Timeout)
+    }
+}
+
+/// SiFive SPI entry of the driver match table.
+pub struct SifiveSpiDriver;
+
+impl Driver for SifiveSpiDriver {
+    fn name(&self) -> &'static str {
+        "sifive-spi"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        &["sifive,spi0"]
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Spi
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::Spi
+    }
+
+    /// Init a new SiFive SPI from the platform layer, in 8 bits frames with the interrupts
+    /// disabled, the transfers poll the FIFO.
+    fn probe(&self, device_info: &Devices) -> Result<(), DriverError> {
+        if device_info.header.device_addr.addr == 0 {
+            panic!(
+                "Encounter a wrong MMIO reg when initializing device. Check the device definition or hardware."
+            );
+        }
+        // Allow the use of expect, once we got the device asked, the trait should be working and
+        // we should get the trait behind the Option<>
+        #[allow(clippy::expect_used)]
+        let device_info_trait = device_info
+            .info
+            .expect("Error: failed to get device trait behind option.");
+        let raw: RawTraitObject = unsafe { core::mem::transmute(device_info_trait) };
+        let spi_device_ptr = raw.data as *const platform::PlatformSpiDevice;
+        let spi_device_ref = unsafe { &*spi_device_ptr };
+        let mut spi = SifiveSpi::new(
+            device_info.header.device_addr,
+            spi_device_ref.clock_frequency,
+            0,
+        );
+        spi.num_cs = spi.probe_num_cs();
+        if spi.num_cs == 0 {
+            return Err(DriverError::InitFailed);
+        }
+        if spi.clock_frequency == 0 {
+            log!(
+                LogLevel::Warn,
+                "SiFive SPI: unknown input clock, the SPI clock is not configured"
+            );
+        }
+        spi.write_reg(IE, 0);
+        spi.write_reg(FMT, FMT_8_BITS);
+        spi.write_reg(CSMODE, CSMODE_AUTO);
+        // Drop the frames left in the RX FIFO by a previous boot.
+        while spi.read_reg(RXDATA) & FIFO_FLAG == 0 {}
+        SPI_SUBSYSTEM
+            .add_spi(SpiDevice::new(SpiDeviceDriver::SifiveSpi(spi)))
+            .map(|_| ())
+            .ok_or(DriverError::InitFailed)
+    }
+}
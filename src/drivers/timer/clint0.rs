@@ -19,9 +19,12 @@ Tests files:
 use core::ptr::{self};
 
 use crate::{
-    drivers::DriverRegion,
+    drivers::{
+        DriverRegion,
+        model::{Driver, DriverClass, DriverError},
+    },
     misc::RawTraitObject,
-    platform::{self, DeviceType, InterruptExtended, platform_get_device_info},
+    platform::{self, DeviceType, Devices, InterruptExtended},
 };
 
 use super::{TIMER_SUBSYSTEM, TimerDevice, TimerType};
@@ -34,32 +37,6 @@ pub struct Clint0 {
 }
 
 impl Clint0 {
-    pub fn init() {
-        let device_info = match platform_get_device_info("sifive,clint0", DeviceType::Timer) {
-            Some(d) => d,
-            None => return,
-        };
-        // Allow the use of expect, once we got the device asked, the trait should be working and
-        // we should get the trait behind the Option<>
-        #[allow(clippy::expect_used)]
-        let device_info_trait = device_info
-            .info
-            .expect("Error: failed to get device trait behind option.");
-        let raw: RawTraitObject = unsafe { core::mem::transmute(device_info_trait) };
-        let timer_device_ptr = raw.data as *const platform::PlatformTimerDevice;
-        let timer_device_ref = unsafe { &*timer_device_ptr };
-        // Init Clint0 driver and update timer sub-system for global access.
-        let clint0: Clint0 = Clint0 {
-            region: device_info.header.device_addr,
-            interrupt_extended: timer_device_ref.interrupt_extended,
-        };
-        let device: TimerDevice = TimerDevice {
-            timer_type: TimerType::ArchitecturalTimer,
-            device: super::TimerDeviceDriver::Clint0(clint0),
-        };
-        TIMER_SUBSYSTEM.add_timer(device);
-    }
-
     /// Read mtime from clint0 addr + offset from `https://chromitem-soc.readthedocs.io/en/latest/clint.html`
     /// Check 2 time value from high addr to avoid miscompute mtime and giving wrong tick, and led
     /// to UB.
@@ -114,3 +91,49 @@ impl Clint0 {
         unsafe { ptr::write_volatile(addr as *mut u32, 1) };
     }
 }
+
+/// Clint0 entry of the driver match table.
+pub struct Clint0Driver;
+
+impl Driver for Clint0Driver {
+    fn name(&self) -> &'static str {
+        "clint0"
+    }
+
+    fn compatible(&self) -> &'static [&'static str] {
+        &["sifive,clint0"]
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Timer
+    }
+
+    fn class(&self) -> DriverClass {
+        DriverClass::Timer
+    }
+
+    fn probe(&self, device_info: &Devices) -> Result<(), DriverError> {
+        // Allow the use of expect, once we got the device asked, the trait should be working and
+        // we should get the trait behind the Option<>
+        #[allow(clippy::expect_used)]
+        let device_info_trait = device_info
+            .info
+            .expect("Error: failed to get device trait behind option.");
+        let raw: RawTraitObject = unsafe { core::mem::transmute(device_info_trait) };
+        let timer_device_ptr = raw.data as *const platform::PlatformTimerDevice;
+        let timer_device_ref = unsafe { &*timer_device_ptr };
+        // Init Clint0 driver and update timer sub-system for global access.
+        let clint0: Clint0 = Clint0 {
+            region: device_info.header.device_addr,
+            interrupt_extended: timer_device_ref.interrupt_extended,
+        };
+        let device: TimerDevice = TimerDevice {
+            timer_type: TimerType::ArchitecturalTimer,
+            device: super::TimerDeviceDriver::Clint0(clint0),
+        };
+        TIMER_SUBSYSTEM
+            .add_timer(device)
+            .map(|_| ())
+            .ok_or(DriverError::InitFailed)
+    }
+}
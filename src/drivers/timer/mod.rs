@@ -8,22 +8,43 @@ Tested:
 - Adding same devices
 - Overflow in the sub-system pools.
 - Selecting the primary timer.
+- Selecting the one-shot timer.
+- One-shot event without timer.
 
 Not tested:
-- ...
+- Per-hart events expiry.
 
 Reasons:
-- ...
+- The events are programmed on the real CLINT, the test framework doesn't handle timer
+  interrupts.
 
 Tests files:
 - 'src/tests/drivers/timer/subsystem.rs'
 */
 
-use core::cell::UnsafeCell;
+use core::cell::{Cell, UnsafeCell};
 
 use clint0::Clint0;
 
-use crate::{config::TIMER_MAX_SIZE, log, logs::LogLevel};
+use crate::{
+    arch::{
+        helpers::current_cpu_core,
+        traps::interrupt::{restore_mstatus_mie, save_and_disable_mstatus_mie},
+    },
+    cmdline::kernel_params,
+    config::{CPU_CORE_NUMBER, TIMER_MAX_SIZE},
+    drivers::{
+        cpufreq::{CPUFREQ, CpuFreqEvent, CpuFreqStage, cpufreq_notifier_register, cpufreq_scale},
+        model::{DriverClass, driver_probe_class},
+        pool::DevicePool,
+    },
+    irq::{IRQ_MAX_PRIORITY, IRQ_TIMER, IrqError, irq_register},
+    ktime::{
+        set_ktime_ms,
+        tick::{get_tick, increment_tick},
+    },
+    task::primitives::task_awake_blocked,
+};
 
 pub mod clint0;
 
@@ -37,6 +58,18 @@ pub enum TimerType {
     SoCTimer,
 }
 
+/// Called from the timer interrupt of the CPU core when its one-shot event expire.
+pub type TimerEventHandler = fn(hart: usize);
+
+/// All errors that can happen when arming a timer event.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TimerError {
+    // There's no timer to program the event.
+    NoTimer,
+    // A one-shot event is already armed on this CPU core.
+    Busy,
+}
+
 #[derive(PartialEq)]
 pub enum TimerDeviceDriver {
     Clint0(Clint0),
@@ -64,13 +97,46 @@ impl TimerDevice {
             TimerDeviceDriver::Clint0(clint0) => clint0.set_mtimecmp(core, delay),
         }
     }
+
+    /// Frequency of the timer in Hz.
+    pub fn frequency(&self) -> u64 {
+        match &self.device {
+            // The CLINT count at the timebase frequency.
+            #[allow(static_mut_refs)]
+            TimerDeviceDriver::Clint0(_) => unsafe { CPUFREQ.frequency as u64 },
+        }
+    }
+}
+
+/// Events of a CPU core, a deadline is a value of the timer programming the event, u64::MAX when
+/// the event is not armed.
+/// tick: the next kernel tick, on the primary timer.
+/// oneshot: the one-shot event, on the one-shot timer or on the primary timer without SoC timer.
+struct TimerEvents {
+    tick: Cell<u64>,
+    oneshot: Cell<u64>,
+    oneshot_handler: Cell<Option<TimerEventHandler>>,
+}
+
+impl TimerEvents {
+    const fn init() -> Self {
+        TimerEvents {
+            tick: Cell::new(u64::MAX),
+            oneshot: Cell::new(u64::MAX),
+            oneshot_handler: Cell::new(None),
+        }
+    }
 }
 
 pub struct TimerSubSystem {
     // Timer pool where all timer initialized is store, waiting to be assigned at another field
-    pub timer_pool: [UnsafeCell<Option<TimerDevice>>; TIMER_MAX_SIZE],
+    pub timer_pool: DevicePool<TimerDevice, TIMER_MAX_SIZE>,
     // Timer for scheduling and global work on the kernel
     pub primary_timer: UnsafeCell<Option<TimerDevice>>,
+    // SoC timer for the one-shot events, separate from the kernel tick
+    pub oneshot_timer: UnsafeCell<Option<TimerDevice>>,
+    // Events of each CPU core, programmed on the compare register of the core
+    events: [TimerEvents; CPU_CORE_NUMBER],
 }
 
 unsafe impl Sync for TimerSubSystem {}
@@ -79,7 +145,9 @@ impl TimerSubSystem {
     pub const fn init() -> Self {
         TimerSubSystem {
             primary_timer: UnsafeCell::new(None),
-            timer_pool: [const { UnsafeCell::new(None) }; TIMER_MAX_SIZE],
+            oneshot_timer: UnsafeCell::new(None),
+            timer_pool: DevicePool::init("Timer"),
+            events: [const { TimerEvents::init() }; CPU_CORE_NUMBER],
         }
     }
 
@@ -88,63 +156,37 @@ impl TimerSubSystem {
     /// Params:
     /// &self: the sub-system structure.
     /// new_timer: structure of a timer driver.
-    pub fn add_timer(&self, new_timer: TimerDevice) {
-        let size = self.get_timer_array_size();
-        if size == TIMER_MAX_SIZE {
-            log!(
-                LogLevel::Warn,
-                "Timer sub-system: subsystem is full, ignoring registration request"
-            );
-            return;
-        }
-        for i in 0..TIMER_MAX_SIZE {
-            let device = unsafe { &*self.timer_pool[i].get() };
-            if let Some(timer) = device {
-                // Check duplication
-                if *timer == new_timer {
-                    log!(
-                        LogLevel::Warn,
-                        "Timer sub-system: duplicate device detected, ignoring registration request"
-                    );
-                    return;
-                }
-            } else {
-                unsafe {
-                    *self.timer_pool[i].get() = Some(new_timer);
-                }
-                break;
-            }
-        }
+    pub fn add_timer(&self, new_timer: TimerDevice) -> Option<usize> {
+        self.timer_pool.add(new_timer)
     }
 
     pub fn get_timer_array_size(&self) -> usize {
-        let mut size: usize = 0;
-        for i in 0..TIMER_MAX_SIZE {
-            let present = unsafe { &*self.timer_pool[i].get() };
-            if present.is_some() {
-                size += 1;
-            }
-        }
-        size
+        self.timer_pool.size()
     }
 
     pub fn select_primary_timer(&self) {
-        for i in 0..TIMER_MAX_SIZE {
-            let get_timer = unsafe { &*self.timer_pool[i].get() };
-            if let Some(timer) = get_timer {
-                if timer.timer_type() == &TimerType::ArchitecturalTimer {
-                    // Update the sub-system primary timer
-                    let extract_timer: Option<TimerDevice> =
-                        unsafe { (*self.timer_pool[i].get()).take() };
-                    unsafe { *self.primary_timer.get() = extract_timer }
-                    // Remove timer in pool to avoid duplication
-                }
-            } else {
-                continue;
-            }
+        if let Some(timer) = self.take_timer(TimerType::ArchitecturalTimer) {
+            unsafe { *self.primary_timer.get() = Some(timer) }
+        }
+    }
+
+    /// Use the first SoC timer of the pool for the one-shot events. Without SoC timer, the
+    /// one-shot events share the compare register of the primary timer with the tick.
+    pub fn select_oneshot_timer(&self) {
+        if let Some(timer) = self.take_timer(TimerType::SoCTimer) {
+            unsafe { *self.oneshot_timer.get() = Some(timer) }
         }
     }
 
+    /// Remove the first timer of the given type from the pool.
+    fn take_timer(&self, timer_type: TimerType) -> Option<TimerDevice> {
+        // Remove timer in pool to avoid duplication
+        let index = self
+            .timer_pool
+            .position(|timer| timer.timer_type() == &timer_type)?;
+        self.timer_pool.remove(index)
+    }
+
     pub fn get_primary_timer(&self) -> &TimerDevice {
         let primary_timer = unsafe { &*self.primary_timer.get() };
         if let Some(timer) = primary_timer {
@@ -153,12 +195,184 @@ impl TimerSubSystem {
             panic!("Error getting the primary timer in the timer sub-system");
         }
     }
+
+    /// Return the timer of the one-shot events: the SoC timer, or the primary timer without SoC
+    /// timer. None if there's no timer at all.
+    pub fn get_oneshot_timer(&self) -> Option<&TimerDevice> {
+        match unsafe { &*self.oneshot_timer.get() } {
+            Some(timer) => Some(timer),
+            None => unsafe { (*self.primary_timer.get()).as_ref() },
+        }
+    }
+
+    fn oneshot_on_primary(&self) -> bool {
+        unsafe { (*self.oneshot_timer.get()).is_none() }
+    }
+
+    /// Program the compare register of the primary timer for the core, with the nearest event
+    /// using it.
+    fn program_primary(&self, hart: usize) {
+        let events = &self.events[hart];
+        let mut next = events.tick.get();
+        if self.oneshot_on_primary() {
+            next = next.min(events.oneshot.get());
+        }
+        self.get_primary_timer().set_delay(hart, next);
+    }
+
+    /// Arm the next tick of the core at deadline, a value of the primary timer.
+    pub fn set_tick_event(&self, hart: usize, deadline: u64) {
+        let mie = save_and_disable_mstatus_mie();
+        self.events[hart].tick.set(deadline);
+        self.program_primary(hart);
+        restore_mstatus_mie(mie);
+    }
+
+    /// Arm the one-shot event of the core, handler is called from the timer interrupt of the core
+    /// once the one-shot timer advanced by delay.
+    pub fn set_oneshot_event(
+        &self,
+        hart: usize,
+        delay: u64,
+        handler: TimerEventHandler,
+    ) -> Result<(), TimerError> {
+        let timer = self.get_oneshot_timer().ok_or(TimerError::NoTimer)?;
+        let events = &self.events[hart];
+        let mie = save_and_disable_mstatus_mie();
+        if events.oneshot_handler.get().is_some() {
+            restore_mstatus_mie(mie);
+            return Err(TimerError::Busy);
+        }
+        let deadline = timer.read_time().saturating_add(delay);
+        events.oneshot.set(deadline);
+        events.oneshot_handler.set(Some(handler));
+        if self.oneshot_on_primary() {
+            self.program_primary(hart);
+        } else {
+            timer.set_delay(hart, deadline);
+        }
+        restore_mstatus_mie(mie);
+        Ok(())
+    }
+
+    /// Disarm the one-shot event of the core.
+    pub fn cancel_oneshot_event(&self, hart: usize) {
+        let timer = match self.get_oneshot_timer() {
+            Some(t) => t,
+            None => return,
+        };
+        let events = &self.events[hart];
+        let mie = save_and_disable_mstatus_mie();
+        events.oneshot.set(u64::MAX);
+        events.oneshot_handler.set(None);
+        if self.oneshot_on_primary() {
+            self.program_primary(hart);
+        } else {
+            timer.set_delay(hart, u64::MAX);
+        }
+        restore_mstatus_mie(mie);
+    }
+
+    /// Scale the remaining time of the armed events of all cores after a timebase change, so the
+    /// events keep their duration.
+    fn rescale_events(&self, old_hz: u32, new_hz: u32) {
+        let now = self.get_primary_timer().read_time();
+        let oneshot_timer = unsafe { &*self.oneshot_timer.get() };
+        let oneshot_now = match oneshot_timer {
+            Some(timer) => timer.read_time(),
+            None => now,
+        };
+        let rescale = |deadline: u64, now: u64| match deadline {
+            u64::MAX => u64::MAX,
+            _ => now.saturating_add(cpufreq_scale(deadline.saturating_sub(now), old_hz, new_hz)),
+        };
+        for (hart, events) in self.events.iter().enumerate() {
+            events.tick.set(rescale(events.tick.get(), now));
+            events
+                .oneshot
+                .set(rescale(events.oneshot.get(), oneshot_now));
+            if let Some(timer) = oneshot_timer {
+                timer.set_delay(hart, events.oneshot.get());
+            }
+            self.program_primary(hart);
+        }
+    }
+
+    /// Disarm the events of the core reached by the timers, and program the compare registers
+    /// with the remaining events.
+    /// Return true if the tick is due, and the handler of the expired one-shot event.
+    /// Must be called from the timer interrupt handler.
+    fn take_expired_events(&self, hart: usize) -> (bool, Option<TimerEventHandler>) {
+        let events = &self.events[hart];
+        let now = self.get_primary_timer().read_time();
+        let tick_due = now >= events.tick.get();
+        if tick_due {
+            events.tick.set(u64::MAX);
+        }
+        let oneshot_timer = unsafe { &*self.oneshot_timer.get() };
+        let oneshot_now = match oneshot_timer {
+            Some(timer) => timer.read_time(),
+            None => now,
+        };
+        let mut handler = None;
+        if oneshot_now >= events.oneshot.get() {
+            events.oneshot.set(u64::MAX);
+            handler = events.oneshot_handler.take();
+            if let Some(timer) = oneshot_timer {
+                timer.set_delay(hart, u64::MAX);
+            }
+        }
+        self.program_primary(hart);
+        (tick_due, handler)
+    }
 }
 
 // Init static timer sub-system
 pub static TIMER_SUBSYSTEM: TimerSubSystem = TimerSubSystem::init();
 
+/// Timer interrupt handler, call the expired one-shot event of the core. On the tick, increment
+/// the global tick, awake the blocked tasks and set the timer for the next tick of the core.
+fn timer_interrupt_handler(_irq: u32, _ctx: usize) {
+    let hart = current_cpu_core();
+    let (tick_due, oneshot) = TIMER_SUBSYSTEM.take_expired_events(hart);
+    if let Some(handler) = oneshot {
+        handler(hart);
+    }
+    if !tick_due {
+        return;
+    }
+    if hart == 0 {
+        increment_tick();
+    }
+    let tick = get_tick();
+    task_awake_blocked(tick);
+    set_ktime_ms(kernel_params().tick_duration);
+}
+
+/// Cpufreq notifier, keep the duration of the armed events after a timebase change. The next
+/// ticks are computed from the new frequency by set_ktime_ms.
+fn timer_cpufreq_notifier(event: &CpuFreqEvent) {
+    if event.stage == CpuFreqStage::PostChange && event.old.timebase != event.new.timebase {
+        TIMER_SUBSYSTEM.rescale_events(event.old.timebase, event.new.timebase);
+    }
+}
+
 pub fn init_timer_subsystem() {
-    Clint0::init();
+    driver_probe_class(DriverClass::Timer);
     TIMER_SUBSYSTEM.select_primary_timer();
+    TIMER_SUBSYSTEM.select_oneshot_timer();
+    if let Err(e) = cpufreq_notifier_register(timer_cpufreq_notifier) {
+        panic!("Failed to register the timer cpufreq notifier: {:?}", e);
+    }
+    // The kernel tick is the highest priority handler on the timer interrupt.
+    match irq_register(
+        IRQ_TIMER,
+        IRQ_MAX_PRIORITY,
+        timer_interrupt_handler,
+        0,
+        None,
+    ) {
+        Ok(_) | Err(IrqError::AlreadyRegistered) => (),
+        Err(e) => panic!("Failed to register the timer tick handler: {:?}", e),
+    }
 }
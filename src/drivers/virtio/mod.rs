@@ -0,0 +1,261 @@
+// See documentation in `Documentation/hardware/virtio.md`
+/*
+File info: Virtio-mmio transport.
+
+Test coverage: None.
+
+Tested:
+
+Not tested:
+- Everything.
+
+Reasons:
+- Testing a transport need to have an MMIO emulation.
+
+Tests files:
+- 'src/tests/drivers/virtio/queue.rs'
+*/
+
+use core::ptr;
+
+use queue::{VIRTQ_PAGE_SIZE, VIRTQ_SIZE, Virtqueue};
+
+use crate::{
+    drivers::DriverRegion,
+    misc::RawTraitObject,
+    platform::{self, Devices},
+};
+
+pub mod queue;
+
+// Registers offset, all registers are 4 bytes wide.
+const VIRTIO_MMIO_MAGIC_VALUE: usize = 0x000;
+const VIRTIO_MMIO_VERSION: usize = 0x004;
+const VIRTIO_MMIO_DEVICE_ID: usize = 0x008;
+const VIRTIO_MMIO_DEVICE_FEATURES: usize = 0x010;
+const VIRTIO_MMIO_DEVICE_FEATURES_SEL: usize = 0x014;
+const VIRTIO_MMIO_DRIVER_FEATURES: usize = 0x020;
+const VIRTIO_MMIO_DRIVER_FEATURES_SEL: usize = 0x024;
+// Legacy only
+const VIRTIO_MMIO_GUEST_PAGE_SIZE: usize = 0x028;
+const VIRTIO_MMIO_QUEUE_SEL: usize = 0x030;
+const VIRTIO_MMIO_QUEUE_NUM_MAX: usize = 0x034;
+const VIRTIO_MMIO_QUEUE_NUM: usize = 0x038;
+// Legacy only
+const VIRTIO_MMIO_QUEUE_ALIGN: usize = 0x03c;
+// Legacy only
+const VIRTIO_MMIO_QUEUE_PFN: usize = 0x040;
+const VIRTIO_MMIO_QUEUE_READY: usize = 0x044;
+const VIRTIO_MMIO_QUEUE_NOTIFY: usize = 0x050;
+const VIRTIO_MMIO_INTERRUPT_STATUS: usize = 0x060;
+const VIRTIO_MMIO_INTERRUPT_ACK: usize = 0x064;
+const VIRTIO_MMIO_STATUS: usize = 0x070;
+const VIRTIO_MMIO_QUEUE_DESC_LOW: usize = 0x080;
+const VIRTIO_MMIO_QUEUE_DESC_HIGH: usize = 0x084;
+const VIRTIO_MMIO_QUEUE_DRIVER_LOW: usize = 0x090;
+const VIRTIO_MMIO_QUEUE_DRIVER_HIGH: usize = 0x094;
+const VIRTIO_MMIO_QUEUE_DEVICE_LOW: usize = 0x0a0;
+const VIRTIO_MMIO_QUEUE_DEVICE_HIGH: usize = 0x0a4;
+const VIRTIO_MMIO_CONFIG_GENERATION: usize = 0x0fc;
+const VIRTIO_MMIO_CONFIG: usize = 0x100;
+
+// "virt" in little endian
+const VIRTIO_MMIO_MAGIC: u32 = 0x7472_6976;
+
+// Device status bits
+const VIRTIO_STATUS_ACKNOWLEDGE: u32 = 1;
+const VIRTIO_STATUS_DRIVER: u32 = 2;
+const VIRTIO_STATUS_DRIVER_OK: u32 = 4;
+const VIRTIO_STATUS_FEATURES_OK: u32 = 8;
+const VIRTIO_STATUS_FAILED: u32 = 128;
+
+// Must be accepted by the driver on a modern device.
+const VIRTIO_F_VERSION_1: u64 = 1 << 32;
+
+// Device ids, a slot without device has the id 0.
+pub const VIRTIO_DEVICE_ID_BLOCK: u32 = 2;
+pub const VIRTIO_DEVICE_ID_CONSOLE: u32 = 3;
+
+/// All errors that can happen when initializing or using a virtio device.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VirtioError {
+    // The device doesn't accept the features asked by the driver.
+    FeaturesRejected,
+    // The queue doesn't exist on the device, or is too small.
+    QueueUnavailable,
+    // All the virtqueues are used, see VIRTIO_QUEUE_MAX_SIZE in config file.
+    OutOfQueueMemory,
+    // There's not enough free descriptors in the queue.
+    QueueFull,
+    // The device returned a used buffer that is not a chain of the queue.
+    InvalidUsedBuffer,
+}
+
+/// Virtio-mmio transport, shared by all the virtio drivers.
+#[derive(Copy, Clone, PartialEq)]
+pub struct VirtioMmio {
+    pub region: DriverRegion,
+    // Interrupt source id on the external interrupt-controller, 0 if not connected
+    pub irq: u32,
+    // 1 for a legacy device, 2 for a modern device
+    pub version: u32,
+    pub device_id: u32,
+}
+
+impl VirtioMmio {
+    /// Check the magic value, version and device id of the slot.
+    /// Return None if the region isn't a virtio-mmio device, or if the slot is empty.
+    pub fn probe(region: DriverRegion, irq: u32) -> Option<Self> {
+        let mut transport = VirtioMmio {
+            region,
+            irq,
+            version: 0,
+            device_id: 0,
+        };
+        if transport.read(VIRTIO_MMIO_MAGIC_VALUE) != VIRTIO_MMIO_MAGIC {
+            return None;
+        }
+        transport.version = transport.read(VIRTIO_MMIO_VERSION);
+        if transport.version != 1 && transport.version != 2 {
+            return None;
+        }
+        transport.device_id = transport.read(VIRTIO_MMIO_DEVICE_ID);
+        if transport.device_id == 0 {
+            return None;
+        }
+        Some(transport)
+    }
+
+    fn read(&self, off: usize) -> u32 {
+        unsafe { ptr::read_volatile((self.region.addr + off) as *const u32) }
+    }
+
+    fn write(&self, off: usize, value: u32) {
+        unsafe { ptr::write_volatile((self.region.addr + off) as *mut u32, value) }
+    }
+
+    fn set_status(&self, bits: u32) {
+        let status = self.read(VIRTIO_MMIO_STATUS);
+        self.write(VIRTIO_MMIO_STATUS, status | bits);
+    }
+
+    /// Reset the device, then acknowledge it and negotiate the features.
+    /// supported: the device specific features the driver can use.
+    /// Return the features accepted by both the driver and the device.
+    pub fn init(&self, supported: u64) -> Result<u64, VirtioError> {
+        self.write(VIRTIO_MMIO_STATUS, 0);
+        self.set_status(VIRTIO_STATUS_ACKNOWLEDGE);
+        self.set_status(VIRTIO_STATUS_DRIVER);
+        self.write(VIRTIO_MMIO_DEVICE_FEATURES_SEL, 0);
+        let low = self.read(VIRTIO_MMIO_DEVICE_FEATURES) as u64;
+        self.write(VIRTIO_MMIO_DEVICE_FEATURES_SEL, 1);
+        let high = self.read(VIRTIO_MMIO_DEVICE_FEATURES) as u64;
+        let mut accepted = ((high << 32) | low) & supported;
+        if self.version == 2 {
+            accepted |= VIRTIO_F_VERSION_1;
+        }
+        self.write(VIRTIO_MMIO_DRIVER_FEATURES_SEL, 0);
+        self.write(VIRTIO_MMIO_DRIVER_FEATURES, accepted as u32);
+        self.write(VIRTIO_MMIO_DRIVER_FEATURES_SEL, 1);
+        self.write(VIRTIO_MMIO_DRIVER_FEATURES, (accepted >> 32) as u32);
+        // Legacy devices don't have the FEATURES_OK step.
+        if self.version == 2 {
+            self.set_status(VIRTIO_STATUS_FEATURES_OK);
+            if self.read(VIRTIO_MMIO_STATUS) & VIRTIO_STATUS_FEATURES_OK == 0 {
+                self.fail();
+                return Err(VirtioError::FeaturesRejected);
+            }
+        } else {
+            self.write(VIRTIO_MMIO_GUEST_PAGE_SIZE, VIRTQ_PAGE_SIZE as u32);
+        }
+        Ok(accepted)
+    }
+
+    /// Give the queue memory to the device.
+    pub fn setup_queue(&self, index: u32, queue: &Virtqueue) -> Result<(), VirtioError> {
+        self.write(VIRTIO_MMIO_QUEUE_SEL, index);
+        let max = self.read(VIRTIO_MMIO_QUEUE_NUM_MAX);
+        if max < VIRTQ_SIZE as u32 {
+            return Err(VirtioError::QueueUnavailable);
+        }
+        self.write(VIRTIO_MMIO_QUEUE_NUM, VIRTQ_SIZE as u32);
+        if self.version == 1 {
+            self.write(VIRTIO_MMIO_QUEUE_ALIGN, queue::VIRTQ_ALIGN as u32);
+            self.write(
+                VIRTIO_MMIO_QUEUE_PFN,
+                (queue.desc_addr() / VIRTQ_PAGE_SIZE) as u32,
+            );
+        } else {
+            // The kernel run on a 32 bits target, high addresses are always 0.
+            self.write(VIRTIO_MMIO_QUEUE_DESC_LOW, queue.desc_addr() as u32);
+            self.write(VIRTIO_MMIO_QUEUE_DESC_HIGH, 0);
+            self.write(VIRTIO_MMIO_QUEUE_DRIVER_LOW, queue.avail_addr() as u32);
+            self.write(VIRTIO_MMIO_QUEUE_DRIVER_HIGH, 0);
+            self.write(VIRTIO_MMIO_QUEUE_DEVICE_LOW, queue.used_addr() as u32);
+            self.write(VIRTIO_MMIO_QUEUE_DEVICE_HIGH, 0);
+            self.write(VIRTIO_MMIO_QUEUE_READY, 1);
+        }
+        Ok(())
+    }
+
+    /// Tell the device the driver is ready, must be called after the queues setup.
+    pub fn driver_ok(&self) {
+        self.set_status(VIRTIO_STATUS_DRIVER_OK);
+    }
+
+    /// Tell the device the driver gave up on it.
+    pub fn fail(&self) {
+        self.set_status(VIRTIO_STATUS_FAILED);
+    }
+
+    /// Reset the device, it stops using the queues.
+    pub fn reset(&self) {
+        self.write(VIRTIO_MMIO_STATUS, 0);
+    }
+
+    /// Tell the device new buffers are available in the queue.
+    pub fn notify(&self, index: u32) {
+        self.write(VIRTIO_MMIO_QUEUE_NOTIFY, index);
+    }
+
+    /// Read and acknowledge the interrupt status, return the status.
+    pub fn ack_interrupt(&self) -> u32 {
+        let status = self.read(VIRTIO_MMIO_INTERRUPT_STATUS);
+        self.write(VIRTIO_MMIO_INTERRUPT_ACK, status);
+        status
+    }
+
+    /// Read a u32 from the device config space.
+    pub fn config_read_u32(&self, off: usize) -> u32 {
+        self.read(VIRTIO_MMIO_CONFIG + off)
+    }
+
+    /// Read a u64 from the device config space, retry while the device update the config.
+    pub fn config_read_u64(&self, off: usize) -> u64 {
+        loop {
+            let generation = self.read(VIRTIO_MMIO_CONFIG_GENERATION);
+            let low = self.config_read_u32(off) as u64;
+            let high = self.config_read_u32(off + 4) as u64;
+            // Legacy devices always return 0.
+            if self.version == 1 || generation == self.read(VIRTIO_MMIO_CONFIG_GENERATION) {
+                return (high << 32) | low;
+            }
+        }
+    }
+}
+
+/// Return the transport of the virtio-mmio slot if its device has the given id, used by the
+/// virtio drivers to match a slot.
+pub fn virtio_mmio_match(device_info: &Devices, device_id: u32) -> Option<VirtioMmio> {
+    // Allow the use of expect, once we got the device asked, the trait should be working and
+    // we should get the trait behind the Option<>
+    #[allow(clippy::expect_used)]
+    let device_info_trait = device_info
+        .info
+        .expect("Error: failed to get device trait behind option.");
+    let raw: RawTraitObject = unsafe { core::mem::transmute(device_info_trait) };
+    let virtio_device_ptr = raw.data as *const platform::PlatformVirtioDevice;
+    let virtio_device_ref = unsafe { &*virtio_device_ptr };
+    VirtioMmio::probe(device_info.header.device_addr, virtio_device_ref.irq)
+        .filter(|transport| transport.device_id == device_id)
+}
@@ -0,0 +1,269 @@
+// See documentation in `Documentation/hardware/virtio.md`
+/*
+File info: Virtio split virtqueue.
+
+Test coverage: Descriptor chain and used ring.
+
+Tested:
+- Add a descriptor chain, check the descriptors and the available ring.
+- Pop a used buffer written by a fake device and free the chain.
+- Queue full.
+- Next head descriptor.
+- Used buffer outside the descriptor table, chain without end.
+
+Not tested:
+- Used by a real device.
+
+Reasons:
+- Need an MMIO emulation of a virtio device.
+
+Tests files:
+- 'src/tests/drivers/virtio/queue.rs'
+*/
+
+use core::{
+    cell::Cell,
+    ptr,
+    sync::atomic::{Ordering, fence},
+};
+
+use crate::config::VIRTIO_QUEUE_MAX_SIZE;
+
+use super::VirtioError;
+
+// Number of descriptors in a queue, a power of 2.
+pub const VIRTQ_SIZE: u16 = 8;
+// Alignment of the used ring, written in QueueAlign on legacy devices.
+pub const VIRTQ_ALIGN: usize = 16;
+// Size and alignment of the queue memory, written in GuestPageSize on legacy devices. The spec
+// only ask for a power of 2, a small page avoid wasting memory for a few descriptors.
+pub const VIRTQ_PAGE_SIZE: usize = 256;
+
+// Descriptor flags
+const VIRTQ_DESC_F_NEXT: u16 = 1;
+const VIRTQ_DESC_F_WRITE: u16 = 2;
+
+const fn align_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+// Layout of the queue memory: descriptor table, available ring, used ring.
+const VIRTQ_DESC_SIZE: usize = 16;
+const VIRTQ_AVAIL_OFF: usize = VIRTQ_DESC_SIZE * VIRTQ_SIZE as usize;
+const VIRTQ_USED_OFF: usize = align_up(VIRTQ_AVAIL_OFF + 6 + 2 * VIRTQ_SIZE as usize, VIRTQ_ALIGN);
+const VIRTQ_USED_END: usize = VIRTQ_USED_OFF + 6 + 8 * VIRTQ_SIZE as usize;
+
+const _: () = {
+    assert!(VIRTQ_SIZE.is_power_of_two());
+    assert!(
+        VIRTQ_USED_END <= VIRTQ_PAGE_SIZE,
+        "Virtqueue doesn't fit in its page"
+    );
+    // Some legacy devices compute the used ring offset without the used_event field.
+    assert!(
+        align_up(VIRTQ_AVAIL_OFF + 4 + 2 * VIRTQ_SIZE as usize, VIRTQ_ALIGN) == VIRTQ_USED_OFF,
+        "Used ring offset is ambiguous, change VIRTQ_SIZE or VIRTQ_ALIGN"
+    );
+};
+
+#[repr(C, align(256))]
+struct VirtqPage([u8; VIRTQ_PAGE_SIZE]);
+
+// Memory of all the virtqueues, never freed, a device keep its queues for the kernel lifetime.
+static mut VIRTQ_POOL: [VirtqPage; VIRTIO_QUEUE_MAX_SIZE] =
+    [const { VirtqPage([0; VIRTQ_PAGE_SIZE]) }; VIRTIO_QUEUE_MAX_SIZE];
+static mut VIRTQ_POOL_USED: usize = 0;
+
+/// A buffer given to the device, part of a descriptor chain.
+#[derive(Copy, Clone)]
+pub struct VirtqBuffer {
+    pub addr: usize,
+    pub len: u32,
+    // The device write in the buffer, else the device only read it.
+    pub device_writable: bool,
+}
+
+/// Split virtqueue, the driver side. The queue memory is shared with the device.
+#[derive(PartialEq)]
+pub struct Virtqueue {
+    base: usize,
+    // First free descriptor, the free descriptors are chained by their next field.
+    free_head: Cell<u16>,
+    num_free: Cell<u16>,
+    // Next available ring entry written by the driver
+    avail_idx: Cell<u16>,
+    // Next used ring entry read by the driver
+    last_used: Cell<u16>,
+}
+
+impl Virtqueue {
+    /// Take a queue memory from the pool and initialize the queue.
+    pub fn alloc() -> Result<Self, VirtioError> {
+        #[allow(static_mut_refs)]
+        let base = unsafe {
+            if VIRTQ_POOL_USED == VIRTIO_QUEUE_MAX_SIZE {
+                return Err(VirtioError::OutOfQueueMemory);
+            }
+            let page = &mut VIRTQ_POOL[VIRTQ_POOL_USED];
+            VIRTQ_POOL_USED += 1;
+            page.0.as_mut_ptr() as usize
+        };
+        let queue = Virtqueue {
+            base,
+            free_head: Cell::new(0),
+            num_free: Cell::new(VIRTQ_SIZE),
+            avail_idx: Cell::new(0),
+            last_used: Cell::new(0),
+        };
+        unsafe { ptr::write_bytes(base as *mut u8, 0, VIRTQ_PAGE_SIZE) };
+        for i in 0..VIRTQ_SIZE {
+            queue.write_desc_next(i, i + 1);
+        }
+        Ok(queue)
+    }
+
+    pub fn desc_addr(&self) -> usize {
+        self.base
+    }
+
+    pub fn avail_addr(&self) -> usize {
+        self.base + VIRTQ_AVAIL_OFF
+    }
+
+    pub fn used_addr(&self) -> usize {
+        self.base + VIRTQ_USED_OFF
+    }
+
+    /// Number of free descriptors.
+    pub fn num_free(&self) -> u16 {
+        self.num_free.get()
+    }
+
+    /// Head descriptor of the next chain added, None if the queue is full. Let a driver pick a
+    /// buffer indexed by descriptor before adding the chain.
+    pub fn next_head(&self) -> Option<u16> {
+        if self.num_free.get() == 0 {
+            return None;
+        }
+        Some(self.free_head.get())
+    }
+
+    fn desc(&self, index: u16) -> usize {
+        self.base + VIRTQ_DESC_SIZE * index as usize
+    }
+
+    fn write_desc_next(&self, index: u16, next: u16) {
+        unsafe { ptr::write_volatile((self.desc(index) + 14) as *mut u16, next) };
+    }
+
+    /// Read a descriptor: address, length, flags and next.
+    pub fn read_desc(&self, index: u16) -> (u64, u32, u16, u16) {
+        let desc = self.desc(index);
+        unsafe {
+            (
+                ptr::read_volatile(desc as *const u64),
+                ptr::read_volatile((desc + 8) as *const u32),
+                ptr::read_volatile((desc + 12) as *const u16),
+                ptr::read_volatile((desc + 14) as *const u16),
+            )
+        }
+    }
+
+    /// Chain the buffers in free descriptors and make the chain available to the device.
+    /// Return the head descriptor of the chain, given back by pop_used.
+    /// The device must be notified after.
+    pub fn add_chain(&self, buffers: &[VirtqBuffer]) -> Result<u16, VirtioError> {
+        if buffers.is_empty() || buffers.len() > self.num_free.get() as usize {
+            return Err(VirtioError::QueueFull);
+        }
+        let head = self.free_head.get();
+        let mut index = head;
+        for (i, buffer) in buffers.iter().enumerate() {
+            let desc = self.desc(index);
+            let next = unsafe { ptr::read_volatile((desc + 14) as *const u16) };
+            let mut flags: u16 = 0;
+            if buffer.device_writable {
+                flags |= VIRTQ_DESC_F_WRITE;
+            }
+            if i + 1 < buffers.len() {
+                flags |= VIRTQ_DESC_F_NEXT;
+            }
+            unsafe {
+                ptr::write_volatile(desc as *mut u64, buffer.addr as u64);
+                ptr::write_volatile((desc + 8) as *mut u32, buffer.len);
+                ptr::write_volatile((desc + 12) as *mut u16, flags);
+            }
+            if i + 1 < buffers.len() {
+                index = next;
+            } else {
+                self.free_head.set(next);
+            }
+        }
+        self.num_free
+            .set(self.num_free.get() - buffers.len() as u16);
+        // Available ring: flags, idx, ring[VIRTQ_SIZE]
+        let avail_idx = self.avail_idx.get();
+        let slot = self.avail_addr() + 4 + 2 * (avail_idx % VIRTQ_SIZE) as usize;
+        unsafe { ptr::write_volatile(slot as *mut u16, head) };
+        // The device must see the descriptors and the ring entry before the new index.
+        fence(Ordering::SeqCst);
+        let avail_idx = avail_idx.wrapping_add(1);
+        unsafe { ptr::write_volatile((self.avail_addr() + 2) as *mut u16, avail_idx) };
+        self.avail_idx.set(avail_idx);
+        fence(Ordering::SeqCst);
+        Ok(head)
+    }
+
+    /// Return the next buffer used by the device: the head descriptor of the chain and the number
+    /// of bytes written by the device, None if there's no used buffer. The chain descriptors are
+    /// freed.
+    /// A used buffer with a head outside the descriptor table, or a chain without end in
+    /// VIRTQ_SIZE descriptors, is skipped without freeing its descriptors, and returns
+    /// InvalidUsedBuffer.
+    pub fn pop_used(&self) -> Result<Option<(u16, u32)>, VirtioError> {
+        // Used ring: flags, idx, ring[VIRTQ_SIZE] of (id: u32, len: u32)
+        let used_idx = unsafe { ptr::read_volatile((self.used_addr() + 2) as *const u16) };
+        let last_used = self.last_used.get();
+        if used_idx == last_used {
+            return Ok(None);
+        }
+        fence(Ordering::SeqCst);
+        let elem = self.used_addr() + 4 + 8 * (last_used % VIRTQ_SIZE) as usize;
+        let (id, len) = unsafe {
+            (
+                ptr::read_volatile(elem as *const u32),
+                ptr::read_volatile((elem + 4) as *const u32),
+            )
+        };
+        self.last_used.set(last_used.wrapping_add(1));
+        // The id is written by the device, never trust it to index the descriptor table.
+        if id >= VIRTQ_SIZE as u32 {
+            return Err(VirtioError::InvalidUsedBuffer);
+        }
+        let head = id as u16;
+        // Find the end of the chain, a chain has at most VIRTQ_SIZE descriptors.
+        let mut index = head;
+        let mut end: Option<(u16, u16)> = None;
+        for freed in 1..=VIRTQ_SIZE {
+            let (_, _, flags, next) = self.read_desc(index);
+            if flags & VIRTQ_DESC_F_NEXT == 0 {
+                end = Some((index, freed));
+                break;
+            }
+            if next >= VIRTQ_SIZE {
+                break;
+            }
+            index = next;
+        }
+        let (last, freed) = end.ok_or(VirtioError::InvalidUsedBuffer)?;
+        // A chain already free, or used twice by the device.
+        if self.num_free.get() + freed > VIRTQ_SIZE {
+            return Err(VirtioError::InvalidUsedBuffer);
+        }
+        // Free the chain, the last descriptor point to the old free head.
+        self.write_desc_next(last, self.free_head.get());
+        self.free_head.set(head);
+        self.num_free.set(self.num_free.get() + freed);
+        Ok(Some((head, len)))
+    }
+}
@@ -0,0 +1,364 @@
+// See documentation: `Documentation/kernel/irq.md`
+/*
+File info: Generic interrupt registration and dispatch.
+
+Test coverage: Registration, dispatch and bottom halves.
+
+Tested:
+- Register and unregister handlers.
+- Invalid irq and priority, overflow in the handler table.
+- Dispatch by priority on a shared irq.
+- Bottom half marked pending by the handler and run later.
+
+Not tested:
+- The bottom half kernel task.
+
+Reasons:
+- The task need the scheduler and timer interrupts, not handled by the test framework.
+
+Tests files:
+- 'src/tests/irq/mod.rs'
+*/
+
+use core::cell::UnsafeCell;
+
+use crate::{
+    arch::traps::interrupt::{restore_mstatus_mie, save_and_disable_mstatus_mie},
+    config::{IRQ_BOTTOM_HALF_TASK_PRIORITY, IRQ_MAX_SIZE},
+    drivers::ext_intc::{EXT_INTC_SUBSYSTEM, ExtIntCError},
+    log,
+    logs::LogLevel,
+    scheduler::scheduler_enqueue_task,
+    task::{primitives::sleep, task_create},
+};
+
+// Local interrupts, the irq is the interrupt code from mcause.
+pub const IRQ_SOFTWARE: u32 = 3;
+pub const IRQ_TIMER: u32 = 7;
+pub const IRQ_EXTERNAL: u32 = 11;
+// Sources of the external interrupt-controller, irq = IRQ_EXT_BASE + source id.
+pub const IRQ_EXT_BASE: u32 = 32;
+// Handlers priority, from 1 to IRQ_MAX_PRIORITY. Highest priority handlers are called first.
+pub const IRQ_MAX_PRIORITY: u32 = 7;
+
+// Stack size of the bottom half kernel task.
+const IRQ_BOTTOM_HALF_TASK_SIZE: usize = 0x800;
+
+/// Interrupt handler, called with the irq and the context given when registering.
+pub type IrqHandler = fn(irq: u32, ctx: usize);
+
+/// All errors that can happen when registering an interrupt handler.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IrqError {
+    // The irq is not a handled local interrupt or an external interrupt source.
+    InvalidIrq,
+    // The priority must be in 1..=IRQ_MAX_PRIORITY.
+    InvalidPriority,
+    // The same handler is already registered on this irq with the same context.
+    AlreadyRegistered,
+    // The handler table is full, see IRQ_MAX_SIZE in config file.
+    TableFull,
+    // There's no handler registered with this id.
+    NotRegistered,
+    // The external interrupt-controller rejected the source.
+    ExtIntC(ExtIntCError),
+}
+
+/// Return the irq of an external interrupt source.
+pub const fn irq_ext(source: u32) -> u32 {
+    IRQ_EXT_BASE + source
+}
+
+/// Return the external interrupt source of an irq, None for a local interrupt.
+pub const fn irq_ext_source(irq: u32) -> Option<u32> {
+    if irq >= IRQ_EXT_BASE {
+        Some(irq - IRQ_EXT_BASE)
+    } else {
+        None
+    }
+}
+
+/// A handler attached to an irq.
+#[derive(Copy, Clone)]
+struct IrqAction {
+    irq: u32,
+    priority: u32,
+    handler: IrqHandler,
+    ctx: usize,
+    // Deferred work, run later in the bottom half kernel task.
+    bottom_half: Option<IrqHandler>,
+    // Number of times the handler ran since the bottom half last ran.
+    pending: u32,
+}
+
+/// Table of all interrupt handlers. An irq can be shared by multiple handlers, they are all
+/// called, highest priority first.
+pub struct IrqSubSystem {
+    actions: UnsafeCell<[Option<IrqAction>; IRQ_MAX_SIZE]>,
+}
+
+unsafe impl Sync for IrqSubSystem {}
+
+impl IrqSubSystem {
+    pub const fn init() -> Self {
+        IrqSubSystem {
+            actions: UnsafeCell::new([None; IRQ_MAX_SIZE]),
+        }
+    }
+
+    /// Register a handler on an irq, and an optional bottom half.
+    /// Return the id of the handler, used to unregister it.
+    // Allow too many arguments, the registration mirror irq_register.
+    #[allow(clippy::too_many_arguments)]
+    pub fn register(
+        &self,
+        irq: u32,
+        priority: u32,
+        handler: IrqHandler,
+        ctx: usize,
+        bottom_half: Option<IrqHandler>,
+    ) -> Result<usize, IrqError> {
+        let local = matches!(irq, IRQ_SOFTWARE | IRQ_TIMER | IRQ_EXTERNAL);
+        if !local && irq <= IRQ_EXT_BASE {
+            return Err(IrqError::InvalidIrq);
+        }
+        if priority == 0 || priority > IRQ_MAX_PRIORITY {
+            return Err(IrqError::InvalidPriority);
+        }
+        let actions = unsafe { &mut *self.actions.get() };
+        // Compare the addresses, a driver initialized twice must not be called twice.
+        if actions
+            .iter()
+            .flatten()
+            .any(|a| a.irq == irq && a.handler as usize == handler as usize && a.ctx == ctx)
+        {
+            return Err(IrqError::AlreadyRegistered);
+        }
+        let id = match actions.iter().position(|a| a.is_none()) {
+            Some(i) => i,
+            None => return Err(IrqError::TableFull),
+        };
+        // The table is read from the interrupt handler.
+        let mie = save_and_disable_mstatus_mie();
+        actions[id] = Some(IrqAction {
+            irq,
+            priority,
+            handler,
+            ctx,
+            bottom_half,
+            pending: 0,
+        });
+        restore_mstatus_mie(mie);
+        Ok(id)
+    }
+
+    /// Remove a handler, return the irq it was registered on.
+    pub fn unregister(&self, id: usize) -> Result<u32, IrqError> {
+        let actions = unsafe { &mut *self.actions.get() };
+        if id >= IRQ_MAX_SIZE {
+            return Err(IrqError::NotRegistered);
+        }
+        let mie = save_and_disable_mstatus_mie();
+        let action = actions[id].take();
+        restore_mstatus_mie(mie);
+        match action {
+            Some(a) => Ok(a.irq),
+            None => Err(IrqError::NotRegistered),
+        }
+    }
+
+    /// Return the highest priority of the handlers registered on the irq, None if there's no
+    /// handler.
+    pub fn irq_priority(&self, irq: u32) -> Option<u32> {
+        let actions = unsafe { &*self.actions.get() };
+        actions
+            .iter()
+            .flatten()
+            .filter(|a| a.irq == irq)
+            .map(|a| a.priority)
+            .max()
+    }
+
+    /// Call all handlers registered on the irq, highest priority first, and mark their bottom
+    /// half as pending. Return false if there's no handler for this irq.
+    /// Must be called with interrupts disabled, from the trap handler.
+    pub fn handle(&self, irq: u32) -> bool {
+        let actions = unsafe { &mut *self.actions.get() };
+        let mut handled = false;
+        for priority in (1..=IRQ_MAX_PRIORITY).rev() {
+            for action in actions.iter_mut().flatten() {
+                if action.irq != irq || action.priority != priority {
+                    continue;
+                }
+                (action.handler)(irq, action.ctx);
+                if action.bottom_half.is_some() {
+                    action.pending = action.pending.saturating_add(1);
+                }
+                handled = true;
+            }
+        }
+        handled
+    }
+
+    /// Run the pending bottom halves, once per pending handler even if the handler ran multiple
+    /// times. Return the number of bottom halves run.
+    pub fn run_bottom_halves(&self) -> usize {
+        let actions = unsafe { &mut *self.actions.get() };
+        let mut run: usize = 0;
+        for entry in actions.iter_mut() {
+            let mie = save_and_disable_mstatus_mie();
+            let work = match entry {
+                Some(action) if action.pending != 0 => {
+                    action.pending = 0;
+                    action.bottom_half.map(|bh| (bh, action.irq, action.ctx))
+                }
+                _ => None,
+            };
+            restore_mstatus_mie(mie);
+            // Run the bottom half with interrupts enabled.
+            if let Some((bottom_half, irq, ctx)) = work {
+                bottom_half(irq, ctx);
+                run += 1;
+            }
+        }
+        run
+    }
+
+    /// Return true if a registered handler has a bottom half.
+    pub fn has_bottom_half(&self) -> bool {
+        let actions = unsafe { &*self.actions.get() };
+        actions.iter().flatten().any(|a| a.bottom_half.is_some())
+    }
+
+    /// Return the external interrupt sources with at least one handler.
+    fn for_each_ext_source(&self, mut f: impl FnMut(u32)) {
+        let actions = unsafe { &*self.actions.get() };
+        for action in actions.iter().flatten() {
+            if let Some(source) = irq_ext_source(action.irq) {
+                f(source);
+            }
+        }
+    }
+
+    pub fn get_irq_array_size(&self) -> usize {
+        let actions = unsafe { &*self.actions.get() };
+        actions.iter().filter(|a| a.is_some()).count()
+    }
+}
+
+pub static IRQ_SUBSYSTEM: IrqSubSystem = IrqSubSystem::init();
+
+// Pid of the bottom half kernel task, None until created.
+static mut IRQ_BOTTOM_HALF_TASK: Option<u16> = None;
+// Set once the kernel can create tasks, bottom halves registered before are started then.
+static mut IRQ_BOTTOM_HALF_READY: bool = false;
+
+/// Register a handler on an irq. Local interrupts use the mcause interrupt code, external sources
+/// use irq_ext(source).
+/// priority: 1..=IRQ_MAX_PRIORITY, for an external source it's also the source priority on the
+/// external interrupt-controller.
+/// ctx: given back to the handler, can be used to find the device.
+/// bottom_half: deferred work run in the bottom half kernel task after the handler ran.
+/// Return the id of the handler, used to unregister it.
+/// The same handler can be registered on an irq multiple times only with different contexts.
+pub fn irq_register(
+    irq: u32,
+    priority: u32,
+    handler: IrqHandler,
+    ctx: usize,
+    bottom_half: Option<IrqHandler>,
+) -> Result<usize, IrqError> {
+    let id = IRQ_SUBSYSTEM.register(irq, priority, handler, ctx, bottom_half)?;
+    if let Some(source) = irq_ext_source(irq) {
+        let source_priority = IRQ_SUBSYSTEM.irq_priority(irq).unwrap_or(priority);
+        if let Err(e) = EXT_INTC_SUBSYSTEM.enable_source(source, source_priority) {
+            // Never keep a handler for a source that cannot be raised.
+            let _ = IRQ_SUBSYSTEM.unregister(id);
+            return Err(IrqError::ExtIntC(e));
+        }
+    }
+    if bottom_half.is_some() {
+        irq_bottom_half_start();
+    }
+    Ok(id)
+}
+
+/// Remove a handler. An external source without handler is disabled.
+pub fn irq_unregister(id: usize) -> Result<(), IrqError> {
+    let irq = IRQ_SUBSYSTEM.unregister(id)?;
+    if let Some(source) = irq_ext_source(irq) {
+        match IRQ_SUBSYSTEM.irq_priority(irq) {
+            Some(priority) => {
+                let _ = EXT_INTC_SUBSYSTEM.enable_source(source, priority);
+            }
+            None => EXT_INTC_SUBSYSTEM.disable_source(source),
+        }
+    }
+    Ok(())
+}
+
+/// Dispatch an interrupt to its handlers, return false if there's no handler.
+pub fn irq_handle(irq: u32) -> bool {
+    IRQ_SUBSYSTEM.handle(irq)
+}
+
+/// Enable on the external interrupt-controller all the sources with a handler, called when the
+/// external interrupt-controller is initialized. A source that cannot be enabled, like a source
+/// that doesn't exist, is logged and keeps its handlers, they never run.
+pub fn irq_enable_ext_sources() {
+    IRQ_SUBSYSTEM.for_each_ext_source(|source| {
+        let irq = irq_ext(source);
+        let priority = IRQ_SUBSYSTEM.irq_priority(irq).unwrap_or(1);
+        if let Err(e) = EXT_INTC_SUBSYSTEM.enable_source(source, priority) {
+            log!(
+                LogLevel::Warn,
+                "Irq sub-system: cannot enable external source {}: {:?}",
+                source,
+                e
+            );
+        }
+    });
+}
+
+/// Allow the creation of the bottom half kernel task, must be called once the memory and the task
+/// list are initialized. Start the task if a bottom half is already registered.
+pub fn irq_bottom_half_init() {
+    unsafe { IRQ_BOTTOM_HALF_READY = true };
+    if IRQ_SUBSYSTEM.has_bottom_half() {
+        irq_bottom_half_start();
+    }
+}
+
+/// Create the bottom half kernel task if needed.
+fn irq_bottom_half_start() {
+    #[allow(static_mut_refs)]
+    if unsafe { !IRQ_BOTTOM_HALF_READY || IRQ_BOTTOM_HALF_TASK.is_some() } {
+        return;
+    }
+    match task_create(
+        "Irq bottom half",
+        irq_bottom_half_task_fn,
+        IRQ_BOTTOM_HALF_TASK_PRIORITY as u8,
+        IRQ_BOTTOM_HALF_TASK_SIZE,
+    ) {
+        Ok(pid) => {
+            unsafe { IRQ_BOTTOM_HALF_TASK = Some(pid) };
+            scheduler_enqueue_task(pid);
+        }
+        Err(e) => {
+            log!(
+                LogLevel::Error,
+                "Irq sub-system: failed to create the bottom half task: {:?}, bottom halves will not run",
+                e
+            );
+        }
+    }
+}
+
+/// Bottom half kernel task, run the pending bottom halves at each tick.
+fn irq_bottom_half_task_fn() -> ! {
+    loop {
+        IRQ_SUBSYSTEM.run_bottom_halves();
+        unsafe { sleep(1) };
+    }
+}
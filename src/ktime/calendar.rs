@@ -0,0 +1,125 @@
+// See documentation in `Documentation/kernel/timing_helpers.md`
+/*
+File info: Calendar breakdown of the Unix time.
+
+Test coverage: Conversion from and to Unix time.
+
+Tested:
+- Epoch, leap days, end of year.
+- Round trip from Unix time.
+- Invalid dates.
+
+Not tested:
+- ...
+
+Reasons:
+- ...
+
+Tests files:
+- 'src/tests/ktime/calendar.rs'
+*/
+
+use core::fmt;
+
+// Days between 0000-03-01 and 1970-01-01 in the proleptic Gregorian calendar.
+const DAYS_TO_EPOCH: u64 = 719_468;
+// Days in a 400 years era.
+const DAYS_PER_ERA: u64 = 146_097;
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// UTC date and time, from the Unix time.
+/// weekday: 0 for Sunday to 6 for Saturday.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DateTime {
+    pub year: u32,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub weekday: u8,
+}
+
+impl DateTime {
+    /// Break down seconds since 1970-01-01 00:00:00 UTC.
+    pub fn from_unix(seconds: u64) -> Self {
+        let days = seconds / SECONDS_PER_DAY;
+        let time = seconds % SECONDS_PER_DAY;
+        // Years start in March, the leap day is the last day of the year.
+        let z = days + DAYS_TO_EPOCH;
+        let era = z / DAYS_PER_ERA;
+        let doe = z - era * DAYS_PER_ERA;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / (DAYS_PER_ERA - 1)) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as u64;
+        DateTime {
+            year: year as u32,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3600) as u8,
+            minute: (time % 3600 / 60) as u8,
+            second: (time % 60) as u8,
+            // 1970-01-01 is a Thursday
+            weekday: ((days + 4) % 7) as u8,
+        }
+    }
+
+    /// Seconds since 1970-01-01 00:00:00 UTC. The weekday is ignored.
+    /// Return None for a date before the epoch or an invalid date.
+    pub fn to_unix(&self) -> Option<u64> {
+        if self.year < 1970
+            || self.month == 0
+            || self.month > 12
+            || self.day == 0
+            || self.day > days_in_month(self.year, self.month)
+            || self.hour > 23
+            || self.minute > 59
+            || self.second > 59
+        {
+            return None;
+        }
+        let month = self.month as u64;
+        let year = self.year as u64 - (month <= 2) as u64;
+        let era = year / 400;
+        let yoe = year - era * 400;
+        let mp = if month > 2 { month - 3 } else { month + 9 };
+        let doy = (153 * mp + 2) / 5 + self.day as u64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * DAYS_PER_ERA + doe - DAYS_TO_EPOCH;
+        Some(
+            days * SECONDS_PER_DAY
+                + self.hour as u64 * 3600
+                + self.minute as u64 * 60
+                + self.second as u64,
+        )
+    }
+}
+
+/// ISO 8601 format: 2024-02-29T13:37:00Z
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+pub fn is_leap_year(year: u32) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+/// Number of days in the month, 0 for an invalid month.
+pub fn days_in_month(year: u32, month: u8) -> u8 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
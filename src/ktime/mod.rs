@@ -10,6 +10,7 @@ Tested:
 
 Not tested:
 - Read ktime and set_mtimecmp_delta
+- Read the wall clock
 
 Reasons:
 - Hard to test those functions, even from a controlled environment like Qemu.
@@ -18,18 +19,23 @@ Tests files:
 - 'src/tests/ktime/mod.rs'
 */
 
-use crate::drivers::cpufreq::CPUFREQ;
-use crate::drivers::timer::TIMER_SUBSYSTEM;
+use crate::arch::helpers::current_cpu_core;
+use crate::drivers::cpufreq::{CPUFREQ, cpufreq_time};
+use crate::drivers::rtc::rtc_read_ns;
+use crate::drivers::timer::{TIMER_SUBSYSTEM, TimerError, TimerEventHandler};
+use calendar::DateTime;
+pub mod calendar;
 pub mod tick;
 pub mod uptime;
 
 // ———— Read ktime in specific time units ————
+// The time keeps counting across a timebase change, see cpufreq_time.
 
 pub fn ktime_seconds() -> u64 {
     #[allow(static_mut_refs)]
     let cpu_freq = unsafe { CPUFREQ.frequency };
     #[allow(static_mut_refs)]
-    let mtime = TIMER_SUBSYSTEM.get_primary_timer().read_time();
+    let mtime = cpufreq_time(TIMER_SUBSYSTEM.get_primary_timer().read_time());
     mtime / cpu_freq as u64
 }
 
@@ -37,7 +43,7 @@ pub fn ktime_ms() -> u64 {
     #[allow(static_mut_refs)]
     let cpu_freq = unsafe { CPUFREQ.frequency };
     #[allow(static_mut_refs)]
-    let mtime = TIMER_SUBSYSTEM.get_primary_timer().read_time();
+    let mtime = cpufreq_time(TIMER_SUBSYSTEM.get_primary_timer().read_time());
     (mtime * 1000) / cpu_freq as u64
 }
 
@@ -45,10 +51,27 @@ pub fn ktime_ns() -> u64 {
     #[allow(static_mut_refs)]
     let cpu_freq = unsafe { CPUFREQ.frequency };
     #[allow(static_mut_refs)]
-    let mtime = TIMER_SUBSYSTEM.get_primary_timer().read_time();
+    let mtime = cpufreq_time(TIMER_SUBSYSTEM.get_primary_timer().read_time());
     (mtime * 1_000_000) / cpu_freq as u64
 }
 
+// ———— Read the wall clock from the RTC sub-system, None without RTC ————
+
+/// Unix time in seconds.
+pub fn ktime_wall_clock() -> Option<u64> {
+    rtc_read_ns().map(|ns| ns / 1_000_000_000)
+}
+
+/// Unix time in nanoseconds.
+pub fn ktime_wall_clock_ns() -> Option<u64> {
+    rtc_read_ns()
+}
+
+/// UTC date and time, see calendar::DateTime.
+pub fn ktime_wall_clock_date() -> Option<DateTime> {
+    ktime_wall_clock().map(DateTime::from_unix)
+}
+
 // Set ktime in specific time units, handle conversion from params duration to correct time unit
 // and use set_mtime_cmp to write to timer sub-system
 
@@ -73,12 +96,32 @@ pub fn set_ktime_seconds(duration: u64) {
     set_mtimecmp_delta(delta_ticks);
 }
 
+/// Set the next tick of the current CPU core, delay timer units from now.
 pub fn set_mtimecmp_delta(delay: u64) {
     #[allow(static_mut_refs)]
     let mtime = TIMER_SUBSYSTEM.get_primary_timer().read_time();
     let delta_mtime = mtime + delay;
-    #[allow(static_mut_refs)]
-    TIMER_SUBSYSTEM
-        .get_primary_timer()
-        .set_delay(0, delta_mtime);
+    TIMER_SUBSYSTEM.set_tick_event(current_cpu_core(), delta_mtime);
+}
+
+// ———— One-shot events, separate from the kernel tick ————
+
+/// Call handler from the timer interrupt of the current CPU core in duration_ns, rounded up to the
+/// timer resolution. Use the SoC timer if there's one, else the primary timer of the core.
+/// Only one one-shot event per CPU core, see ktime_oneshot_cancel.
+pub fn ktime_oneshot_ns(duration_ns: u64, handler: TimerEventHandler) -> Result<(), TimerError> {
+    let timer = TIMER_SUBSYSTEM
+        .get_oneshot_timer()
+        .ok_or(TimerError::NoTimer)?;
+    let delay = (timer.frequency() * duration_ns).div_ceil(1_000_000_000);
+    TIMER_SUBSYSTEM.set_oneshot_event(current_cpu_core(), delay, handler)
+}
+
+pub fn ktime_oneshot_us(duration_us: u64, handler: TimerEventHandler) -> Result<(), TimerError> {
+    ktime_oneshot_ns(duration_us * 1000, handler)
+}
+
+/// Disarm the one-shot event of the current CPU core.
+pub fn ktime_oneshot_cancel() {
+    TIMER_SUBSYSTEM.cancel_oneshot_event(current_cpu_core());
 }
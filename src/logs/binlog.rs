@@ -0,0 +1,382 @@
+// See documentation in `Documentation/kernel/logs.md`
+/*
+File info: Binary logs, log! sends the id of its format string and the raw arguments, the format
+strings are only kept in the ELF file and read by the host decoder `tools/lrnrtos-log`.
+core::fmt is still used for the arguments sent as text, the image is only a bit smaller, see the
+sizes in the documentation.
+
+Test coverage: Format strings and frame encoding.
+
+Tested:
+- Count of the format string arguments, escapes and unsupported arguments.
+- Debug or Display argument of a placeholder.
+- Frame header, integers, strings and preformatted arguments.
+- Frame truncated on a whole argument.
+
+Not tested:
+- The log section and the ids.
+- The output of the log! macro.
+
+Reasons:
+- The test kernel is not built with the binlog feature.
+
+Tests files:
+- 'src/tests/logs/binlog.rs'
+*/
+
+use core::fmt::{self, Write};
+
+use crate::{
+    cmdline::kernel_params, drivers::serials::console::console_index, ktime::ktime_wall_clock,
+};
+
+use super::{
+    LogLevel,
+    buffer::{LogLine, log_write_line},
+};
+
+// First byte of a frame, never in UTF-8 text, the decoder prints the other bytes as they are.
+pub const BINLOG_FRAME_START: u8 = 0xff;
+// Flag of the level byte, the frame has the wall clock time.
+pub const BINLOG_LEVEL_TIME: u8 = 0x80;
+// Level byte of a trace! frame, after the LogLevel values.
+pub const BINLOG_LEVEL_TRACE: u8 = 4;
+// Tags of the arguments.
+pub const BINLOG_ARG_UNSIGNED: u8 = 0;
+pub const BINLOG_ARG_SIGNED: u8 = 1;
+pub const BINLOG_ARG_BOOL: u8 = 2;
+pub const BINLOG_ARG_CHAR: u8 = 3;
+pub const BINLOG_ARG_STR: u8 = 4;
+// Argument formatted by the kernel, printed as it is by the decoder.
+pub const BINLOG_ARG_TEXT: u8 = 5;
+// Size of the frame start and of the frame length.
+const BINLOG_FRAME_HEADER_SIZE: usize = 2;
+
+/// Binary log frame: the start byte, the length of the rest of the frame, the level, the id of the
+/// format string, the wall clock time if there's one, then the arguments. Integers are LEB128, the
+/// signed ones zigzag encoded.
+/// An argument that doesn't fit is dropped with all the next ones, the decoder prints `<?>`.
+pub struct BinlogFrame {
+    line: LogLine,
+    full: bool,
+}
+
+impl BinlogFrame {
+    pub fn init(level: u8, id: usize, time: Option<u64>) -> Self {
+        let mut frame = BinlogFrame {
+            line: LogLine::init_binary(),
+            full: false,
+        };
+        let level = match time {
+            Some(_) => level | BINLOG_LEVEL_TIME,
+            None => level,
+        };
+        frame.line.push_bytes(&[BINLOG_FRAME_START, 0, level]);
+        frame.line.push_bytes(binlog_varint(id as u64).as_bytes());
+        if let Some(time) = time {
+            frame.line.push_bytes(binlog_varint(time).as_bytes());
+        }
+        frame
+    }
+
+    /// Add an argument, tag and encoded value, nothing is added if it doesn't fit.
+    fn push_arg(&mut self, tag: u8, value: &[u8]) {
+        let start = self.line.len();
+        if self.full || !self.line.push_bytes(&[tag]) || !self.line.push_bytes(value) {
+            self.line.truncate(start);
+            self.full = true;
+        }
+    }
+
+    pub fn push_unsigned(&mut self, value: u64) {
+        self.push_arg(BINLOG_ARG_UNSIGNED, binlog_varint(value).as_bytes());
+    }
+
+    pub fn push_signed(&mut self, value: i64) {
+        let zigzag = ((value << 1) ^ (value >> 63)) as u64;
+        self.push_arg(BINLOG_ARG_SIGNED, binlog_varint(zigzag).as_bytes());
+    }
+
+    pub fn push_bool(&mut self, value: bool) {
+        self.push_arg(BINLOG_ARG_BOOL, &[value as u8]);
+    }
+
+    pub fn push_char(&mut self, value: char) {
+        self.push_arg(BINLOG_ARG_CHAR, binlog_varint(value as u64).as_bytes());
+    }
+
+    /// Add a string argument, the end of a string too long for the frame is dropped.
+    pub fn push_str(&mut self, tag: u8, value: &str) {
+        if self.full {
+            return;
+        }
+        // The tag and a 2 bytes length, LOG_LINE_MAX_SIZE is below 256.
+        let mut n = core::cmp::min(value.len(), self.line.remaining().saturating_sub(3));
+        while !value.is_char_boundary(n) {
+            n -= 1;
+        }
+        let start = self.line.len();
+        if !self.line.push_bytes(&[tag])
+            || !self.line.push_bytes(binlog_varint(n as u64).as_bytes())
+            || !self.line.push_bytes(&value.as_bytes()[..n])
+        {
+            self.line.truncate(start);
+            self.full = true;
+        }
+    }
+
+    /// Add an argument formatted by the kernel, for the types the decoder doesn't know.
+    pub fn push_text(&mut self, args: fmt::Arguments) {
+        let mut text = LogLine::init();
+        let _ = text.write_fmt(args);
+        self.push_str(BINLOG_ARG_TEXT, text.as_str());
+    }
+
+    /// Write the length of the frame, return the line to write.
+    pub fn finish(mut self) -> LogLine {
+        let len = self.line.len() - BINLOG_FRAME_HEADER_SIZE;
+        self.line.as_bytes_mut()[1] = len as u8;
+        self.line
+    }
+}
+
+/// LEB128 encoded integer, 7 bits per byte, the high bit is set on all bytes except the last one.
+pub struct BinlogVarint {
+    buf: [u8; 10],
+    len: usize,
+}
+
+impl BinlogVarint {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+pub fn binlog_varint(mut value: u64) -> BinlogVarint {
+    let mut varint = BinlogVarint {
+        buf: [0; 10],
+        len: 0,
+    };
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            varint.buf[varint.len] = byte;
+            varint.len += 1;
+            return varint;
+        }
+        varint.buf[varint.len] = byte | 0x80;
+        varint.len += 1;
+    }
+}
+
+/// Copy of the format string kept in the log section, ended by a nul byte. N is the length of the
+/// format string + 1.
+pub const fn binlog_fmt<const N: usize>(fmt: &str) -> [u8; N] {
+    let bytes = fmt.as_bytes();
+    let mut out = [0; N];
+    let mut i = 0;
+    while i < bytes.len() {
+        out[i] = bytes[i];
+        i += 1;
+    }
+    out
+}
+
+/// Number of arguments of the format string, None if it has a named or positional argument, or an
+/// unclosed brace. The arguments are given after the format string in the binary logs.
+pub const fn binlog_fmt_args(fmt: &str) -> Option<usize> {
+    let bytes = fmt.as_bytes();
+    let mut count = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'{' {
+            if i + 1 < bytes.len() && bytes[i + 1] == b'{' {
+                i += 2;
+                continue;
+            }
+            if i + 1 >= bytes.len() || (bytes[i + 1] != b':' && bytes[i + 1] != b'}') {
+                return None;
+            }
+            while i < bytes.len() && bytes[i] != b'}' {
+                i += 1;
+            }
+            if i == bytes.len() {
+                return None;
+            }
+            count += 1;
+        } else if bytes[i] == b'}' {
+            if i + 1 >= bytes.len() || bytes[i + 1] != b'}' {
+                return None;
+            }
+            i += 1;
+        }
+        i += 1;
+    }
+    Some(count)
+}
+
+/// True if the argument at index is formatted with Debug, `{:?}`, else with Display. Only used on
+/// format strings accepted by binlog_fmt_args.
+pub const fn binlog_fmt_debug(fmt: &str, index: usize) -> bool {
+    let bytes = fmt.as_bytes();
+    let mut count = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'{' && i + 1 < bytes.len() && bytes[i + 1] == b'{' {
+            i += 2;
+            continue;
+        }
+        if bytes[i] == b'{' {
+            let mut debug = false;
+            while i < bytes.len() && bytes[i] != b'}' {
+                debug |= bytes[i] == b'?';
+                i += 1;
+            }
+            if count == index {
+                return debug;
+            }
+            count += 1;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Argument encoded as a raw value, formatted by the decoder.
+pub trait BinlogArg {
+    fn binlog_arg(&self, frame: &mut BinlogFrame);
+}
+
+macro_rules! binlog_arg_impl {
+    ($push:ident, $as:ty, $($t:ty),*) => {
+        $(impl BinlogArg for $t {
+            fn binlog_arg(&self, frame: &mut BinlogFrame) {
+                frame.$push(*self as $as);
+            }
+        })*
+    };
+}
+
+binlog_arg_impl!(push_unsigned, u64, u8, u16, u32, u64, usize);
+binlog_arg_impl!(push_signed, i64, i8, i16, i32, i64, isize);
+
+impl BinlogArg for bool {
+    fn binlog_arg(&self, frame: &mut BinlogFrame) {
+        frame.push_bool(*self);
+    }
+}
+
+impl BinlogArg for char {
+    fn binlog_arg(&self, frame: &mut BinlogFrame) {
+        frame.push_char(*self);
+    }
+}
+
+impl BinlogArg for str {
+    fn binlog_arg(&self, frame: &mut BinlogFrame) {
+        frame.push_str(BINLOG_ARG_STR, self);
+    }
+}
+
+impl<T: BinlogArg + ?Sized> BinlogArg for &T {
+    fn binlog_arg(&self, frame: &mut BinlogFrame) {
+        (**self).binlog_arg(frame);
+    }
+}
+
+/// Argument of a log! call, DEBUG is the format trait of its placeholder. The raw encoding is
+/// used when the type has one, else the argument is formatted by the kernel, see the binlog_args
+/// macro.
+pub struct BinlogArgRef<'a, T: ?Sized, const DEBUG: bool>(pub &'a T);
+
+pub trait BinlogEncodeRaw {
+    fn binlog_encode(&self, frame: &mut BinlogFrame);
+}
+
+impl<T: BinlogArg + ?Sized, const DEBUG: bool> BinlogEncodeRaw for &BinlogArgRef<'_, T, DEBUG> {
+    fn binlog_encode(&self, frame: &mut BinlogFrame) {
+        self.0.binlog_arg(frame);
+    }
+}
+
+pub trait BinlogEncodeFmt {
+    fn binlog_encode(&self, frame: &mut BinlogFrame);
+}
+
+impl<T: fmt::Display + ?Sized> BinlogEncodeFmt for BinlogArgRef<'_, T, false> {
+    fn binlog_encode(&self, frame: &mut BinlogFrame) {
+        frame.push_text(format_args!("{}", self.0));
+    }
+}
+
+impl<T: fmt::Debug + ?Sized> BinlogEncodeFmt for BinlogArgRef<'_, T, true> {
+    fn binlog_encode(&self, frame: &mut BinlogFrame) {
+        frame.push_text(format_args!("{:?}", self.0));
+    }
+}
+
+/// Binary log function, used by the log! macro with the binlog feature. The arguments are encoded
+/// only if the level is logged.
+/// id: offset of the format string in the log section.
+/// args: encode the arguments in the frame.
+pub fn binlog_log(level: LogLevel, id: usize, args: &mut dyn FnMut(&mut BinlogFrame)) {
+    if level >= kernel_params().log_level {
+        binlog_write(level as u8, console_index(Some(level)), id, args);
+    }
+}
+
+/// Binary trace function, used by the trace! macro with the binlog feature.
+pub fn binlog_trace(id: usize, args: &mut dyn FnMut(&mut BinlogFrame)) {
+    if kernel_params().trace {
+        // The trace events are debug traffic, routed like the debug logs.
+        let console = console_index(Some(LogLevel::Debug));
+        binlog_write(BINLOG_LEVEL_TRACE, console, id, args);
+    }
+}
+
+fn binlog_write(level: u8, console: usize, id: usize, args: &mut dyn FnMut(&mut BinlogFrame)) {
+    let mut frame = BinlogFrame::init(level, id, ktime_wall_clock());
+    args(&mut frame);
+    log_write_line(console, &frame.finish());
+}
+
+/// Id of a format string: a copy of the format string is kept in the `.lrnrtos_log` section, not
+/// loaded, the id is its address in the section. Only the arguments after the format string are
+/// supported.
+#[macro_export]
+macro_rules! binlog_id {
+    ($fmt:literal $(, $arg:expr)*) => {{
+        const _: () = assert!(
+            matches!(
+                $crate::logs::binlog::binlog_fmt_args($fmt),
+                Some(n) if n == <[&str]>::len(&[$(stringify!($arg)),*])
+            ),
+            "Binary logs: the arguments must be given after the format string, without name or position"
+        );
+        #[unsafe(link_section = ".lrnrtos_log")]
+        static BINLOG_FMT: [u8; $fmt.len() + 1] = $crate::logs::binlog::binlog_fmt($fmt);
+        &BINLOG_FMT as *const [u8; $fmt.len() + 1] as usize
+    }};
+}
+
+/// Encode the arguments of a log! call in the frame, the index selects the format trait of the
+/// placeholder.
+#[macro_export]
+macro_rules! binlog_args {
+    ($frame:ident, $fmt:literal, $index:expr;) => {};
+    ($frame:ident, $fmt:literal, $index:expr; $arg:expr $(, $rest:expr)*) => {
+        {
+            #[allow(unused_imports)]
+            use $crate::logs::binlog::{BinlogEncodeFmt as _, BinlogEncodeRaw as _};
+            // The raw encoding is found first, on &BinlogArgRef, the formatted one on
+            // BinlogArgRef.
+            (&&$crate::logs::binlog::BinlogArgRef::<
+                _,
+                { $crate::logs::binlog::binlog_fmt_debug($fmt, $index) },
+            >(&$arg))
+                .binlog_encode($frame);
+        }
+        $crate::binlog_args!($frame, $fmt, $index + 1; $($rest),*);
+    };
+}
@@ -0,0 +1,372 @@
+// See documentation in `Documentation/kernel/logs.md`
+/*
+File info: Log ring buffer, log! writes in it and a low priority kernel task drains it to the
+consoles.
+
+Test coverage: Ring buffer and log lines.
+
+Tested:
+- Push and pop records, with their console.
+- Records wrapping around the end of the buffer.
+- Drop count when the buffer is full.
+- Log lines truncated on a char boundary.
+- Binary lines kept binary.
+
+Not tested:
+- The drain task.
+- The flush at panic.
+- The static line of the logs written with the interrupts disabled.
+
+Reasons:
+- The task need the scheduler and timer interrupts, not handled by the test framework.
+- A panic stops the test kernel.
+- The test kernel doesn't create the drain task, its logs are written directly to the console.
+
+Tests files:
+- 'src/tests/logs/buffer.rs'
+- 'src/tests/logs/binlog.rs'
+*/
+
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+};
+
+use crate::{
+    arch::traps::interrupt::{read_mstatus_mie, restore_mstatus_mie, save_and_disable_mstatus_mie},
+    config::{LOG_BUFFER_SIZE, LOG_DRAIN_TASK_PRIORITY, LOG_LINE_MAX_SIZE},
+    drivers::serials::console::console_index,
+    log,
+    print::{print_console, print_console_bytes},
+    scheduler::scheduler_enqueue_task,
+    task::{primitives::sleep, task_create},
+};
+
+use super::LogLevel;
+
+// Size of a record header in the ring buffer, the console index and the length of the line.
+const LOG_RECORD_HEADER_SIZE: usize = 2;
+// Flag of the console index in a record header, the line is a binary log frame.
+const LOG_RECORD_BINARY: u8 = 0x80;
+// Stack size of the drain task, it formats the drop count and writes the lines.
+const LOG_DRAIN_TASK_SIZE: usize = 0x800;
+
+/// One formatted log line, truncated to LOG_LINE_MAX_SIZE bytes on a char boundary, or one
+/// binary log frame, see src/logs/binlog.rs.
+pub struct LogLine {
+    buf: [u8; LOG_LINE_MAX_SIZE],
+    len: usize,
+    binary: bool,
+}
+
+impl LogLine {
+    pub const fn init() -> Self {
+        LogLine {
+            buf: [0; LOG_LINE_MAX_SIZE],
+            len: 0,
+            binary: false,
+        }
+    }
+
+    /// Empty binary line, written as raw bytes to the serial device.
+    pub const fn init_binary() -> Self {
+        LogLine {
+            buf: [0; LOG_LINE_MAX_SIZE],
+            len: 0,
+            binary: true,
+        }
+    }
+
+    /// Text of the line, empty for a binary line.
+    pub fn as_str(&self) -> &str {
+        if self.binary {
+            return "";
+        }
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.buf[..self.len]
+    }
+
+    /// Add the bytes at the end of a binary line, nothing is added and return false if they don't
+    /// fit.
+    pub fn push_bytes(&mut self, data: &[u8]) -> bool {
+        if data.len() > LOG_LINE_MAX_SIZE - self.len {
+            return false;
+        }
+        self.buf[self.len..self.len + data.len()].copy_from_slice(data);
+        self.len += data.len();
+        true
+    }
+
+    /// Remove the bytes after len.
+    pub fn truncate(&mut self, len: usize) {
+        self.len = core::cmp::min(self.len, len);
+    }
+
+    pub fn is_binary(&self) -> bool {
+        self.binary
+    }
+
+    /// Free space at the end of the line.
+    pub fn remaining(&self) -> usize {
+        LOG_LINE_MAX_SIZE - self.len
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Empty the line, it becomes a text line.
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.binary = false;
+    }
+}
+
+impl fmt::Write for LogLine {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut n = core::cmp::min(s.len(), LOG_LINE_MAX_SIZE - self.len);
+        while !s.is_char_boundary(n) {
+            n -= 1;
+        }
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        // The end of a long line is dropped, the formatting still succeeds.
+        Ok(())
+    }
+}
+
+/// Ring buffer of log records, a record is the console index, the length and the line. The high
+/// bit of the console index marks a binary line.
+/// Records are written by any task or interrupt handler, and read by a single reader, the drain
+/// task or the flush at panic.
+/// read and write are free running, the used size is write - read, N must be a power of 2.
+/// buf: records, a record can wrap around the end.
+/// read: start of the oldest record, only changed by the reader.
+/// write: end of the newest record, only changed by the writers.
+/// dropped: number of records dropped because the buffer was full.
+pub struct LogBuffer<const N: usize> {
+    buf: UnsafeCell<[u8; N]>,
+    read: AtomicUsize,
+    write: AtomicUsize,
+    dropped: AtomicU32,
+}
+
+unsafe impl<const N: usize> Sync for LogBuffer<N> {}
+
+impl<const N: usize> LogBuffer<N> {
+    pub const fn init() -> Self {
+        LogBuffer {
+            buf: UnsafeCell::new([0; N]),
+            read: AtomicUsize::new(0),
+            write: AtomicUsize::new(0),
+            dropped: AtomicU32::new(0),
+        }
+    }
+
+    /// Add the line to the buffer, drop it and count it if there's not enough space.
+    /// Return false if the line was dropped.
+    pub fn push(&self, console: usize, line: &LogLine) -> bool {
+        let record_size = LOG_RECORD_HEADER_SIZE + line.len();
+        // The target has no compare-and-swap, the space is reserved with the interrupts disabled,
+        // a handler logging in the middle of a record would corrupt it. Nothing waits on the
+        // reader.
+        let mie = save_and_disable_mstatus_mie();
+        let write = self.write.load(Ordering::Relaxed);
+        let read = self.read.load(Ordering::Acquire);
+        let pushed = if N - write.wrapping_sub(read) < record_size {
+            self.dropped
+                .store(self.dropped.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+            false
+        } else {
+            let buf = unsafe { &mut *self.buf.get() };
+            buf[write % N] = if line.is_binary() {
+                console as u8 | LOG_RECORD_BINARY
+            } else {
+                console as u8
+            };
+            buf[write.wrapping_add(1) % N] = line.len() as u8;
+            for (i, byte) in line.as_bytes().iter().copied().enumerate() {
+                buf[write.wrapping_add(LOG_RECORD_HEADER_SIZE + i) % N] = byte;
+            }
+            // The reader sees the record once it's fully written.
+            self.write
+                .store(write.wrapping_add(record_size), Ordering::Release);
+            true
+        };
+        restore_mstatus_mie(mie);
+        pushed
+    }
+
+    /// Remove the oldest record, copy its line in line and return its console index, None if the
+    /// buffer is empty.
+    /// Only one reader at a time, the writers are never blocked by it.
+    pub fn pop(&self, line: &mut LogLine) -> Option<usize> {
+        let read = self.read.load(Ordering::Relaxed);
+        if read == self.write.load(Ordering::Acquire) {
+            return None;
+        }
+        let buf = unsafe { &*self.buf.get() };
+        let header = buf[read % N];
+        let len = buf[read.wrapping_add(1) % N] as usize;
+        for i in 0..len {
+            line.buf[i] = buf[read.wrapping_add(LOG_RECORD_HEADER_SIZE + i) % N];
+        }
+        line.len = len;
+        line.binary = header & LOG_RECORD_BINARY != 0;
+        // The space of the record is given back to the writers once the line is copied.
+        self.read.store(
+            read.wrapping_add(LOG_RECORD_HEADER_SIZE + len),
+            Ordering::Release,
+        );
+        Some((header & !LOG_RECORD_BINARY) as usize)
+    }
+
+    /// Number of bytes used by the records.
+    pub fn used(&self) -> usize {
+        self.write
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.read.load(Ordering::Acquire))
+    }
+
+    /// Number of records dropped since the boot.
+    pub fn dropped(&self) -> u32 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+pub static LOG_BUFFER: LogBuffer<LOG_BUFFER_SIZE> = LogBuffer::init();
+
+// True once the drain task runs, the logs are written synchronously before and after a flush.
+static LOG_BUFFERED: AtomicBool = AtomicBool::new(false);
+// Drop count already reported by log_drain.
+static LOG_DROPPED_REPORTED: AtomicU32 = AtomicU32::new(0);
+// Line of the logs written with the interrupts disabled, by the interrupt handlers on the trap
+// stack and by the critical sections. Nothing preempts them, one line is enough.
+static mut LOG_LINE_IRQ_OFF: LogLine = LogLine::init();
+
+/// Write the log line, in the ring buffer once the drain task runs, else directly to the console.
+/// With the interrupts disabled, the line is formatted in a static line instead of the stack of the
+/// caller, the trap stack of the interrupt handlers is small.
+pub fn log_write(console: usize, args: fmt::Arguments) {
+    if !LOG_BUFFERED.load(Ordering::Acquire) {
+        print_console(console, args);
+        return;
+    }
+    if read_mstatus_mie() == 0 {
+        log_write_irq_off(console, args);
+    } else {
+        log_write_stack(console, args);
+    }
+}
+
+/// Format the line on the stack of the task, another task or a handler may log at the same time.
+/// Not inlined, the line must not be on the stack of log_write when the interrupts are disabled.
+#[inline(never)]
+fn log_write_stack(console: usize, args: fmt::Arguments) {
+    let mut line = LogLine::init();
+    let _ = fmt::Write::write_fmt(&mut line, args);
+    LOG_BUFFER.push(console, &line);
+}
+
+/// Format the line in the static line, must be called with the interrupts disabled.
+fn log_write_irq_off(console: usize, args: fmt::Arguments) {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let line = unsafe { &mut LOG_LINE_IRQ_OFF };
+    line.clear();
+    let _ = fmt::Write::write_fmt(line, args);
+    LOG_BUFFER.push(console, line);
+}
+
+/// Write the binary log line, in the ring buffer once the drain task runs, else directly to the
+/// console.
+pub fn log_write_line(console: usize, line: &LogLine) {
+    if !LOG_BUFFERED.load(Ordering::Acquire) {
+        log_line_print(console, line);
+        return;
+    }
+    LOG_BUFFER.push(console, line);
+}
+
+/// Write the line to the console, a binary line only goes to the serial device.
+fn log_line_print(console: usize, line: &LogLine) {
+    if line.is_binary() {
+        print_console_bytes(console, line.as_bytes());
+    } else {
+        print_console(console, format_args!("{}", line.as_str()));
+    }
+}
+
+/// Write all the records of the ring buffer to their console, then report the new dropped
+/// records. Return the number of records written.
+pub fn log_drain() -> usize {
+    let mut line = LogLine::init();
+    let mut written = 0;
+    while let Some(console) = LOG_BUFFER.pop(&mut line) {
+        log_line_print(console, &line);
+        written += 1;
+    }
+    let dropped = LOG_BUFFER.dropped();
+    let reported = LOG_DROPPED_REPORTED.load(Ordering::Relaxed);
+    if dropped != reported {
+        LOG_DROPPED_REPORTED.store(dropped, Ordering::Relaxed);
+        print_console(
+            console_index(Some(LogLevel::Warn)),
+            format_args!(
+                "\x1b[33;1m[WARNING]\x1b[0m Logs: {} messages dropped, log buffer full\n",
+                dropped - reported
+            ),
+        );
+    }
+    written
+}
+
+/// Write the pending records synchronously and stop buffering, the next logs are written directly
+/// to the console. Used at panic and poweroff, the drain task will not run anymore.
+pub fn log_flush() {
+    LOG_BUFFERED.store(false, Ordering::Release);
+    log_drain();
+}
+
+/// Create the drain task and start buffering the logs, must be called once the memory and the
+/// task list are initialized. On error, the logs stay synchronous.
+pub fn log_drain_init() {
+    match task_create(
+        "Log drain",
+        log_drain_task_fn,
+        LOG_DRAIN_TASK_PRIORITY as u8,
+        LOG_DRAIN_TASK_SIZE,
+    ) {
+        Ok(pid) => {
+            scheduler_enqueue_task(pid);
+            LOG_BUFFERED.store(true, Ordering::Release);
+        }
+        Err(e) => {
+            log!(
+                LogLevel::Error,
+                "Logs: failed to create the drain task: {:?}, logs stay synchronous",
+                e
+            );
+        }
+    }
+}
+
+/// Drain kernel task, write the logs at each tick.
+fn log_drain_task_fn() -> ! {
+    loop {
+        log_drain();
+        unsafe { sleep(1) };
+    }
+}
@@ -1,9 +1,16 @@
+use core::fmt;
+
 // Actually used when logs feature is enabled
-use crate::config::LOG_LEVEL;
-#[allow(unused)]
-use crate::print;
+use crate::cmdline::kernel_params;
+use crate::drivers::serials::console::console_index;
+use crate::ktime::calendar::DateTime;
+use crate::ktime::ktime_wall_clock;
+use buffer::log_write;
+
+pub mod binlog;
+pub mod buffer;
 
-#[derive(PartialEq, PartialOrd)]
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
 /// Enum for logging level
 pub enum LogLevel {
     // Debug, lot of logs, possibly exploded kernel size
@@ -16,28 +23,72 @@ pub enum LogLevel {
     Error,
 }
 
-/// Main log function, used for all logs, call to print! macro inside to avoid repeating codes. Use
-/// hexadecimal escape code to make prefix log in color
+impl LogLevel {
+    /// Level from its name, like in the kernel command line, the case is ignored.
+    pub fn from_name(name: &str) -> Option<LogLevel> {
+        [
+            ("debug", LogLevel::Debug),
+            ("info", LogLevel::Info),
+            ("warn", LogLevel::Warn),
+            ("error", LogLevel::Error),
+        ]
+        .into_iter()
+        .find(|(level_name, _)| level_name.eq_ignore_ascii_case(name))
+        .map(|(_, level)| level)
+    }
+}
+
+/// Log timestamp from the wall clock, empty without RTC.
+struct LogTimestamp(Option<u64>);
+
+impl fmt::Display for LogTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(seconds) => write!(f, "{} ", DateTime::from_unix(seconds)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Main log function, used for all logs, write to the console of the level to avoid repeating
+/// codes. Use hexadecimal escape code to make prefix log in color
+/// Once the drain task runs, the line is written in the log ring buffer, the caller doesn't wait
+/// for the UART, see Documentation/kernel/logs.md.
 ///
 /// Params:
 /// level: use LogLevel enum to define which logging level used.
 /// msg: the message to print as an &str
 pub fn log(level: LogLevel, msg: core::fmt::Arguments) {
-    if level >= LOG_LEVEL {
-        match level {
-            LogLevel::Info => print!("\x1b[32;1m[INFO]\x1b[0m {}\n", msg),
-            LogLevel::Debug => print!("\x1b[35;1m[DEBUG]\x1b[0m {}\n", msg),
-            LogLevel::Warn => print!("\x1b[33;1m[WARNING]\x1b[0m {}\n", msg),
-            LogLevel::Error => {
-                print!("\x1b[31;1m[ERROR]\x1b[0m {}\n", msg);
-            }
-        }
+    if level >= kernel_params().log_level {
+        let time = LogTimestamp(ktime_wall_clock());
+        // The route of the level first, see Documentation/kernel/subsystems.md
+        let console = console_index(Some(level));
+        let prefix = match level {
+            LogLevel::Info => "\x1b[32;1m[INFO]\x1b[0m",
+            LogLevel::Debug => "\x1b[35;1m[DEBUG]\x1b[0m",
+            LogLevel::Warn => "\x1b[33;1m[WARNING]\x1b[0m",
+            LogLevel::Error => "\x1b[31;1m[ERROR]\x1b[0m",
+        };
+        log_write(console, format_args!("{} {}{}\n", prefix, time, msg));
+    }
+}
+
+/// Trace function, used by the trace! macro. The trace events are printed only when the tracing is
+/// enabled on the kernel command line, whatever the log level.
+pub fn trace(msg: core::fmt::Arguments) {
+    if kernel_params().trace {
+        let time = LogTimestamp(ktime_wall_clock());
+        // The trace events are debug traffic, routed like the debug logs.
+        log_write(
+            console_index(Some(LogLevel::Debug)),
+            format_args!("\x1b[36;1m[TRACE]\x1b[0m {}{}\n", time, msg),
+        );
     }
 }
 
 // Log macro enabled by features. Avoid using feature on all module.
 
-#[cfg(feature = "logs")]
+#[cfg(all(feature = "logs", not(feature = "binlog")))]
 #[macro_export]
 macro_rules! log {
     ($log_level:expr, $($arg:tt)*) => {
@@ -45,8 +96,47 @@ macro_rules! log {
     };
 }
 
+// Binary logs, the format string is sent as an id, see Documentation/kernel/logs.md.
+#[cfg(all(feature = "logs", feature = "binlog"))]
+#[macro_export]
+macro_rules! log {
+    ($log_level:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::logs::binlog::binlog_log(
+            $log_level,
+            $crate::binlog_id!($fmt $(, $arg)*),
+            &mut |_frame| {
+                $crate::binlog_args!(_frame, $fmt, 0; $($arg),*);
+            },
+        );
+    };
+}
+
 #[cfg(not(feature = "logs"))]
 #[macro_export]
 macro_rules! log {
     ($($arg:tt)*) => {};
 }
+
+#[cfg(all(feature = "logs", not(feature = "binlog")))]
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        $crate::logs::trace(format_args!($($arg)*));
+    };
+}
+
+#[cfg(all(feature = "logs", feature = "binlog"))]
+#[macro_export]
+macro_rules! trace {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::logs::binlog::binlog_trace($crate::binlog_id!($fmt $(, $arg)*), &mut |_frame| {
+            $crate::binlog_args!(_frame, $fmt, 0; $($arg),*);
+        });
+    };
+}
+
+#[cfg(not(feature = "logs"))]
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => {};
+}
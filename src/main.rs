@@ -50,6 +50,18 @@ pub mod primitives;
 // Scheduler module
 pub mod scheduler;
 
+// Interrupt registration and dispatch module
+pub mod irq;
+
+// Poweroff and reboot module
+pub mod power;
+
+// Task watchdog module
+pub mod watchdog;
+
+// Kernel command line module
+pub mod cmdline;
+
 // Test module
 #[cfg(feature = "test")]
 pub mod tests;
@@ -57,6 +69,7 @@ pub mod tests;
 // Use from modules
 #[cfg(not(feature = "test"))]
 use core::panic::PanicInfo;
+use drivers::display::init_display_subsystem;
 use logs::LogLevel;
 use mem::mem_kernel_stack_info;
 
@@ -73,7 +86,16 @@ unsafe extern "C" fn main() -> ! {
         kernel_stack.top,
         kernel_stack.bottom
     );
+    // The display needs the memory for its framebuffer.
+    log!(LogLevel::Debug, "Display sub-system initializing...");
+    init_display_subsystem();
+    log!(
+        LogLevel::Debug,
+        "Display sub-system successfully initialized."
+    );
     log!(LogLevel::Info, "LrnRTOS started!");
+    irq::irq_bottom_half_init();
+    logs::buffer::log_drain_init();
     #[cfg(feature = "idle_task")]
     task_idle_task();
     loop {
@@ -87,6 +109,9 @@ unsafe extern "C" fn main() -> ! {
 #[panic_handler]
 #[cfg(not(feature = "test"))]
 fn panic_handler(panic: &PanicInfo) -> ! {
-    kprint_fmt!("PANIC {:?}", panic);
-    loop {}
+    // The buffered logs before the panic are written first.
+    logs::buffer::log_flush();
+    kprint_fmt!("PANIC {:?}\n", panic);
+    mem::mem_stacks_report();
+    power::shutdown(power::POWER_PANIC_EXIT_CODE)
 }
@@ -0,0 +1,375 @@
+// See documentation: `Documentation/kernel/dma.md`
+/*
+File info: DMA buffers for the drivers, with CPU and device ownership.
+
+Test coverage: Allocation, alignment, ownership and cache hooks.
+
+Tested:
+- Allocate a buffer with the asked alignment and size.
+- Invalid alignment, size too large to be aligned.
+- Give the buffer to the device and back, CPU access denied while the device owns it.
+- Share the buffer with the device, no owner change while shared.
+- Cache hooks called with the buffer range and the direction.
+- Free and re-use of a freed buffer.
+
+Not tested:
+- A real device transfer.
+
+Reasons:
+- The DMA buffers are only used by the drivers, the transfer depends on the device.
+
+Tests files:
+- 'src/tests/mem/dma.rs'
+*/
+
+use core::ptr;
+
+use crate::{
+    config::{DMA_BUFFER_MAX_SIZE, DMA_MIN_ALIGN},
+    drivers::DriverRegion,
+    log,
+    logs::LogLevel,
+};
+
+use super::mem_region_alloc;
+
+/// All errors that can happen when using a DMA buffer.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DmaError {
+    // The memory allocator cannot allocate the asked size.
+    OutOfMemory,
+    // The DMA buffer table is full, see DMA_BUFFER_MAX_SIZE in config file.
+    TableFull,
+    // There's no DMA buffer with this id.
+    InvalidId,
+    // The alignment is not a power of two.
+    InvalidAlignment,
+    // The device owns the buffer, the CPU cannot access or free it.
+    DeviceOwned,
+    // The CPU already owns the buffer.
+    CpuOwned,
+    // The buffer is shared with the device, it cannot change owner or be freed before being
+    // taken back with dma_unshare.
+    Shared,
+}
+
+/// Direction of the data between the memory and the device, used to select the cache
+/// maintenance.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DmaDirection {
+    // The device reads the buffer, like a virtqueue request or a SPI tx buffer.
+    ToDevice,
+    // The device writes the buffer, like a block read or a SPI rx buffer.
+    FromDevice,
+    Bidirectional,
+}
+
+/// Who can access the buffer. Only the owner can access the buffer, the CPU gives the buffer to
+/// the device before starting a transfer and takes it back once the transfer is done.
+/// A shared buffer is accessed by both at any time, like a framebuffer scanned out by the device
+/// while the CPU draws in it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DmaOwner {
+    Cpu,
+    Device,
+    Shared,
+}
+
+/// Cache maintenance hooks, called with the address and size of the buffer.
+/// clean: write back the dirty cache lines, before the device reads the buffer.
+/// invalidate: drop the cache lines, before the CPU reads what the device wrote.
+/// The default hooks do nothing, for cores without data cache or with DMA coherent caches.
+#[derive(Copy, Clone)]
+pub struct DmaCacheOps {
+    pub clean: fn(addr: usize, size: usize),
+    pub invalidate: fn(addr: usize, size: usize),
+}
+
+fn dma_cache_nop(_addr: usize, _size: usize) {}
+
+impl DmaCacheOps {
+    pub const fn coherent() -> Self {
+        DmaCacheOps {
+            clean: dma_cache_nop,
+            invalidate: dma_cache_nop,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct DmaBuffer {
+    // Aligned address of the buffer, the address seen by the device
+    addr: usize,
+    // Size asked when allocating the buffer
+    size: usize,
+    // Usable size of the memory region from addr, can be more than size when the buffer is
+    // re-used.
+    capacity: usize,
+    direction: DmaDirection,
+    owner: DmaOwner,
+    // A freed buffer keep its memory region to be re-used, the allocator cannot free memory.
+    free: bool,
+}
+
+impl DmaBuffer {
+    fn region(&self) -> DriverRegion {
+        DriverRegion {
+            addr: self.addr,
+            size: self.size,
+        }
+    }
+}
+
+struct DmaBufferTable {
+    buffers: [Option<DmaBuffer>; DMA_BUFFER_MAX_SIZE],
+    cache_ops: DmaCacheOps,
+}
+
+impl DmaBufferTable {
+    const fn init() -> Self {
+        DmaBufferTable {
+            buffers: [None; DMA_BUFFER_MAX_SIZE],
+            cache_ops: DmaCacheOps::coherent(),
+        }
+    }
+
+    /// Allocate a new buffer, re-use a freed buffer if its address has the alignment and its
+    /// capacity is large enough. Return the id of the buffer, the id is the index in the table.
+    fn alloc(
+        &mut self,
+        size: usize,
+        align: usize,
+        direction: DmaDirection,
+    ) -> Result<usize, DmaError> {
+        let mut empty_index: Option<usize> = None;
+        for i in 0..DMA_BUFFER_MAX_SIZE {
+            match &mut self.buffers[i] {
+                Some(buffer)
+                    if buffer.free
+                        && buffer.capacity >= size
+                        && buffer.addr.is_multiple_of(align) =>
+                {
+                    buffer.free = false;
+                    buffer.size = size;
+                    buffer.direction = direction;
+                    buffer.owner = DmaOwner::Cpu;
+                    return Ok(i);
+                }
+                Some(_) => continue,
+                None => {
+                    if empty_index.is_none() {
+                        empty_index = Some(i);
+                    }
+                }
+            }
+        }
+        let index = empty_index.ok_or(DmaError::TableFull)?;
+        // The region is aligned on 16 bytes, allocate the extra bytes needed to align it, and round
+        // the size to the alignment so no other object share the last cache line. A size too large
+        // to be rounded can't fit in memory anyway.
+        let capacity = size
+            .checked_next_multiple_of(align)
+            .and_then(|capacity| capacity.checked_add(align))
+            .ok_or(DmaError::OutOfMemory)?;
+        let reg = mem_region_alloc(capacity).ok_or(DmaError::OutOfMemory)?;
+        let addr = reg[1].next_multiple_of(align);
+        self.buffers[index] = Some(DmaBuffer {
+            addr,
+            size,
+            capacity: reg[0] - addr,
+            direction,
+            owner: DmaOwner::Cpu,
+            free: false,
+        });
+        Ok(index)
+    }
+
+    fn get(&mut self, id: usize) -> Result<&mut DmaBuffer, DmaError> {
+        if id >= DMA_BUFFER_MAX_SIZE {
+            return Err(DmaError::InvalidId);
+        }
+        match &mut self.buffers[id] {
+            Some(buffer) if !buffer.free => Ok(buffer),
+            _ => Err(DmaError::InvalidId),
+        }
+    }
+
+    /// Return the buffer if the CPU owns it.
+    fn get_cpu(&mut self, id: usize) -> Result<&mut DmaBuffer, DmaError> {
+        let buffer = self.get(id)?;
+        match buffer.owner {
+            DmaOwner::Cpu => Ok(buffer),
+            DmaOwner::Device => Err(DmaError::DeviceOwned),
+            DmaOwner::Shared => Err(DmaError::Shared),
+        }
+    }
+
+    /// Return the buffer if the CPU can access it, owned by the CPU or shared with the device.
+    fn get_access(&mut self, id: usize) -> Result<&mut DmaBuffer, DmaError> {
+        let buffer = self.get(id)?;
+        if buffer.owner == DmaOwner::Device {
+            return Err(DmaError::DeviceOwned);
+        }
+        Ok(buffer)
+    }
+}
+
+static mut DMA_BUFFERS: DmaBufferTable = DmaBufferTable::init();
+
+/// Allocate a new DMA buffer of the given size, aligned on align, owned by the CPU. The buffer is
+/// zeroed.
+/// The alignment must be a power of two, it's at least DMA_MIN_ALIGN, the cache line size.
+/// The buffer is allocated with the same allocator as the task stacks, it never overlaps a task
+/// stack or another kernel object. Without MMU, its address is the physical address given to the
+/// device.
+/// Return the id of the buffer, used in all the other DMA functions.
+pub fn dma_alloc(size: usize, align: usize, direction: DmaDirection) -> Result<usize, DmaError> {
+    if !align.is_power_of_two() {
+        return Err(DmaError::InvalidAlignment);
+    }
+    let align = align.max(DMA_MIN_ALIGN);
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let table = unsafe { &mut DMA_BUFFERS };
+    let id = match table.alloc(size, align, direction) {
+        Ok(id) => id,
+        Err(e) => {
+            log!(
+                LogLevel::Error,
+                "Failed to allocate a DMA buffer of size: {:#x}: {:?}",
+                size,
+                e
+            );
+            return Err(e);
+        }
+    };
+    let buffer = table.get(id)?;
+    // Never give the content of a previous buffer to a new driver.
+    unsafe { ptr::write_bytes(buffer.addr as *mut u8, 0, buffer.size) };
+    Ok(id)
+}
+
+/// Give the buffer to the device, the CPU must own it. The cache is cleaned if the device reads
+/// the buffer.
+/// Return the region of the buffer, the address and size to program in the device.
+pub fn dma_to_device(id: usize) -> Result<DriverRegion, DmaError> {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let table = unsafe { &mut DMA_BUFFERS };
+    let cache_ops = table.cache_ops;
+    let buffer = table.get_cpu(id)?;
+    if buffer.direction != DmaDirection::FromDevice {
+        (cache_ops.clean)(buffer.addr, buffer.size);
+    }
+    buffer.owner = DmaOwner::Device;
+    Ok(buffer.region())
+}
+
+/// Take the buffer back from the device once the transfer is done. The cache is invalidated if
+/// the device wrote the buffer.
+pub fn dma_to_cpu(id: usize) -> Result<(), DmaError> {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let table = unsafe { &mut DMA_BUFFERS };
+    let cache_ops = table.cache_ops;
+    let buffer = table.get(id)?;
+    match buffer.owner {
+        DmaOwner::Cpu => return Err(DmaError::CpuOwned),
+        DmaOwner::Shared => return Err(DmaError::Shared),
+        DmaOwner::Device => {}
+    }
+    if buffer.direction != DmaDirection::ToDevice {
+        (cache_ops.invalidate)(buffer.addr, buffer.size);
+    }
+    buffer.owner = DmaOwner::Cpu;
+    Ok(())
+}
+
+/// Share the buffer with the device, the CPU must own it. The CPU and the device both access the
+/// buffer until it's taken back with dma_unshare, there's no cache maintenance in between: the
+/// memory must be DMA coherent, like the QEMU memory. The cache is cleaned once if the device
+/// reads the buffer.
+/// Return the region of the buffer, the address and size to program in the device.
+pub fn dma_share(id: usize) -> Result<DriverRegion, DmaError> {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let table = unsafe { &mut DMA_BUFFERS };
+    let cache_ops = table.cache_ops;
+    let buffer = table.get_cpu(id)?;
+    if buffer.direction != DmaDirection::FromDevice {
+        (cache_ops.clean)(buffer.addr, buffer.size);
+    }
+    buffer.owner = DmaOwner::Shared;
+    Ok(buffer.region())
+}
+
+/// Take a shared buffer back, the device must not access it anymore. The cache is invalidated if
+/// the device wrote the buffer.
+pub fn dma_unshare(id: usize) -> Result<(), DmaError> {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let table = unsafe { &mut DMA_BUFFERS };
+    let cache_ops = table.cache_ops;
+    let buffer = table.get(id)?;
+    match buffer.owner {
+        DmaOwner::Cpu => return Err(DmaError::CpuOwned),
+        DmaOwner::Device => return Err(DmaError::DeviceOwned),
+        DmaOwner::Shared => {}
+    }
+    if buffer.direction != DmaDirection::ToDevice {
+        (cache_ops.invalidate)(buffer.addr, buffer.size);
+    }
+    buffer.owner = DmaOwner::Cpu;
+    Ok(())
+}
+
+/// Return the buffer as a mutable slice, only if the CPU owns it or shares it with the device.
+///
+/// # Safety
+///
+/// - The slice must not be used after giving the buffer to the device with dma_to_device, or
+///   after freeing it with dma_free.
+/// - A slice of a shared buffer must not be used after dma_unshare, the device may be accessing
+///   the buffer at any time before.
+/// - No other slice of the same buffer must be used at the same time, a second call gives a new
+///   slice of the same memory.
+pub unsafe fn dma_get<'a>(id: usize) -> Result<&'a mut [u8], DmaError> {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let buffer = unsafe { DMA_BUFFERS.get_access(id)? };
+    Ok(unsafe { core::slice::from_raw_parts_mut(buffer.addr as *mut u8, buffer.size) })
+}
+
+/// Return the region of the buffer, whatever the owner.
+pub fn dma_region(id: usize) -> Result<DriverRegion, DmaError> {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let buffer = unsafe { DMA_BUFFERS.get(id)? };
+    Ok(buffer.region())
+}
+
+pub fn dma_owner(id: usize) -> Result<DmaOwner, DmaError> {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let buffer = unsafe { DMA_BUFFERS.get(id)? };
+    Ok(buffer.owner)
+}
+
+/// Free the buffer, the CPU must own it.
+/// The memory region is kept by the kernel and re-used by the next allocation that fit in it.
+pub fn dma_free(id: usize) -> Result<(), DmaError> {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let buffer = unsafe { DMA_BUFFERS.get_cpu(id)? };
+    buffer.free = true;
+    Ok(())
+}
+
+/// Set the cache maintenance hooks, for cores with a data cache not coherent with the DMA.
+pub fn dma_set_cache_ops(ops: DmaCacheOps) {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    unsafe {
+        DMA_BUFFERS.cache_ops = ops
+    };
+}
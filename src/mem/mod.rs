@@ -6,6 +6,8 @@ Test coverage: All basic implementation and check correct value compared to Qemu
 Tested:
 - Memory structure methods.
 - Task allocation.
+- Shared buffers ownership.
+- DMA buffers alignment and ownership.
 
 Not tested:
 - The switch from the early boot stack, and final kernel stack.
@@ -15,17 +17,27 @@ Reasons:
 
 Tests files:
 - 'src/tests/mem/mod.rs'
+- 'src/tests/mem/shared.rs'
+- 'src/tests/mem/dma.rs'
 */
 
+pub mod dma;
 mod kernel;
+pub mod shared;
 
 use core::mem;
 
 use kernel::{__kernel_end, __kernel_start, KernelStack};
 
 use crate::{
-    arch::mem::update_kernel_sp, config::KERNEL_STACK_SIZE, log, logs::LogLevel,
+    arch::{
+        mem::update_kernel_sp, scheduler::sched_stack_usage, traps::trap_frame::trap_stack_usage,
+    },
+    config::KERNEL_STACK_SIZE,
+    kprint_fmt, log,
+    logs::LogLevel,
     platform::mem::platform_init_mem,
+    primitives::stack::StackUsage,
 };
 
 pub struct Memory {
@@ -62,19 +74,27 @@ impl Memory {
     }
 
     // Allow unused because this method can be useful for later
-    #[allow(unused)]
     fn mem_available(&self) -> [usize; 2] {
         [self.available, self.kernel_img_end]
     }
 
     pub fn task_alloc(&mut self, size: usize) -> Option<[usize; 2]> {
-        let available = self.available;
-        let bottom = self.kernel_img_end;
-        if available <= size {
+        let reg = self.region_alloc(size);
+        if reg.is_none() {
             log!(
                 LogLevel::Error,
                 "Error allocating new task stask. No available space, try reducing the task stack size"
             );
+        }
+        reg
+    }
+
+    /// Allocate a region of the given size, aligned on 16 bytes, under the last allocated region.
+    /// Return the hi and lo address of the region, or None if there's no available space.
+    fn region_alloc(&mut self, size: usize) -> Option<[usize; 2]> {
+        let available = self.available;
+        let bottom = self.kernel_img_end;
+        if available <= size {
             return None;
         }
         // Compute new available address from available - size asked.
@@ -145,6 +165,28 @@ pub fn mem_kernel_stack_info<'a>() -> &'a KernelStack {
     }
 }
 
+/// Print the usage of the trap and scheduler stacks, used in diagnostic output.
+/// Use kprint to still be available from the panic handler.
+pub fn mem_stacks_report() {
+    let stacks: [(&str, StackUsage); 2] = [
+        ("trap", trap_stack_usage()),
+        ("scheduler", sched_stack_usage()),
+    ];
+    for (name, usage) in stacks {
+        kprint_fmt!(
+            "Stack {}: used {}/{} bytes{}\n",
+            name,
+            usage.used,
+            usage.size,
+            if usage.canary_intact {
+                ""
+            } else {
+                ", OVERFLOW detected"
+            }
+        );
+    }
+}
+
 pub fn mem_update_kernel_sp() {
     let sp: usize = unsafe { MEMORY.kernel_stack.top };
     update_kernel_sp(sp);
@@ -152,12 +194,30 @@ pub fn mem_update_kernel_sp() {
 
 /// Return the hi and lo address of the RAM
 /// first index is hi, second is lo
+/// Return the size of the memory still available for the task stacks and the buffers.
+pub fn mem_free_size() -> usize {
+    #[allow(static_mut_refs)]
+    let [available, bottom] = unsafe { MEMORY.mem_available() };
+    available.saturating_sub(bottom)
+}
+
 pub fn mem_reg_info() -> [usize; 2] {
     let hi = unsafe { MEMORY.mem_end };
     let lo = unsafe { MEMORY.mem_start };
     [hi, lo]
 }
 
+/// Return hi and lo address of a region allocated for a kernel object, like a shared buffer.
+/// First element of array is the hi address, last one is lo address. The region is usable from lo
+/// address.
+pub fn mem_region_alloc(size: usize) -> Option<[usize; 2]> {
+    // Allow static mut refs for now
+    #[allow(static_mut_refs)]
+    unsafe {
+        MEMORY.region_alloc(size)
+    }
+}
+
 /// Return hi and lo address usable.
 /// First element of array is the hi address usable, last one is lo address usable.
 pub fn mem_task_alloc(size: usize) -> Option<[usize; 2]> {
@@ -0,0 +1,278 @@
+// See documentation: `Documentation/kernel/shared_buffer.md`
+/*
+File info: Shared buffers between tasks, with ownership transfer.
+
+Test coverage: Allocation, transfer, lend and return.
+
+Tested:
+- Allocate a buffer and access it from the owner.
+- Transfer the ownership to another task.
+- Lend a buffer to another task and return it.
+- Access denied for a task that doesn't own the buffer.
+
+Not tested:
+- Free and re-use of a freed buffer.
+
+Reasons:
+- Free is trivial, and re-use only depends on the size of the freed buffer.
+
+Tests files:
+- 'src/tests/mem/shared.rs'
+*/
+
+use core::ptr;
+
+use crate::{
+    config::SHARED_BUFFER_MAX_SIZE,
+    log,
+    logs::LogLevel,
+    task::{list::task_list_get_task_by_pid, task_current_pid},
+};
+
+use super::mem_region_alloc;
+
+/// All errors that can happen when using a shared buffer.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SharedBufferError {
+    // The memory allocator cannot allocate the asked size.
+    OutOfMemory,
+    // The shared buffer table is full, see SHARED_BUFFER_MAX_SIZE in config file.
+    TableFull,
+    // There's no shared buffer with this id.
+    InvalidId,
+    // The target task doesn't exist.
+    InvalidTask,
+    // The shared buffer API must be used from a task.
+    NoCurrentTask,
+    // The current task is not the owner of the buffer.
+    NotOwner,
+    // The buffer is lent, the owner cannot use, transfer or lend it until it's returned.
+    Lent,
+    // The buffer is not lent to the current task.
+    NotBorrower,
+}
+
+/// A buffer allocated by the kernel and shared between tasks.
+/// Only one task can access the buffer at a time: the borrower if the buffer is lent, else the
+/// owner.
+#[derive(Copy, Clone)]
+struct SharedBuffer {
+    // Lo address of the buffer
+    addr: usize,
+    // Size asked when allocating the buffer
+    size: usize,
+    // Size of the memory region behind the buffer, can be more than size when the buffer is
+    // re-used.
+    capacity: usize,
+    // Pid of the task owning the buffer
+    owner: u16,
+    // Pid of the task the buffer is lent to
+    borrower: Option<u16>,
+    // A freed buffer keep its memory region to be re-used, the allocator cannot free memory.
+    free: bool,
+}
+
+impl SharedBuffer {
+    /// Return the pid of the task allowed to access the buffer.
+    fn accessor(&self) -> u16 {
+        match self.borrower {
+            Some(pid) => pid,
+            None => self.owner,
+        }
+    }
+}
+
+struct SharedBufferTable {
+    buffers: [Option<SharedBuffer>; SHARED_BUFFER_MAX_SIZE],
+}
+
+impl SharedBufferTable {
+    const fn init() -> Self {
+        SharedBufferTable {
+            buffers: [None; SHARED_BUFFER_MAX_SIZE],
+        }
+    }
+
+    /// Allocate a new buffer for the given owner, re-use a freed buffer if one is large enough.
+    /// Return the id of the buffer, the id is the index in the table.
+    fn alloc(&mut self, owner: u16, size: usize) -> Result<usize, SharedBufferError> {
+        let mut empty_index: Option<usize> = None;
+        for i in 0..SHARED_BUFFER_MAX_SIZE {
+            match &mut self.buffers[i] {
+                Some(buffer) if buffer.free && buffer.capacity >= size => {
+                    buffer.free = false;
+                    buffer.size = size;
+                    buffer.owner = owner;
+                    buffer.borrower = None;
+                    return Ok(i);
+                }
+                Some(_) => continue,
+                None => {
+                    if empty_index.is_none() {
+                        empty_index = Some(i);
+                    }
+                }
+            }
+        }
+        let index = match empty_index {
+            Some(i) => i,
+            None => return Err(SharedBufferError::TableFull),
+        };
+        let reg = match mem_region_alloc(size) {
+            Some(r) => r,
+            None => return Err(SharedBufferError::OutOfMemory),
+        };
+        self.buffers[index] = Some(SharedBuffer {
+            addr: reg[1],
+            size,
+            capacity: reg[0] - reg[1],
+            owner,
+            borrower: None,
+            free: false,
+        });
+        Ok(index)
+    }
+
+    fn get(&mut self, id: usize) -> Result<&mut SharedBuffer, SharedBufferError> {
+        if id >= SHARED_BUFFER_MAX_SIZE {
+            return Err(SharedBufferError::InvalidId);
+        }
+        match &mut self.buffers[id] {
+            Some(buffer) if !buffer.free => Ok(buffer),
+            _ => Err(SharedBufferError::InvalidId),
+        }
+    }
+
+    /// Return the buffer if the current task is its owner and the buffer is not lent.
+    fn get_owned(&mut self, id: usize, pid: u16) -> Result<&mut SharedBuffer, SharedBufferError> {
+        let buffer = self.get(id)?;
+        if buffer.owner != pid {
+            return Err(SharedBufferError::NotOwner);
+        }
+        if buffer.borrower.is_some() {
+            return Err(SharedBufferError::Lent);
+        }
+        Ok(buffer)
+    }
+}
+
+static mut SHARED_BUFFERS: SharedBufferTable = SharedBufferTable::init();
+
+/// Check that the given pid is an existing task.
+fn check_task(pid: u16) -> Result<(), SharedBufferError> {
+    match task_list_get_task_by_pid(pid) {
+        Some(_) => Ok(()),
+        None => Err(SharedBufferError::InvalidTask),
+    }
+}
+
+fn current_pid() -> Result<u16, SharedBufferError> {
+    match task_current_pid() {
+        Some(pid) => Ok(pid),
+        None => Err(SharedBufferError::NoCurrentTask),
+    }
+}
+
+/// Allocate a new shared buffer of the given size, owned by the current task. The buffer is zeroed.
+/// Return the id of the buffer, used in all the other shared buffer functions.
+pub fn shared_buffer_alloc(size: usize) -> Result<usize, SharedBufferError> {
+    let pid = current_pid()?;
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let table = unsafe { &mut SHARED_BUFFERS };
+    let id = match table.alloc(pid, size) {
+        Ok(id) => id,
+        Err(e) => {
+            log!(
+                LogLevel::Error,
+                "Failed to allocate a shared buffer of size: {:#x}: {:?}",
+                size,
+                e
+            );
+            return Err(e);
+        }
+    };
+    let buffer = table.get(id)?;
+    // Never give the content of a previous buffer to a new owner.
+    unsafe { ptr::write_bytes(buffer.addr as *mut u8, 0, buffer.size) };
+    Ok(id)
+}
+
+/// Give the ownership of the buffer to another task. The current task must be the owner, and the
+/// buffer must not be lent.
+pub fn shared_buffer_transfer(id: usize, to: u16) -> Result<(), SharedBufferError> {
+    let pid = current_pid()?;
+    check_task(to)?;
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let buffer = unsafe { SHARED_BUFFERS.get_owned(id, pid)? };
+    buffer.owner = to;
+    Ok(())
+}
+
+/// Lend the buffer to another task. The current task stays the owner but cannot use the buffer
+/// until the borrower return it.
+pub fn shared_buffer_lend(id: usize, to: u16) -> Result<(), SharedBufferError> {
+    let pid = current_pid()?;
+    check_task(to)?;
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let buffer = unsafe { SHARED_BUFFERS.get_owned(id, pid)? };
+    buffer.borrower = Some(to);
+    Ok(())
+}
+
+/// Return a lent buffer to its owner. The current task must be the borrower.
+pub fn shared_buffer_return(id: usize) -> Result<(), SharedBufferError> {
+    let pid = current_pid()?;
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let buffer = unsafe { SHARED_BUFFERS.get(id)? };
+    if buffer.borrower != Some(pid) {
+        return Err(SharedBufferError::NotBorrower);
+    }
+    buffer.borrower = None;
+    Ok(())
+}
+
+/// Free the buffer. The current task must be the owner, and the buffer must not be lent.
+/// The memory region is kept by the kernel and re-used by the next allocation that fit in it.
+pub fn shared_buffer_free(id: usize) -> Result<(), SharedBufferError> {
+    let pid = current_pid()?;
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let buffer = unsafe { SHARED_BUFFERS.get_owned(id, pid)? };
+    buffer.free = true;
+    Ok(())
+}
+
+/// Return the buffer as a mutable slice, only if the current task is allowed to access it: the
+/// borrower if the buffer is lent, else the owner.
+///
+/// # Safety
+///
+/// - The slice must not be used after transferring, lending, giving back or freeing the buffer,
+///   the next accessor gets its own slice of the same memory.
+/// - No other slice of the same buffer must be used at the same time, a second call gives a new
+///   slice of the same memory.
+pub unsafe fn shared_buffer_get<'a>(id: usize) -> Result<&'a mut [u8], SharedBufferError> {
+    let pid = current_pid()?;
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let buffer = unsafe { SHARED_BUFFERS.get(id)? };
+    if buffer.accessor() != pid {
+        return match buffer.owner == pid {
+            true => Err(SharedBufferError::Lent),
+            false => Err(SharedBufferError::NotOwner),
+        };
+    }
+    Ok(unsafe { core::slice::from_raw_parts_mut(buffer.addr as *mut u8, buffer.size) })
+}
+
+/// Return the owner pid of the buffer.
+pub fn shared_buffer_owner(id: usize) -> Result<u16, SharedBufferError> {
+    // Allow static mut refs for now, kernel only run in monocore
+    #[allow(static_mut_refs)]
+    let buffer = unsafe { SHARED_BUFFERS.get(id)? };
+    Ok(buffer.owner)
+}
@@ -183,24 +183,91 @@ pub fn fdt_get_prop_value(prop: Property) -> ArrayVec<u8, 40> {
     prop_value
 }
 
+/// Get a string property value, without the NUL terminator. Empty if the value is not valid
+/// UTF-8. Unlike fdt_get_prop_value, the value is not limited in size, like the bootargs.
+pub fn fdt_get_prop_str(prop: Property) -> &'static str {
+    let value = unsafe {
+        core::slice::from_raw_parts(prop.off_value as *const u8, prop.value_len as usize)
+    };
+    let len = value.iter().position(|c| *c == 0).unwrap_or(value.len());
+    str::from_utf8(&value[..len]).unwrap_or("")
+}
+
+/// Find a node by its full path, like "/soc/serial@10000000". A path not starting with '/' is an
+/// alias of the /aliases node, like "serial0". The options after ':' are ignored, like the baud
+/// rate in a stdout-path.
+pub fn fdt_get_node_by_path<'a>(path: &str) -> Option<&'a FdtNode> {
+    let path = path.split(':').next().unwrap_or(path);
+    if !path.starts_with('/') {
+        let aliases = fdt_get_node_by_path("/aliases")?;
+        let alias = fdt_get_prop_str(fdt_get_node_prop(aliases, path)?);
+        // An alias is always a full path, an alias to an alias is not followed.
+        if !alias.starts_with('/') {
+            return None;
+        }
+        return fdt_get_node_by_path(alias);
+    }
+    fdt_get_all_nodes()
+        .iter()
+        .find(|node| fdt_node_path_is(node, path))
+}
+
+/// Compare the node names from the node up to the root with the path components.
+fn fdt_node_path_is(node: &FdtNode, path: &str) -> bool {
+    // "/soc/serial@10000000" is "serial@10000000", "soc" then "", the root node name.
+    let mut components = path.trim_end_matches('/').rsplit('/');
+    let mut current = *node;
+    loop {
+        match components.next() {
+            Some(component) if component.as_bytes() == fdt_get_node_name(&current).as_slice() => {}
+            _ => return false,
+        }
+        match current.parent_node_index {
+            Some(parent) => current = fdt_get_node(parent),
+            None => return components.next().is_none(),
+        }
+    }
+}
+
 /// Find node by compatible property
 pub fn fdt_get_node_by_compatible(compatible: &str) -> Option<&FdtNode> {
+    fdt_get_node_by_compatible_nth(compatible, 0)
+}
+
+/// Find the nth node with the given compatible property, used when the same device is present
+/// multiple times, like virtio-mmio slots.
+pub fn fdt_get_node_by_compatible_nth(compatible: &str, nth: usize) -> Option<&FdtNode> {
     let nodes = fdt_get_all_nodes();
+    let mut found: usize = 0;
     for node in nodes {
-        let compatible_prop = match fdt_get_node_prop(node, "compatible") {
-            Some(c) => c,
-            None => continue,
-        };
-        let compatible_value = fdt_get_prop_value(compatible_prop);
-        let compatible_str: &str =
-            str::from_utf8(&compatible_value).expect("Failed to cast ArrayVec as &str");
-        if compatible == compatible_str {
-            return Some(node);
+        if fdt_node_is_compatible(node, compatible) {
+            if found == nth {
+                return Some(node);
+            }
+            found += 1;
         }
     }
     None
 }
 
+/// Check if one of the strings of the node compatible property is the given compatible.
+/// The compatible property is a list, from the most specific to the most generic compatible.
+pub fn fdt_node_is_compatible(node: &FdtNode, compatible: &str) -> bool {
+    let compatible_prop = match fdt_get_node_prop(node, "compatible") {
+        Some(c) => c,
+        None => return false,
+    };
+    let value = unsafe {
+        core::slice::from_raw_parts(
+            compatible_prop.off_value as *const u8,
+            compatible_prop.value_len as usize,
+        )
+    };
+    value
+        .split(|c| *c == 0)
+        .any(|each| each == compatible.as_bytes())
+}
+
 /// Find node by device_type property
 pub fn fdt_get_node_by_device_type(device_type: &str) -> Option<&FdtNode> {
     let nodes = fdt_get_all_nodes();
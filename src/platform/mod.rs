@@ -27,12 +27,13 @@ use core::ptr;
 use arrayvec::ArrayVec;
 use platform_info::PlatformInfo;
 
-use crate::{devices_info::DEVICES, drivers::DriverRegion, kprint};
+use crate::{cmdline::cmdline_init_fdt, devices_info::DEVICES, drivers::DriverRegion, kprint};
 use fdt::{
     FdtNode, fdt_present,
     helpers::{
-        fdt_get_node, fdt_get_node_by_compatible, fdt_get_node_by_phandle, fdt_get_node_prop,
-        fdt_get_prop_by_node_name, fdt_get_prop_u32_value,
+        fdt_get_node, fdt_get_node_by_compatible, fdt_get_node_by_compatible_nth,
+        fdt_get_node_by_phandle, fdt_get_node_prop, fdt_get_prop_by_node_name,
+        fdt_get_prop_u32_value,
     },
     parse_dtb_file,
 };
@@ -47,6 +48,8 @@ pub fn platform_init(dtb_addr: usize) {
         unsafe {
             PLATFORM_INFO.set_mode_fdt()
         };
+        // The FDT is not kept, the bootargs and stdout-path are copied in the kernel parameters.
+        cmdline_init_fdt();
     }
     // Condition with just kprint for debug purpose
     #[allow(static_mut_refs)]
@@ -68,6 +71,13 @@ pub enum DeviceType {
     Timer,
     CpuIntC,
     CpuFreq,
+    ExtIntC,
+    Virtio,
+    Rtc,
+    Power,
+    Gpio,
+    Spi,
+    Firmware,
 }
 
 pub trait DeviceInfo {}
@@ -102,25 +112,58 @@ impl Devices<'_> {
 
     pub fn init_fdt<'a>(compatible: &'a str, device_type: DeviceType) -> Option<Devices<'a>> {
         let node: &FdtNode = fdt_get_node_by_compatible(compatible)?;
+        Some(Devices::init_fdt_node(node, compatible, device_type))
+    }
+
+    /// Same as init_fdt, from an already found node.
+    pub fn init_fdt_node<'a>(
+        node: &FdtNode,
+        compatible: &'a str,
+        device_type: DeviceType,
+    ) -> Devices<'a> {
         let device_addr: DriverRegion = DriverRegion::new(node);
-        Some(Devices {
+        Devices {
             header: DevicesHeader {
                 device_type,
                 compatible,
                 device_addr,
             },
             info: None,
-        })
+        }
     }
 }
 
 unsafe impl<'a> Sync for Devices<'a> {}
 
-pub struct PlatformSerialDevice {}
+pub struct PlatformSerialDevice {
+    // Frequency of the clock used to compute the baud rate divisor
+    pub clock_frequency: u32,
+    // Interrupt source id of the device on the external interrupt controller
+    pub irq: u32,
+}
 
 impl PlatformSerialDevice {
     pub const fn init() -> Self {
-        PlatformSerialDevice {}
+        PlatformSerialDevice {
+            clock_frequency: 0,
+            irq: 0,
+        }
+    }
+
+    pub fn init_fdt(node: &FdtNode) -> Self {
+        // Both props are optional, keep 0 if missing, the driver will handle it.
+        let clock_frequency = match fdt_get_node_prop(node, "clock-frequency") {
+            Some(p) => fdt_get_prop_u32_value(p),
+            None => 0,
+        };
+        let irq = match fdt_get_node_prop(node, "interrupts") {
+            Some(p) => fdt_get_prop_u32_value(p),
+            None => 0,
+        };
+        PlatformSerialDevice {
+            clock_frequency,
+            irq,
+        }
     }
 }
 
@@ -133,11 +176,7 @@ impl PlatformCpuIntCDevice {
         PlatformCpuIntCDevice { core_id: 0 }
     }
 
-    pub fn init_fdt(compatible: &'_ str) -> Self {
-        let node: &FdtNode = match fdt_get_node_by_compatible(compatible) {
-            Some(n) => n,
-            None => panic!("Error while creating new CPU interrupt-controller generic structure"),
-        };
+    pub fn init_fdt(node: &FdtNode) -> Self {
         // Allow expect use, the node riscv,cpu-intc should always have a parent node.
         #[allow(clippy::expect_used)]
         let parent_node = fdt_get_node(
@@ -197,11 +236,7 @@ impl PlatformTimerDevice {
             }; 4],
         }
     }
-    pub fn init_fdt(compatible: &str) -> Self {
-        let node: &FdtNode = match fdt_get_node_by_compatible(compatible) {
-            Some(n) => n,
-            None => panic!("Error while creating new TimerDevice Generic structure"),
-        };
+    pub fn init_fdt(node: &FdtNode) -> Self {
         let interrupt: InterruptExtended = InterruptExtended {
             cpu_intc: u32::MAX,
             irq_len: 0,
@@ -298,57 +333,361 @@ impl PlatformTimerDevice {
     }
 }
 
+/// A context of an external interrupt-controller, a context is a pair of hart and privilege mode.
+/// Only machine mode contexts are saved, the kernel runs in machine mode.
+#[derive(Copy, Clone, PartialEq)]
+pub struct ExtIntCContext {
+    // CPU core id, u32::MAX if the context is not used
+    pub hart: u32,
+    // Context id on the interrupt-controller
+    pub context: u32,
+}
+
+pub struct PlatformExtIntCDevice {
+    // Number of interrupt sources, source 0 doesn't exist
+    pub ndev: u32,
+    pub contexts: [ExtIntCContext; 4],
+}
+
+impl PlatformExtIntCDevice {
+    pub const fn init() -> Self {
+        PlatformExtIntCDevice {
+            ndev: 0,
+            contexts: [ExtIntCContext {
+                hart: u32::MAX,
+                context: 0,
+            }; 4],
+        }
+    }
+
+    /// Parse the interrupt-controller node. Each entry of the interrupts-extended property is a
+    /// context, the context id is the entry index. Keep only the machine external interrupt
+    /// contexts, irq 11 on the cpu interrupt-controller.
+    pub fn init_fdt(node: &FdtNode) -> Self {
+        let mut device = PlatformExtIntCDevice::init();
+        // Allow expect use, an external interrupt-controller must define the number of sources
+        #[allow(clippy::expect_used)]
+        let ndev = fdt_get_node_prop(node, "riscv,ndev")
+            .expect("ERROR: interrupt-controller node is missing 'riscv,ndev' property\n");
+        device.ndev = fdt_get_prop_u32_value(ndev);
+        // Allow expect use, an external interrupt-controller must be connected to the harts
+        #[allow(clippy::expect_used)]
+        let interrupt_extended = fdt_get_node_prop(node, "interrupts-extended")
+            .expect("ERROR: interrupt-controller node is missing 'interrupts-extended' property\n");
+        let mut cursor = interrupt_extended.off_value;
+        let end = interrupt_extended.off_value + interrupt_extended.value_len as usize;
+        let mut context: u32 = 0;
+        let mut saved: usize = 0;
+        while cursor < end {
+            let phandle = u32::from_be(unsafe { ptr::read(cursor as *const u32) });
+            // Allow the use of expect, we want to fail directly if we can't find those node
+            #[allow(clippy::expect_used)]
+            let intc_node = fdt_get_node_by_phandle(phandle).expect(
+                "ERROR: cannot find associate phandle node from interrupt-controller interrupts-extended property",
+            );
+            #[allow(clippy::expect_used)]
+            let interrupt_cells = fdt_get_node_prop(&intc_node, "#interrupt-cells").expect(
+                "ERROR: interrupt-controller phandle node is missing the property '#interrupt-cells'",
+            );
+            let interrupt_cells_value = fdt_get_prop_u32_value(interrupt_cells);
+            let irq = u32::from_be(unsafe { ptr::read((cursor + 4) as *const u32) });
+            // Allow expect use, a cpu interrupt-controller always has a cpu parent node.
+            #[allow(clippy::expect_used)]
+            let cpu_node = fdt_get_node(
+                intc_node
+                    .parent_node_index
+                    .expect("ERROR: failed to get the cpu node"),
+            );
+            #[allow(clippy::expect_used)]
+            let cpu_reg = fdt_get_node_prop(&cpu_node, "reg")
+                .expect("ERROR: failed to get core id from associated core from intc");
+            // Machine external interrupt
+            if irq == 11 && saved < device.contexts.len() {
+                device.contexts[saved] = ExtIntCContext {
+                    hart: fdt_get_prop_u32_value(cpu_reg),
+                    context,
+                };
+                saved += 1;
+            }
+            cursor += 4 + interrupt_cells_value as usize * 4;
+            context += 1;
+        }
+        device
+    }
+}
+
+pub struct PlatformVirtioDevice {
+    // Interrupt source id of the device on the external interrupt controller
+    pub irq: u32,
+}
+
+impl PlatformVirtioDevice {
+    pub const fn init() -> Self {
+        PlatformVirtioDevice { irq: 0 }
+    }
+
+    pub fn init_fdt(node: &FdtNode) -> Self {
+        // Keep 0 if missing, the device is used without interrupt.
+        let irq = match fdt_get_node_prop(node, "interrupts") {
+            Some(p) => fdt_get_prop_u32_value(p),
+            None => 0,
+        };
+        PlatformVirtioDevice { irq }
+    }
+}
+
+pub struct PlatformRtcDevice {
+    // Interrupt source id of the device on the external interrupt controller
+    pub irq: u32,
+}
+
+impl PlatformRtcDevice {
+    pub const fn init() -> Self {
+        PlatformRtcDevice { irq: 0 }
+    }
+
+    pub fn init_fdt(node: &FdtNode) -> Self {
+        // Keep 0 if missing, the alarm is not available without interrupt.
+        let irq = match fdt_get_node_prop(node, "interrupts") {
+            Some(p) => fdt_get_prop_u32_value(p),
+            None => 0,
+        };
+        PlatformRtcDevice { irq }
+    }
+}
+
+// Max number of pins of a GPIO controller, one bit per pin in the registers.
+pub const GPIO_MAX_PINS: usize = 32;
+
+pub struct PlatformGpioDevice {
+    // Number of pins of the controller
+    pub ngpios: u32,
+    // Interrupt source id of each pin on the external interrupt controller, 0 if not connected
+    pub irqs: [u32; GPIO_MAX_PINS],
+}
+
+impl PlatformGpioDevice {
+    pub const fn init() -> Self {
+        PlatformGpioDevice {
+            ngpios: 0,
+            irqs: [0u32; GPIO_MAX_PINS],
+        }
+    }
+
+    /// The interrupts property has one interrupt per pin, from pin 0. Without ngpios property,
+    /// the number of pins is the number of interrupts, or GPIO_MAX_PINS without interrupts.
+    pub fn init_fdt(node: &FdtNode) -> Self {
+        let mut device = PlatformGpioDevice::init();
+        let mut nirq: usize = 0;
+        if let Some(interrupts) = fdt_get_node_prop(node, "interrupts") {
+            let mut cursor = interrupts.off_value;
+            for _ in 0..(interrupts.value_len as usize / 4).min(GPIO_MAX_PINS) {
+                device.irqs[nirq] = u32::from_be(unsafe { ptr::read(cursor as *const u32) });
+                cursor += 4;
+                nirq += 1;
+            }
+        }
+        device.ngpios = match fdt_get_node_prop(node, "ngpios") {
+            Some(p) => fdt_get_prop_u32_value(p).min(GPIO_MAX_PINS as u32),
+            None if nirq > 0 => nirq as u32,
+            None => GPIO_MAX_PINS as u32,
+        };
+        device
+    }
+}
+
+pub struct PlatformSpiDevice {
+    // Frequency of the input clock of the controller, 0 if unknown
+    pub clock_frequency: u32,
+}
+
+impl PlatformSpiDevice {
+    pub const fn init() -> Self {
+        PlatformSpiDevice { clock_frequency: 0 }
+    }
+
+    /// The input clock is the clock-frequency of the node in the clocks property.
+    pub fn init_fdt(node: &FdtNode) -> Self {
+        let clock_frequency = fdt_get_node_prop(node, "clocks")
+            .and_then(|clocks| fdt_get_node_by_phandle(fdt_get_prop_u32_value(clocks)))
+            .and_then(|clock| fdt_get_node_prop(&clock, "clock-frequency"))
+            .map_or(0, fdt_get_prop_u32_value);
+        PlatformSpiDevice { clock_frequency }
+    }
+}
+
+/// A syscon-poweroff or syscon-reboot action: write value at offset in the regmap region, only the
+/// bits in mask are changed.
+pub struct PlatformPowerDevice {
+    pub offset: u32,
+    pub value: u32,
+    pub mask: u32,
+}
+
+impl PlatformPowerDevice {
+    pub const fn init() -> Self {
+        PlatformPowerDevice {
+            offset: 0,
+            value: 0,
+            mask: u32::MAX,
+        }
+    }
+
+    pub fn init_fdt(node: &FdtNode) -> Self {
+        let mut device = PlatformPowerDevice::init();
+        if let Some(p) = fdt_get_node_prop(node, "offset") {
+            device.offset = fdt_get_prop_u32_value(p);
+        }
+        if let Some(p) = fdt_get_node_prop(node, "value") {
+            device.value = fdt_get_prop_u32_value(p);
+        }
+        if let Some(p) = fdt_get_node_prop(node, "mask") {
+            device.mask = fdt_get_prop_u32_value(p);
+        }
+        // The value is optional if the mask is given, the mask is written.
+        if fdt_get_node_prop(node, "value").is_none() {
+            device.value = device.mask;
+        }
+        device
+    }
+}
+
 // Implement DeviceInfo trait to all Device type structure
 impl DeviceInfo for PlatformSerialDevice {}
 impl DeviceInfo for PlatformTimerDevice {}
 impl DeviceInfo for PlatformCpuIntCDevice {}
 impl DeviceInfo for PlatformCpuFreqDevice {}
+impl DeviceInfo for PlatformExtIntCDevice {}
+impl DeviceInfo for PlatformVirtioDevice {}
+impl DeviceInfo for PlatformRtcDevice {}
+impl DeviceInfo for PlatformPowerDevice {}
+impl DeviceInfo for PlatformGpioDevice {}
+impl DeviceInfo for PlatformSpiDevice {}
 
 static mut TIMER_DEVICE_INSTANCE: PlatformTimerDevice = PlatformTimerDevice::init();
 static mut SERIAL_DEVICE_INSTANCE: PlatformSerialDevice = PlatformSerialDevice::init();
 static mut CPU_INTC_DEVICE_INSTANCE: PlatformCpuIntCDevice = PlatformCpuIntCDevice::init();
 static mut CPU_FREQ_INSTANCE: PlatformCpuFreqDevice = PlatformCpuFreqDevice::init();
-
-fn init_fdt_device(compatible: &'_ str, device_type: DeviceType) -> Option<Devices<'_>> {
+static mut EXT_INTC_DEVICE_INSTANCE: PlatformExtIntCDevice = PlatformExtIntCDevice::init();
+static mut VIRTIO_DEVICE_INSTANCE: PlatformVirtioDevice = PlatformVirtioDevice::init();
+static mut RTC_DEVICE_INSTANCE: PlatformRtcDevice = PlatformRtcDevice::init();
+static mut POWER_DEVICE_INSTANCE: PlatformPowerDevice = PlatformPowerDevice::init();
+static mut GPIO_DEVICE_INSTANCE: PlatformGpioDevice = PlatformGpioDevice::init();
+static mut SPI_DEVICE_INSTANCE: PlatformSpiDevice = PlatformSpiDevice::init();
+
+fn init_fdt_device(
+    compatible: &'_ str,
+    device_type: DeviceType,
+    nth: usize,
+) -> Option<Devices<'_>> {
     let mut default_device: Devices = Devices::init();
     match device_type {
         #[allow(static_mut_refs)]
         DeviceType::Serial => {
-            let serial_device: PlatformSerialDevice = PlatformSerialDevice::init();
+            let node: &FdtNode = fdt_get_node_by_compatible_nth(compatible, nth)?;
+            let serial_device: PlatformSerialDevice = PlatformSerialDevice::init_fdt(node);
             unsafe { SERIAL_DEVICE_INSTANCE = serial_device };
-            let get_device = Devices::init_fdt(compatible, device_type);
-            get_device?;
-            // Allow the use of expect, we check the Option<> before but we don't want any surprise
-            #[allow(clippy::expect_used)]
-            let mut device: Devices = get_device.expect("Error: failed to get the serial device");
+            let mut device: Devices = Devices::init_fdt_node(node, compatible, device_type);
             device.info = Some(unsafe { &mut SERIAL_DEVICE_INSTANCE });
             default_device = device;
         }
         #[allow(static_mut_refs)]
         DeviceType::Timer => {
-            let timer_device: PlatformTimerDevice = PlatformTimerDevice::init_fdt(compatible);
+            let node: &FdtNode = fdt_get_node_by_compatible_nth(compatible, nth)?;
+            let timer_device: PlatformTimerDevice = PlatformTimerDevice::init_fdt(node);
             unsafe { TIMER_DEVICE_INSTANCE = timer_device };
-            let get_device = Devices::init_fdt(compatible, device_type);
-            get_device?;
-            // Allow the use of expect, we check the Option<> before but we don't want any surprise
-            #[allow(clippy::expect_used)]
-            let mut device: Devices = get_device.expect("Error: failed to get the timer device");
+            let mut device: Devices = Devices::init_fdt_node(node, compatible, device_type);
             device.info = Some(unsafe { &mut TIMER_DEVICE_INSTANCE });
             default_device = device;
         }
         #[allow(static_mut_refs)]
         DeviceType::CpuIntC => {
-            let cpu_intc_device: PlatformCpuIntCDevice =
-                PlatformCpuIntCDevice::init_fdt(compatible);
+            // The cpu interrupt-controller has no reg, keep the default region.
+            let node: &FdtNode = fdt_get_node_by_compatible_nth(compatible, nth)?;
+            let cpu_intc_device: PlatformCpuIntCDevice = PlatformCpuIntCDevice::init_fdt(node);
             unsafe { CPU_INTC_DEVICE_INSTANCE = cpu_intc_device };
+            default_device.header.compatible = compatible;
+            default_device.header.device_type = device_type;
             default_device.info = Some(unsafe { &mut CPU_INTC_DEVICE_INSTANCE });
         }
         #[allow(static_mut_refs)]
         DeviceType::CpuFreq => {
+            // Not a node, there's only one CPU frequency.
+            if nth > 0 {
+                return None;
+            }
             let cpu_freq_device: PlatformCpuFreqDevice = PlatformCpuFreqDevice::init_fdt();
             unsafe { CPU_FREQ_INSTANCE = cpu_freq_device };
             default_device.info = Some(unsafe { &mut CPU_FREQ_INSTANCE });
         }
+        #[allow(static_mut_refs)]
+        DeviceType::ExtIntC => {
+            let node: &FdtNode = fdt_get_node_by_compatible_nth(compatible, nth)?;
+            let ext_intc_device: PlatformExtIntCDevice = PlatformExtIntCDevice::init_fdt(node);
+            unsafe { EXT_INTC_DEVICE_INSTANCE = ext_intc_device };
+            let mut device: Devices = Devices::init_fdt_node(node, compatible, device_type);
+            device.info = Some(unsafe { &mut EXT_INTC_DEVICE_INSTANCE });
+            default_device = device;
+        }
+        #[allow(static_mut_refs)]
+        DeviceType::Virtio => {
+            let node: &FdtNode = fdt_get_node_by_compatible_nth(compatible, nth)?;
+            let virtio_device: PlatformVirtioDevice = PlatformVirtioDevice::init_fdt(node);
+            unsafe { VIRTIO_DEVICE_INSTANCE = virtio_device };
+            let mut device: Devices = Devices::init_fdt_node(node, compatible, device_type);
+            device.info = Some(unsafe { &mut VIRTIO_DEVICE_INSTANCE });
+            default_device = device;
+        }
+        #[allow(static_mut_refs)]
+        DeviceType::Rtc => {
+            let node: &FdtNode = fdt_get_node_by_compatible_nth(compatible, nth)?;
+            let rtc_device: PlatformRtcDevice = PlatformRtcDevice::init_fdt(node);
+            unsafe { RTC_DEVICE_INSTANCE = rtc_device };
+            let mut device: Devices = Devices::init_fdt_node(node, compatible, device_type);
+            device.info = Some(unsafe { &mut RTC_DEVICE_INSTANCE });
+            default_device = device;
+        }
+        #[allow(static_mut_refs)]
+        DeviceType::Power => {
+            let node: &FdtNode = fdt_get_node_by_compatible_nth(compatible, nth)?;
+            let power_device: PlatformPowerDevice = PlatformPowerDevice::init_fdt(node);
+            unsafe { POWER_DEVICE_INSTANCE = power_device };
+            // A syscon-poweroff or syscon-reboot node use the region of the syscon in regmap.
+            let mut device: Devices = match fdt_get_node_prop(node, "regmap") {
+                Some(regmap) => {
+                    let regmap_node = fdt_get_node_by_phandle(fdt_get_prop_u32_value(regmap))?;
+                    Devices::init_fdt_node(&regmap_node, compatible, device_type)
+                }
+                None => Devices::init_fdt_node(node, compatible, device_type),
+            };
+            device.info = Some(unsafe { &mut POWER_DEVICE_INSTANCE });
+            default_device = device;
+        }
+        #[allow(static_mut_refs)]
+        DeviceType::Gpio => {
+            let node: &FdtNode = fdt_get_node_by_compatible_nth(compatible, nth)?;
+            let gpio_device: PlatformGpioDevice = PlatformGpioDevice::init_fdt(node);
+            unsafe { GPIO_DEVICE_INSTANCE = gpio_device };
+            let mut device: Devices = Devices::init_fdt_node(node, compatible, device_type);
+            device.info = Some(unsafe { &mut GPIO_DEVICE_INSTANCE });
+            default_device = device;
+        }
+        #[allow(static_mut_refs)]
+        DeviceType::Spi => {
+            let node: &FdtNode = fdt_get_node_by_compatible_nth(compatible, nth)?;
+            let spi_device: PlatformSpiDevice = PlatformSpiDevice::init_fdt(node);
+            unsafe { SPI_DEVICE_INSTANCE = spi_device };
+            let mut device: Devices = Devices::init_fdt_node(node, compatible, device_type);
+            // The registers are the first region, the second is the memory mapped flash.
+            device.header.device_addr = DriverRegion::new_nth(node, 0);
+            device.info = Some(unsafe { &mut SPI_DEVICE_INSTANCE });
+            default_device = device;
+        }
+        DeviceType::Firmware => {
+            // The firmware interfaces have no device information, only their region.
+            let node: &FdtNode = fdt_get_node_by_compatible_nth(compatible, nth)?;
+            default_device = Devices::init_fdt_node(node, compatible, device_type);
+        }
     }
     Some(default_device)
 }
@@ -356,11 +695,20 @@ fn init_fdt_device(compatible: &'_ str, device_type: DeviceType) -> Option<Devic
 pub fn platform_get_device_info(
     compatible: &'_ str,
     device_type: DeviceType,
+) -> Option<Devices<'_>> {
+    platform_get_device_info_nth(compatible, device_type, 0)
+}
+
+/// Get the nth device with the given compatible, for devices present multiple times.
+pub fn platform_get_device_info_nth(
+    compatible: &'_ str,
+    device_type: DeviceType,
+    nth: usize,
 ) -> Option<Devices<'_>> {
     #[allow(static_mut_refs)]
     match unsafe { PLATFORM_INFO.read_mode() } {
         true => {
-            let get_device = init_fdt_device(compatible, device_type);
+            let get_device = init_fdt_device(compatible, device_type, nth);
             match get_device.is_none() {
                 true => None,
                 // Allow the use of expect, we check the Option<> before, but we don't want any
@@ -369,13 +717,10 @@ pub fn platform_get_device_info(
                 false => Some(get_device.expect("Error: failed to get the device from FDT")),
             }
         }
-        false => {
-            for each in DEVICES {
-                if each.header.compatible == compatible {
-                    return Some(*each);
-                }
-            }
-            None
-        }
+        false => DEVICES
+            .iter()
+            .filter(|each| each.header.compatible == compatible)
+            .nth(nth)
+            .copied(),
     }
 }
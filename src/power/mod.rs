@@ -0,0 +1,62 @@
+// See documentation in `Documentation/kernel/power.md`
+/*
+File info: Kernel power API, stop or reset the machine.
+
+Test coverage: None.
+
+Tested:
+
+Not tested:
+- Everything.
+
+Reasons:
+- Both functions never return, they stop or reset QEMU.
+
+Tests files:
+*/
+
+use crate::{
+    arch::traps::{disable_interrupts, interrupt::halt},
+    drivers::power::POWER_SUBSYSTEM,
+    kprint_fmt,
+    logs::buffer::log_flush,
+};
+
+// Exit code used by the panic handlers.
+pub const POWER_PANIC_EXIT_CODE: u16 = 1;
+
+/// Stop the machine with the exit code, given to the emulator by the SiFive test device.
+/// Without power device, halt the CPU. Never return, usable from the panic handler.
+pub fn shutdown(exit_code: u16) -> ! {
+    disable_interrupts();
+    // Write the buffered logs, the drain task will not run anymore.
+    log_flush();
+    if let Some(power) = POWER_SUBSYSTEM.get_power() {
+        if exit_code != 0 && !power.supports_exit_code() {
+            kprint_fmt!(
+                "Power: exit code {} not supported by the device\n",
+                exit_code
+            );
+        }
+        power.poweroff(exit_code);
+    }
+    halt_forever()
+}
+
+/// Reset the machine. Without power device, or if the device cannot reset, halt the CPU.
+/// Never return, usable from the panic handler.
+pub fn reboot() -> ! {
+    disable_interrupts();
+    log_flush();
+    if let Some(power) = POWER_SUBSYSTEM.get_power() {
+        power.reboot();
+    }
+    halt_forever()
+}
+
+fn halt_forever() -> ! {
+    loop {
+        // Interrupts are disabled, only a debugger or a reset wake the CPU.
+        unsafe { halt() };
+    }
+}
@@ -7,6 +7,7 @@ Tested:
 - push
 - pop
 - get_head
+- remove, push after a remove
 
 Not tested:
 
@@ -46,8 +47,8 @@ impl<const N: usize> IndexedLinkedList<N> {
     /// Push the new node in the linked list. Can update the current node in it.
     /// Avoid duplication on id. The id is unique in the list.
     pub fn push(&mut self, id: usize, value: usize) {
-        // Get the size of the list
-        let size = self.size();
+        // Number of nodes, the free entries can be anywhere in the list after a pop or a remove.
+        let size = self.count;
         if size == self.list.len() {
             log!(LogLevel::Warn, "The delta-list is full, abort push.");
             return;
@@ -212,6 +213,38 @@ impl<const N: usize> IndexedLinkedList<N> {
         self.take_node(head)
     }
 
+    /// Remove the node with the given id and return it, None if the id is not in the list.
+    /// Update the linked list head or tail if the node was one of them.
+    pub fn remove(&mut self, id: usize) -> Option<IndexedLinkedListNode> {
+        if self.count == 0 {
+            return None;
+        }
+        let mut prev_node_ptr: Option<usize> = None;
+        let mut current_node: usize = self.head;
+        for _ in 0..self.list.len() {
+            let node = self.list[current_node]?;
+            if node.id != id {
+                prev_node_ptr = Some(current_node);
+                current_node = node.next_node?;
+                continue;
+            }
+            match prev_node_ptr {
+                None => self.head = node.next_node.unwrap_or(0),
+                Some(prev) => {
+                    if let Some(prev_node) = self.get_node(prev) {
+                        prev_node.next_node = node.next_node;
+                    }
+                    if node.next_node.is_none() {
+                        self.tail = prev;
+                    }
+                }
+            }
+            self.count -= 1;
+            return self.take_node(current_node);
+        }
+        None
+    }
+
     pub fn get_head_node(&self) -> Option<&IndexedLinkedListNode> {
         self.list[self.head].as_ref()
     }
@@ -7,6 +7,7 @@ Tested:
 - init
 - push
 - pop
+- is_full
 
 Not tested:
 - read
@@ -47,7 +48,7 @@ impl<T: Copy, const N: usize> RingBuffer<T, N> {
     /// Add new element to tail, increment tail.
     pub fn push(&mut self, new: T) {
         // Check if buffer is full
-        if (self.tail + 1) % N == self.head {
+        if self.is_full() {
             log!(LogLevel::Warn, "Ring buffer full, abort push.");
             return;
         }
@@ -96,6 +97,11 @@ impl<T: Copy, const N: usize> RingBuffer<T, N> {
         }
     }
 
+    /// Return true if a push would be aborted.
+    pub fn is_full(&self) -> bool {
+        (self.tail + 1) % N == self.head
+    }
+
     pub fn size(&self) -> usize {
         self.count
     }
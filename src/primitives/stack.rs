@@ -1,19 +1,42 @@
 /*
 File info: AlignedStack primitive type.
 
-Test coverage: ...
+Test coverage: All methods.
 
 Tested:
+- paint
+- high_water_mark
+- canary_intact
+- top
 
 Not tested:
 
-Reasons: Not even really implemented so there's no need to test something that doesn't even consider finish
+Reasons:
 
 Tests files:
+- 'src/tests/primitives/stack.rs'
 
 References:
 */
 
+use core::ptr;
+
+// Byte used to paint a stack, used to compute the stack high-water mark.
+pub const STACK_PAINT: u8 = 0xA5;
+// Canary written at the bottom of a stack, a stack overflow will overwrite it.
+pub const STACK_CANARY: u32 = 0xDEAD_C0DE;
+
+/// Stack usage report, used in diagnostic output.
+/// size: size of the stack in bytes.
+/// used: max number of bytes used since the stack was painted.
+/// canary_intact: false if the stack overflowed.
+#[derive(Copy, Clone)]
+pub struct StackUsage {
+    pub size: usize,
+    pub used: usize,
+    pub canary_intact: bool,
+}
+
 #[repr(align(16))]
 pub struct AlignedStack16<const N: usize> {
     pub buf: [u8; N],
@@ -23,6 +46,55 @@ impl<const N: usize> AlignedStack16<N> {
     // Don't bother with this warning
     #[allow(clippy::new_without_default)]
     pub const fn new() -> Self {
+        // A stack must keep sp aligned on 16 bytes, and have space for the canary.
+        const {
+            assert!(
+                N.is_multiple_of(16) && N > 16,
+                "Stack size must be a multiple of 16"
+            )
+        };
         AlignedStack16 { buf: [0u8; N] }
     }
+
+    /// Return the top of the stack, the initial stack pointer. The stack grows downward.
+    pub fn top(&mut self) -> *mut u8 {
+        self.buf.as_mut_ptr().wrapping_add(N)
+    }
+
+    /// Fill the stack with STACK_PAINT and write the canary at the bottom.
+    /// Must only be used when the stack is not in use.
+    pub fn paint(&mut self) {
+        self.buf.fill(STACK_PAINT);
+        let canary_ptr = self.buf.as_mut_ptr() as *mut u32;
+        unsafe { ptr::write_volatile(canary_ptr, STACK_CANARY) };
+    }
+
+    /// Return true if the canary at the bottom of the stack has not been overwritten.
+    pub fn canary_intact(&self) -> bool {
+        let canary_ptr = self.buf.as_ptr() as *const u32;
+        unsafe { ptr::read_volatile(canary_ptr) == STACK_CANARY }
+    }
+
+    /// Return the max number of bytes used since the stack was painted. Count the bytes still
+    /// painted from the bottom of the stack, just above the canary.
+    pub fn high_water_mark(&self) -> usize {
+        let canary_size = size_of::<u32>();
+        let mut untouched: usize = 0;
+        for i in canary_size..N {
+            let byte = unsafe { ptr::read_volatile(self.buf.as_ptr().wrapping_add(i)) };
+            if byte != STACK_PAINT {
+                break;
+            }
+            untouched += 1;
+        }
+        N - canary_size - untouched
+    }
+
+    pub fn usage(&self) -> StackUsage {
+        StackUsage {
+            size: N,
+            used: self.high_water_mark(),
+            canary_intact: self.canary_intact(),
+        }
+    }
 }
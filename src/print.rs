@@ -1,9 +1,34 @@
-use crate::drivers::serials::SERIAL_SUBSYSTEM;
+use crate::drivers::{
+    display::fbcon::fbcon_print,
+    serials::{SERIAL_SUBSYSTEM, console::console_index},
+};
 
-/// Get uart set with default console and pass arg to write_fmt function of the driver
+/// Write to the console of the current output, the route of the current task or the default
+/// console, see Documentation/kernel/subsystems.md.
+/// The output is also written to the framebuffer console, if there's a display.
 pub fn print(arg: core::fmt::Arguments) {
-    let device = unsafe { SERIAL_SUBSYSTEM.get_default_console() };
+    print_console(console_index(None), arg);
+}
+
+/// Write to the serial device at index, the default console if the device was removed.
+/// The output is also written to the framebuffer console, if there's a display.
+pub fn print_console(index: usize, arg: core::fmt::Arguments) {
+    let device = match unsafe { SERIAL_SUBSYSTEM.devices.get_mut(index) } {
+        Some(device) => device,
+        None => unsafe { SERIAL_SUBSYSTEM.get_default_console() },
+    };
     let _ = device.write_fmt(arg);
+    fbcon_print(arg);
+}
+
+/// Write raw bytes to the serial device at index, the default console if the device was removed.
+/// Used for binary data, not written to the framebuffer console.
+pub fn print_console_bytes(index: usize, data: &[u8]) {
+    let device = match unsafe { SERIAL_SUBSYSTEM.devices.get_mut(index) } {
+        Some(device) => device,
+        None => unsafe { SERIAL_SUBSYSTEM.get_default_console() },
+    };
+    device.write_bytes(data);
 }
 
 /// Macro for easier use of print function and to use format_args macro
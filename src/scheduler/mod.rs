@@ -19,7 +19,7 @@ use crate::{
     LogLevel,
     arch::{
         helpers::current_cpu_core,
-        scheduler::{SCHEDULER_CTX, SchedulerCtx, sched_ctx_restore},
+        scheduler::{SCHEDULER_CTX, SchedulerCtx, sched_ctx_restore, sched_stack_check},
     },
     config::{BLOCK_QUEUE_MAX_SIZE, CPU_CORE_NUMBER, RUN_QUEUE_MAX_SIZE, TASK_MAX_PRIORITY},
     log,
@@ -30,6 +30,7 @@ use crate::{
         list::{task_list_get_idle_task, task_list_get_task_by_pid, task_list_update_task_by_pid},
         task_awake_block_control, task_awake_tick, task_context_switch, task_pid, task_priority,
     },
+    trace,
 };
 
 // Reflect the run queue state
@@ -56,6 +57,7 @@ pub static mut BLOCKED_QUEUE: [IndexedLinkedList<BLOCK_QUEUE_MAX_SIZE>; CPU_CORE
 /// Not the best way to use the RingBuffer but it will do.
 #[unsafe(no_mangle)]
 pub fn scheduler() {
+    sched_stack_check();
     let core: usize = current_cpu_core();
     #[allow(static_mut_refs)]
     let current_run_queue = unsafe { &mut RUN_QUEUE[core] };
@@ -126,9 +128,9 @@ pub fn scheduler() {
         let priority: usize = task_priority(&current_task).into();
         task_list_update_task_by_pid(pid, current_task);
         // Push current task to the priority buffer
-        current_run_queue[priority as usize].push(pid);
+        current_run_queue[priority].push(pid);
         // Update the bitmap priority bit.
-        current_run_queue_bitmap.set_bit(priority as usize);
+        current_run_queue_bitmap.set_bit(priority);
     }
     // Update and load next task
     #[allow(static_mut_refs)]
@@ -152,6 +154,10 @@ pub fn scheduler() {
     let next_task = task_list_get_task_by_pid(next_task_pid).unwrap();
     next_task.state = TaskState::Running;
     task_list_update_task_by_pid(next_task_pid, *next_task);
+    trace!(
+        "Scheduler: switch to task {} on core {}",
+        next_task_pid, core
+    );
     unsafe { TASK_HANDLER = next_task }
     task_context_switch(next_task);
 }
@@ -161,3 +167,26 @@ pub fn switch_scheduler_ctx() {
     let ctx = unsafe { &mut SCHEDULER_CTX } as *mut SchedulerCtx;
     unsafe { sched_ctx_restore(ctx) };
 }
+
+/// Push a ready task to the run queue of the current CPU core, used to start a kernel task
+/// created after boot.
+pub fn scheduler_enqueue_task(pid: u16) {
+    let core: usize = current_cpu_core();
+    let task = match task_list_get_task_by_pid(pid) {
+        Some(t) => t,
+        None => {
+            log!(
+                LogLevel::Error,
+                "Cannot enqueue task: {}, the task doesn't exist.",
+                pid
+            );
+            return;
+        }
+    };
+    let priority: usize = task_priority(task).into();
+    #[allow(static_mut_refs)]
+    unsafe {
+        RUN_QUEUE[core][priority].push(pid);
+        RUN_QUEUE_BITMAP[core].set_bit(priority);
+    }
+}
@@ -36,11 +36,12 @@ impl TaskList {
     }
 
     /// Add the new task to the task list and return the pid.
-    fn add_task(&mut self, new_task: Task) -> u16 {
+    /// Return None if the task list is full.
+    fn add_task(&mut self, new_task: Task) -> Option<u16> {
         // Check possible overflow, abort if self.size == TASK_LIST_MAX_SIZE
         if self.size as usize == TASK_LIST_MAX_SIZE {
             log!(LogLevel::Warn, "Task list is full, ignore adding new task.");
-            return 0;
+            return None;
         }
         // Iter over the list to add new task
         for i in 0..TASK_LIST_MAX_SIZE {
@@ -55,11 +56,11 @@ impl TaskList {
                 unsafe { *self.list[i].get() = Some(update_task) };
                 // Increment current list size by 1
                 self.size += 1;
-                return update_task.pid;
+                return Some(update_task.pid);
             }
         }
         // This should never be reach
-        0
+        None
     }
 
     pub fn get_task(&mut self, pid: u16) -> Option<&mut Task> {
@@ -107,8 +108,8 @@ pub static mut TASK_LIST: TaskList = TaskList::init();
 // Allow private_interfaces because we want don't want this function to handle the task, it's just
 // a public API wrapping the TaskList::add_task function
 #[allow(private_interfaces)]
-/// Add new task to the TASK_LIST static and return new task pid
-pub fn task_list_add_task(new_task: Task) -> u16 {
+/// Add new task to the TASK_LIST static and return new task pid, or None if the list is full.
+pub fn task_list_add_task(new_task: Task) -> Option<u16> {
     // Allow static mut refs for now, kernel only run in monocore
     #[allow(static_mut_refs)]
     unsafe {
@@ -5,6 +5,9 @@ Test coverage: Basic task creation, memory region and context switch(save and re
 
 Tested:
 - task_create function.
+- task_create errors: invalid priority, stack too small, quota exceeded, task list full and out of
+  memory. The quota and the full list are checked on the parameters check, with a given quota and
+  list size.
 - Context save and context restore.
 
 Not tested:
@@ -17,10 +20,14 @@ Tests files:
 - 'src/tests/task/mod.rs'
 */
 
-use list::task_list_add_task;
+use list::{task_list_add_task, task_list_get_task_by_pid, task_list_size};
 
 use crate::{
-    arch::{task::task_context::TaskContext, traps::interrupt::enable_and_halt},
+    arch::{
+        task::task_context::TaskContext,
+        traps::{interrupt::enable_and_halt, trap_frame::TrapFrame},
+    },
+    config::{TASK_LIST_MAX_SIZE, TASK_MAX_PRIORITY, TASK_MEMORY_QUOTA},
     log,
     logs::LogLevel,
     mem::mem_task_alloc,
@@ -52,6 +59,27 @@ pub enum TaskState {
     Terminated,
 }
 
+// Minimum stack size of a task.
+// A task stack must at least be able to hold a saved task context and a trap frame, aligned on 16
+// bytes like the memory allocator does.
+pub const TASK_STACK_MIN_SIZE: usize =
+    (size_of::<TaskContext>() + size_of::<TrapFrame>() + (16 - 1)) & !(16 - 1);
+
+/// All errors that can happen when creating a task.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TaskError {
+    // The memory allocator cannot allocate the asked stack size.
+    OutOfMemory,
+    // The task list is full, see TASK_LIST_MAX_SIZE in config file.
+    TaskListFull,
+    // The priority is not in 0..TASK_MAX_PRIORITY.
+    InvalidPriority,
+    // The asked stack size is below TASK_STACK_MIN_SIZE.
+    StackTooSmall,
+    // The asked stack size is above TASK_MEMORY_QUOTA.
+    QuotaExceeded,
+}
+
 #[derive(Copy, Clone)]
 pub enum TaskBlockControl {
     AwakeTick(usize),
@@ -77,33 +105,22 @@ pub struct Task {
 }
 
 impl Task {
-    /// Return an Option, if the memory allocator cannot allocate the asked size for the task,
-    /// return None, else return the task.
-    fn init(name: &str, func: fn() -> !, priority: u8, size: usize) -> Option<Self> {
+    /// Return a Result, if the memory allocator cannot allocate the asked size for the task,
+    /// return TaskError::OutOfMemory, else return the task.
+    fn init(name: &str, func: fn() -> !, priority: u8, size: usize) -> Result<Self, TaskError> {
         // Copy bytes in name str to slice
         let name_b = name.as_bytes();
         let mut buf = [0u8; 16];
         let len = core::cmp::min(name_b.len(), 16 - 1);
         buf[..len].copy_from_slice(&name_b[..len]);
         // Allocate the asked size
-        let mem_reg = mem_task_alloc(size);
-        // If the allocator cannot allocate the asked size, return None and log error.
-        if mem_reg.is_none() {
-            log!(
-                LogLevel::Error,
-                "The size asked to create the task: {name} couldn't be allocate, abort task creation."
-            );
-            return None;
-        }
+        let mem_reg = match mem_task_alloc(size) {
+            Some(reg) => reg,
+            None => return Err(TaskError::OutOfMemory),
+        };
         // Return new task
-        Some(Task {
-            // Allow expect use, we check the Option<> before, but if we can't get the memory
-            // region behind it, fail-fast, we don't want a task with wrong mem reg or UB.
-            #[allow(clippy::expect_used)]
-            context: TaskContext::init(
-                mem_reg.expect("Error: failed to get the task memory region"),
-                func,
-            ),
+        Ok(Task {
+            context: TaskContext::init(mem_reg, func),
             block_control: TaskBlockControl::None,
             func,
             pid: 0,
@@ -136,31 +153,86 @@ impl Task {
     }
 }
 
+/// Check the task parameters before allocating anything for the task.
+/// quota: max stack size, TASK_MEMORY_QUOTA.
+/// list_size: number of tasks in the task list.
+pub fn task_check_params(
+    priority: u8,
+    size: usize,
+    quota: Option<usize>,
+    list_size: usize,
+) -> Result<(), TaskError> {
+    if priority as usize >= TASK_MAX_PRIORITY {
+        return Err(TaskError::InvalidPriority);
+    }
+    if size < TASK_STACK_MIN_SIZE {
+        return Err(TaskError::StackTooSmall);
+    }
+    if let Some(quota) = quota
+        && size > quota
+    {
+        return Err(TaskError::QuotaExceeded);
+    }
+    // Check the task list before allocating the task stack, the allocator cannot free memory.
+    if list_size >= TASK_LIST_MAX_SIZE {
+        return Err(TaskError::TaskListFull);
+    }
+    Ok(())
+}
+
 /// Create a new task. And register it to the task list.
+/// Return the pid of the new task, or the reason why the task couldn't be created.
 /// name: name of the task as &str.
-/// state: next state of the task.
 /// func: function pointer to the task entry point, the function must never return.
 /// priority: the task priority, highest priority will be executed first and prioritized by the
-/// scheduler.
-/// size: the task size asked for RAM allocation.
-pub fn task_create(name: &str, func: fn() -> !, priority: u8, size: usize) {
-    let new_task = Task::init(name, func, priority, size);
-    if let Some(task) = new_task {
-        // Allow the use of expect to avoid casting non-utf8 char to str.
-        #[allow(clippy::expect_used)]
-        let name = str::from_utf8(&task.name).expect("Failed to cast bytes buffer to &str.");
-        let updated_task_pid = task_list_add_task(task);
-        log!(
-            LogLevel::Info,
-            "Successfully created task: {name} with pid: {}",
-            updated_task_pid
-        );
-    } else {
+/// scheduler. Must be below TASK_MAX_PRIORITY.
+/// size: the task size asked for RAM allocation. Must be at least TASK_STACK_MIN_SIZE, and below
+/// TASK_MEMORY_QUOTA if there's one.
+pub fn task_create(
+    name: &str,
+    func: fn() -> !,
+    priority: u8,
+    size: usize,
+) -> Result<u16, TaskError> {
+    if let Err(e) = task_check_params(priority, size, TASK_MEMORY_QUOTA, task_list_size() as usize)
+    {
         log!(
             LogLevel::Error,
-            "Failed to create task: {name}, try reducing task size if possible."
+            "Failed to create task: {}, invalid parameters: {:?}",
+            name,
+            e
         );
+        return Err(e);
     }
+    let task = match Task::init(name, func, priority, size) {
+        Ok(t) => t,
+        Err(e) => {
+            log!(
+                LogLevel::Error,
+                "Failed to create task: {}, the size asked couldn't be allocate, try reducing task size if possible.",
+                name
+            );
+            return Err(e);
+        }
+    };
+    let pid = match task_list_add_task(task) {
+        Some(p) => p,
+        None => {
+            log!(
+                LogLevel::Error,
+                "Failed to create task: {}, the task list is full.",
+                name
+            );
+            return Err(TaskError::TaskListFull);
+        }
+    };
+    log!(
+        LogLevel::Info,
+        "Successfully created task: {} with pid: {}",
+        name,
+        pid
+    );
+    Ok(pid)
 }
 
 /// Temporary function to trigger context switch on a given task
@@ -176,6 +248,25 @@ pub fn task_pid(task: &Task) -> u16 {
     task.pid
 }
 
+/// Return the name of the task, as given when creating it.
+pub fn task_name(task: &Task) -> &str {
+    let len = task
+        .name
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(task.name.len());
+    core::str::from_utf8(&task.name[..len]).unwrap_or("<invalid name>")
+}
+
+/// Return the pid of the current task, or None if called outside of a task.
+pub fn task_current_pid() -> Option<u16> {
+    let current_task: *mut Task = unsafe { TASK_HANDLER };
+    if current_task.is_null() {
+        return None;
+    }
+    Some(task_pid(unsafe { &*current_task }))
+}
+
 pub fn task_priority(task: &Task) -> u8 {
     task.priority
 }
@@ -191,13 +282,30 @@ pub fn task_awake_block_control(task: &mut Task) {
     task.block_control = TaskBlockControl::None;
 }
 
+/// Restart the task from its entry point, with an empty stack. The task keeps its pid, priority
+/// and state. The current task restarts when returning from the trap, the trap entry restores the
+/// context of the task from the task list.
+/// Return false if the task doesn't exist.
+pub fn task_restart(pid: u16) -> bool {
+    let task = match task_list_get_task_by_pid(pid) {
+        Some(t) => t,
+        None => return false,
+    };
+    let func = task.func;
+    task.context.reset(func);
+    true
+}
+
 /// Create the idle task
 pub fn task_idle_task() {
     let task_name: &str = "Idle task";
     let func: fn() -> ! = idle_task_fn;
     let priority: u8 = 0;
-    let size: usize = 0x100;
-    task_create(task_name, func, priority, size);
+    let size: usize = 0x200;
+    // The idle task must always exist, if it cannot be created the scheduler will not work.
+    if let Err(e) = task_create(task_name, func, priority, size) {
+        panic!("Failed to create the idle task: {:?}", e);
+    }
 }
 
 fn idle_task_fn() -> ! {
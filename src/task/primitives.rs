@@ -9,9 +9,12 @@ Tested:
 
 Not tested:
 - delay
+- task_wake
 
 Reasons:
 - delay is hard to test, for now we test it by just checking it manually.
+- task_wake is called by the interrupt handlers on a sleeping task, the test framework doesn't
+  handle the interrupts.
 
 Tests files:
 - 'src/tests/task/primitives.rs'
@@ -129,6 +132,30 @@ pub fn task_awake_blocked(tick: usize) {
     }
 }
 
+/// Wake a task blocked by sleep before its awake tick, used by the interrupt handlers to wake the
+/// task waiting on their device. The task is moved to the head of the blocked queue, and the
+/// scheduler runs when the trap returns.
+/// Must be called with interrupts disabled. Return false if the task is not blocked.
+pub fn task_wake(pid: u16) -> bool {
+    let task = match task_list_get_task_by_pid(pid) {
+        Some(t) => t,
+        None => return false,
+    };
+    if task.state != TaskState::Blocked {
+        return false;
+    }
+    let core: usize = current_cpu_core();
+    #[allow(static_mut_refs)]
+    let current_blocked_queue = unsafe { &mut BLOCKED_QUEUE[core] };
+    if current_blocked_queue.remove(pid as usize).is_none() {
+        return false;
+    }
+    task.block_control = TaskBlockControl::AwakeTick(0);
+    current_blocked_queue.push(pid as usize, 0);
+    need_reschedule();
+    true
+}
+
 /// Interrupt all operation on the CPU for the given time.
 pub fn delay(ms: usize) {
     set_ktime_ms(ms as u64);
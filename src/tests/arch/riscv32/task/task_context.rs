@@ -1,9 +1,10 @@
 use crate::print;
 use crate::scheduler::RUN_QUEUE;
-use core::{mem, ptr};
+use core::mem;
 
 use crate::{
-    arch::{scheduler::init_sched_ctx, task::task_context::TaskContext, traps::interrupt::halt},
+    arch::{scheduler::init_sched_ctx, task::task_context::TaskContext},
+    power::shutdown,
     scheduler::{RUN_QUEUE_BITMAP, scheduler},
     task::{
         CURRENT_TASK_PID, TASK_HANDLER, list::task_list_get_task_by_pid, primitives::r#yield,
@@ -92,8 +93,7 @@ fn test_context_switch_a() -> ! {
         i += 2;
         print!("A {i}\n");
         if i >= 31 {
-            unsafe { ptr::write_volatile(0x100000 as *mut u32, 0x5555) };
-            unsafe { halt() };
+            shutdown(0);
         } else {
             unsafe { r#yield() };
         }
@@ -106,8 +106,7 @@ fn test_context_switch_b() -> ! {
         i += 2;
         print!("B {i}\n");
         if i >= 30 {
-            unsafe { ptr::write_volatile(0x100000 as *mut u32, 0x5555) };
-            unsafe { halt() };
+            shutdown(0);
         } else {
             unsafe { r#yield() };
         }
@@ -122,9 +121,9 @@ fn test_context_switch_b() -> ! {
 pub fn test_task_context_switch() -> u8 {
     // Temporary task creation and retrieving to test context switch.
     // pid 2
-    task_create("A", test_context_switch_a, 1, 0x1000);
+    task_create("A", test_context_switch_a, 1, 0x1000).unwrap();
     // pid 3
-    task_create("B", test_context_switch_b, 1, 0x1000);
+    task_create("B", test_context_switch_b, 1, 0x1000).unwrap();
     #[allow(static_mut_refs)]
     unsafe {
         // Access the queue and bitmap from CPU core 0
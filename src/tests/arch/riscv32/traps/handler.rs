@@ -1,5 +1,6 @@
 use crate::{
     arch::traps::{handler::trap_handler, trap_frame::TrapFrame},
+    drivers::timer::init_timer_subsystem,
     ktime::tick::get_tick,
     tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
 };
@@ -9,6 +10,8 @@ pub fn test_trap_handler_timer_interrupt() -> u8 {
     // Random mepc
     // TODO: improve mepc security in trap handler
     let mepc: usize = 125696;
+    // Register the tick handler on the timer interrupt.
+    init_timer_subsystem();
     let current_tick = get_tick();
     let mut trap_frame = TrapFrame::init();
     unsafe { trap_handler(mepc, 0, cause, 0, 0, &mut trap_frame) };
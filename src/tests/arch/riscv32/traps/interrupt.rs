@@ -1,10 +1,10 @@
 use crate::{
     arch::traps::{
         interrupt::{
-            enable_mie_msie, enable_mie_mtie, mscratch_read, mscratch_set_trap_frame,
-            mtvec_read_mode, mtvec_read_trap_entry, mtvec_set_trap_entry,
-            mtvec_switch_to_direct_mode, mtvec_switch_to_vectored_mode, read_mie_msie,
-            read_mie_mtie, trap_entry,
+            disable_mie_meie, enable_mie_meie, enable_mie_msie, enable_mie_mtie, mscratch_read,
+            mscratch_set_trap_frame, mtvec_read_mode, mtvec_read_trap_entry, mtvec_set_trap_entry,
+            mtvec_switch_to_direct_mode, mtvec_switch_to_vectored_mode, read_mie_meie,
+            read_mie_msie, read_mie_mtie, trap_entry,
         },
         trap_frame::KERNEL_TRAP_FRAME,
     },
@@ -80,6 +80,18 @@ pub fn test_mie_msie() -> u8 {
     0
 }
 
+pub fn test_mie_meie() -> u8 {
+    disable_mie_meie();
+    if read_mie_meie() != 0 {
+        panic!("mie.meie should have been cleared to disable machine external interrupt");
+    }
+    enable_mie_meie();
+    if read_mie_meie() == 0 {
+        panic!("mie.meie should have been updated to enable machine external interrupt");
+    }
+    0
+}
+
 // pub fn test_mstatus_mie() {
 //     let current_mstatus_mie = read_mstatus_mie();
 //     // Set safety tick to not trigger timer interrupt after enabling it.
@@ -125,6 +137,11 @@ pub fn interrupt_enabling_test_suite() {
                 test_mie_msie,
                 TestBehavior::Default,
             ),
+            TestCase::init(
+                "RISC-V 32 bits mie.meie",
+                test_mie_meie,
+                TestBehavior::Default,
+            ),
             // TestCase {
             //     name: "RISC-V 32 bits mstatus.mie",
             //     func: test_mstatus_mie,
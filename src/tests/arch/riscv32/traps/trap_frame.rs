@@ -1,5 +1,8 @@
 use crate::{
-    arch::traps::trap_frame::{KERNEL_TRAP_FRAME, TRAP_STACK_BUFF, TrapFrame, init_trap_frame},
+    arch::traps::trap_frame::{
+        KERNEL_TRAP_FRAME, TRAP_STACK_BUFF, TrapFrame, init_trap_frame, trap_stack_usage,
+    },
+    config::TRAP_STACK_SIZE,
     test_failed,
     tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
 };
@@ -39,10 +42,28 @@ pub fn test_trap_frame_init() -> u8 {
     // Ok because test env, no concurrency
     #[allow(static_mut_refs)]
     if unsafe { KERNEL_TRAP_FRAME.trap_stack }
-        != unsafe { TRAP_STACK_BUFF.buf.as_mut_ptr().wrapping_add(1024) }
+        != unsafe {
+            TRAP_STACK_BUFF
+                .buf
+                .as_mut_ptr()
+                .wrapping_add(TRAP_STACK_SIZE)
+        }
     {
         panic!("Trap frame trap_stack field should be initialized with ptr to TRAP_STACK_BUFF");
     }
+    let usage = trap_stack_usage();
+    if !usage.canary_intact {
+        test_failed!("Trap stack canary should be intact after init_trap_frame");
+        return 1;
+    }
+    if usage.size != TRAP_STACK_SIZE {
+        test_failed!(
+            "Trap stack size should be: {}, got: {}",
+            TRAP_STACK_SIZE,
+            usage.size
+        );
+        return 1;
+    }
     0
 }
 
@@ -0,0 +1,157 @@
+use crate::{
+    cmdline::{CmdlineError, KernelParams, kernel_params},
+    config::{LOG_LEVEL, TICK_DURATION},
+    drivers::{DriverRegion, serials::SERIAL_SUBSYSTEM},
+    logs::LogLevel,
+    platform::fdt::helpers::fdt_get_node_by_path,
+    test_failed,
+    tests::{
+        TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior, test_filter_match,
+    },
+};
+
+pub fn test_cmdline_defaults() -> u8 {
+    let params = KernelParams::init();
+    if params.log_level != LOG_LEVEL || params.tick_duration != TICK_DURATION {
+        test_failed!("The kernel parameters should default to the config file values");
+        return 1;
+    }
+    if params.trace || params.test_filter.is_some() || params.stdout_path.is_some() {
+        test_failed!("The tracing, test filter and stdout-path should be empty by default");
+        return 1;
+    }
+    0
+}
+
+pub fn test_cmdline_parse() -> u8 {
+    let mut params = KernelParams::init();
+    let ignored = params.parse("  loglevel=Warn tick=10\ttrace test=fw_cfg\n");
+    if ignored != 0 {
+        test_failed!("No option should be ignored, got: {}", ignored);
+        return 1;
+    }
+    if params.log_level != LogLevel::Warn {
+        test_failed!("loglevel=Warn should set the log level, the case is ignored");
+        return 1;
+    }
+    if params.tick_duration != 10 {
+        test_failed!(
+            "tick=10 should set the tick duration, got: {}",
+            params.tick_duration
+        );
+        return 1;
+    }
+    if !params.trace || params.set("trace", "off").is_err() || params.trace {
+        test_failed!("trace should enable the tracing, trace=off disable it");
+        return 1;
+    }
+    if params.test_filter != Some("fw_cfg") {
+        test_failed!("test=fw_cfg should set the test filter");
+        return 1;
+    }
+    0
+}
+
+pub fn test_cmdline_invalid() -> u8 {
+    let mut params = KernelParams::init();
+    if params.set("loglevel", "verbose") != Err(CmdlineError::InvalidValue)
+        || params.set("tick", "0") != Err(CmdlineError::InvalidValue)
+        || params.set("tick", "fast") != Err(CmdlineError::InvalidValue)
+        || params.set("test", "") != Err(CmdlineError::InvalidValue)
+        || params.set("trace", "yes") != Err(CmdlineError::InvalidValue)
+        || params.set("quiet", "") != Err(CmdlineError::UnknownParam)
+    {
+        test_failed!("Invalid options should be rejected with the matching error");
+        return 1;
+    }
+    let ignored = params.parse("tick loglevel=error foo=bar");
+    if ignored != 2 || params.log_level != LogLevel::Error {
+        test_failed!(
+            "The invalid options should be ignored and the valid ones applied, ignored: {}",
+            ignored
+        );
+        return 1;
+    }
+    if params.tick_duration != TICK_DURATION {
+        test_failed!("A rejected option should keep the parameter unchanged");
+        return 1;
+    }
+    0
+}
+
+pub fn test_cmdline_test_filter() -> u8 {
+    if !test_filter_match("Firmware fw_cfg", "FW_CFG") || !test_filter_match("Firmware", "firm") {
+        test_failed!("The test filter should match a part of the suite name, ignoring the case");
+        return 1;
+    }
+    if test_filter_match("Watchdog sub-system", "timer") || test_filter_match("RTC", "RTC sub") {
+        test_failed!("The test filter should not match other suites");
+        return 1;
+    }
+    0
+}
+
+pub fn test_cmdline_stdout_path() -> u8 {
+    // QEMU virt gives its UART in the /chosen stdout-path, read by the platform init.
+    let stdout_path = match kernel_params().stdout_path {
+        Some(path) => path,
+        None => {
+            test_failed!("The stdout-path should be read from the FDT /chosen node");
+            return 1;
+        }
+    };
+    match fdt_get_node_by_path(stdout_path) {
+        Some(node) if DriverRegion::new(node).addr == 0x10000000 => {}
+        _ => {
+            test_failed!("The stdout-path {} should be the UART node", stdout_path);
+            return 1;
+        }
+    }
+    if SERIAL_SUBSYSTEM
+        .get_serial(SERIAL_SUBSYSTEM.default_console_index())
+        .map(|serial| serial.region().addr)
+        != Some(0x10000000)
+    {
+        test_failed!("The default console should be the stdout-path device");
+        return 1;
+    }
+    0
+}
+
+pub fn cmdline_test_suite() {
+    const CMDLINE_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Command line default parameters",
+                test_cmdline_defaults,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Command line parse options",
+                test_cmdline_parse,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Command line invalid options",
+                test_cmdline_invalid,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Command line test filter",
+                test_cmdline_test_filter,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Command line FDT stdout-path",
+                test_cmdline_stdout_path,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Kernel command line",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&CMDLINE_TEST_SUITE)
+    };
+}
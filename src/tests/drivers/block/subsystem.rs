@@ -0,0 +1,60 @@
+use crate::{
+    drivers::block::{BLOCK_SECTOR_SIZE, BlockError, BlockSubSystem},
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+pub fn test_block_subsystem_empty() -> u8 {
+    let block_subsystem = BlockSubSystem::init();
+    if block_subsystem.get_block_array_size() != 0 {
+        test_failed!("Block sub-system should be initialized empty.");
+        return 1;
+    }
+    if block_subsystem.get_block(0).is_some() {
+        test_failed!("Block sub-system should not contain a device at index 0");
+        return 1;
+    }
+    // Out of the pool
+    if block_subsystem.get_block(usize::MAX).is_some() {
+        test_failed!("Block sub-system should not contain a device out of the pool");
+        return 1;
+    }
+    0
+}
+
+pub fn test_block_subsystem_no_device() -> u8 {
+    let block_subsystem = BlockSubSystem::init();
+    let mut buf = [0u8; BLOCK_SECTOR_SIZE];
+    if block_subsystem.read_sectors(0, 0, &mut buf) != Err(BlockError::NoDevice) {
+        test_failed!("Reading from a missing device should return NoDevice");
+        return 1;
+    }
+    if block_subsystem.write_sectors(0, 0, &buf) != Err(BlockError::NoDevice) {
+        test_failed!("Writing to a missing device should return NoDevice");
+        return 1;
+    }
+    0
+}
+
+pub fn block_subsystem_test_suite() {
+    const BLOCK_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Block sub-system initialized empty",
+                test_block_subsystem_empty,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Block sub-system request on missing device",
+                test_block_subsystem_no_device,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Block sub-system",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&BLOCK_TEST_SUITE)
+    };
+}
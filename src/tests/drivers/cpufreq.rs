@@ -0,0 +1,127 @@
+use crate::{
+    drivers::cpufreq::{
+        CpuFreqError, CpuFreqEvent, CpuFreqOpp, CpuFreqStage, cpufreq_change, cpufreq_get,
+        cpufreq_notifier_register, cpufreq_notifier_unregister, cpufreq_rebase_offset,
+        cpufreq_scale,
+    },
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+// Last events seen by the test notifier, and number of switch calls
+static mut PRE_EVENT: Option<CpuFreqEvent> = None;
+static mut POST_EVENT: Option<CpuFreqEvent> = None;
+static mut SWITCH_CALLS: usize = 0;
+
+fn test_notifier(event: &CpuFreqEvent) {
+    match event.stage {
+        CpuFreqStage::PreChange => unsafe { PRE_EVENT = Some(*event) },
+        CpuFreqStage::PostChange => unsafe { POST_EVENT = Some(*event) },
+    }
+}
+
+fn test_switch(_opp: &CpuFreqOpp) {
+    unsafe { SWITCH_CALLS += 1 };
+}
+
+pub fn test_cpufreq_notifier_chain() -> u8 {
+    let id = cpufreq_notifier_register(test_notifier).unwrap();
+    if cpufreq_notifier_register(test_notifier) != Ok(id) {
+        test_failed!("A notifier should not be registered twice");
+        return 1;
+    }
+    // Same operating point, QEMU virt timebase cannot change
+    let opp = cpufreq_get();
+    cpufreq_change(opp, test_switch).unwrap();
+    #[allow(static_mut_refs)]
+    let (pre, post, calls) = unsafe { (PRE_EVENT, POST_EVENT, SWITCH_CALLS) };
+    if calls != 1 {
+        test_failed!("The switch function should be called once");
+        return 1;
+    }
+    if pre.map(|e| (e.old, e.new)) != Some((opp, opp)) || post.map(|e| e.new) != Some(opp) {
+        test_failed!("The notifier should get the pre and post change events");
+        return 1;
+    }
+    cpufreq_notifier_unregister(id).unwrap();
+    if cpufreq_notifier_unregister(id) != Err(CpuFreqError::InvalidId) {
+        test_failed!("Unregistering a notifier twice should return InvalidId");
+        return 1;
+    }
+    unsafe { POST_EVENT = None };
+    cpufreq_change(opp, test_switch).unwrap();
+    #[allow(static_mut_refs)]
+    if unsafe { POST_EVENT }.is_some() {
+        test_failed!("An unregistered notifier should not be called");
+        return 1;
+    }
+    0
+}
+
+pub fn test_cpufreq_invalid_opp() -> u8 {
+    let opp = CpuFreqOpp {
+        timebase: 0,
+        bus: 0,
+    };
+    if cpufreq_change(opp, test_switch) != Err(CpuFreqError::InvalidFrequency) {
+        test_failed!("An operating point without timebase should be refused");
+        return 1;
+    }
+    0
+}
+
+pub fn test_cpufreq_time_continuity() -> u8 {
+    // 10MHz to 20MHz after 2s: the 2s are kept, and count at 20MHz
+    let now: u64 = 20_000_000;
+    let offset = cpufreq_rebase_offset(0, now, 10_000_000, 20_000_000);
+    if (now as i64 + offset) as u64 / 20_000_000 != 2 {
+        test_failed!("The time should be continuous across a timebase change");
+        return 1;
+    }
+    // 1s later at 20MHz
+    if (now as i64 + 20_000_000 + offset) as u64 / 20_000_000 != 3 {
+        test_failed!("The time should count at the new timebase after a change");
+        return 1;
+    }
+    // Back to 10MHz 1s later, 3s kept
+    let now = now + 20_000_000;
+    let offset = cpufreq_rebase_offset(offset, now, 20_000_000, 10_000_000);
+    if (now as i64 + offset) as u64 / 10_000_000 != 3 {
+        test_failed!("The time should stay continuous after a second change");
+        return 1;
+    }
+    // A remaining delay of 1ms keep its duration
+    if cpufreq_scale(10_000, 10_000_000, 20_000_000) != 20_000 {
+        test_failed!("A delay should be scaled to the new timebase");
+        return 1;
+    }
+    0
+}
+
+pub fn cpufreq_test_suite() {
+    const CPUFREQ_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Cpufreq notifier chain",
+                test_cpufreq_notifier_chain,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Cpufreq invalid operating point",
+                test_cpufreq_invalid_opp,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Cpufreq time continuity",
+                test_cpufreq_time_continuity,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Cpufreq",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&CPUFREQ_TEST_SUITE)
+    };
+}
@@ -0,0 +1,228 @@
+use crate::{
+    drivers::display::{
+        FbRect, Framebuffer,
+        fbcon::{FBCON_BG, FBCON_FG, FBCON_STATUS_BG, FBCON_STATUS_FG, FbCon, FbConError},
+        font::font_glyph,
+        ramfb::ramfb_config,
+    },
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+// 32x32 pixels, 4 columns and 4 rows of characters.
+const TEST_FB_SIZE: usize = 32;
+static mut TEST_FB_MEM: [u32; TEST_FB_SIZE * TEST_FB_SIZE] = [0; TEST_FB_SIZE * TEST_FB_SIZE];
+
+// Framebuffer in memory, used to check the pixels drawn by the console.
+fn test_framebuffer_view() -> Framebuffer {
+    #[allow(static_mut_refs)]
+    let addr = unsafe { TEST_FB_MEM.as_mut_ptr() } as usize;
+    Framebuffer {
+        addr,
+        width: TEST_FB_SIZE,
+        height: TEST_FB_SIZE,
+        stride: TEST_FB_SIZE * 4,
+    }
+}
+
+// Framebuffer in memory, cleared for each test.
+fn test_framebuffer() -> Framebuffer {
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_FB_MEM.fill(0)
+    };
+    test_framebuffer_view()
+}
+
+pub fn test_font_glyph() -> u8 {
+    if font_glyph(b'A')[0] != 0x0c {
+        test_failed!("Glyph of A should be read from the font");
+        return 1;
+    }
+    if font_glyph(0x7f) != font_glyph(b'?') {
+        test_failed!("A character outside the font should use the replacement glyph");
+        return 1;
+    }
+    0
+}
+
+pub fn test_framebuffer_pixels() -> u8 {
+    let fb = test_framebuffer();
+    fb.put_pixel(3, 2, 0x00ff_0000);
+    if fb.get_pixel(3, 2) != 0x00ff_0000 {
+        test_failed!("Pixel should be set");
+        return 1;
+    }
+    // Outside the framebuffer, ignored.
+    fb.put_pixel(TEST_FB_SIZE, 0, 0x00ff_0000);
+    if fb.get_pixel(TEST_FB_SIZE, 0) != 0 || fb.get_pixel(0, 1) != 0 {
+        test_failed!("Pixel outside the framebuffer should be ignored");
+        return 1;
+    }
+    fb.fill_rect(
+        FbRect {
+            x: 30,
+            y: 30,
+            width: 8,
+            height: 8,
+        },
+        0x0000_00ff,
+    );
+    if fb.get_pixel(31, 31) != 0x0000_00ff || fb.get_pixel(29, 31) != 0 {
+        test_failed!("Rectangle should be filled and clipped to the framebuffer");
+        return 1;
+    }
+    0
+}
+
+pub fn test_fbcon_draw_char() -> u8 {
+    let mut con = FbCon::new(test_framebuffer(), 0);
+    con.clear();
+    con.put_char('A');
+    // First row of A is 0x0c, pixels 2 and 3.
+    let fb = test_framebuffer_view();
+    if fb.get_pixel(2, 0) != FBCON_FG || fb.get_pixel(0, 0) != FBCON_BG {
+        test_failed!("Character should be drawn with the font at the cursor");
+        return 1;
+    }
+    if con.cursor() != (1, 0) {
+        test_failed!("Cursor should move after a character");
+        return 1;
+    }
+    0
+}
+
+pub fn test_fbcon_wrap_scroll() -> u8 {
+    let mut con = FbCon::new(test_framebuffer(), 1);
+    con.clear();
+    for c in "ABCDE".chars() {
+        con.put_char(c);
+    }
+    if con.cursor() != (1, 2) {
+        test_failed!("Line should wrap after the last column");
+        return 1;
+    }
+    con.clear();
+    for c in "A\n\nB\n".chars() {
+        con.put_char(c);
+    }
+    // The last new line scrolled, B moved from the row 3 to the row 2.
+    let fb = test_framebuffer_view();
+    if con.cursor() != (0, 3) {
+        test_failed!("Cursor should stay on the last row after a scroll");
+        return 1;
+    }
+    if fb.get_pixel(0, 16) != FBCON_FG || fb.get_pixel(0, 24) != FBCON_BG {
+        test_failed!("Console rows should move up on scroll");
+        return 1;
+    }
+    if fb.get_pixel(0, 0) != FBCON_STATUS_BG {
+        test_failed!("Status panel should not scroll");
+        return 1;
+    }
+    0
+}
+
+pub fn test_fbcon_ansi_colors() -> u8 {
+    let mut con = FbCon::new(test_framebuffer(), 0);
+    con.clear();
+    for c in "\x1b[31;1mA\x1b[0mA".chars() {
+        con.put_char(c);
+    }
+    let fb = test_framebuffer_view();
+    if fb.get_pixel(2, 0) != 0x00ff_5555 || fb.get_pixel(10, 0) != FBCON_FG {
+        test_failed!("ANSI color sequence should set the foreground color");
+        return 1;
+    }
+    if con.cursor() != (2, 0) {
+        test_failed!("ANSI escape sequences should not be drawn");
+        return 1;
+    }
+    0
+}
+
+pub fn test_fbcon_status() -> u8 {
+    let mut con = FbCon::new(test_framebuffer(), 1);
+    con.clear();
+    if con.set_status(0, format_args!("H{}", 1)) != Ok(()) {
+        test_failed!("Status line 0 should be set");
+        return 1;
+    }
+    // First row of H is 0x33, pixel 0 set.
+    let fb = test_framebuffer_view();
+    if fb.get_pixel(0, 0) != FBCON_STATUS_FG || fb.get_pixel(16, 0) != FBCON_STATUS_BG {
+        test_failed!("Status line should be drawn with the status colors");
+        return 1;
+    }
+    if con.cursor() != (0, 1) {
+        test_failed!("Status line should not move the console cursor");
+        return 1;
+    }
+    if con.set_status(1, format_args!("")) != Err(FbConError::InvalidLine) {
+        test_failed!("Status line outside the panel should return InvalidLine");
+        return 1;
+    }
+    0
+}
+
+pub fn test_ramfb_config() -> u8 {
+    let fb = Framebuffer {
+        addr: 0x8100_0000,
+        width: 640,
+        height: 480,
+        stride: 2560,
+    };
+    let expected: [u8; 28] = [
+        0x00, 0x00, 0x00, 0x00, 0x81, 0x00, 0x00, 0x00, b'4', b'2', b'R', b'X', 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x02, 0x80, 0x00, 0x00, 0x01, 0xe0, 0x00, 0x00, 0x0a, 0x00,
+    ];
+    if ramfb_config(&fb) != expected {
+        test_failed!("ramfb config should be address, fourcc, flags, width, height and stride");
+        return 1;
+    }
+    0
+}
+
+pub fn fbcon_test_suite() {
+    const FBCON_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init("Font glyph", test_font_glyph, TestBehavior::Default),
+            TestCase::init(
+                "Framebuffer pixels",
+                test_framebuffer_pixels,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Framebuffer console draw character",
+                test_fbcon_draw_char,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Framebuffer console wrap and scroll",
+                test_fbcon_wrap_scroll,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Framebuffer console ANSI colors",
+                test_fbcon_ansi_colors,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Framebuffer console status panel",
+                test_fbcon_status,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "ramfb configuration",
+                test_ramfb_config,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Framebuffer console",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&FBCON_TEST_SUITE)
+    };
+}
@@ -0,0 +1,65 @@
+use crate::{
+    drivers::ext_intc::{ExtIntCError, ExtIntCSubSystem, plic::PLIC_MAX_PRIORITY},
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+pub fn test_ext_intc_subsystem_enable_source() -> u8 {
+    // Sub-system without device, only the parameters are checked.
+    let ext_intc_subsystem = ExtIntCSubSystem::init();
+    if ext_intc_subsystem.get_ext_intc().is_some() {
+        test_failed!("External interrupt-controller sub-system should be initialized empty.");
+        return 1;
+    }
+    if ext_intc_subsystem.enable_source(10, 1) != Ok(()) {
+        test_failed!("Failed to enable source 10");
+        return 1;
+    }
+    if ext_intc_subsystem.enable_source(10, PLIC_MAX_PRIORITY) != Ok(()) {
+        test_failed!("Failed to enable source 10 with the max priority");
+        return 1;
+    }
+    0
+}
+
+pub fn test_ext_intc_subsystem_invalid() -> u8 {
+    let ext_intc_subsystem = ExtIntCSubSystem::init();
+    if ext_intc_subsystem.enable_source(0, 1) != Err(ExtIntCError::InvalidIrq) {
+        test_failed!("Source 0 doesn't exist, enable should return InvalidIrq");
+        return 1;
+    }
+    if ext_intc_subsystem.enable_source(10, 0) != Err(ExtIntCError::InvalidPriority) {
+        test_failed!("Priority 0 disable the source, enable should return InvalidPriority");
+        return 1;
+    }
+    if ext_intc_subsystem.enable_source(10, PLIC_MAX_PRIORITY + 1)
+        != Err(ExtIntCError::InvalidPriority)
+    {
+        test_failed!("Priority above the max should return InvalidPriority");
+        return 1;
+    }
+    0
+}
+
+pub fn ext_intc_subsystem_test_suite() {
+    const EXT_INTC_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "External interrupt-controller sub-system enable source",
+                test_ext_intc_subsystem_enable_source,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "External interrupt-controller sub-system invalid source",
+                test_ext_intc_subsystem_invalid,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "External interrupt-controller",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&EXT_INTC_TEST_SUITE)
+    };
+}
@@ -0,0 +1,93 @@
+use crate::{
+    drivers::firmware::{
+        FirmwareError, FirmwareSubSystem, firmware_find_file,
+        fw_cfg::{
+            FW_CFG_DMA_SELECT, FW_CFG_DMA_WRITE, FW_CFG_FILE_SIZE, FwCfgFile, fw_cfg_dma_access,
+        },
+    },
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+pub fn test_firmware_subsystem_empty() -> u8 {
+    let firmware_subsystem = FirmwareSubSystem::init();
+    if firmware_subsystem.get_firmware().is_some() {
+        test_failed!("Firmware sub-system should be initialized empty.");
+        return 1;
+    }
+    0
+}
+
+pub fn test_fw_cfg_file_parse() -> u8 {
+    let mut entry = [0u8; FW_CFG_FILE_SIZE];
+    entry[0..4].copy_from_slice(&28u32.to_be_bytes());
+    entry[4..6].copy_from_slice(&0x0020u16.to_be_bytes());
+    entry[8..17].copy_from_slice(b"etc/ramfb");
+    let file = FwCfgFile::parse(&entry);
+    if file.size != 28 || file.select != 0x0020 {
+        test_failed!("fw_cfg file size and select should be read as big endian");
+        return 1;
+    }
+    if file.name() != "etc/ramfb" {
+        test_failed!("fw_cfg file name should stop at the NUL padding");
+        return 1;
+    }
+    0
+}
+
+pub fn test_fw_cfg_dma_access() -> u8 {
+    let control = (0x0020 << 16) | FW_CFG_DMA_SELECT | FW_CFG_DMA_WRITE;
+    let access = fw_cfg_dma_access(control, 28, 0x8010_0000);
+    let expected: [u8; 16] = [
+        0x00, 0x20, 0x00, 0x18, 0x00, 0x00, 0x00, 0x1c, 0x00, 0x00, 0x00, 0x00, 0x80, 0x10, 0x00,
+        0x00,
+    ];
+    if access != expected {
+        test_failed!("fw_cfg DMA access should be control, length and address in big endian");
+        return 1;
+    }
+    0
+}
+
+pub fn test_firmware_missing_file() -> u8 {
+    // QEMU virt always has a fw_cfg device, the test must also pass without.
+    let res = firmware_find_file("opt/lrnrtos/missing");
+    if res != Err(FirmwareError::NotFound) && res != Err(FirmwareError::NoDevice) {
+        test_failed!("Finding a missing file should return NotFound or NoDevice");
+        return 1;
+    }
+    0
+}
+
+pub fn fw_cfg_test_suite() {
+    const FW_CFG_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Firmware sub-system initialized empty",
+                test_firmware_subsystem_empty,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "fw_cfg file directory entry",
+                test_fw_cfg_file_parse,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "fw_cfg DMA access encoding",
+                test_fw_cfg_dma_access,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Firmware missing file",
+                test_firmware_missing_file,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Firmware fw_cfg",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&FW_CFG_TEST_SUITE)
+    };
+}
@@ -0,0 +1 @@
+pub mod fw_cfg;
@@ -0,0 +1,149 @@
+use crate::{
+    drivers::{
+        DriverRegion,
+        gpio::{
+            GpioDevice, GpioDeviceDriver, GpioDirection, GpioError, GpioSubSystem, GpioTrigger,
+            gpio_read,
+            sifive_gpio::{SifiveGpio, trigger_ie_offsets},
+        },
+    },
+    platform::GPIO_MAX_PINS,
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+// Fake device, the region is never accessed by the tests.
+fn test_gpio_device(addr: usize) -> GpioDevice {
+    GpioDevice {
+        driver: GpioDeviceDriver::SifiveGpio(SifiveGpio::new(
+            DriverRegion { addr, size: 0x1000 },
+            16,
+            [0; GPIO_MAX_PINS],
+        )),
+    }
+}
+
+pub fn test_gpio_subsystem_empty() -> u8 {
+    let gpio_subsystem = GpioSubSystem::init();
+    if gpio_subsystem.get_gpio_array_size() != 0 {
+        test_failed!("GPIO sub-system should be initialized empty.");
+        return 1;
+    }
+    if gpio_subsystem.get_gpio(0).is_some() {
+        test_failed!("GPIO sub-system should not contain a device");
+        return 1;
+    }
+    0
+}
+
+pub fn test_gpio_subsystem_add() -> u8 {
+    let gpio_subsystem = GpioSubSystem::init();
+    if gpio_subsystem.add_gpio(test_gpio_device(0)) != Some(0) {
+        test_failed!("GPIO sub-system should add the device at index 0");
+        return 1;
+    }
+    if gpio_subsystem.add_gpio(test_gpio_device(0)).is_some() {
+        test_failed!("GPIO sub-system should refuse a duplicate device");
+        return 1;
+    }
+    if gpio_subsystem.get_gpio_array_size() != 1 {
+        test_failed!("GPIO sub-system should contain 1 device");
+        return 1;
+    }
+    0
+}
+
+pub fn test_gpio_invalid_pin() -> u8 {
+    let gpio = test_gpio_device(0);
+    // The pin is checked before any access to the device.
+    if gpio.read(16) != Err(GpioError::InvalidPin) {
+        test_failed!("Reading a pin out of the controller should return InvalidPin");
+        return 1;
+    }
+    if gpio.write(u32::MAX, true) != Err(GpioError::InvalidPin) {
+        test_failed!("Writing a pin out of the controller should return InvalidPin");
+        return 1;
+    }
+    if gpio.set_direction(16, GpioDirection::Output) != Err(GpioError::InvalidPin) {
+        test_failed!(
+            "Setting the direction of a pin out of the controller should return InvalidPin"
+        );
+        return 1;
+    }
+    if gpio.arm(16, GpioTrigger::RisingEdge) != Err(GpioError::InvalidPin) {
+        test_failed!("Arming a pin out of the controller should return InvalidPin");
+        return 1;
+    }
+    0
+}
+
+pub fn test_gpio_no_device() -> u8 {
+    // QEMU virt has no GPIO controller.
+    if gpio_read(5, 0) != Err(GpioError::NoDevice) {
+        test_failed!("Reading a pin of a missing controller should return NoDevice");
+        return 1;
+    }
+    0
+}
+
+pub fn test_gpio_trigger_registers() -> u8 {
+    if trigger_ie_offsets(GpioTrigger::RisingEdge) != [0x18] {
+        test_failed!("Rising edge should use the rise interrupt enable register");
+        return 1;
+    }
+    if trigger_ie_offsets(GpioTrigger::FallingEdge) != [0x20] {
+        test_failed!("Falling edge should use the fall interrupt enable register");
+        return 1;
+    }
+    if trigger_ie_offsets(GpioTrigger::BothEdges) != [0x18, 0x20] {
+        test_failed!("Both edges should use the rise and fall interrupt enable registers");
+        return 1;
+    }
+    if trigger_ie_offsets(GpioTrigger::HighLevel) != [0x28] {
+        test_failed!("High level should use the high interrupt enable register");
+        return 1;
+    }
+    if trigger_ie_offsets(GpioTrigger::LowLevel) != [0x30] {
+        test_failed!("Low level should use the low interrupt enable register");
+        return 1;
+    }
+    0
+}
+
+pub fn gpio_subsystem_test_suite() {
+    const GPIO_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "GPIO sub-system initialized empty",
+                test_gpio_subsystem_empty,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "GPIO sub-system add and duplicate device",
+                test_gpio_subsystem_add,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "GPIO invalid pin",
+                test_gpio_invalid_pin,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "GPIO missing controller",
+                test_gpio_no_device,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "GPIO trigger interrupt registers",
+                test_gpio_trigger_registers,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "GPIO sub-system",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&GPIO_TEST_SUITE)
+    };
+}
@@ -1,3 +1,15 @@
+pub mod block;
 pub mod cpu_intc;
+pub mod cpufreq;
+pub mod display;
+pub mod ext_intc;
+pub mod firmware;
+pub mod gpio;
+pub mod model;
+pub mod pool;
+pub mod power;
+pub mod rtc;
 pub mod serials;
+pub mod spi;
 pub mod timer;
+pub mod virtio;
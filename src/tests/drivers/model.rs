@@ -0,0 +1,130 @@
+use crate::{
+    drivers::{
+        model::{
+            DRIVER_TABLE, DriverClass, DriverError, device_is_bound, device_registry_get,
+            device_registry_iter, device_registry_size, device_remove,
+        },
+        serials::SERIAL_SUBSYSTEM,
+    },
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+pub fn test_driver_table() -> u8 {
+    for driver in DRIVER_TABLE {
+        if driver.compatible().is_empty() {
+            test_failed!("Driver {} has no compatible string", driver.name());
+            return 1;
+        }
+    }
+    // The boot UART must be probed first to stay the default console
+    let first_serial = DRIVER_TABLE
+        .iter()
+        .find(|d| d.class() == DriverClass::Serial)
+        .unwrap();
+    if first_serial.name() != "ns16550a" {
+        test_failed!(
+            "The first serial driver should be ns16550a, got: {}",
+            first_serial.name()
+        );
+        return 1;
+    }
+    0
+}
+
+pub fn test_device_registry() -> u8 {
+    // The sub-systems are initialized before the tests, at least the boot UART, the CPU
+    // interrupt-controller and the timer are bound.
+    if device_registry_size() < 3 {
+        test_failed!(
+            "The device registry should contain the boot devices, got: {}",
+            device_registry_size()
+        );
+        return 1;
+    }
+    let classes = [
+        DriverClass::Serial,
+        DriverClass::CpuIntC,
+        DriverClass::Timer,
+    ];
+    for class in classes {
+        if !device_registry_iter().any(|(_, d)| d.driver.class() == class) {
+            test_failed!("No device of class {:?} in the registry", class);
+            return 1;
+        }
+    }
+    let console = SERIAL_SUBSYSTEM.get_serial(0).unwrap();
+    let (_, registered) = device_registry_iter()
+        .find(|(_, d)| d.region == console.region())
+        .unwrap();
+    if !device_is_bound(
+        registered.driver.name(),
+        registered.compatible,
+        registered.region,
+        registered.nth,
+    ) {
+        test_failed!("The default console should be bound");
+        return 1;
+    }
+    0
+}
+
+pub fn test_device_remove() -> u8 {
+    let size = device_registry_size();
+    let (index, _) = device_registry_iter()
+        .find(|(_, d)| d.driver.class() == DriverClass::Timer)
+        .unwrap();
+    if device_remove(index) != Err(DriverError::NotRemovable) {
+        test_failed!("Removing the timer should return NotRemovable");
+        return 1;
+    }
+    if device_registry_size() != size || device_registry_get(index).is_none() {
+        test_failed!("A device not removed should stay in the registry");
+        return 1;
+    }
+    // The default console cannot be removed
+    let console = SERIAL_SUBSYSTEM.get_serial(0).unwrap().region();
+    let (index, _) = device_registry_iter()
+        .find(|(_, d)| d.region == console)
+        .unwrap();
+    if device_remove(index) != Err(DriverError::Busy) {
+        test_failed!("Removing the default console should return Busy");
+        return 1;
+    }
+    let free = (0..size + 1)
+        .find(|i| device_registry_get(*i).is_none())
+        .unwrap();
+    if device_remove(free) != Err(DriverError::NotRegistered) {
+        test_failed!("Removing a free index should return NotRegistered");
+        return 1;
+    }
+    0
+}
+
+pub fn driver_model_test_suite() {
+    const DRIVER_MODEL_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Driver match table",
+                test_driver_table,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Device registry after boot",
+                test_device_registry,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Device remove errors",
+                test_device_remove,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Driver model",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&DRIVER_MODEL_TEST_SUITE)
+    };
+}
@@ -0,0 +1,97 @@
+use crate::{
+    drivers::pool::DevicePool,
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+pub fn test_device_pool_add_remove() -> u8 {
+    let pool: DevicePool<u32, 3> = DevicePool::init("Test");
+    if pool.size() != 0 || pool.capacity() != 3 {
+        test_failed!("Device pool should be initialized empty with a capacity of 3");
+        return 1;
+    }
+    if pool.add(10) != Some(0) || pool.add(11) != Some(1) {
+        test_failed!("Devices should be added at the first free index");
+        return 1;
+    }
+    if pool.get(1) != Some(&11) || pool.get(3).is_some() {
+        test_failed!("Getting a device should return the device at index");
+        return 1;
+    }
+    // The index of the other devices doesn't change after a remove
+    if pool.remove(0) != Some(10) || pool.get(1) != Some(&11) || pool.size() != 1 {
+        test_failed!("Removing a device should not move the other devices");
+        return 1;
+    }
+    if pool.add(12) != Some(0) {
+        test_failed!("A removed index should be reused");
+        return 1;
+    }
+    let mut sum: usize = 0;
+    for (i, device) in pool.iter() {
+        sum += i + *device as usize;
+    }
+    if sum != 24 || pool.position(|d| *d == 11) != Some(1) {
+        test_failed!("Iterating over the pool should return each device with its index");
+        return 1;
+    }
+    0
+}
+
+pub fn test_device_pool_duplicate_overflow() -> u8 {
+    let pool: DevicePool<u32, 2> = DevicePool::init("Test");
+    pool.add(1).unwrap();
+    // Those ones should trigger a warning and not be added
+    if pool.add(1).is_some() {
+        test_failed!("A duplicate device should not be added");
+        return 1;
+    }
+    pool.add(2).unwrap();
+    if pool.add(3).is_some() || pool.size() != 2 {
+        test_failed!("A device should not be added in a full pool");
+        return 1;
+    }
+    0
+}
+
+pub fn test_device_pool_insert() -> u8 {
+    let pool: DevicePool<u32, 2> = DevicePool::init("Test");
+    if !pool.insert(1, 5) || pool.get(1) != Some(&5) || pool.get(0).is_some() {
+        test_failed!("Inserting a device should use the given index");
+        return 1;
+    }
+    // Those ones should trigger a warning and not be added
+    if pool.insert(1, 6) || pool.insert(0, 5) || pool.insert(2, 7) {
+        test_failed!("Inserting at a used index, a duplicate or out of the pool should fail");
+        return 1;
+    }
+    0
+}
+
+pub fn device_pool_test_suite() {
+    const DEVICE_POOL_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Device pool add and remove",
+                test_device_pool_add_remove,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Device pool duplicate and overflow",
+                test_device_pool_duplicate_overflow,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Device pool insert at index",
+                test_device_pool_insert,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Device pool",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&DEVICE_POOL_TEST_SUITE)
+    };
+}
@@ -0,0 +1,61 @@
+use crate::{
+    drivers::{
+        DriverRegion,
+        power::{PowerDevice, PowerDeviceDriver, PowerSubSystem, sifive_test::SifiveTest},
+    },
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+pub fn test_power_subsystem_impl() -> u8 {
+    let power_subsystem = PowerSubSystem::init();
+    if power_subsystem.get_power().is_some() {
+        test_failed!("Power sub-system should be initialized empty.");
+        return 1;
+    }
+    let region = DriverRegion {
+        addr: 0x10_0000,
+        size: 0x1000,
+    };
+    power_subsystem.add_power(PowerDevice {
+        driver: PowerDeviceDriver::SifiveTest(SifiveTest { region }),
+    });
+    match power_subsystem.get_power() {
+        Some(power) if power.supports_exit_code() => {}
+        _ => {
+            test_failed!("Power sub-system should hold the SiFive test device");
+            return 1;
+        }
+    }
+    // Only one device, the second one is ignored
+    power_subsystem.add_power(PowerDevice {
+        driver: PowerDeviceDriver::SifiveTest(SifiveTest {
+            region: DriverRegion {
+                addr: 0x20_0000,
+                size: 0x1000,
+            },
+        }),
+    });
+    let kept = power_subsystem.get_power().unwrap();
+    if kept.driver != PowerDeviceDriver::SifiveTest(SifiveTest { region }) {
+        test_failed!("Power sub-system should keep the first device registered");
+        return 1;
+    }
+    0
+}
+
+pub fn power_subsystem_test_suite() {
+    const POWER_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[TestCase::init(
+            "Power sub-system basic implementation",
+            test_power_subsystem_impl,
+            TestBehavior::Default,
+        )],
+        name: "Power sub-system",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&POWER_TEST_SUITE)
+    };
+}
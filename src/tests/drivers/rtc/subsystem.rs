@@ -0,0 +1,59 @@
+use crate::{
+    drivers::rtc::{RtcError, RtcSubSystem},
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+fn test_alarm_handler(_time_ns: u64) {}
+
+pub fn test_rtc_subsystem_empty() -> u8 {
+    let rtc_subsystem = RtcSubSystem::init();
+    if rtc_subsystem.get_rtc_array_size() != 0 {
+        test_failed!("RTC sub-system should be initialized empty.");
+        return 1;
+    }
+    if rtc_subsystem.get_primary_rtc().is_some() {
+        test_failed!("RTC sub-system should not have a primary RTC");
+        return 1;
+    }
+    // Out of the pool
+    if rtc_subsystem.get_rtc(usize::MAX).is_some() {
+        test_failed!("RTC sub-system should not contain a device out of the pool");
+        return 1;
+    }
+    0
+}
+
+pub fn test_rtc_subsystem_no_device() -> u8 {
+    let rtc_subsystem = RtcSubSystem::init();
+    if rtc_subsystem.set_alarm(u64::MAX, test_alarm_handler) != Err(RtcError::NoDevice) {
+        test_failed!("Setting an alarm without RTC should return NoDevice");
+        return 1;
+    }
+    // Must not fail without device
+    rtc_subsystem.cancel_alarm();
+    0
+}
+
+pub fn rtc_subsystem_test_suite() {
+    const RTC_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "RTC sub-system initialized empty",
+                test_rtc_subsystem_empty,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "RTC sub-system alarm without device",
+                test_rtc_subsystem_no_device,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "RTC sub-system",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&RTC_TEST_SUITE)
+    };
+}
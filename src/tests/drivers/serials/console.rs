@@ -0,0 +1,156 @@
+use crate::{
+    config::{CONSOLE_TASK_ROUTE_MAX_SIZE, SERIAL_MAX_SIZE},
+    drivers::{
+        DriverRegion,
+        serials::{
+            SerialDevice, SerialDeviceDriver, SerialManager,
+            console::{ConsoleError, ConsoleRoutes},
+            ns16550a::Ns16550,
+        },
+    },
+    logs::LogLevel,
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+/// Serial sub-system with two UARTs, the operator one at index 0 and the debug one at index 1.
+fn test_console_serials() -> SerialManager {
+    let serials = SerialManager::init();
+    for addr in [0x10000000, 0x10000100] {
+        serials.add_serial(SerialDevice {
+            _id: 0,
+            default_console: false,
+            driver: SerialDeviceDriver::Ns16550(Ns16550 {
+                region: DriverRegion { addr, size: 0x100 },
+                irq: 0,
+            }),
+        });
+    }
+    serials
+}
+
+pub fn test_console_route_level() -> u8 {
+    let serials = test_console_serials();
+    let routes = ConsoleRoutes::init();
+    if routes.console(&serials, Some(LogLevel::Debug), None) != 0 {
+        test_failed!("Without route, the logs should go to the default console");
+        return 1;
+    }
+    routes
+        .route_level(&serials, LogLevel::Debug, Some(1))
+        .unwrap();
+    if routes.console(&serials, Some(LogLevel::Debug), None) != 1
+        || routes.console(&serials, Some(LogLevel::Error), None) != 0
+        || routes.console(&serials, None, None) != 0
+    {
+        test_failed!("Only the debug logs should go to the UART at index 1");
+        return 1;
+    }
+    routes.route_level(&serials, LogLevel::Debug, None).unwrap();
+    if routes.level_route(LogLevel::Debug).is_some() {
+        test_failed!("Clearing the route should send the debug logs to the default console");
+        return 1;
+    }
+    if routes.route_level(&serials, LogLevel::Error, Some(SERIAL_MAX_SIZE))
+        != Err(ConsoleError::NoDevice)
+    {
+        test_failed!("A route to a missing device should be refused");
+        return 1;
+    }
+    0
+}
+
+pub fn test_console_route_task() -> u8 {
+    let serials = test_console_serials();
+    let routes = ConsoleRoutes::init();
+    routes.route_task(&serials, 3, Some(1)).unwrap();
+    if routes.console(&serials, None, Some(3)) != 1 || routes.console(&serials, None, Some(4)) != 0
+    {
+        test_failed!("Only the output of the task 3 should go to the UART at index 1");
+        return 1;
+    }
+    // The route of the level is used before the route of the task
+    routes
+        .route_level(&serials, LogLevel::Error, Some(0))
+        .unwrap();
+    if routes.console(&serials, Some(LogLevel::Error), Some(3)) != 0
+        || routes.console(&serials, Some(LogLevel::Info), Some(3)) != 1
+    {
+        test_failed!("The route of the log level should be used before the route of the task");
+        return 1;
+    }
+    routes.route_task(&serials, 3, None).unwrap();
+    if routes.task_route(3).is_some() {
+        test_failed!("Clearing the route of the task should remove it");
+        return 1;
+    }
+    0
+}
+
+pub fn test_console_route_task_full() -> u8 {
+    let serials = test_console_serials();
+    let routes = ConsoleRoutes::init();
+    for pid in 0..CONSOLE_TASK_ROUTE_MAX_SIZE as u16 {
+        routes.route_task(&serials, pid, Some(1)).unwrap();
+    }
+    if routes.route_task(&serials, CONSOLE_TASK_ROUTE_MAX_SIZE as u16, Some(1))
+        != Err(ConsoleError::TableFull)
+    {
+        test_failed!("Routing a task in a full table should return TableFull");
+        return 1;
+    }
+    // Changing the route of a routed task doesn't need a free entry
+    if routes.route_task(&serials, 0, Some(0)).is_err() || routes.task_route(0) != Some(0) {
+        test_failed!("Changing the route of a routed task should replace its entry");
+        return 1;
+    }
+    0
+}
+
+pub fn test_console_removed_device() -> u8 {
+    let serials = test_console_serials();
+    let routes = ConsoleRoutes::init();
+    routes
+        .route_level(&serials, LogLevel::Debug, Some(1))
+        .unwrap();
+    routes.route_task(&serials, 3, Some(1)).unwrap();
+    serials.remove_serial(1).unwrap();
+    if routes.console(&serials, Some(LogLevel::Debug), Some(3)) != 0 {
+        test_failed!("A route to a removed device should fall back to the default console");
+        return 1;
+    }
+    0
+}
+
+pub fn console_test_suite() {
+    const CONSOLE_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Console route log level",
+                test_console_route_level,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Console route task",
+                test_console_route_task,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Console full task route table",
+                test_console_route_task_full,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Console route to a removed device",
+                test_console_removed_device,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Serial console routing",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&CONSOLE_TEST_SUITE)
+    };
+}
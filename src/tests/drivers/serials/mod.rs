@@ -1,2 +1,3 @@
+pub mod console;
 pub mod ns16550a;
 pub mod subsystem;
@@ -1,17 +1,65 @@
 use crate::{
+    config::SERIAL_BAUD_RATE,
     drivers::{
         DriverRegion,
-        serials::{SerialDevice, SerialDeviceDriver, SerialDriver, ns16550a::Ns16550},
+        serials::{
+            SerialDevice, SerialDeviceDriver, SerialDriver, SerialError,
+            ns16550a::{NS16550_RX_FIFO_SIZE, Ns16550},
+        },
     },
+    test_failed,
     tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
 };
 
+// Registers of the device in memory, a register keeps the last value written.
+static mut TEST_NS16550_REGS: [u8; 8] = [0; 8];
+// Offsets of the registers used by the tests.
+const TEST_DLL: usize = 0;
+const TEST_DLM_IER: usize = 1;
+const TEST_FCR: usize = 2;
+const TEST_LCR: usize = 3;
+const TEST_MCR: usize = 4;
+const TEST_LSR: usize = 5;
+// LSR: transmitter empty and holding register empty, nothing received.
+const TEST_LSR_IDLE: u8 = 0b0110_0000;
+// LSR: data ready.
+const TEST_LSR_DR: u8 = 0b0000_0001;
+
+// Device on the registers in memory, cleared for each test, the transmitter is idle.
+fn test_ns16550() -> Ns16550 {
+    #[allow(static_mut_refs)]
+    let addr = unsafe {
+        TEST_NS16550_REGS.fill(0);
+        TEST_NS16550_REGS[TEST_LSR] = TEST_LSR_IDLE;
+        TEST_NS16550_REGS.as_mut_ptr()
+    } as usize;
+    Ns16550 {
+        region: DriverRegion { addr, size: 8 },
+        irq: 0,
+    }
+}
+
+fn test_ns16550_reg(off: usize) -> u8 {
+    #[allow(static_mut_refs)]
+    unsafe {
+        core::ptr::read_volatile(TEST_NS16550_REGS.as_ptr().add(off))
+    }
+}
+
+fn test_ns16550_set_reg(off: usize, value: u8) {
+    #[allow(static_mut_refs)]
+    unsafe {
+        core::ptr::write_volatile(TEST_NS16550_REGS.as_mut_ptr().add(off), value)
+    }
+}
+
 pub fn test_ns16550_qemu_putchar() -> u8 {
     let ns16550: Ns16550 = Ns16550 {
         region: DriverRegion {
             addr: 0x1000_0000,
             size: 0x100,
         },
+        irq: 10,
     };
     let mut device = SerialDevice {
         _id: 0,
@@ -19,8 +67,150 @@ pub fn test_ns16550_qemu_putchar() -> u8 {
         driver: SerialDeviceDriver::Ns16550(ns16550),
     };
     // Write in buff using putchar
-    match &mut device.driver {
-        SerialDeviceDriver::Ns16550(ns16550) => ns16550.putchar(0x00000001),
+    if let SerialDeviceDriver::Ns16550(ns16550) = &mut device.driver
+        && ns16550.putchar(0x00000001).is_err()
+    {
+        test_failed!("Putchar on the UART of QEMU virt should not time out");
+        return 1;
+    }
+    0
+}
+
+pub fn test_ns16550_hw_init() -> u8 {
+    let ns16550 = test_ns16550();
+    if ns16550.hw_init(16 * SERIAL_BAUD_RATE * 0x302).is_err() {
+        test_failed!("Init should succeed with a valid clock");
+        return 1;
+    }
+    if test_ns16550_reg(TEST_DLL) != 0x02 {
+        test_failed!("Init should write the divisor");
+        return 1;
+    }
+    // 8N1 with DLAB cleared, FIFOs enabled and cleared, DTR RTS and OUT2.
+    if test_ns16550_reg(TEST_LCR) != 0b0000_0011
+        || test_ns16550_reg(TEST_FCR) != 0b0000_0111
+        || test_ns16550_reg(TEST_MCR) != 0b0000_1011
+    {
+        test_failed!("Init should set the frame format, the FIFOs and the modem control");
+        return 1;
+    }
+    if test_ns16550_reg(TEST_DLM_IER) != 0b0000_0001 {
+        test_failed!("Init should enable the received data available interrupt only");
+        return 1;
+    }
+    0
+}
+
+pub fn test_ns16550_hw_init_keep_divisor() -> u8 {
+    let ns16550 = test_ns16550();
+    test_ns16550_set_reg(TEST_DLL, 0x05);
+    // Unknown clock, the divisor of the firmware is kept.
+    if ns16550.hw_init(0).is_err() || test_ns16550_reg(TEST_DLL) != 0x05 {
+        test_failed!("Init without clock should keep the divisor");
+        return 1;
+    }
+    // Clock too slow for the baud rate, the device is still initialized.
+    if ns16550.hw_init(1) != Err(SerialError::InvalidClock) || test_ns16550_reg(TEST_DLL) != 0x05 {
+        test_failed!("Init with an invalid clock should keep the divisor and return an error");
+        return 1;
+    }
+    if test_ns16550_reg(TEST_LCR) != 0b0000_0011 || test_ns16550_reg(TEST_DLM_IER) != 0b0000_0001 {
+        test_failed!("Init with an invalid clock should still set the frame format");
+        return 1;
+    }
+    0
+}
+
+pub fn test_ns16550_divisor() -> u8 {
+    if Ns16550::divisor(16 * SERIAL_BAUD_RATE * 10) != Ok(10) {
+        test_failed!("Divisor should be the clock frequency over 16 times the baud rate");
+        return 1;
+    }
+    if Ns16550::divisor(0) != Err(SerialError::InvalidClock)
+        || Ns16550::divisor(16 * SERIAL_BAUD_RATE - 1) != Err(SerialError::InvalidClock)
+    {
+        test_failed!("A clock too slow for the baud rate should be refused");
+        return 1;
+    }
+    0
+}
+
+pub fn test_ns16550_set_clock() -> u8 {
+    let ns16550 = test_ns16550();
+    // Received data available interrupt enabled.
+    test_ns16550_set_reg(TEST_DLM_IER, 0b0000_0001);
+    if ns16550.set_clock(16 * SERIAL_BAUD_RATE * 0x302).is_err() {
+        test_failed!("Set clock should succeed with a valid clock");
+        return 1;
+    }
+    if test_ns16550_reg(TEST_DLL) != 0x02 || test_ns16550_reg(TEST_LCR) != 0b0000_0011 {
+        test_failed!("Set clock should write the divisor and restore the frame format");
+        return 1;
+    }
+    // The divisor latch high and the interrupt enable are the same register in memory.
+    if test_ns16550_reg(TEST_DLM_IER) != 0b0000_0001 {
+        test_failed!("Set clock should restore the interrupt enable register");
+        return 1;
+    }
+    if ns16550.set_clock(1) != Err(SerialError::InvalidClock) {
+        test_failed!("Set clock should refuse a clock too slow for the baud rate");
+        return 1;
+    }
+    if test_ns16550_reg(TEST_DLL) != 0x02 {
+        test_failed!("Set clock should keep the old divisor on error");
+        return 1;
+    }
+    0
+}
+
+pub fn test_ns16550_putchar_timeout() -> u8 {
+    let ns16550 = test_ns16550();
+    if ns16550.putchar(b'a') != Ok(()) || test_ns16550_reg(TEST_DLL) != b'a' {
+        test_failed!("Putchar should write the char when the transmitter is idle");
+        return 1;
+    }
+    // The transmitter never empties.
+    test_ns16550_set_reg(TEST_LSR, 0);
+    if ns16550.putchar(b'b') != Err(SerialError::Timeout) || test_ns16550_reg(TEST_DLL) != b'a' {
+        test_failed!("Putchar should time out without writing when the transmitter stays full");
+        return 1;
+    }
+    if ns16550.flush() != Err(SerialError::Timeout) {
+        test_failed!("Flush should time out when the transmitter stays busy");
+        return 1;
+    }
+    0
+}
+
+pub fn test_ns16550_getchar() -> u8 {
+    let ns16550 = test_ns16550();
+    if ns16550.getchar().is_some() {
+        test_failed!("Getchar should return None when nothing is received");
+        return 1;
+    }
+    // Data ready is never cleared by a read of the registers in memory, the drain is bounded by
+    // the FIFO size.
+    test_ns16550_set_reg(TEST_LSR, TEST_LSR_IDLE | TEST_LSR_DR);
+    test_ns16550_set_reg(TEST_DLL, b'x');
+    if ns16550.getchar() != Some(b'x') {
+        test_failed!("Getchar should return the received char");
+        return 1;
+    }
+    test_ns16550_set_reg(TEST_LSR, TEST_LSR_IDLE);
+    let mut received: usize = 1;
+    while ns16550.getchar() == Some(b'x') {
+        received += 1;
+        if received > NS16550_RX_FIFO_SIZE {
+            break;
+        }
+    }
+    if received != NS16550_RX_FIFO_SIZE {
+        test_failed!(
+            "A drain should read {} chars, got: {}",
+            NS16550_RX_FIFO_SIZE,
+            received
+        );
+        return 1;
     }
     0
 }
@@ -40,3 +230,46 @@ pub fn ns16550_test_suite() {
         TEST_MANAGER.add_suite(&NS16550_TEST_SUITE)
     };
 }
+
+pub fn ns16550_registers_test_suite() {
+    const NS16550_REGISTERS_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Ns16550 hardware init",
+                test_ns16550_hw_init,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Ns16550 hardware init keeps the firmware divisor",
+                test_ns16550_hw_init_keep_divisor,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Ns16550 baud rate divisor",
+                test_ns16550_divisor,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Ns16550 set clock keeps the divisor on error",
+                test_ns16550_set_clock,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Ns16550 putchar and flush time out",
+                test_ns16550_putchar_timeout,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Ns16550 getchar drains at most the FIFO size",
+                test_ns16550_getchar,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Ns16550 registers",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&NS16550_REGISTERS_TEST_SUITE)
+    };
+}
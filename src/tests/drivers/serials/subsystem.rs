@@ -1,12 +1,11 @@
 use crate::{
+    config::SERIAL_MAX_SIZE,
     drivers::{
         DriverRegion,
-        serials::{
-            SerialDevice, SerialDeviceDriver, SerialManager, init_serial_subsystem,
-            ns16550a::Ns16550,
-        },
+        serials::{SerialDevice, SerialDeviceDriver, SerialManager, ns16550a::Ns16550},
     },
     platform::{DeviceType, platform_get_device_info},
+    test_failed,
     tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
 };
 
@@ -21,6 +20,7 @@ pub fn test_serial_subsystem_impl() -> u8 {
     let device_info = platform_get_device_info("ns16550a", DeviceType::Serial).unwrap();
     let ns16550: Ns16550 = Ns16550 {
         region: device_info.header.device_addr,
+        irq: 0,
     };
     let device = SerialDevice {
         _id: 0,
@@ -35,20 +35,17 @@ pub fn test_serial_subsystem_impl() -> u8 {
         );
     }
     // Check default console
-    let serial = unsafe { &*serial_subsystem.devices[0].get() };
-    if !serial.as_ref().unwrap().default_console {
+    let serial = serial_subsystem.devices.get(0);
+    if !serial.unwrap().default_console {
         panic!("Serial sub-system failed to set new serial as default console.");
     }
 
     let default_console = unsafe { serial_subsystem.get_default_console() };
-    if default_console != serial.as_ref().unwrap() {
+    if default_console != serial.unwrap() {
         panic!(
             "Error getting the default console, default console get is different than the one saved before."
         );
     }
-
-    // Initialize serial subsystem, don't know where to put it instead of here
-    init_serial_subsystem();
     0
 }
 
@@ -63,6 +60,7 @@ pub fn test_serial_subsystem_same_device() -> u8 {
     let device_info = platform_get_device_info("ns16550a", DeviceType::Serial).unwrap();
     let ns16550: Ns16550 = Ns16550 {
         region: device_info.header.device_addr,
+        irq: 0,
     };
     let device = SerialDevice {
         _id: 0,
@@ -73,6 +71,7 @@ pub fn test_serial_subsystem_same_device() -> u8 {
     let device_info = platform_get_device_info("ns16550a", DeviceType::Serial).unwrap();
     let ns16551: Ns16550 = Ns16550 {
         region: device_info.header.device_addr,
+        irq: 0,
     };
     let device1 = SerialDevice {
         _id: 0,
@@ -101,6 +100,7 @@ pub fn test_serial_subsystem_overflow() -> u8 {
             addr: 0x10000000,
             size: 0x100,
         },
+        irq: 0,
     };
     const DEVICE: SerialDevice = SerialDevice {
         _id: 0,
@@ -113,6 +113,7 @@ pub fn test_serial_subsystem_overflow() -> u8 {
             addr: 0x10000001,
             size: 0x200,
         },
+        irq: 0,
     };
     let device1 = SerialDevice {
         _id: 0,
@@ -125,6 +126,7 @@ pub fn test_serial_subsystem_overflow() -> u8 {
             addr: 0x10000002,
             size: 0x300,
         },
+        irq: 0,
     };
     let device2 = SerialDevice {
         _id: 0,
@@ -137,6 +139,7 @@ pub fn test_serial_subsystem_overflow() -> u8 {
             addr: 0x10000003,
             size: 0x400,
         },
+        irq: 0,
     };
     let device3 = SerialDevice {
         _id: 0,
@@ -149,6 +152,7 @@ pub fn test_serial_subsystem_overflow() -> u8 {
             addr: 0x10000004,
             size: 0x500,
         },
+        irq: 0,
     };
     let device4 = SerialDevice {
         _id: 0,
@@ -161,26 +165,21 @@ pub fn test_serial_subsystem_overflow() -> u8 {
     serial_subsystem.add_serial(device2);
     serial_subsystem.add_serial(device3);
     // Save the state of the serial subsystem
-    // Manually copy the inner values since UnsafeCell doesn't implement Clone
-    let serial_subsystem_snapshot = unsafe {
-        [
-            &*serial_subsystem.devices[0].get(),
-            &*serial_subsystem.devices[1].get(),
-            &*serial_subsystem.devices[2].get(),
-            &*serial_subsystem.devices[3].get(),
-        ]
-    };
+    let serial_subsystem_snapshot = [
+        serial_subsystem.devices.get(0),
+        serial_subsystem.devices.get(1),
+        serial_subsystem.devices.get(2),
+        serial_subsystem.devices.get(3),
+    ];
     // This one should trigger a warning and not be registered to the sub-system
     serial_subsystem.add_serial(device4);
     // Check if the subsystem has changed after the overflow aborted
-    let current_devices = unsafe {
-        [
-            &*serial_subsystem.devices[0].get(),
-            &*serial_subsystem.devices[1].get(),
-            &*serial_subsystem.devices[2].get(),
-            &*serial_subsystem.devices[3].get(),
-        ]
-    };
+    let current_devices = [
+        serial_subsystem.devices.get(0),
+        serial_subsystem.devices.get(1),
+        serial_subsystem.devices.get(2),
+        serial_subsystem.devices.get(3),
+    ];
     if serial_subsystem_snapshot != current_devices {
         panic!(
             "Serial sub-system state has changed after handling the overflow. This should not happened"
@@ -194,12 +193,14 @@ pub fn test_serial_subsystem_overflow() -> u8 {
     let default_console_region = {
         match &default_console.driver {
             SerialDeviceDriver::Ns16550(ns16550) => ns16550.region,
+            SerialDeviceDriver::VirtioConsole(_) => panic!("Default console should be a Ns16550"),
         }
     };
     // Get first device registered MMIO reg
     let device_region = {
         match DEVICE.driver {
             SerialDeviceDriver::Ns16550(ns16550) => ns16550.region,
+            SerialDeviceDriver::VirtioConsole(_) => panic!("Device should be a Ns16550"),
         }
     };
     // Compare default console with first device registered
@@ -209,6 +210,90 @@ pub fn test_serial_subsystem_overflow() -> u8 {
     0
 }
 
+/// Test the access to a device by index and by driver name.
+pub fn test_serial_subsystem_find() -> u8 {
+    let serial_subsystem: SerialManager = SerialManager::init();
+    if serial_subsystem.find("ns16550a").is_some() || serial_subsystem.get_serial(0).is_some() {
+        test_failed!("Empty serial sub-system should not find any device");
+        return 1;
+    }
+    let ns16550: Ns16550 = Ns16550 {
+        region: DriverRegion {
+            addr: 0x10000000,
+            size: 0x100,
+        },
+        irq: 0,
+    };
+    serial_subsystem.add_serial(SerialDevice {
+        _id: 0,
+        default_console: false,
+        driver: SerialDeviceDriver::Ns16550(ns16550),
+    });
+    if serial_subsystem.find("ns16550a") != Some(0) {
+        test_failed!("Ns16550 should be found at index 0");
+        return 1;
+    }
+    if serial_subsystem.find("virtio-console").is_some() {
+        test_failed!("No virtio console should be found");
+        return 1;
+    }
+    match serial_subsystem.get_serial(0) {
+        Some(serial) if serial.name() == "ns16550a" => {}
+        _ => {
+            test_failed!("get_serial should return the Ns16550 at index 0");
+            return 1;
+        }
+    }
+    if serial_subsystem.get_serial(SERIAL_MAX_SIZE).is_some() {
+        test_failed!("get_serial should return None out of the pool");
+        return 1;
+    }
+    0
+}
+
+/// Test the selection of another default console.
+pub fn test_serial_subsystem_default_console() -> u8 {
+    let serial_subsystem: SerialManager = SerialManager::init();
+    for addr in [0x10000000, 0x10000100] {
+        serial_subsystem.add_serial(SerialDevice {
+            _id: 0,
+            default_console: false,
+            driver: SerialDeviceDriver::Ns16550(Ns16550 {
+                region: DriverRegion { addr, size: 0x100 },
+                irq: 0,
+            }),
+        });
+    }
+    if serial_subsystem.default_console_index() != 0
+        || serial_subsystem.get_serial(1).unwrap().default_console
+    {
+        test_failed!("The first device added should be the only default console");
+        return 1;
+    }
+    if serial_subsystem.set_default_console(SERIAL_MAX_SIZE) {
+        test_failed!("A missing device should not become the default console");
+        return 1;
+    }
+    if !serial_subsystem.set_default_console(1) {
+        test_failed!("The device at index 1 should become the default console");
+        return 1;
+    }
+    let default_console = unsafe { serial_subsystem.get_default_console() };
+    if default_console.region().addr != 0x10000100 {
+        test_failed!("The default console should be the device at index 1");
+        return 1;
+    }
+    if serial_subsystem.get_serial(0).unwrap().default_console {
+        test_failed!("The previous default console should not be flagged anymore");
+        return 1;
+    }
+    if serial_subsystem.remove_serial(1).is_some() || serial_subsystem.remove_serial(0).is_none() {
+        test_failed!("Only the new default console should be protected from removal");
+        return 1;
+    }
+    0
+}
+
 pub fn serial_subsystem_test_suite() {
     const SERIAL_SUBSYSTEM_TEST_SUITE: TestSuite = TestSuite {
         tests: &[
@@ -227,6 +312,16 @@ pub fn serial_subsystem_test_suite() {
                 test_serial_subsystem_overflow,
                 TestBehavior::Default,
             ),
+            TestCase::init(
+                "Serial sub-system find device",
+                test_serial_subsystem_find,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Serial sub-system default console",
+                test_serial_subsystem_default_console,
+                TestBehavior::Default,
+            ),
         ],
         name: "Serial sub-system",
         behavior: TestSuiteBehavior::Default,
@@ -0,0 +1,174 @@
+use crate::{
+    drivers::{
+        DriverRegion,
+        bus::BusLock,
+        spi::{
+            SpiBus, SpiChip, SpiDevice, SpiDeviceDriver, SpiError, SpiMode, SpiSubSystem,
+            sifive_spi::{SifiveSpi, sifive_spi_sckdiv},
+            spi_transfer,
+        },
+    },
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+// Fake device, the region is never accessed by the tests.
+fn test_spi_device(addr: usize) -> SpiDevice {
+    SpiDevice::new(SpiDeviceDriver::SifiveSpi(SifiveSpi::new(
+        DriverRegion { addr, size: 0x1000 },
+        0,
+        1,
+    )))
+}
+
+const TEST_CHIP: SpiChip = SpiChip {
+    cs: 0,
+    mode: SpiMode::Mode0,
+    freq_hz: 1_000_000,
+};
+
+pub fn test_spi_subsystem_add() -> u8 {
+    let spi_subsystem = SpiSubSystem::init();
+    if spi_subsystem.get_spi_array_size() != 0 {
+        test_failed!("SPI sub-system should be initialized empty.");
+        return 1;
+    }
+    if spi_subsystem.add_spi(test_spi_device(0)) != Some(0) {
+        test_failed!("SPI sub-system should add the device at index 0");
+        return 1;
+    }
+    if spi_subsystem.add_spi(test_spi_device(0)).is_some() {
+        test_failed!("SPI sub-system should refuse a duplicate device");
+        return 1;
+    }
+    0
+}
+
+pub fn test_bus_lock() -> u8 {
+    let lock = BusLock::init();
+    if !lock.try_lock() {
+        test_failed!("A free bus lock should be taken");
+        return 1;
+    }
+    if lock.try_lock() {
+        test_failed!("A taken bus lock should not be taken again");
+        return 1;
+    }
+    // Outside a task the owner would never release the lock.
+    if lock.lock() {
+        test_failed!("A taken bus lock should not be waited outside a task");
+        return 1;
+    }
+    lock.unlock();
+    if lock.is_locked() || !lock.lock() {
+        test_failed!("An unlocked bus lock should be taken again");
+        return 1;
+    }
+    0
+}
+
+pub fn test_spi_guard() -> u8 {
+    let spi_subsystem = SpiSubSystem::init();
+    spi_subsystem.add_spi(test_spi_device(0));
+    {
+        let guard = spi_subsystem.lock(0).unwrap();
+        if !guard.is_locked() {
+            test_failed!("SPI controller should be locked by the guard");
+            return 1;
+        }
+        if spi_subsystem.try_lock(0).err() != Some(SpiError::Busy) {
+            test_failed!("SPI controller locked should return Busy");
+            return 1;
+        }
+    }
+    if spi_subsystem.get_spi(0).unwrap().is_locked() {
+        test_failed!("SPI controller should be unlocked when the guard is dropped");
+        return 1;
+    }
+    if spi_subsystem.lock(1).err() != Some(SpiError::NoDevice) {
+        test_failed!("Locking a missing SPI controller should return NoDevice");
+        return 1;
+    }
+    0
+}
+
+pub fn test_spi_invalid_args() -> u8 {
+    let spi = test_spi_device(0);
+    // The arguments are checked before any access to the device.
+    let mut rx = [0u8; 2];
+    if spi.transfer(&TEST_CHIP, &[0u8; 3], &mut rx) != Err(SpiError::InvalidLength) {
+        test_failed!("Transfer with different tx and rx length should return InvalidLength");
+        return 1;
+    }
+    let chip = SpiChip { cs: 1, ..TEST_CHIP };
+    if spi.write(&chip, &[0u8; 2]) != Err(SpiError::InvalidChipSelect) {
+        test_failed!("Chip select out of the controller should return InvalidChipSelect");
+        return 1;
+    }
+    // QEMU virt has no SPI controller.
+    if spi_transfer(5, &TEST_CHIP, &[0u8; 2], &mut rx) != Err(SpiError::NoDevice) {
+        test_failed!("Transfer on a missing controller should return NoDevice");
+        return 1;
+    }
+    0
+}
+
+pub fn test_sifive_spi_sckdiv() -> u8 {
+    // SCK = clock / (2 * (div + 1))
+    if sifive_spi_sckdiv(100_000_000, 50_000_000) != 0 {
+        test_failed!("Half the input clock should use divider 0");
+        return 1;
+    }
+    if sifive_spi_sckdiv(100_000_000, 1_000_000) != 49 {
+        test_failed!("1MHz from 100MHz should use divider 49");
+        return 1;
+    }
+    // Rounded up, never above the wanted frequency
+    if sifive_spi_sckdiv(100_000_000, 3_000_000) != 16 {
+        test_failed!("3MHz from 100MHz should use divider 16");
+        return 1;
+    }
+    if sifive_spi_sckdiv(100_000_000, 1) != 0xfff || sifive_spi_sckdiv(100_000_000, 0) != 0xfff {
+        test_failed!("Too low frequency should use the max divider");
+        return 1;
+    }
+    if sifive_spi_sckdiv(100_000_000, 200_000_000) != 0 {
+        test_failed!("Too high frequency should use divider 0");
+        return 1;
+    }
+    0
+}
+
+pub fn spi_subsystem_test_suite() {
+    const SPI_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "SPI sub-system add and duplicate device",
+                test_spi_subsystem_add,
+                TestBehavior::Default,
+            ),
+            TestCase::init("Bus lock", test_bus_lock, TestBehavior::Default),
+            TestCase::init(
+                "SPI guard lock and unlock",
+                test_spi_guard,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "SPI invalid arguments",
+                test_spi_invalid_args,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "SiFive SPI clock divider",
+                test_sifive_spi_sckdiv,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "SPI sub-system",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&SPI_TEST_SUITE)
+    };
+}
@@ -2,7 +2,7 @@ use crate::{
     drivers::{
         DriverRegion,
         timer::{
-            TimerDevice, TimerDeviceDriver, TimerSubSystem, TimerType, clint0::Clint0,
+            TimerDevice, TimerDeviceDriver, TimerError, TimerSubSystem, TimerType, clint0::Clint0,
             init_timer_subsystem,
         },
     },
@@ -131,21 +131,17 @@ pub fn test_timer_subsystem_overflow() -> u8 {
     // Register all devices
     timer_subsystem.add_timer(device);
     timer_subsystem.add_timer(device1);
-    let timer_subsystem_snapshot = unsafe {
-        [
-            &*timer_subsystem.timer_pool[0].get(),
-            &*timer_subsystem.timer_pool[1].get(),
-        ]
-    };
+    let timer_subsystem_snapshot = [
+        timer_subsystem.timer_pool.get(0),
+        timer_subsystem.timer_pool.get(1),
+    ];
     // This one should trigger a warning and not be registered to the sub-system
     timer_subsystem.add_timer(device2);
     // Recreate a snapshot of subsystem
-    let timer_subsystem_snapshot_updated = unsafe {
-        [
-            &*timer_subsystem.timer_pool[0].get(),
-            &*timer_subsystem.timer_pool[1].get(),
-        ]
-    };
+    let timer_subsystem_snapshot_updated = [
+        timer_subsystem.timer_pool.get(0),
+        timer_subsystem.timer_pool.get(1),
+    ];
     // Check if the subsystem has changed after the overflow aborted
     if timer_subsystem_snapshot != timer_subsystem_snapshot_updated {
         panic!(
@@ -199,6 +195,58 @@ pub fn test_timer_subsystem_primary_timer() -> u8 {
     0
 }
 
+fn test_oneshot_handler(_hart: usize) {}
+
+pub fn test_timer_subsystem_oneshot_timer() -> u8 {
+    let timer_subsystem = TimerSubSystem::init();
+    if timer_subsystem.get_oneshot_timer().is_some() {
+        panic!("Timer sub-system without timer should not have a one-shot timer");
+    }
+    if timer_subsystem.set_oneshot_event(0, 10, test_oneshot_handler) != Err(TimerError::NoTimer) {
+        panic!("Arming a one-shot event without timer should return NoTimer");
+    }
+    const INT_EXT: [InterruptExtended; 4] = [InterruptExtended {
+        cpu_intc: 0,
+        irq_len: 2,
+        irq_ids: [3, 7, 0, 0],
+    }; 4];
+    const ARCH_DEVICE: TimerDevice = TimerDevice {
+        timer_type: TimerType::ArchitecturalTimer,
+        device: TimerDeviceDriver::Clint0(Clint0 {
+            region: DriverRegion {
+                addr: 0x2000000,
+                size: 0x10000,
+            },
+            interrupt_extended: INT_EXT,
+        }),
+    };
+    const SOC_DEVICE: TimerDevice = TimerDevice {
+        timer_type: TimerType::SoCTimer,
+        device: TimerDeviceDriver::Clint0(Clint0 {
+            region: DriverRegion {
+                addr: 0x2010000,
+                size: 0x10000,
+            },
+            interrupt_extended: INT_EXT,
+        }),
+    };
+    timer_subsystem.add_timer(SOC_DEVICE);
+    timer_subsystem.add_timer(ARCH_DEVICE);
+    timer_subsystem.select_primary_timer();
+    // Without SoC timer selected, the one-shot events use the primary timer
+    if timer_subsystem.get_oneshot_timer() != Some(&ARCH_DEVICE) {
+        panic!("The one-shot timer should be the primary timer without SoC timer");
+    }
+    timer_subsystem.select_oneshot_timer();
+    if timer_subsystem.get_oneshot_timer() != Some(&SOC_DEVICE) {
+        panic!("The one-shot timer should be the SoC timer registered");
+    }
+    if timer_subsystem.get_timer_array_size() != 0 {
+        panic!("Both timers should have been removed from the pool");
+    }
+    0
+}
+
 pub fn timer_subsystem_test_suite() {
     const TIMER_SUB_SYSTEM_TEST_SUITE: TestSuite = TestSuite {
         tests: &[
@@ -222,6 +270,11 @@ pub fn timer_subsystem_test_suite() {
                 test_timer_subsystem_primary_timer,
                 TestBehavior::Default,
             ),
+            TestCase::init(
+                "Timer sub-system check one-shot timer",
+                test_timer_subsystem_oneshot_timer,
+                TestBehavior::Default,
+            ),
         ],
         name: "Timer sub-system",
         behavior: TestSuiteBehavior::Default,
@@ -0,0 +1,180 @@
+use core::ptr;
+
+use crate::{
+    drivers::virtio::{
+        VirtioError,
+        queue::{VIRTQ_PAGE_SIZE, VIRTQ_SIZE, VirtqBuffer, Virtqueue},
+    },
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+pub fn test_virtqueue_chain() -> u8 {
+    let queue = Virtqueue::alloc().unwrap();
+    if !queue.desc_addr().is_multiple_of(VIRTQ_PAGE_SIZE) {
+        test_failed!("Virtqueue memory should be aligned on VIRTQ_PAGE_SIZE");
+        return 1;
+    }
+    let buffers = [
+        VirtqBuffer {
+            addr: 0x8000_1000,
+            len: 16,
+            device_writable: false,
+        },
+        VirtqBuffer {
+            addr: 0x8000_2000,
+            len: 1,
+            device_writable: true,
+        },
+    ];
+    let head = queue.add_chain(&buffers).unwrap();
+    if queue.num_free() != VIRTQ_SIZE - 2 {
+        test_failed!("2 descriptors should be used, free: {}", queue.num_free());
+        return 1;
+    }
+    let (addr, len, flags, next) = queue.read_desc(head);
+    if addr != 0x8000_1000 || len != 16 || flags != 1 {
+        test_failed!("Wrong head descriptor, flags should only be NEXT");
+        return 1;
+    }
+    let (addr, len, flags, _) = queue.read_desc(next);
+    if addr != 0x8000_2000 || len != 1 || flags != 2 {
+        test_failed!("Wrong last descriptor, flags should only be WRITE");
+        return 1;
+    }
+    // Available ring: idx then first entry
+    let avail_idx = unsafe { ptr::read_volatile((queue.avail_addr() + 2) as *const u16) };
+    let avail_head = unsafe { ptr::read_volatile((queue.avail_addr() + 4) as *const u16) };
+    if avail_idx != 1 || avail_head != head {
+        test_failed!("Available ring should contain the chain head");
+        return 1;
+    }
+    // Nothing used by the device yet
+    if queue.pop_used() != Ok(None) {
+        test_failed!("Used ring should be empty");
+        return 1;
+    }
+    // Fake device: write the used element and the used idx
+    unsafe {
+        ptr::write_volatile((queue.used_addr() + 4) as *mut u32, head as u32);
+        ptr::write_volatile((queue.used_addr() + 8) as *mut u32, 1);
+        ptr::write_volatile((queue.used_addr() + 2) as *mut u16, 1);
+    }
+    if queue.pop_used() != Ok(Some((head, 1))) {
+        test_failed!("Used ring should return the chain head and the written length");
+        return 1;
+    }
+    if queue.num_free() != VIRTQ_SIZE {
+        test_failed!(
+            "All descriptors should be freed, free: {}",
+            queue.num_free()
+        );
+        return 1;
+    }
+    0
+}
+
+pub fn test_virtqueue_full() -> u8 {
+    let queue = Virtqueue::alloc().unwrap();
+    let buffer = VirtqBuffer {
+        addr: 0x8000_1000,
+        len: 4,
+        device_writable: false,
+    };
+    for _ in 0..VIRTQ_SIZE {
+        let next = queue.next_head();
+        if next != Some(queue.add_chain(&[buffer]).unwrap()) {
+            test_failed!("next_head should return the head of the next chain added");
+            return 1;
+        }
+    }
+    if queue.next_head().is_some() {
+        test_failed!("next_head should return None on a full queue");
+        return 1;
+    }
+    if queue.add_chain(&[buffer]) != Err(VirtioError::QueueFull) {
+        test_failed!("Adding a chain in a full queue should return QueueFull");
+        return 1;
+    }
+    if queue.add_chain(&[]) != Err(VirtioError::QueueFull) {
+        test_failed!("Adding an empty chain should fail");
+        return 1;
+    }
+    0
+}
+
+pub fn test_virtqueue_invalid_used() -> u8 {
+    let queue = Virtqueue::alloc().unwrap();
+    let buffers = [
+        VirtqBuffer {
+            addr: 0x8000_1000,
+            len: 16,
+            device_writable: false,
+        },
+        VirtqBuffer {
+            addr: 0x8000_2000,
+            len: 1,
+            device_writable: true,
+        },
+    ];
+    let head = queue.add_chain(&buffers).unwrap();
+    let (_, _, _, next) = queue.read_desc(head);
+    // Fake device: a used element outside the descriptor table
+    unsafe {
+        ptr::write_volatile((queue.used_addr() + 4) as *mut u32, VIRTQ_SIZE as u32);
+        ptr::write_volatile((queue.used_addr() + 8) as *mut u32, 1);
+        ptr::write_volatile((queue.used_addr() + 2) as *mut u16, 1);
+    }
+    if queue.pop_used() != Err(VirtioError::InvalidUsedBuffer) {
+        test_failed!("A used element outside the descriptor table should be rejected");
+        return 1;
+    }
+    // Corrupted chain: the last descriptor point back to the head
+    unsafe {
+        let last = queue.desc_addr() + 16 * next as usize;
+        ptr::write_volatile((last + 12) as *mut u16, 1);
+        ptr::write_volatile((last + 14) as *mut u16, head);
+        ptr::write_volatile((queue.used_addr() + 12) as *mut u32, head as u32);
+        ptr::write_volatile((queue.used_addr() + 2) as *mut u16, 2);
+    }
+    if queue.pop_used() != Err(VirtioError::InvalidUsedBuffer) {
+        test_failed!("A chain without end should be rejected");
+        return 1;
+    }
+    if queue.num_free() != VIRTQ_SIZE - 2 {
+        test_failed!(
+            "The descriptors of an invalid used buffer should not be freed, free: {}",
+            queue.num_free()
+        );
+        return 1;
+    }
+    if queue.pop_used() != Ok(None) {
+        test_failed!("The invalid used elements should be consumed");
+        return 1;
+    }
+    0
+}
+
+pub fn virtqueue_test_suite() {
+    const VIRTQUEUE_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Virtqueue descriptor chain and used ring",
+                test_virtqueue_chain,
+                TestBehavior::Default,
+            ),
+            TestCase::init("Virtqueue full", test_virtqueue_full, TestBehavior::Default),
+            TestCase::init(
+                "Virtqueue invalid used buffer",
+                test_virtqueue_invalid_used,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Virtqueue",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&VIRTQUEUE_TEST_SUITE)
+    };
+}
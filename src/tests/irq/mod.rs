@@ -0,0 +1,207 @@
+use crate::{
+    config::IRQ_MAX_SIZE,
+    irq::{IRQ_MAX_PRIORITY, IRQ_TIMER, IrqError, IrqSubSystem, irq_ext},
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+// Order of the handlers calls, written by the handlers.
+static mut CALL_ORDER: [usize; 2] = [0; 2];
+static mut CALL_COUNT: usize = 0;
+static mut BOTTOM_HALF_COUNT: usize = 0;
+
+fn test_handler(_irq: u32, _ctx: usize) {}
+
+fn test_order_handler(_irq: u32, ctx: usize) {
+    unsafe {
+        if CALL_COUNT < 2 {
+            CALL_ORDER[CALL_COUNT] = ctx;
+        }
+        CALL_COUNT += 1;
+    }
+}
+
+fn test_bottom_half(_irq: u32, _ctx: usize) {
+    unsafe { BOTTOM_HALF_COUNT += 1 };
+}
+
+pub fn test_irq_subsystem_register() -> u8 {
+    let irq_subsystem = IrqSubSystem::init();
+    if irq_subsystem.get_irq_array_size() != 0 {
+        test_failed!("Irq sub-system should be initialized empty.");
+        return 1;
+    }
+    let id = irq_subsystem
+        .register(irq_ext(10), 1, test_handler, 0, None)
+        .unwrap();
+    if irq_subsystem.get_irq_array_size() != 1 {
+        test_failed!(
+            "Irq sub-system should contain 1 handler, got: {}",
+            irq_subsystem.get_irq_array_size()
+        );
+        return 1;
+    }
+    // Same handler with another context, on a shared irq
+    irq_subsystem
+        .register(irq_ext(10), 2, test_handler, 1, None)
+        .unwrap();
+    if irq_subsystem.irq_priority(irq_ext(10)) != Some(2) {
+        test_failed!("The irq priority should be the highest handler priority");
+        return 1;
+    }
+    if irq_subsystem.register(irq_ext(10), 1, test_handler, 0, None)
+        != Err(IrqError::AlreadyRegistered)
+    {
+        test_failed!("Registering twice the same handler should return AlreadyRegistered");
+        return 1;
+    }
+    if irq_subsystem.unregister(id) != Ok(irq_ext(10)) {
+        test_failed!("Failed to unregister the handler on irq {}", irq_ext(10));
+        return 1;
+    }
+    if irq_subsystem.unregister(id) != Err(IrqError::NotRegistered) {
+        test_failed!("Unregistering a missing handler should return NotRegistered");
+        return 1;
+    }
+    0
+}
+
+pub fn test_irq_subsystem_invalid() -> u8 {
+    let irq_subsystem = IrqSubSystem::init();
+    if irq_subsystem.register(0, 1, test_handler, 0, None) != Err(IrqError::InvalidIrq) {
+        test_failed!("Irq 0 doesn't exist, registration should return InvalidIrq");
+        return 1;
+    }
+    // Only the software, timer and external interrupts are handled
+    if irq_subsystem.register(5, 1, test_handler, 0, None) != Err(IrqError::InvalidIrq) {
+        test_failed!(
+            "Irq 5 isn't a handled local interrupt, registration should return InvalidIrq"
+        );
+        return 1;
+    }
+    if irq_subsystem.register(irq_ext(0), 1, test_handler, 0, None) != Err(IrqError::InvalidIrq) {
+        test_failed!("External source 0 doesn't exist, registration should return InvalidIrq");
+        return 1;
+    }
+    if irq_subsystem.register(IRQ_TIMER, 0, test_handler, 0, None) != Err(IrqError::InvalidPriority)
+    {
+        test_failed!("Priority 0 should return InvalidPriority");
+        return 1;
+    }
+    if irq_subsystem.register(IRQ_TIMER, IRQ_MAX_PRIORITY + 1, test_handler, 0, None)
+        != Err(IrqError::InvalidPriority)
+    {
+        test_failed!("Priority above the max should return InvalidPriority");
+        return 1;
+    }
+    0
+}
+
+pub fn test_irq_subsystem_overflow() -> u8 {
+    let irq_subsystem = IrqSubSystem::init();
+    for source in 1..=IRQ_MAX_SIZE as u32 {
+        irq_subsystem
+            .register(irq_ext(source), 1, test_handler, 0, None)
+            .unwrap();
+    }
+    let overflow_irq = irq_ext(IRQ_MAX_SIZE as u32 + 1);
+    if irq_subsystem.register(overflow_irq, 1, test_handler, 0, None) != Err(IrqError::TableFull) {
+        test_failed!("Registering in a full handler table should return TableFull");
+        return 1;
+    }
+    0
+}
+
+pub fn test_irq_subsystem_dispatch() -> u8 {
+    let irq_subsystem = IrqSubSystem::init();
+    // Register the lowest priority first, the highest must still be called first.
+    irq_subsystem
+        .register(IRQ_TIMER, 1, test_order_handler, 1, None)
+        .unwrap();
+    irq_subsystem
+        .register(IRQ_TIMER, 5, test_order_handler, 5, None)
+        .unwrap();
+    if !irq_subsystem.handle(IRQ_TIMER) {
+        test_failed!("Irq {} should be handled", IRQ_TIMER);
+        return 1;
+    }
+    let (count, order) = unsafe { (CALL_COUNT, CALL_ORDER) };
+    if count != 2 || order != [5, 1] {
+        test_failed!(
+            "Handlers should be called by priority, got count: {}, order: {:?}",
+            count,
+            order
+        );
+        return 1;
+    }
+    if irq_subsystem.handle(irq_ext(3)) {
+        test_failed!("Irq without handler should not be handled");
+        return 1;
+    }
+    0
+}
+
+pub fn test_irq_subsystem_bottom_half() -> u8 {
+    let irq_subsystem = IrqSubSystem::init();
+    irq_subsystem
+        .register(irq_ext(5), 1, test_handler, 0, Some(test_bottom_half))
+        .unwrap();
+    if !irq_subsystem.has_bottom_half() {
+        test_failed!("Irq sub-system should contain a bottom half");
+        return 1;
+    }
+    if irq_subsystem.run_bottom_halves() != 0 {
+        test_failed!("Bottom half should not run before the handler");
+        return 1;
+    }
+    // Multiple interrupts before the bottom half run, the bottom half run once.
+    irq_subsystem.handle(irq_ext(5));
+    irq_subsystem.handle(irq_ext(5));
+    if irq_subsystem.run_bottom_halves() != 1 || unsafe { BOTTOM_HALF_COUNT } != 1 {
+        test_failed!("Pending bottom half should run once");
+        return 1;
+    }
+    if irq_subsystem.run_bottom_halves() != 0 {
+        test_failed!("Bottom half should not run again without interrupt");
+        return 1;
+    }
+    0
+}
+
+pub fn irq_test_suite() {
+    const IRQ_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Irq sub-system register handler",
+                test_irq_subsystem_register,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Irq sub-system invalid registration",
+                test_irq_subsystem_invalid,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Irq sub-system handling overflow",
+                test_irq_subsystem_overflow,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Irq sub-system dispatch by priority",
+                test_irq_subsystem_dispatch,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Irq sub-system bottom half",
+                test_irq_subsystem_bottom_half,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Irq sub-system",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&IRQ_TEST_SUITE)
+    };
+}
@@ -0,0 +1,118 @@
+use crate::{
+    ktime::calendar::{DateTime, days_in_month, is_leap_year},
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+pub fn test_calendar_from_unix() -> u8 {
+    let epoch = DateTime::from_unix(0);
+    let expected = DateTime {
+        year: 1970,
+        month: 1,
+        day: 1,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        weekday: 4,
+    };
+    if epoch != expected {
+        test_failed!(
+            "Epoch should be Thursday 1970-01-01T00:00:00Z, got: {}",
+            epoch
+        );
+        return 1;
+    }
+    // Leap day
+    let leap = DateTime::from_unix(1_709_213_820);
+    if leap.year != 2024 || leap.month != 2 || leap.day != 29 || leap.weekday != 4 {
+        test_failed!("1709213820 should be Thursday 2024-02-29, got: {}", leap);
+        return 1;
+    }
+    if leap.hour != 13 || leap.minute != 37 || leap.second != 0 {
+        test_failed!("1709213820 should be 13:37:00, got: {}", leap);
+        return 1;
+    }
+    // Last second of a year
+    let end = DateTime::from_unix(946_684_799);
+    if end.year != 1999 || end.month != 12 || end.day != 31 || end.hour != 23 || end.second != 59 {
+        test_failed!("946684799 should be 1999-12-31T23:59:59Z, got: {}", end);
+        return 1;
+    }
+    0
+}
+
+pub fn test_calendar_round_trip() -> u8 {
+    // Around the 2000 and 2100 century days, and past 2038
+    let times: [u64; 6] = [
+        951_782_400,
+        951_868_800,
+        4_107_542_400,
+        4_107_628_800,
+        2_147_483_648,
+        1_760_875_199,
+    ];
+    for time in times {
+        let date = DateTime::from_unix(time);
+        if date.to_unix() != Some(time) {
+            test_failed!("Round trip failed for {}, date: {}", time, date);
+            return 1;
+        }
+    }
+    // 2100 is not a leap year, 2000 is
+    if is_leap_year(2100) || !is_leap_year(2000) || days_in_month(2100, 2) != 28 {
+        test_failed!("Wrong leap year rule");
+        return 1;
+    }
+    0
+}
+
+pub fn test_calendar_invalid() -> u8 {
+    let mut date = DateTime::from_unix(0);
+    date.year = 1969;
+    if date.to_unix().is_some() {
+        test_failed!("A date before the epoch should return None");
+        return 1;
+    }
+    date.year = 2023;
+    date.month = 2;
+    date.day = 29;
+    if date.to_unix().is_some() {
+        test_failed!("2023-02-29 should return None");
+        return 1;
+    }
+    date.month = 13;
+    date.day = 1;
+    if date.to_unix().is_some() {
+        test_failed!("Month 13 should return None");
+        return 1;
+    }
+    0
+}
+
+pub fn calendar_test_suite() {
+    const CALENDAR_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Calendar from Unix time",
+                test_calendar_from_unix,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Calendar round trip",
+                test_calendar_round_trip,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Calendar invalid date",
+                test_calendar_invalid,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Calendar",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&CALENDAR_TEST_SUITE)
+    };
+}
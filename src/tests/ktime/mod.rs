@@ -1,3 +1,5 @@
+pub mod calendar;
+
 use crate::tests::{TEST_MANAGER, TestBehavior, TestSuite, TestSuiteBehavior};
 
 use super::TestCase;
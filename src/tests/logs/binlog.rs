@@ -0,0 +1,168 @@
+use crate::{
+    config::LOG_LINE_MAX_SIZE,
+    logs::{
+        LogLevel,
+        binlog::{
+            BINLOG_ARG_STR, BINLOG_ARG_TEXT, BINLOG_FRAME_START, BinlogFrame, binlog_fmt_args,
+            binlog_fmt_debug,
+        },
+        buffer::{LogBuffer, LogLine},
+    },
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+pub fn test_binlog_fmt_args() -> u8 {
+    if binlog_fmt_args("") != Some(0)
+        || binlog_fmt_args("irq {} source {:#x}: {:?}") != Some(3)
+        || binlog_fmt_args("{{escaped}} {}") != Some(1)
+    {
+        test_failed!("The arguments of the format string should be counted, without escapes");
+        return 1;
+    }
+    if binlog_fmt_args("task {name}").is_some()
+        || binlog_fmt_args("{0} {0}").is_some()
+        || binlog_fmt_args("unclosed {").is_some()
+        || binlog_fmt_args("single }").is_some()
+    {
+        test_failed!("Named, positional arguments and single braces should be refused");
+        return 1;
+    }
+    0
+}
+
+pub fn test_binlog_fmt_debug() -> u8 {
+    let fmt = "{{?}} {} {:?} {:#x} {:#?}";
+    if binlog_fmt_debug(fmt, 0) || !binlog_fmt_debug(fmt, 1) || binlog_fmt_debug(fmt, 2) {
+        test_failed!("Only the {{:?}} placeholders should be formatted with Debug");
+        return 1;
+    }
+    if !binlog_fmt_debug(fmt, 3) {
+        test_failed!("The alternate Debug placeholder should be formatted with Debug");
+        return 1;
+    }
+    0
+}
+
+pub fn test_binlog_frame() -> u8 {
+    let mut frame = BinlogFrame::init(LogLevel::Warn as u8, 300, None);
+    frame.push_unsigned(5);
+    frame.push_signed(-2);
+    frame.push_str(BINLOG_ARG_STR, "ok");
+    let line = frame.finish();
+    // Start, length, level, id 300 in LEB128, then the tagged arguments, -2 is zigzag encoded.
+    let expected = [
+        BINLOG_FRAME_START,
+        11,
+        2,
+        0xac,
+        0x02,
+        0,
+        5,
+        1,
+        3,
+        BINLOG_ARG_STR,
+        2,
+        b'o',
+        b'k',
+    ];
+    if !line.is_binary() || line.as_bytes() != expected {
+        test_failed!("Unexpected binary log frame: {:?}", line.as_bytes());
+        return 1;
+    }
+    let line = BinlogFrame::init(LogLevel::Info as u8, 0, Some(1)).finish();
+    if line.as_bytes() != [BINLOG_FRAME_START, 3, 0x81, 0, 1] {
+        test_failed!(
+            "The wall clock time should follow the id, flagged in the level: {:?}",
+            line.as_bytes()
+        );
+        return 1;
+    }
+    0
+}
+
+pub fn test_binlog_args() -> u8 {
+    let mut frame = BinlogFrame::init(LogLevel::Debug as u8, 0, None);
+    let frame_ref = &mut frame;
+    // Raw integer and string, formatted Debug for the types without raw encoding.
+    crate::binlog_args!(frame_ref, "{} {} {:?}", 0; 7u8, "pid", LogLevel::Warn);
+    let line = frame.finish();
+    let args = &line.as_bytes()[4..];
+    if args[..2] != [0, 7] || args[2..7] != [BINLOG_ARG_STR, 3, b'p', b'i', b'd'] {
+        test_failed!("Integers and strings should be encoded raw: {:?}", args);
+        return 1;
+    }
+    if args[7..] != [BINLOG_ARG_TEXT, 4, b'W', b'a', b'r', b'n'] {
+        test_failed!(
+            "The other types should be formatted by the kernel: {:?}",
+            args
+        );
+        return 1;
+    }
+    0
+}
+
+pub fn test_binlog_frame_full() -> u8 {
+    let long = [b'a'; LOG_LINE_MAX_SIZE];
+    let long = core::str::from_utf8(&long).unwrap_or("");
+    let mut frame = BinlogFrame::init(LogLevel::Debug as u8, 0, None);
+    frame.push_str(BINLOG_ARG_STR, long);
+    frame.push_unsigned(1);
+    let line = frame.finish();
+    let bytes = line.as_bytes();
+    if bytes.len() > LOG_LINE_MAX_SIZE || bytes[1] as usize != bytes.len() - 2 {
+        test_failed!("A full frame should fit in a log line, with its length");
+        return 1;
+    }
+    // The string is truncated to the free space, the next argument is dropped.
+    if bytes[bytes.len() - 1] != b'a' {
+        test_failed!("The argument after a full frame should be dropped");
+        return 1;
+    }
+    // A binary line keeps its type in the log ring buffer.
+    let buffer: LogBuffer<256> = LogBuffer::init();
+    let mut read = LogLine::init();
+    if !buffer.push(3, &line) || buffer.pop(&mut read) != Some(3) || !read.is_binary() {
+        test_failed!("A binary line should be read back as binary, with its console");
+        return 1;
+    }
+    if read.as_bytes() != bytes {
+        test_failed!("A binary line should be read back unchanged");
+        return 1;
+    }
+    0
+}
+
+pub fn binlog_test_suite() {
+    const BINLOG_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Binary log format string arguments",
+                test_binlog_fmt_args,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Binary log Debug placeholders",
+                test_binlog_fmt_debug,
+                TestBehavior::Default,
+            ),
+            TestCase::init("Binary log frame", test_binlog_frame, TestBehavior::Default),
+            TestCase::init(
+                "Binary log arguments encoding",
+                test_binlog_args,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Binary log full frame",
+                test_binlog_frame_full,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Binary logs",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&BINLOG_TEST_SUITE)
+    };
+}
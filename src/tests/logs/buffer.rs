@@ -0,0 +1,144 @@
+use core::fmt::Write;
+
+use crate::{
+    config::LOG_LINE_MAX_SIZE,
+    logs::buffer::{LogBuffer, LogLine},
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+fn test_log_line(s: &str) -> LogLine {
+    let mut line = LogLine::init();
+    let _ = line.write_str(s);
+    line
+}
+
+pub fn test_log_buffer_push_pop() -> u8 {
+    let buffer: LogBuffer<64> = LogBuffer::init();
+    let mut line = LogLine::init();
+    if buffer.pop(&mut line).is_some() || buffer.used() != 0 {
+        test_failed!("A new log buffer should be empty");
+        return 1;
+    }
+    if !buffer.push(0, &test_log_line("first\n")) || !buffer.push(1, &test_log_line("second\n")) {
+        test_failed!("The lines should fit in the log buffer");
+        return 1;
+    }
+    if buffer.pop(&mut line) != Some(0) || line.as_str() != "first\n" {
+        test_failed!("The oldest line should be read first, with its console");
+        return 1;
+    }
+    if buffer.pop(&mut line) != Some(1) || line.as_str() != "second\n" {
+        test_failed!("The second line should be read on the console 1");
+        return 1;
+    }
+    if buffer.pop(&mut line).is_some() || buffer.used() != 0 {
+        test_failed!("The log buffer should be empty once all the lines are read");
+        return 1;
+    }
+    0
+}
+
+pub fn test_log_buffer_wrap() -> u8 {
+    let buffer: LogBuffer<16> = LogBuffer::init();
+    let mut line = LogLine::init();
+    // 12 bytes records, the second record of each round wraps around the end.
+    for round in 0..8 {
+        if !buffer.push(round, &test_log_line("abcdefghij")) {
+            test_failed!(
+                "The log buffer should have space after a read, round: {}",
+                round
+            );
+            return 1;
+        }
+        if buffer.pop(&mut line) != Some(round) || line.as_str() != "abcdefghij" {
+            test_failed!(
+                "A record wrapping around the end should be read back, round: {}",
+                round
+            );
+            return 1;
+        }
+    }
+    if buffer.dropped() != 0 {
+        test_failed!("No record should be dropped");
+        return 1;
+    }
+    0
+}
+
+pub fn test_log_buffer_dropped() -> u8 {
+    let buffer: LogBuffer<16> = LogBuffer::init();
+    let mut line = LogLine::init();
+    if !buffer.push(0, &test_log_line("abcdefghij")) || buffer.push(0, &test_log_line("klmnop")) {
+        test_failed!("The second line should not fit in the log buffer");
+        return 1;
+    }
+    if buffer.dropped() != 1 {
+        test_failed!(
+            "The dropped line should be counted, got: {}",
+            buffer.dropped()
+        );
+        return 1;
+    }
+    // The line written before the buffer was full is kept, the dropped one is not partly written.
+    if buffer.pop(&mut line) != Some(0)
+        || line.as_str() != "abcdefghij"
+        || buffer.pop(&mut line).is_some()
+    {
+        test_failed!("Only the line pushed before the buffer was full should be read");
+        return 1;
+    }
+    0
+}
+
+pub fn test_log_line_truncated() -> u8 {
+    let mut line = LogLine::init();
+    for _ in 0..LOG_LINE_MAX_SIZE {
+        let _ = line.write_str("é");
+    }
+    if line.len() > LOG_LINE_MAX_SIZE || line.len() < LOG_LINE_MAX_SIZE - 1 {
+        test_failed!(
+            "A long line should be truncated to LOG_LINE_MAX_SIZE, got: {}",
+            line.len()
+        );
+        return 1;
+    }
+    if line.as_str().chars().any(|c| c != 'é') {
+        test_failed!("A line should be truncated on a char boundary");
+        return 1;
+    }
+    0
+}
+
+pub fn log_buffer_test_suite() {
+    const LOG_BUFFER_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Log buffer push and pop",
+                test_log_buffer_push_pop,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Log buffer wrap around",
+                test_log_buffer_wrap,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Log buffer dropped lines",
+                test_log_buffer_dropped,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Log line truncated",
+                test_log_line_truncated,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Log ring buffer",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&LOG_BUFFER_TEST_SUITE)
+    };
+}
@@ -0,0 +1,212 @@
+use crate::{
+    config::DMA_MIN_ALIGN,
+    mem::dma::{
+        DmaCacheOps, DmaDirection, DmaError, DmaOwner, dma_alloc, dma_free, dma_get, dma_owner,
+        dma_region, dma_set_cache_ops, dma_share, dma_to_cpu, dma_to_device, dma_unshare,
+    },
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+// Address of the last range given to each cache hook
+static mut CLEANED: usize = 0;
+static mut INVALIDATED: usize = 0;
+
+fn test_clean(addr: usize, _size: usize) {
+    unsafe { CLEANED = addr };
+}
+
+fn test_invalidate(addr: usize, _size: usize) {
+    unsafe { INVALIDATED = addr };
+}
+
+pub fn test_dma_alloc_align() -> u8 {
+    if dma_alloc(64, 48, DmaDirection::ToDevice) != Err(DmaError::InvalidAlignment) {
+        test_failed!("DMA allocation with an alignment not power of two should fail");
+        return 1;
+    }
+    if dma_alloc(usize::MAX - 8, 16, DmaDirection::ToDevice) != Err(DmaError::OutOfMemory) {
+        test_failed!("DMA allocation with a size overflowing the alignment should fail");
+        return 1;
+    }
+    let id = dma_alloc(100, 256, DmaDirection::ToDevice).unwrap();
+    let region = dma_region(id).unwrap();
+    if !region.addr.is_multiple_of(256) || region.size != 100 {
+        test_failed!("DMA buffer should have the asked alignment and size");
+        return 1;
+    }
+    let buffer = unsafe { dma_get(id) }.unwrap();
+    if buffer.len() != 100 || buffer.iter().any(|b| *b != 0) {
+        test_failed!("DMA buffer should be zeroed");
+        return 1;
+    }
+    // The alignment is at least the cache line size
+    let small = dma_alloc(8, 1, DmaDirection::ToDevice).unwrap();
+    if !dma_region(small)
+        .unwrap()
+        .addr
+        .is_multiple_of(DMA_MIN_ALIGN)
+    {
+        test_failed!("DMA buffer should be aligned on DMA_MIN_ALIGN");
+        return 1;
+    }
+    dma_free(id).unwrap();
+    dma_free(small).unwrap();
+    0
+}
+
+pub fn test_dma_ownership() -> u8 {
+    dma_set_cache_ops(DmaCacheOps {
+        clean: test_clean,
+        invalidate: test_invalidate,
+    });
+    let id = dma_alloc(32, 16, DmaDirection::Bidirectional).unwrap();
+    unsafe { dma_get(id) }.unwrap()[0] = 0xAA;
+    let region = dma_to_device(id).unwrap();
+    if unsafe { CLEANED } != region.addr || dma_owner(id) != Ok(DmaOwner::Device) {
+        test_failed!("Giving a DMA buffer to the device should clean the cache");
+        return 1;
+    }
+    if unsafe { dma_get(id) } != Err(DmaError::DeviceOwned)
+        || dma_free(id) != Err(DmaError::DeviceOwned)
+    {
+        test_failed!("The CPU should not access a DMA buffer owned by the device");
+        return 1;
+    }
+    if dma_to_device(id) != Err(DmaError::DeviceOwned) {
+        test_failed!("A DMA buffer owned by the device should not be given again");
+        return 1;
+    }
+    dma_to_cpu(id).unwrap();
+    if unsafe { INVALIDATED } != region.addr || unsafe { dma_get(id) }.unwrap()[0] != 0xAA {
+        test_failed!("Taking a DMA buffer back should invalidate the cache");
+        return 1;
+    }
+    if dma_to_cpu(id) != Err(DmaError::CpuOwned) {
+        test_failed!("A DMA buffer owned by the CPU should not be taken back");
+        return 1;
+    }
+    dma_set_cache_ops(DmaCacheOps::coherent());
+    dma_free(id).unwrap();
+    0
+}
+
+pub fn test_dma_shared() -> u8 {
+    let id = dma_alloc(32, 16, DmaDirection::ToDevice).unwrap();
+    let region = dma_share(id).unwrap();
+    if dma_owner(id) != Ok(DmaOwner::Shared) || region != dma_region(id).unwrap() {
+        test_failed!("Sharing a DMA buffer should give its region to the device");
+        return 1;
+    }
+    // The CPU keeps drawing in a shared buffer, like a framebuffer
+    match unsafe { dma_get(id) } {
+        Ok(buffer) => buffer[0] = 0xAA,
+        Err(_) => {
+            test_failed!("The CPU should access a DMA buffer shared with the device");
+            return 1;
+        }
+    }
+    if dma_to_device(id) != Err(DmaError::Shared)
+        || dma_to_cpu(id) != Err(DmaError::Shared)
+        || dma_free(id) != Err(DmaError::Shared)
+    {
+        test_failed!("A shared DMA buffer should not change owner or be freed");
+        return 1;
+    }
+    dma_unshare(id).unwrap();
+    if dma_owner(id) != Ok(DmaOwner::Cpu) || dma_unshare(id) != Err(DmaError::CpuOwned) {
+        test_failed!("Taking a shared DMA buffer back should give it to the CPU");
+        return 1;
+    }
+    dma_free(id).unwrap();
+    0
+}
+
+pub fn test_dma_direction() -> u8 {
+    dma_set_cache_ops(DmaCacheOps {
+        clean: test_clean,
+        invalidate: test_invalidate,
+    });
+    unsafe {
+        CLEANED = 0;
+        INVALIDATED = 0;
+    }
+    // The device only writes the buffer, nothing to clean
+    let rx = dma_alloc(32, 16, DmaDirection::FromDevice).unwrap();
+    dma_to_device(rx).unwrap();
+    dma_to_cpu(rx).unwrap();
+    if unsafe { CLEANED } != 0 || unsafe { INVALIDATED } != dma_region(rx).unwrap().addr {
+        test_failed!("A DMA buffer from the device should only be invalidated");
+        return 1;
+    }
+    unsafe { INVALIDATED = 0 };
+    // The device only reads the buffer, nothing to invalidate
+    let tx = dma_alloc(32, 16, DmaDirection::ToDevice).unwrap();
+    dma_to_device(tx).unwrap();
+    dma_to_cpu(tx).unwrap();
+    if unsafe { INVALIDATED } != 0 || unsafe { CLEANED } != dma_region(tx).unwrap().addr {
+        test_failed!("A DMA buffer to the device should only be cleaned");
+        return 1;
+    }
+    dma_set_cache_ops(DmaCacheOps::coherent());
+    dma_free(rx).unwrap();
+    dma_free(tx).unwrap();
+    0
+}
+
+pub fn test_dma_reuse() -> u8 {
+    let id = dma_alloc(128, 64, DmaDirection::ToDevice).unwrap();
+    let addr = dma_region(id).unwrap().addr;
+    unsafe { dma_get(id) }.unwrap()[0] = 0xAA;
+    dma_free(id).unwrap();
+    if dma_region(id) != Err(DmaError::InvalidId) {
+        test_failed!("A freed DMA buffer should not be accessed");
+        return 1;
+    }
+    // Smaller buffer with the same alignment re-use the freed region, zeroed
+    let new_id = dma_alloc(64, 64, DmaDirection::ToDevice).unwrap();
+    if dma_region(new_id).unwrap().addr != addr || unsafe { dma_get(new_id) }.unwrap()[0] != 0 {
+        test_failed!("A freed DMA buffer should be re-used and zeroed");
+        return 1;
+    }
+    dma_free(new_id).unwrap();
+    0
+}
+
+pub fn dma_buffer_test_suite() {
+    const DMA_BUFFER_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "DMA buffer allocation and alignment",
+                test_dma_alloc_align,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "DMA buffer CPU and device ownership",
+                test_dma_ownership,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "DMA buffer shared with the device",
+                test_dma_shared,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "DMA buffer cache hooks direction",
+                test_dma_direction,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "DMA buffer free and re-use",
+                test_dma_reuse,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "DMA buffer",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&DMA_BUFFER_TEST_SUITE)
+    };
+}
@@ -5,6 +5,9 @@ use crate::{
 
 use super::{TestCase, TestSuite};
 
+pub mod dma;
+pub mod shared;
+
 pub fn test_memory_impl() -> u8 {
     memory_init();
     let reg = mem_reg_info();
@@ -0,0 +1,94 @@
+use core::ptr;
+
+use crate::{
+    mem::shared::{
+        SharedBufferError, shared_buffer_alloc, shared_buffer_get, shared_buffer_lend,
+        shared_buffer_owner, shared_buffer_return, shared_buffer_transfer,
+    },
+    task::{TASK_HANDLER, TASK_STACK_MIN_SIZE, list::task_list_get_task_by_pid, task_create},
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+/// This function is only used to create task for testing purpose.
+/// This must never be used in other cases
+fn task_fn_ptr() -> ! {
+    #[allow(clippy::empty_loop)]
+    loop {}
+}
+
+/// Set the given task as the current task.
+fn set_current_task(pid: u16) {
+    let task = task_list_get_task_by_pid(pid).unwrap();
+    unsafe { TASK_HANDLER = task };
+}
+
+pub fn test_shared_buffer_ownership() -> u8 {
+    // Shared buffers can only be used from a task
+    if shared_buffer_alloc(64) != Err(SharedBufferError::NoCurrentTask) {
+        panic!("Shared buffer allocation outside of a task should fail.");
+    }
+    let owner = task_create("Buffer owner", task_fn_ptr, 1, TASK_STACK_MIN_SIZE).unwrap();
+    let receiver = task_create("Buffer receiver", task_fn_ptr, 1, TASK_STACK_MIN_SIZE).unwrap();
+    set_current_task(owner);
+    let id = shared_buffer_alloc(64).unwrap();
+    let buffer = unsafe { shared_buffer_get(id) }.unwrap();
+    if buffer.len() != 64 || buffer.iter().any(|b| *b != 0) {
+        panic!("Shared buffer should have the asked size and be zeroed.");
+    }
+    buffer[0] = 0xAA;
+    // Transfer the buffer to the receiver
+    shared_buffer_transfer(id, receiver).unwrap();
+    if shared_buffer_owner(id) != Ok(receiver) {
+        panic!("Shared buffer owner should be updated after a transfer.");
+    }
+    if unsafe { shared_buffer_get(id) } != Err(SharedBufferError::NotOwner) {
+        panic!("Previous owner should not access the buffer after a transfer.");
+    }
+    if shared_buffer_transfer(id, owner) != Err(SharedBufferError::NotOwner) {
+        panic!("Previous owner should not transfer the buffer after a transfer.");
+    }
+    // Access from the new owner, the content is not copied
+    set_current_task(receiver);
+    let buffer = unsafe { shared_buffer_get(id) }.unwrap();
+    if buffer[0] != 0xAA {
+        panic!("Shared buffer content should be kept after a transfer.");
+    }
+    // Lend the buffer to the previous owner
+    shared_buffer_lend(id, owner).unwrap();
+    if unsafe { shared_buffer_get(id) } != Err(SharedBufferError::Lent) {
+        panic!("Owner should not access a lent buffer.");
+    }
+    set_current_task(owner);
+    if unsafe { shared_buffer_get(id) }.is_err() {
+        panic!("Borrower should access a lent buffer.");
+    }
+    if shared_buffer_transfer(id, owner) != Err(SharedBufferError::NotOwner) {
+        panic!("Borrower should not transfer a lent buffer.");
+    }
+    shared_buffer_return(id).unwrap();
+    if unsafe { shared_buffer_get(id) } != Err(SharedBufferError::NotOwner) {
+        panic!("Borrower should not access the buffer once returned.");
+    }
+    set_current_task(receiver);
+    if unsafe { shared_buffer_get(id) }.is_err() {
+        panic!("Owner should access the buffer once returned.");
+    }
+    unsafe { TASK_HANDLER = ptr::null_mut() };
+    0
+}
+
+pub fn shared_buffer_test_suite() {
+    const SHARED_BUFFER_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[TestCase::init(
+            "Shared buffer ownership transfer and lend",
+            test_shared_buffer_ownership,
+            TestBehavior::Default,
+        )],
+        name: "Shared buffer",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&SHARED_BUFFER_TEST_SUITE)
+    };
+}
@@ -3,22 +3,34 @@
 #![allow(clippy::unwrap_used)]
 #![allow(clippy::expect_used)]
 
-use core::ptr;
-
 mod arch;
+mod cmdline;
 mod drivers;
+mod irq;
 mod ktime;
+mod logs;
 mod mem;
 mod platform;
 mod primitives;
 mod scheduler;
 mod suites;
 mod task;
+mod watchdog;
 
 use platform::test_platform_init;
 use suites::test_suites;
 
-use crate::{info::KERNEL_VERSION, kprint, kprint_fmt};
+use crate::{
+    cmdline::{cmdline_init, kernel_params},
+    drivers::{
+        firmware::init_firmware_subsystem, power::init_power_subsystem,
+        serials::init_serial_subsystem,
+    },
+    info::KERNEL_VERSION,
+    kprint, kprint_fmt,
+    logs::buffer::log_flush,
+    power::{POWER_PANIC_EXIT_CODE, shutdown},
+};
 
 #[macro_export]
 macro_rules! test_kprint {
@@ -55,14 +67,18 @@ pub fn test_failed(s: core::fmt::Arguments) {
 
 #[panic_handler]
 pub fn test_panic(s: &core::panic::PanicInfo) -> ! {
+    log_flush();
     kprint_fmt!("\x1b[31;1m[KERNEL INTEGRITY FAILURE]\x1b[0m {:?}", s);
-    loop {}
+    shutdown(POWER_PANIC_EXIT_CODE)
 }
 
+// Max number of test suites in the test manager, raise it when adding a test suite over it.
+pub const TEST_SUITE_MAX_SIZE: usize = 64;
+
 pub struct TestManager<'a> {
     // Represent the next empty index to push new test suite, also used to know how many test suite
     // in test_pool by suite_nb - 1.
-    pub test_pool: [TestSuite<'a>; 20],
+    pub test_pool: [TestSuite<'a>; TEST_SUITE_MAX_SIZE],
     pub suite_nb: Option<usize>,
     pub suite_passed: usize,
     pub suite_failed: usize,
@@ -71,7 +87,7 @@ pub struct TestManager<'a> {
 impl<'a> TestManager<'a> {
     pub const fn init() -> Self {
         TestManager {
-            test_pool: [TestSuite::init_default(); 20],
+            test_pool: [TestSuite::init_default(); TEST_SUITE_MAX_SIZE],
             suite_nb: None,
             suite_passed: 0,
             suite_failed: 0,
@@ -79,15 +95,15 @@ impl<'a> TestManager<'a> {
     }
 
     pub fn add_suite(&'a mut self, new_test_suite: &'a TestSuite) {
-        if self.suite_nb.is_none() {
-            self.test_pool[0] = *new_test_suite;
-            self.suite_nb = Some(1)
-        } else {
-            self.test_pool[self.suite_nb.unwrap()] = *new_test_suite;
-            if let Some(nb) = self.suite_nb.as_mut() {
-                *nb += 1;
-            }
+        let index = self.suite_nb.unwrap_or(0);
+        if index >= TEST_SUITE_MAX_SIZE {
+            panic!(
+                "Test manager: the test pool is full, can't add the test suite: {}, raise TEST_SUITE_MAX_SIZE: {}",
+                new_test_suite.name, TEST_SUITE_MAX_SIZE
+            );
         }
+        self.test_pool[index] = *new_test_suite;
+        self.suite_nb = Some(index + 1);
     }
 }
 
@@ -146,6 +162,13 @@ impl<'a> TestCase<'a> {
     }
 }
 
+/// The test suite name contains the filter, the case is ignored.
+pub fn test_filter_match(name: &str, filter: &str) -> bool {
+    name.as_bytes()
+        .windows(filter.len())
+        .any(|window| window.eq_ignore_ascii_case(filter.as_bytes()))
+}
+
 pub static mut TEST_MANAGER: TestManager = TestManager::init();
 
 pub fn test_runner(core: usize, dtb_addr: usize) -> ! {
@@ -158,6 +181,17 @@ pub fn test_runner(core: usize, dtb_addr: usize) -> ! {
     test_info!("Running test: platform_init");
     test_platform_init(dtb_addr);
     test_kprint!("platform_init");
+    // Needed to exit QEMU with the tests result
+    init_power_subsystem();
+    // The logs of the firmware sub-system and the command line need the default console
+    init_serial_subsystem();
+    // The test filter is given by the host on the kernel command line
+    init_firmware_subsystem();
+    cmdline_init();
+    let filter = kernel_params().test_filter;
+    if let Some(filter) = filter {
+        kprint_fmt!("Running the test suites matching: {}\n", filter);
+    }
     // All test suites
     test_suites();
 
@@ -173,12 +207,16 @@ pub fn test_runner(core: usize, dtb_addr: usize) -> ! {
     let mut test_suites_failed: usize = 0;
     let mut test_suites_skipped: usize = 0;
     // Iterate over all test suite and run all test inside
-    for test_suite in unsafe { TEST_MANAGER.test_pool } {
+    for index in 0..test_suites_nb {
+        // Copy only the registered suite, not the whole pool on the stack.
+        let test_suite = unsafe { TEST_MANAGER.test_pool[index] };
         let test_nb = test_suite.tests.len();
         if test_nb == 0 {
             continue;
         }
-        if test_suite.behavior == TestSuiteBehavior::Skipped {
+        if test_suite.behavior == TestSuiteBehavior::Skipped
+            || filter.is_some_and(|filter| !test_filter_match(test_suite.name, filter))
+        {
             test_suites_skipped += 1;
             continue;
         }
@@ -239,8 +277,6 @@ pub fn test_runner(core: usize, dtb_addr: usize) -> ! {
         "Kernel test mode using kernel version: {}\n",
         KERNEL_VERSION,
     );
-    // Exit Qemu at the end of the tests
-    unsafe { ptr::write_volatile(0x100000 as *mut u32, 0x5555) };
-    #[allow(clippy::empty_loop)]
-    loop {}
+    // Exit Qemu at the end of the tests, the exit code tell the CI if a test failed
+    shutdown(if tests_failed == 0 { 0 } else { 1 })
 }
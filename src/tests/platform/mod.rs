@@ -1,8 +1,11 @@
 use crate::{
+    cmdline::cmdline_init_fdt,
+    drivers::DriverRegion,
+    misc::RawTraitObject,
     platform::{
-        DeviceType, PLATFORM_INFO,
-        fdt::{fdt_present, parse_dtb_file},
-        platform_get_device_info,
+        DeviceType, PLATFORM_INFO, PlatformPowerDevice,
+        fdt::{fdt_present, helpers::fdt_get_node_by_path, parse_dtb_file},
+        platform_get_device_info, platform_get_device_info_nth,
     },
     tests::{TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
 };
@@ -20,6 +23,7 @@ pub fn test_platform_init(dtb_addr: usize) {
             unsafe {
                 PLATFORM_INFO.set_mode_fdt()
             };
+            cmdline_init_fdt();
         } else {
             panic!("FDT should be present");
         }
@@ -74,6 +78,34 @@ pub fn test_platform_get_device_info_fdt() -> u8 {
     0
 }
 
+/// Test finding the FDT nodes by path, QEMU virt has its UART at /soc/serial@10000000.
+pub fn test_platform_fdt_node_by_path() -> u8 {
+    if fdt_get_node_by_path("/").is_none() || fdt_get_node_by_path("/chosen").is_none() {
+        panic!("The root and /chosen nodes should be found");
+    }
+    let serial = match fdt_get_node_by_path("/soc/serial@10000000") {
+        Some(node) => DriverRegion::new(node),
+        None => panic!("The UART node should be found by its path"),
+    };
+    if serial.addr != 0x10000000 {
+        panic!(
+            "The UART node found should be at 0x10000000, got: {:#x}",
+            serial.addr
+        );
+    }
+    // The options of a stdout-path are ignored
+    if fdt_get_node_by_path("/soc/serial@10000000:115200n8").is_none() {
+        panic!("The options after ':' should be ignored");
+    }
+    if fdt_get_node_by_path("/soc/serial@20000000").is_some()
+        || fdt_get_node_by_path("/serial@10000000").is_some()
+        || fdt_get_node_by_path("missing-alias").is_some()
+    {
+        panic!("A wrong path or a missing alias should not find a node");
+    }
+    0
+}
+
 /// Test getting device info from static
 pub fn test_platform_get_device_info_static() -> u8 {
     // Reset platform info to use static
@@ -117,6 +149,61 @@ pub fn test_platform_get_device_info_static() -> u8 {
     0
 }
 
+/// Test getting a device present multiple times from static
+pub fn test_platform_get_device_info_nth_static() -> u8 {
+    unsafe { PLATFORM_INFO.flags = 0 };
+    let first = platform_get_device_info_nth("virtio,mmio", DeviceType::Virtio, 0);
+    let last = platform_get_device_info_nth("virtio,mmio", DeviceType::Virtio, 7);
+    if first.is_none() || last.is_none() {
+        panic!("should get Some from the virtio-mmio slots 0 and 7.");
+    }
+    let first_addr = first.unwrap().header.device_addr.addr;
+    let last_addr = last.unwrap().header.device_addr.addr;
+    if first_addr != 0x1000_1000 || last_addr != 0x1000_8000 {
+        panic!(
+            "Virtio-mmio slots should be at 0x10001000 and 0x10008000, got: {:#x} and {:#x}",
+            first_addr, last_addr
+        );
+    }
+    if platform_get_device_info_nth("virtio,mmio", DeviceType::Virtio, 8).is_some() {
+        panic!("should get None past the last virtio-mmio slot.");
+    }
+    0
+}
+
+/// Test getting the power devices from FDT, the sifive,test0 compatible is not the first string of
+/// the compatible property, the syscon-poweroff region is the region of its regmap.
+pub fn test_platform_get_power_device_fdt() -> u8 {
+    let test = platform_get_device_info("sifive,test0", DeviceType::Power);
+    if test.is_none() {
+        panic!("should get Some from sifive,test0, listed after sifive,test1 in the FDT.");
+    }
+    let test_addr = test.unwrap().header.device_addr.addr;
+    if test_addr != 0x10_0000 {
+        panic!(
+            "sifive,test0 should have the MMIO address: '0x100000', got: {:#x}",
+            test_addr
+        );
+    }
+    let poweroff = platform_get_device_info("syscon-poweroff", DeviceType::Power);
+    if poweroff.is_none() {
+        panic!("should get Some from syscon-poweroff.");
+    }
+    let poweroff = poweroff.unwrap();
+    if poweroff.header.device_addr.addr != test_addr {
+        panic!("syscon-poweroff should use the region of its regmap, the sifive,test0 device.");
+    }
+    let raw: RawTraitObject = unsafe { core::mem::transmute(poweroff.info.unwrap()) };
+    let power_device = unsafe { &*(raw.data as *const PlatformPowerDevice) };
+    if power_device.value != 0x5555 || power_device.offset != 0 {
+        panic!(
+            "syscon-poweroff should write 0x5555 at offset 0, got: {:#x} at {:#x}",
+            power_device.value, power_device.offset
+        );
+    }
+    0
+}
+
 pub fn platform_test_suite() {
     const PLATFORM_TEST_SUITE: TestSuite = TestSuite {
         tests: &[
@@ -125,11 +212,26 @@ pub fn platform_test_suite() {
                 test_platform_get_device_info_fdt,
                 TestBehavior::Default,
             ),
+            TestCase::init(
+                "platform_power_device_fdt",
+                test_platform_get_power_device_fdt,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "platform_fdt_node_by_path",
+                test_platform_fdt_node_by_path,
+                TestBehavior::Default,
+            ),
             TestCase::init(
                 "platform_device_info_static",
                 test_platform_get_device_info_static,
                 TestBehavior::Default,
             ),
+            TestCase::init(
+                "platform_device_info_nth_static",
+                test_platform_get_device_info_nth_static,
+                TestBehavior::Default,
+            ),
         ],
         name: "Platform",
         behavior: TestSuiteBehavior::Default,
@@ -99,6 +99,51 @@ fn test_indexed_linked_list_pop() -> u8 {
     0
 }
 
+fn test_indexed_linked_list_remove() -> u8 {
+    let mut list: IndexedLinkedList<4> = IndexedLinkedList::new();
+    list.push(1, 70);
+    list.push(2, 80);
+    list.push(3, 75);
+    if list.remove(4).is_some() {
+        test_failed!("removing an id not in the list should return None\n");
+        return 1;
+    }
+    // Head, in the index 0 of the list
+    if list.remove(1).map(|node| node.id) != Some(1) {
+        test_failed!("remove should return the task 1\n");
+        return 1;
+    }
+    if list.get_head_node().map(|node| node.id) != Some(3) {
+        test_failed!("head node should be the task 3 after removing the head\n");
+        return 1;
+    }
+    // Push after a remove, the free entry is not the last one
+    list.push(1, 0);
+    if list.get_count() != 3 || list.get_head_node().map(|node| node.id) != Some(1) {
+        test_failed!("push after a remove should add the task 1 at the head\n");
+        return 1;
+    }
+    // Tail
+    if list.remove(2).map(|node| node.id) != Some(2) {
+        test_failed!("remove should return the task 2\n");
+        return 1;
+    }
+    let tail = list.get_index(list.get_tail());
+    if tail.id != 3 || tail.next_node.is_some() {
+        test_failed!("tail node should be the task 3, got: {}\n", tail.id);
+        return 1;
+    }
+    let ids = [
+        list.pop().map(|node| node.id),
+        list.pop().map(|node| node.id),
+    ];
+    if ids != [Some(1), Some(3)] || list.get_count() != 0 {
+        test_failed!("the list should only contain the tasks 1 and 3 in order\n");
+        return 1;
+    }
+    0
+}
+
 pub fn indexed_linked_list_primitive_test_suite() {
     const INDEXED_LINKED_LIST_TEST_SUITE: TestSuite = TestSuite {
         tests: &[
@@ -117,6 +162,11 @@ pub fn indexed_linked_list_primitive_test_suite() {
                 test_indexed_linked_list_pop,
                 TestBehavior::Default,
             ),
+            TestCase::init(
+                "IndexedLinkedList remove",
+                test_indexed_linked_list_remove,
+                TestBehavior::Default,
+            ),
         ],
         name: "IndexedLinkedList primitive type",
         behavior: TestSuiteBehavior::Default,
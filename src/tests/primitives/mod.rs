@@ -1,2 +1,3 @@
 pub mod indexed_linked_list;
 pub mod ring_buff;
+pub mod stack;
@@ -24,6 +24,10 @@ pub fn test_ringbuffer_init() -> u8 {
     ring_buff.pop();
     ring_buff.push(1);
     ring_buff.push(1);
+    if !ring_buff.is_full() {
+        test_failed!("Ring buffer should be full after 2 push");
+        return 1;
+    }
     test_info!("Next output expected to be: Ring buffer full, abort push.");
     ring_buff.push(1);
     if ring_buff.size() != 2 {
@@ -0,0 +1,116 @@
+use crate::{
+    primitives::stack::{AlignedStack16, STACK_CANARY, STACK_PAINT},
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+};
+
+pub fn test_stack_paint() -> u8 {
+    let mut stack: AlignedStack16<64> = AlignedStack16::new();
+    if stack.canary_intact() {
+        test_failed!("Stack canary should not be intact before paint");
+        return 1;
+    }
+    stack.paint();
+    if !stack.canary_intact() {
+        test_failed!("Stack canary should be intact after paint");
+        return 1;
+    }
+    if stack.buf[63] != STACK_PAINT {
+        test_failed!(
+            "Stack should be painted with: {:#x}, got: {:#x}",
+            STACK_PAINT,
+            stack.buf[63]
+        );
+        return 1;
+    }
+    let top = stack.top();
+    if top != stack.buf.as_mut_ptr().wrapping_add(64) {
+        test_failed!("Stack top should be the end of the stack buffer");
+        return 1;
+    }
+    0
+}
+
+pub fn test_stack_high_water_mark() -> u8 {
+    let mut stack: AlignedStack16<64> = AlignedStack16::new();
+    stack.paint();
+    if stack.high_water_mark() != 0 {
+        test_failed!(
+            "Painted stack high-water mark should be 0, got: {}",
+            stack.high_water_mark()
+        );
+        return 1;
+    }
+    // Simulate 24 bytes pushed on the stack, the stack grows downward.
+    for i in 40..64 {
+        stack.buf[i] = 0;
+    }
+    if stack.high_water_mark() != 24 {
+        test_failed!(
+            "Stack high-water mark should be 24, got: {}",
+            stack.high_water_mark()
+        );
+        return 1;
+    }
+    // Repaint should reset the high-water mark.
+    stack.paint();
+    if stack.usage().used != 0 {
+        test_failed!(
+            "Repainted stack usage should be 0, got: {}",
+            stack.usage().used
+        );
+        return 1;
+    }
+    0
+}
+
+pub fn test_stack_overflow() -> u8 {
+    let mut stack: AlignedStack16<64> = AlignedStack16::new();
+    stack.paint();
+    // Simulate a stack overflow, the whole stack has been used including the canary.
+    stack.buf.fill(0);
+    if stack.canary_intact() {
+        test_failed!(
+            "Stack canary: {:#x} should be overwritten after an overflow",
+            STACK_CANARY
+        );
+        return 1;
+    }
+    let usage = stack.usage();
+    if usage.canary_intact || usage.used != 60 {
+        test_failed!(
+            "Overflowed stack usage should report a broken canary and 60 bytes used, got: {}",
+            usage.used
+        );
+        return 1;
+    }
+    0
+}
+
+pub fn stack_primitive_test_suite() {
+    const STACK_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "AlignedStack16 paint",
+                test_stack_paint,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "AlignedStack16 high-water mark",
+                test_stack_high_water_mark,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "AlignedStack16 overflow",
+                test_stack_overflow,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "AlignedStack16 primitive type",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&STACK_TEST_SUITE)
+    };
+}
@@ -6,18 +6,39 @@ use super::{
             trap_frame::trap_frame_test_suite,
         },
     },
+    cmdline::cmdline_test_suite,
     drivers::{
+        block::subsystem::block_subsystem_test_suite,
         cpu_intc::subsystem::cpu_intc_subsystem_test_suite,
-        serials::{ns16550a::ns16550_test_suite, subsystem::serial_subsystem_test_suite},
+        cpufreq::cpufreq_test_suite,
+        display::fbcon::fbcon_test_suite,
+        ext_intc::subsystem::ext_intc_subsystem_test_suite,
+        firmware::fw_cfg::fw_cfg_test_suite,
+        gpio::subsystem::gpio_subsystem_test_suite,
+        model::driver_model_test_suite,
+        pool::device_pool_test_suite,
+        power::subsystem::power_subsystem_test_suite,
+        rtc::subsystem::rtc_subsystem_test_suite,
+        serials::{
+            console::console_test_suite,
+            ns16550a::{ns16550_registers_test_suite, ns16550_test_suite},
+            subsystem::serial_subsystem_test_suite,
+        },
+        spi::subsystem::spi_subsystem_test_suite,
         timer::subsystem::timer_subsystem_test_suite,
+        virtio::queue::virtqueue_test_suite,
     },
-    ktime::ktime_test_suite,
-    mem::memory_test_suite,
+    irq::irq_test_suite,
+    ktime::{calendar::calendar_test_suite, ktime_test_suite},
+    logs::{binlog::binlog_test_suite, buffer::log_buffer_test_suite},
+    mem::{dma::dma_buffer_test_suite, memory_test_suite, shared::shared_buffer_test_suite},
     platform::platform_test_suite,
     primitives::indexed_linked_list::indexed_linked_list_primitive_test_suite,
     primitives::ring_buff::ring_buff_primitive_test_suite,
+    primitives::stack::stack_primitive_test_suite,
     scheduler::scheduler_test_suite,
     task::{list::task_list_test_suite, primitives::task_primitives_test_suite, task_test_suite},
+    watchdog::watchdog_test_suite,
 };
 
 // Call all test suite function to auto register all suites in test manager.
@@ -25,18 +46,41 @@ use super::{
 pub fn test_suites() {
     platform_test_suite();
     serial_subsystem_test_suite();
+    console_test_suite();
+    log_buffer_test_suite();
+    binlog_test_suite();
     ring_buff_primitive_test_suite();
     indexed_linked_list_primitive_test_suite();
+    stack_primitive_test_suite();
     timer_subsystem_test_suite();
     cpu_intc_subsystem_test_suite();
+    ext_intc_subsystem_test_suite();
+    irq_test_suite();
+    virtqueue_test_suite();
+    block_subsystem_test_suite();
+    rtc_subsystem_test_suite();
+    gpio_subsystem_test_suite();
+    spi_subsystem_test_suite();
+    fw_cfg_test_suite();
+    cmdline_test_suite();
+    fbcon_test_suite();
+    power_subsystem_test_suite();
+    device_pool_test_suite();
+    driver_model_test_suite();
+    cpufreq_test_suite();
+    watchdog_test_suite();
     ktime_test_suite();
+    calendar_test_suite();
     ns16550_test_suite();
+    ns16550_registers_test_suite();
     trap_frame_test_suite();
     interrupt_enabling_test_suite();
     trap_handler_test_suite();
     memory_test_suite();
     task_list_test_suite();
     task_test_suite();
+    shared_buffer_test_suite();
+    dma_buffer_test_suite();
     task_context_test_suite();
     task_primitives_test_suite();
     scheduler_test_suite();
@@ -1,5 +1,10 @@
 use crate::{
-    task::{list::task_list_size, task_create},
+    config::{TASK_LIST_MAX_SIZE, TASK_MEMORY_QUOTA},
+    task::{
+        TASK_STACK_MIN_SIZE, TaskError,
+        list::{task_list_get_task_by_pid, task_list_size},
+        task_check_params, task_create, task_name, task_restart,
+    },
     test_info,
     tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
 };
@@ -20,7 +25,10 @@ pub fn test_task_create() -> u8 {
         panic!("Task list should be initialized at 0.");
     }
     test_info!("The next output should be: Successfully created task: Testing task");
-    task_create("Testing task", task_fn_ptr, 7, 64);
+    let pid = task_create("Testing task", task_fn_ptr, 7, TASK_STACK_MIN_SIZE);
+    if pid != Ok(1) {
+        panic!("Task creation should return the pid 1, got: {:?}", pid);
+    }
     let update_list_size = task_list_size();
     if update_list_size != 1 {
         panic!("Task list should have been updated with the created task.");
@@ -28,13 +36,163 @@ pub fn test_task_create() -> u8 {
     0
 }
 
+pub fn test_task_create_invalid_priority() -> u8 {
+    let list_size = task_list_size();
+    let task = task_create("Invalid priority", task_fn_ptr, 32, TASK_STACK_MIN_SIZE);
+    if task != Err(TaskError::InvalidPriority) {
+        panic!(
+            "Task creation should fail with InvalidPriority, got: {:?}",
+            task
+        );
+    }
+    if task_list_size() != list_size {
+        panic!("Task list should not be updated when the task creation failed.");
+    }
+    0
+}
+
+pub fn test_task_create_stack_too_small() -> u8 {
+    let list_size = task_list_size();
+    let task = task_create("Stack too small", task_fn_ptr, 7, TASK_STACK_MIN_SIZE - 1);
+    if task != Err(TaskError::StackTooSmall) {
+        panic!(
+            "Task creation should fail with StackTooSmall, got: {:?}",
+            task
+        );
+    }
+    if task_list_size() != list_size {
+        panic!("Task list should not be updated when the task creation failed.");
+    }
+    0
+}
+
+pub fn test_task_create_quota_exceeded() -> u8 {
+    // The quota is a build config, checked with a quota of 2 minimum stacks.
+    let quota = Some(TASK_STACK_MIN_SIZE * 2);
+    let res = task_check_params(7, TASK_STACK_MIN_SIZE * 2 + 16, quota, 0);
+    if res != Err(TaskError::QuotaExceeded) {
+        panic!("Task check should fail with QuotaExceeded, got: {:?}", res);
+    }
+    if task_check_params(7, TASK_STACK_MIN_SIZE * 2, quota, 0).is_err() {
+        panic!("Task check should accept a stack size equal to the quota.");
+    }
+    if let Some(quota) = TASK_MEMORY_QUOTA {
+        let list_size = task_list_size();
+        let task = task_create("Quota exceeded", task_fn_ptr, 7, quota + 16);
+        if task != Err(TaskError::QuotaExceeded) {
+            panic!(
+                "Task creation should fail with QuotaExceeded, got: {:?}",
+                task
+            );
+        }
+        if task_list_size() != list_size {
+            panic!("Task list should not be updated when the task creation failed.");
+        }
+    }
+    0
+}
+
+pub fn test_task_create_task_list_full() -> u8 {
+    // Filling the real task list would allocate stacks never freed, and leave no space for the
+    // next suites, the check is done with a full list size.
+    let res = task_check_params(7, TASK_STACK_MIN_SIZE, None, TASK_LIST_MAX_SIZE);
+    if res != Err(TaskError::TaskListFull) {
+        panic!("Task check should fail with TaskListFull, got: {:?}", res);
+    }
+    if task_check_params(7, TASK_STACK_MIN_SIZE, None, TASK_LIST_MAX_SIZE - 1).is_err() {
+        panic!("Task check should accept a task in the last slot of the list.");
+    }
+    0
+}
+
+pub fn test_task_create_out_of_memory() -> u8 {
+    if TASK_MEMORY_QUOTA.is_some() {
+        test_info!("A stack bigger than the memory is refused by the quota, skipping");
+        return 0;
+    }
+    let list_size = task_list_size();
+    // Bigger than the whole memory, the allocator refuses it without allocating anything.
+    let task = task_create("Out of memory", task_fn_ptr, 7, usize::MAX & !(16 - 1));
+    if task != Err(TaskError::OutOfMemory) {
+        panic!(
+            "Task creation should fail with OutOfMemory, got: {:?}",
+            task
+        );
+    }
+    if task_list_size() != list_size {
+        panic!("Task list should not be updated when the task creation failed.");
+    }
+    0
+}
+
+pub fn test_task_restart() -> u8 {
+    // Task created by test_task_create
+    let task = task_list_get_task_by_pid(1).unwrap();
+    if task_name(task) != "Testing task" {
+        panic!(
+            "Task name should be: Testing task, got: {}",
+            task_name(task)
+        );
+    }
+    let stack_top = task.context.address_space[0];
+    task.context.pc = 0;
+    task.context.sp = stack_top - 0x10;
+    task.context.gpr[10] = 42;
+    if !task_restart(1) {
+        panic!("Task restart should find the task with pid 1.");
+    }
+    let task = task_list_get_task_by_pid(1).unwrap();
+    if task.context.pc != task_fn_ptr as *const () as usize as u32
+        || task.context.sp != stack_top
+        || task.context.gpr[10] != 0
+    {
+        panic!("Task context should be reset to the task entry point with an empty stack.");
+    }
+    if task_restart(u16::MAX) {
+        panic!("Task restart should fail on an unknown pid.");
+    }
+    0
+}
+
 pub fn task_test_suite() {
     const TASK_TEST_SUITE: TestSuite = TestSuite {
-        tests: &[TestCase::init(
-            "Task creation and register to task list",
-            test_task_create,
-            TestBehavior::Default,
-        )],
+        tests: &[
+            TestCase::init(
+                "Task creation and register to task list",
+                test_task_create,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Task creation with an invalid priority",
+                test_task_create_invalid_priority,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Task creation with a stack too small",
+                test_task_create_stack_too_small,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Task creation above the memory quota",
+                test_task_create_quota_exceeded,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Task creation with a full task list",
+                test_task_create_task_list_full,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Task creation without enough memory",
+                test_task_create_out_of_memory,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Task restart from its entry point",
+                test_task_restart,
+                TestBehavior::Default,
+            ),
+        ],
         name: "Task",
         behavior: TestSuiteBehavior::Default,
     };
@@ -6,6 +6,7 @@ use crate::{
     config::TICK_SAFETY_DURATION,
     kprint,
     ktime::set_ktime_seconds,
+    power::shutdown,
     scheduler::{BLOCKED_QUEUE, RUN_QUEUE, RUN_QUEUE_BITMAP},
     task::{
         CURRENT_TASK_PID, TASK_HANDLER,
@@ -16,7 +17,6 @@ use crate::{
     test_failed, test_info,
     tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
 };
-use core::ptr;
 
 fn task_fn() -> ! {
     let mut i: usize = 0;
@@ -25,7 +25,7 @@ fn task_fn() -> ! {
         delay(1000);
         if i >= 8 {
             // Exit Qemu
-            unsafe { ptr::write_volatile(0x100000 as *mut u32, 0x5555) };
+            shutdown(0);
         }
         i += 1;
     }
@@ -57,18 +57,16 @@ fn task_testing_sleep() -> ! {
             "The run queue should be empty, got: {}",
             current_run_queue[1].size()
         );
-        // Use infinite loop to make the CI crash from timeout. Can't return test failed from
-        // here.
-        loop {}
+        // Can't return test failed from here, exit QEMU with a failure.
+        shutdown(1);
     }
     if current_blocked_queue.get_count() != 1 {
         test_failed!(
             "The block queue should have 1 task in it, got: {}",
             current_blocked_queue.get_count()
         );
-        // Use infinite loop to make the CI crash from timeout. Can't return test failed from
-        // here.
-        loop {}
+        // Can't return test failed from here, exit QEMU with a failure.
+        shutdown(1);
     }
     unsafe { trap_handler(mepc, 0, cause, 0, 0, &mut trap_frame) };
     if current_blocked_queue.get_count() != 0 {
@@ -76,28 +74,23 @@ fn task_testing_sleep() -> ! {
             "The block queue should be empty, got: {}",
             current_blocked_queue.get_count()
         );
-        // Use infinite loop to make the CI crash from timeout. Can't return test failed from
-        // here.
-        loop {}
+        // Can't return test failed from here, exit QEMU with a failure.
+        shutdown(1);
     }
     if current_run_queue[1].size() != 1 {
         test_failed!(
             "The run queue should have 1 task in it, got: {}",
             current_run_queue[1].size()
         );
-        // Use infinite loop to make the CI crash from timeout. Can't return test failed from
-        // here.
-        loop {}
+        // Can't return test failed from here, exit QEMU with a failure.
+        shutdown(1);
     }
     test_info!("Invariant from sleep and blocked queue successfully respected. Exit qemu...");
-    unsafe { ptr::write_volatile(0x100000 as *mut u32, 0x5555) };
-    // Check with condition the invariant, blocked queue updated etc
-    // Recall trap handler, then check that the block queue is empty.
-    loop {}
+    shutdown(0);
 }
 
 fn test_task_primitives_delay() -> u8 {
-    task_create("Test delay", task_fn, 1, 0x1000);
+    task_create("Test delay", task_fn, 1, 0x1000).unwrap();
     unsafe { CURRENT_TASK_PID = 2 };
     let mut task = task_list_get_task_by_pid(unsafe { CURRENT_TASK_PID });
     unsafe { TASK_HANDLER = *task.as_mut().unwrap() };
@@ -112,9 +105,9 @@ fn test_task_primitives_delay() -> u8 {
 
 fn test_task_primitives_sleep() -> u8 {
     // pid 2
-    task_create("Test sleep", task_sleep_fn, 1, 0x1000);
+    task_create("Test sleep", task_sleep_fn, 1, 0x1000).unwrap();
     // pid 3
-    task_create("Test sleep invariants", task_testing_sleep, 1, 0x1000);
+    task_create("Test sleep invariants", task_testing_sleep, 1, 0x1000).unwrap();
     unsafe { CURRENT_TASK_PID = 2 };
     let mut task = task_list_get_task_by_pid(unsafe { CURRENT_TASK_PID });
     unsafe { TASK_HANDLER = *task.as_mut().unwrap() };
@@ -0,0 +1,190 @@
+use crate::{
+    config::{WATCHDOG_MAX_RESTARTS, WATCHDOG_MAX_SIZE},
+    test_failed,
+    tests::{TEST_MANAGER, TestBehavior, TestCase, TestSuite, TestSuiteBehavior},
+    watchdog::{WatchdogAction, WatchdogError, WatchdogReport, WatchdogSubSystem},
+};
+
+pub fn test_watchdog_subsystem_register() -> u8 {
+    let watchdog = WatchdogSubSystem::init();
+    if watchdog.get_watchdog_array_size() != 0 {
+        test_failed!("Watchdog sub-system should be initialized empty.");
+        return 1;
+    }
+    watchdog
+        .register(1, 10, WatchdogAction::RestartTask, 0)
+        .unwrap();
+    if !watchdog.is_watched(1) || watchdog.get_watchdog_array_size() != 1 {
+        test_failed!("The task 1 should be watched.");
+        return 1;
+    }
+    // Register the same task again replaces the entry
+    watchdog.register(1, 20, WatchdogAction::Panic, 0).unwrap();
+    if watchdog.get_watchdog_array_size() != 1 {
+        test_failed!(
+            "Registering twice the same task should not add an entry, got: {}",
+            watchdog.get_watchdog_array_size()
+        );
+        return 1;
+    }
+    if watchdog.check_in(2, 0) != Err(WatchdogError::NotRegistered) {
+        test_failed!("Check in of a task not watched should return NotRegistered");
+        return 1;
+    }
+    watchdog.unregister(1).unwrap();
+    if watchdog.is_watched(1) || watchdog.unregister(1) != Err(WatchdogError::NotRegistered) {
+        test_failed!("The task 1 should not be watched after unregister");
+        return 1;
+    }
+    0
+}
+
+pub fn test_watchdog_subsystem_invalid() -> u8 {
+    let watchdog = WatchdogSubSystem::init();
+    if watchdog.register(1, 0, WatchdogAction::Reset, 0) != Err(WatchdogError::InvalidTimeout) {
+        test_failed!("A timeout of 0 tick should return InvalidTimeout");
+        return 1;
+    }
+    for pid in 0..WATCHDOG_MAX_SIZE {
+        watchdog
+            .register(pid as u16, 10, WatchdogAction::Reset, 0)
+            .unwrap();
+    }
+    if watchdog.register(WATCHDOG_MAX_SIZE as u16, 10, WatchdogAction::Reset, 0)
+        != Err(WatchdogError::TableFull)
+    {
+        test_failed!("Registering a task on a full watchdog should return TableFull");
+        return 1;
+    }
+    0
+}
+
+pub fn test_watchdog_subsystem_expired() -> u8 {
+    let watchdog = WatchdogSubSystem::init();
+    watchdog.register(1, 10, WatchdogAction::Panic, 0).unwrap();
+    watchdog.register(2, 5, WatchdogAction::Reset, 0).unwrap();
+    let mut stalled: [Option<(u16, WatchdogAction)>; 2] = [None; 2];
+    let mut count: usize = 0;
+    watchdog.expired(4, |pid, action| {
+        stalled[count] = Some((pid, action));
+        count += 1;
+    });
+    if count != 0 {
+        test_failed!(
+            "No task should be stalled before its deadline, got: {}",
+            count
+        );
+        return 1;
+    }
+    // The task 1 check in, only the task 2 miss its deadline
+    watchdog.check_in(1, 4).unwrap();
+    watchdog.expired(10, |pid, action| {
+        stalled[count] = Some((pid, action));
+        count += 1;
+    });
+    if count != 1 || stalled[0] != Some((2, WatchdogAction::Reset)) {
+        test_failed!("Only the task 2 should be stalled at tick 10");
+        return 1;
+    }
+    // The deadline is re-armed after expiry
+    count = 0;
+    watchdog.expired(11, |_, _| count += 1);
+    if count != 0 {
+        test_failed!("The deadline of the stalled task should be re-armed");
+        return 1;
+    }
+    0
+}
+
+pub fn test_watchdog_subsystem_escalation() -> u8 {
+    let watchdog = WatchdogSubSystem::init();
+    watchdog
+        .register(1, 1, WatchdogAction::RestartTask, 0)
+        .unwrap();
+    let mut last = None;
+    for tick in 1..=WATCHDOG_MAX_RESTARTS {
+        watchdog.expired(tick, |_, action| last = Some(action));
+        if last != Some(WatchdogAction::RestartTask) {
+            test_failed!("The task should be restarted at tick {}", tick);
+            return 1;
+        }
+    }
+    watchdog.expired(WATCHDOG_MAX_RESTARTS + 1, |_, action| last = Some(action));
+    if last != Some(WatchdogAction::Reset) {
+        test_failed!(
+            "The watchdog should reset the system after {} restarts",
+            WATCHDOG_MAX_RESTARTS
+        );
+        return 1;
+    }
+    0
+}
+
+pub fn test_watchdog_subsystem_reports() -> u8 {
+    let watchdog = WatchdogSubSystem::init();
+    if watchdog.take_report().is_some() || watchdog.take_dropped() != 0 {
+        test_failed!("Watchdog sub-system should be initialized without reports.");
+        return 1;
+    }
+    for pid in 0..=WATCHDOG_MAX_SIZE {
+        watchdog.report(WatchdogReport {
+            pid: pid as u16,
+            action: WatchdogAction::RestartTask,
+        });
+    }
+    // The reports are taken oldest first, the one above the queue size is dropped.
+    for pid in 0..WATCHDOG_MAX_SIZE {
+        let report = watchdog.take_report();
+        if report.map(|r| r.pid) != Some(pid as u16) {
+            test_failed!("Report of pid {} should be taken, got: {:?}", pid, report);
+            return 1;
+        }
+    }
+    if watchdog.take_report().is_some() {
+        test_failed!("All the reports should be taken");
+        return 1;
+    }
+    if watchdog.take_dropped() != 1 || watchdog.take_dropped() != 0 {
+        test_failed!("The report on a full queue should be counted once as dropped");
+        return 1;
+    }
+    0
+}
+
+pub fn watchdog_test_suite() {
+    const WATCHDOG_TEST_SUITE: TestSuite = TestSuite {
+        tests: &[
+            TestCase::init(
+                "Watchdog sub-system register task",
+                test_watchdog_subsystem_register,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Watchdog sub-system invalid registration",
+                test_watchdog_subsystem_invalid,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Watchdog sub-system expired deadline",
+                test_watchdog_subsystem_expired,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Watchdog sub-system restart escalation",
+                test_watchdog_subsystem_escalation,
+                TestBehavior::Default,
+            ),
+            TestCase::init(
+                "Watchdog sub-system stall reports",
+                test_watchdog_subsystem_reports,
+                TestBehavior::Default,
+            ),
+        ],
+        name: "Watchdog sub-system",
+        behavior: TestSuiteBehavior::Default,
+    };
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEST_MANAGER.add_suite(&WATCHDOG_TEST_SUITE)
+    };
+}
@@ -0,0 +1,385 @@
+// See documentation: `Documentation/kernel/watchdog.md`
+/*
+File info: Task watchdog, detect the tasks that stop checking in.
+
+Test coverage: Registration, check-in and expiry.
+
+Tested:
+- Register, re-register and unregister a task.
+- Invalid timeout and full watchdog table.
+- Expired deadline detection, re-arm after expiry.
+- Escalation from task restart to system reset.
+- Stall reports queued and taken in order, dropped when the queue is full.
+
+Not tested:
+- The actions on a stalled task, from the tick path.
+- The reports logged from the watchdog bottom half.
+- The registration of the watchdog handler on the timer interrupt.
+
+Reasons:
+- The actions panic, reset the machine or need a running task, not handled by the test framework.
+- The bottom half task need the scheduler and timer interrupts, not handled by the test framework.
+- The handler is registered by the first watched task, the tests use the sub-system directly.
+
+Tests files:
+- 'src/tests/watchdog/mod.rs'
+*/
+
+use core::cell::UnsafeCell;
+
+use crate::{
+    arch::{
+        helpers::current_cpu_core,
+        traps::interrupt::{restore_mstatus_mie, save_and_disable_mstatus_mie},
+    },
+    cmdline::kernel_params,
+    config::{WATCHDOG_DEFAULT_ACTION, WATCHDOG_MAX_RESTARTS, WATCHDOG_MAX_SIZE},
+    irq::{IRQ_MAX_PRIORITY, IRQ_TIMER, IrqError, irq_register},
+    ktime::tick::get_tick,
+    log,
+    logs::LogLevel,
+    power::reboot,
+    task::{list::task_list_get_task_by_pid, task_current_pid, task_name, task_restart},
+};
+
+/// Action triggered when a watched task misses its deadline.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WatchdogAction {
+    // Panic, the panic handler prints the report and stops the machine.
+    Panic,
+    // Restart the task from its entry point.
+    RestartTask,
+    // Reset the machine through the power sub-system.
+    Reset,
+}
+
+/// All errors that can happen on a watchdog request.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WatchdogError {
+    // The request must be made from a task.
+    NotInTask,
+    // The timeout must be at least one tick.
+    InvalidTimeout,
+    // The watchdog table is full, see WATCHDOG_MAX_SIZE in config file.
+    TableFull,
+    // The task is not watched.
+    NotRegistered,
+    // The watchdog handler cannot be registered on the timer interrupt.
+    Irq(IrqError),
+}
+
+/// Task restarted by the watchdog, reported later out of the timer interrupt handler.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WatchdogReport {
+    pub pid: u16,
+    pub action: WatchdogAction,
+}
+
+#[derive(Copy, Clone)]
+struct WatchdogEntry {
+    pid: u16,
+    // Timeout in ticks
+    timeout: usize,
+    // Tick at which the task is considered stalled
+    deadline: usize,
+    action: WatchdogAction,
+    // Number of restarts since the task registered
+    restarts: usize,
+}
+
+/// Define and manage all watched tasks, one entry per task.
+/// reports: stalls not reported yet, oldest first.
+/// dropped: number of reports dropped because the queue was full.
+pub struct WatchdogSubSystem {
+    entries: [UnsafeCell<Option<WatchdogEntry>>; WATCHDOG_MAX_SIZE],
+    reports: UnsafeCell<[Option<WatchdogReport>; WATCHDOG_MAX_SIZE]>,
+    dropped: UnsafeCell<usize>,
+}
+
+unsafe impl Sync for WatchdogSubSystem {}
+
+impl WatchdogSubSystem {
+    pub const fn init() -> Self {
+        WatchdogSubSystem {
+            entries: [const { UnsafeCell::new(None) }; WATCHDOG_MAX_SIZE],
+            reports: UnsafeCell::new([None; WATCHDOG_MAX_SIZE]),
+            dropped: UnsafeCell::new(0),
+        }
+    }
+
+    /// Watch the task, it must check in before timeout ticks from tick. Registering a task
+    /// already watched replaces its timeout and action.
+    pub fn register(
+        &self,
+        pid: u16,
+        timeout: usize,
+        action: WatchdogAction,
+        tick: usize,
+    ) -> Result<(), WatchdogError> {
+        if timeout == 0 {
+            return Err(WatchdogError::InvalidTimeout);
+        }
+        let entry = WatchdogEntry {
+            pid,
+            timeout,
+            deadline: tick + timeout,
+            action,
+            restarts: 0,
+        };
+        let mie = save_and_disable_mstatus_mie();
+        let result = match self.find(pid).or_else(|| self.find_free()) {
+            Some(i) => {
+                unsafe { *self.entries[i].get() = Some(entry) };
+                Ok(())
+            }
+            None => Err(WatchdogError::TableFull),
+        };
+        restore_mstatus_mie(mie);
+        result
+    }
+
+    /// Stop watching the task.
+    pub fn unregister(&self, pid: u16) -> Result<(), WatchdogError> {
+        let mie = save_and_disable_mstatus_mie();
+        let result = match self.find(pid) {
+            Some(i) => {
+                unsafe { *self.entries[i].get() = None };
+                Ok(())
+            }
+            None => Err(WatchdogError::NotRegistered),
+        };
+        restore_mstatus_mie(mie);
+        result
+    }
+
+    /// Push the deadline of the task to timeout ticks from tick.
+    pub fn check_in(&self, pid: u16, tick: usize) -> Result<(), WatchdogError> {
+        let mie = save_and_disable_mstatus_mie();
+        let result = match self.find(pid) {
+            Some(i) => {
+                if let Some(entry) = unsafe { (*self.entries[i].get()).as_mut() } {
+                    entry.deadline = tick + entry.timeout;
+                }
+                Ok(())
+            }
+            None => Err(WatchdogError::NotRegistered),
+        };
+        restore_mstatus_mie(mie);
+        result
+    }
+
+    pub fn is_watched(&self, pid: u16) -> bool {
+        self.find(pid).is_some()
+    }
+
+    pub fn get_watchdog_array_size(&self) -> usize {
+        let mut size: usize = 0;
+        for i in 0..WATCHDOG_MAX_SIZE {
+            let present = unsafe { &*self.entries[i].get() };
+            if present.is_some() {
+                size += 1;
+            }
+        }
+        size
+    }
+
+    /// Call handler with the pid and the action of each task that missed its deadline at tick.
+    /// The deadline of the task is re-armed, a task restarted more than WATCHDOG_MAX_RESTARTS
+    /// times escalates to a reset.
+    /// Must be called with interrupts disabled or from the interrupt handler.
+    pub fn expired<F: FnMut(u16, WatchdogAction)>(&self, tick: usize, mut handler: F) {
+        for i in 0..WATCHDOG_MAX_SIZE {
+            let entry = match unsafe { (*self.entries[i].get()).as_mut() } {
+                Some(e) if tick >= e.deadline => e,
+                _ => continue,
+            };
+            entry.deadline = tick + entry.timeout;
+            let mut action = entry.action;
+            if action == WatchdogAction::RestartTask {
+                entry.restarts += 1;
+                if entry.restarts > WATCHDOG_MAX_RESTARTS {
+                    action = WatchdogAction::Reset;
+                }
+            }
+            handler(entry.pid, action);
+        }
+    }
+
+    /// Queue the report of a stalled task, it's dropped and counted if the queue is full.
+    pub fn report(&self, report: WatchdogReport) {
+        let mie = save_and_disable_mstatus_mie();
+        let reports = unsafe { &mut *self.reports.get() };
+        match reports.iter_mut().find(|r| r.is_none()) {
+            Some(free) => *free = Some(report),
+            None => unsafe { *self.dropped.get() += 1 },
+        }
+        restore_mstatus_mie(mie);
+    }
+
+    /// Remove and return the oldest report.
+    pub fn take_report(&self) -> Option<WatchdogReport> {
+        let mie = save_and_disable_mstatus_mie();
+        let reports = unsafe { &mut *self.reports.get() };
+        let report = reports[0].take();
+        if report.is_some() {
+            reports.rotate_left(1);
+        }
+        restore_mstatus_mie(mie);
+        report
+    }
+
+    /// Return and reset the number of dropped reports.
+    pub fn take_dropped(&self) -> usize {
+        let mie = save_and_disable_mstatus_mie();
+        let dropped = unsafe { core::mem::take(&mut *self.dropped.get()) };
+        restore_mstatus_mie(mie);
+        dropped
+    }
+
+    fn find_free(&self) -> Option<usize> {
+        (0..WATCHDOG_MAX_SIZE).find(|&i| unsafe { (*self.entries[i].get()).is_none() })
+    }
+
+    fn find(&self, pid: u16) -> Option<usize> {
+        (0..WATCHDOG_MAX_SIZE).find(
+            |&i| matches!(unsafe { &*self.entries[i].get() }, Some(entry) if entry.pid == pid),
+        )
+    }
+}
+
+pub static WATCHDOG_SUBSYSTEM: WatchdogSubSystem = WatchdogSubSystem::init();
+
+// Set once the watchdog handler is registered on the timer interrupt.
+static mut WATCHDOG_IRQ_REGISTERED: bool = false;
+
+/// Number of ticks covering at least timeout_ms.
+fn watchdog_ms_to_ticks(timeout_ms: u64) -> usize {
+    timeout_ms.div_ceil(kernel_params().tick_duration) as usize
+}
+
+/// Watch the current task with WATCHDOG_DEFAULT_ACTION, see watchdog_register_with_action.
+pub fn watchdog_register(timeout_ms: u64) -> Result<(), WatchdogError> {
+    watchdog_register_with_action(timeout_ms, WATCHDOG_DEFAULT_ACTION)
+}
+
+/// Watch the current task, it must call watchdog_check_in at least every timeout_ms, or the
+/// action is triggered.
+pub fn watchdog_register_with_action(
+    timeout_ms: u64,
+    action: WatchdogAction,
+) -> Result<(), WatchdogError> {
+    let pid = task_current_pid().ok_or(WatchdogError::NotInTask)?;
+    watchdog_irq_init()?;
+    WATCHDOG_SUBSYSTEM.register(pid, watchdog_ms_to_ticks(timeout_ms), action, get_tick())
+}
+
+/// Register the watchdog on the timer interrupt, after the kernel tick handler, when the first
+/// task is watched. The irq bottom half task is only created then, it doesn't take a slot of the
+/// task list when no task is watched.
+fn watchdog_irq_init() -> Result<(), WatchdogError> {
+    if unsafe { WATCHDOG_IRQ_REGISTERED } {
+        return Ok(());
+    }
+    match irq_register(
+        IRQ_TIMER,
+        IRQ_MAX_PRIORITY - 1,
+        watchdog_irq_handler,
+        0,
+        Some(watchdog_bottom_half),
+    ) {
+        Ok(_) | Err(IrqError::AlreadyRegistered) => {
+            unsafe { WATCHDOG_IRQ_REGISTERED = true };
+            Ok(())
+        }
+        Err(e) => Err(WatchdogError::Irq(e)),
+    }
+}
+
+/// Watchdog handler on the timer interrupt, check the deadlines on the CPU core 0, the tick is
+/// incremented before by the kernel tick handler.
+fn watchdog_irq_handler(_irq: u32, _ctx: usize) {
+    if current_cpu_core() == 0 {
+        watchdog_tick(get_tick());
+    }
+}
+
+/// Watchdog bottom half, log the tasks restarted by the watchdog out of the interrupt handler.
+fn watchdog_bottom_half(_irq: u32, _ctx: usize) {
+    watchdog_report();
+}
+
+/// Stop watching the current task.
+pub fn watchdog_unregister() -> Result<(), WatchdogError> {
+    let pid = task_current_pid().ok_or(WatchdogError::NotInTask)?;
+    WATCHDOG_SUBSYSTEM.unregister(pid)
+}
+
+/// Tell the watchdog that the current task is alive.
+pub fn watchdog_check_in() -> Result<(), WatchdogError> {
+    let pid = task_current_pid().ok_or(WatchdogError::NotInTask)?;
+    WATCHDOG_SUBSYSTEM.check_in(pid, get_tick())
+}
+
+/// Check the deadlines of the watched tasks, called from the watchdog timer handler on the CPU
+/// core 0.
+pub fn watchdog_tick(tick: usize) {
+    WATCHDOG_SUBSYSTEM.expired(tick, watchdog_stalled);
+}
+
+/// Trigger the action of the stalled task. Called from the timer interrupt handler, on the trap
+/// stack: a restart is only queued for watchdog_report, the panic message has the task, and a
+/// reset logs before the machine stops, nothing runs after it.
+fn watchdog_stalled(pid: u16, action: WatchdogAction) {
+    let task = task_list_get_task_by_pid(pid);
+    let name = match &task {
+        Some(t) => task_name(t),
+        None => "<unknown>",
+    };
+    match action {
+        WatchdogAction::Panic => panic!("Watchdog: task {} with pid: {} stalled", name, pid),
+        WatchdogAction::RestartTask => {
+            WATCHDOG_SUBSYSTEM.report(WatchdogReport { pid, action });
+            if !task_restart(pid) {
+                // The task doesn't exist anymore, nothing to watch.
+                let _ = WATCHDOG_SUBSYSTEM.unregister(pid);
+            }
+        }
+        WatchdogAction::Reset => {
+            log!(
+                LogLevel::Error,
+                "Watchdog: task {} with pid: {} missed its deadline, action: {:?}",
+                name,
+                pid,
+                action
+            );
+            reboot()
+        }
+    }
+}
+
+/// Log the stalled tasks queued by the timer interrupt handler, called from the watchdog bottom
+/// half.
+pub fn watchdog_report() {
+    while let Some(report) = WATCHDOG_SUBSYSTEM.take_report() {
+        let task = task_list_get_task_by_pid(report.pid);
+        let name = match &task {
+            Some(t) => task_name(t),
+            None => "<unknown>",
+        };
+        log!(
+            LogLevel::Error,
+            "Watchdog: task {} with pid: {} missed its deadline, action: {:?}",
+            name,
+            report.pid,
+            report.action
+        );
+    }
+    let dropped = WATCHDOG_SUBSYSTEM.take_dropped();
+    if dropped != 0 {
+        log!(
+            LogLevel::Error,
+            "Watchdog: {} stall reports dropped, report queue full",
+            dropped
+        );
+    }
+}
@@ -0,0 +1,245 @@
+// Decode the binary log frames of the kernel output.
+// See documentation in `Documentation/kernel/logs.md`
+
+use crate::format::{Value, format};
+
+// Must match src/logs/binlog.rs
+const BINLOG_FRAME_START: u8 = 0xff;
+const BINLOG_LEVEL_TIME: u8 = 0x80;
+const BINLOG_ARG_UNSIGNED: u8 = 0;
+const BINLOG_ARG_SIGNED: u8 = 1;
+const BINLOG_ARG_BOOL: u8 = 2;
+const BINLOG_ARG_CHAR: u8 = 3;
+const BINLOG_ARG_STR: u8 = 4;
+const BINLOG_ARG_TEXT: u8 = 5;
+
+// Prefix of each level, like the kernel text logs, the trace events are the last level.
+const LEVEL_PREFIXES: [&str; 5] = [
+    "\x1b[35;1m[DEBUG]\x1b[0m",
+    "\x1b[32;1m[INFO]\x1b[0m",
+    "\x1b[33;1m[WARNING]\x1b[0m",
+    "\x1b[31;1m[ERROR]\x1b[0m",
+    "\x1b[36;1m[TRACE]\x1b[0m",
+];
+
+enum State {
+    // Bytes outside of a frame, print! and kprint output.
+    Text,
+    // Start byte read, waiting for the length.
+    Length,
+    // Reading a frame, number of bytes left.
+    Frame(usize),
+}
+
+/// Stream decoder, the frames can be split between reads.
+pub struct Decoder<'a> {
+    strings: &'a [u8],
+    state: State,
+    frame: Vec<u8>,
+}
+
+impl<'a> Decoder<'a> {
+    /// strings: content of the .lrnrtos_log section of the kernel ELF file.
+    pub fn new(strings: &'a [u8]) -> Self {
+        Decoder {
+            strings,
+            state: State::Text,
+            frame: Vec::new(),
+        }
+    }
+
+    /// Decode the bytes, the text is copied to out, each frame is replaced by its log line.
+    pub fn feed(&mut self, data: &[u8], out: &mut Vec<u8>) {
+        for &byte in data {
+            match self.state {
+                State::Text if byte == BINLOG_FRAME_START => self.state = State::Length,
+                State::Text => out.push(byte),
+                State::Length => {
+                    self.frame.clear();
+                    self.state = State::Frame(byte as usize);
+                }
+                State::Frame(left) => {
+                    self.frame.push(byte);
+                    self.state = State::Frame(left - 1);
+                }
+            }
+            if let State::Frame(0) = self.state {
+                out.extend_from_slice(self.decode_frame().as_bytes());
+                self.state = State::Text;
+            }
+        }
+    }
+
+    /// Log line of the frame read, or an error line.
+    fn decode_frame(&self) -> String {
+        match self.decode_frame_fields() {
+            Ok(line) => line,
+            Err(e) => format!("[binlog: {e}]\n"),
+        }
+    }
+
+    fn decode_frame_fields(&self) -> Result<String, String> {
+        let mut reader = Reader(&self.frame);
+        let level = reader.byte()?;
+        let id = reader.varint()? as usize;
+        let time = if level & BINLOG_LEVEL_TIME != 0 {
+            Some(reader.varint()?)
+        } else {
+            None
+        };
+        let prefix = LEVEL_PREFIXES
+            .get((level & !BINLOG_LEVEL_TIME) as usize)
+            .ok_or_else(|| format!("unknown level {level}"))?;
+        let fmt = self.format_string(id)?;
+        let mut args = Vec::new();
+        while !reader.is_empty() {
+            args.push(reader.value()?);
+        }
+        let time = time.map(|t| date_time(t) + " ").unwrap_or_default();
+        Ok(format!("{prefix} {time}{}\n", format(fmt, &args)))
+    }
+
+    /// Format string at the id, the offset in the log section.
+    fn format_string(&self, id: usize) -> Result<&'a str, String> {
+        let bytes = self
+            .strings
+            .get(id..)
+            .ok_or_else(|| format!("unknown format string id {id}, wrong ELF file?"))?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        std::str::from_utf8(&bytes[..end]).map_err(|_| format!("invalid format string id {id}"))
+    }
+}
+
+/// Frame content reader.
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let (&byte, rest) = self.0.split_first().ok_or("truncated frame")?;
+        self.0 = rest;
+        Ok(byte)
+    }
+
+    /// LEB128 integer.
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("invalid integer".into())
+    }
+
+    fn str(&mut self) -> Result<String, String> {
+        let len = self.varint()? as usize;
+        if len > self.0.len() {
+            return Err("truncated string".into());
+        }
+        let (s, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(String::from_utf8_lossy(s).into_owned())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.byte()? {
+            BINLOG_ARG_UNSIGNED => Ok(Value::Unsigned(self.varint()?)),
+            BINLOG_ARG_SIGNED => {
+                let zigzag = self.varint()?;
+                Ok(Value::Signed((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64)))
+            }
+            BINLOG_ARG_BOOL => Ok(Value::Bool(self.byte()? != 0)),
+            BINLOG_ARG_CHAR => {
+                let c = self.varint()? as u32;
+                Ok(Value::Char(
+                    char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER),
+                ))
+            }
+            BINLOG_ARG_STR => Ok(Value::Str(self.str()?)),
+            BINLOG_ARG_TEXT => Ok(Value::Text(self.str()?)),
+            tag => Err(format!("unknown argument tag {tag}")),
+        }
+    }
+}
+
+/// Date of the wall clock time, like DateTime in src/ktime/calendar.rs.
+fn date_time(seconds: u64) -> String {
+    let days = seconds / 86400;
+    let time = seconds % 86400;
+    // Years start in March, the leap day is the last day of the year.
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as u64;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STRINGS: &[u8] = b"boot\0irq {} source {:#x}: {:?}\0";
+
+    fn decode(data: &[u8]) -> String {
+        let mut out = Vec::new();
+        Decoder::new(STRINGS).feed(data, &mut out);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn decode_text_and_frames() {
+        // Info, id 0, no argument, between two texts.
+        let out = decode(b"hello\n\xff\x02\x01\x00bye\n");
+        assert_eq!(out, "hello\n\x1b[32;1m[INFO]\x1b[0m boot\nbye\n");
+    }
+
+    #[test]
+    fn decode_arguments() {
+        // Warn at 2024-01-01T00:00:00Z, id 5, unsigned 7, unsigned 42, text NotFound.
+        let mut frame = vec![0x82, 5, 0x80, 0x81, 0xc8, 0xac, 0x06];
+        frame.extend_from_slice(&[0, 7, 0, 42, 5, 8]);
+        frame.extend_from_slice(b"NotFound");
+        let mut data = vec![0xff, frame.len() as u8];
+        data.extend_from_slice(&frame);
+        assert_eq!(
+            decode(&data),
+            "\x1b[33;1m[WARNING]\x1b[0m 2024-01-01T00:00:00Z irq 7 source 0x2a: NotFound\n"
+        );
+    }
+
+    #[test]
+    fn decode_frame_split_between_reads() {
+        let mut out = Vec::new();
+        let mut decoder = Decoder::new(STRINGS);
+        decoder.feed(b"\xff\x02", &mut out);
+        decoder.feed(b"\x03\x00", &mut out);
+        assert_eq!(out, b"\x1b[31;1m[ERROR]\x1b[0m boot\n");
+    }
+
+    #[test]
+    fn decode_invalid_frame() {
+        assert_eq!(
+            decode(b"\xff\x02\x01\x7f"),
+            "[binlog: unknown format string id 127, wrong ELF file?]\n"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+// Minimal ELF reader, only what's needed to find a section by name.
+// See documentation in `Documentation/kernel/logs.md`
+
+// ELF identification
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+const ELF_CLASS_32: u8 = 1;
+const ELF_CLASS_64: u8 = 2;
+const ELF_DATA_LSB: u8 = 1;
+
+/// Header fields of a section.
+struct Section {
+    name: usize,
+    offset: usize,
+    size: usize,
+}
+
+/// Little endian ELF file, 32 or 64 bits.
+pub struct Elf<'a> {
+    data: &'a [u8],
+    is_64: bool,
+}
+
+impl<'a> Elf<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, String> {
+        if data.len() < 16 || &data[..4] != ELF_MAGIC {
+            return Err("not an ELF file".into());
+        }
+        if data[5] != ELF_DATA_LSB {
+            return Err("big endian ELF files are not supported".into());
+        }
+        let is_64 = match data[4] {
+            ELF_CLASS_32 => false,
+            ELF_CLASS_64 => true,
+            class => return Err(format!("unknown ELF class {class}")),
+        };
+        Ok(Elf { data, is_64 })
+    }
+
+    /// Content of the section with the given name.
+    pub fn section(&self, name: &str) -> Result<&'a [u8], String> {
+        let (shoff, shentsize, shnum, shstrndx) = if self.is_64 {
+            (
+                self.read(0x28, 8)?,
+                self.read(0x3a, 2)?,
+                self.read(0x3c, 2)?,
+                self.read(0x3e, 2)?,
+            )
+        } else {
+            (
+                self.read(0x20, 4)?,
+                self.read(0x2e, 2)?,
+                self.read(0x30, 2)?,
+                self.read(0x32, 2)?,
+            )
+        };
+        let header = |index: usize| self.section_header(shoff + index * shentsize);
+        let strtab = header(shstrndx)?;
+        for index in 0..shnum {
+            let section = header(index)?;
+            if self.name(&strtab, section.name)? == name.as_bytes() {
+                return self.content(&section);
+            }
+        }
+        Err(format!("no {name} section"))
+    }
+
+    fn section_header(&self, off: usize) -> Result<Section, String> {
+        if self.is_64 {
+            Ok(Section {
+                name: self.read(off, 4)?,
+                offset: self.read(off + 0x18, 8)?,
+                size: self.read(off + 0x20, 8)?,
+            })
+        } else {
+            Ok(Section {
+                name: self.read(off, 4)?,
+                offset: self.read(off + 0x10, 4)?,
+                size: self.read(off + 0x14, 4)?,
+            })
+        }
+    }
+
+    fn content(&self, section: &Section) -> Result<&'a [u8], String> {
+        self.data
+            .get(section.offset..section.offset + section.size)
+            .ok_or_else(|| "section outside of the file".into())
+    }
+
+    /// Nul terminated name at off in the section names table.
+    fn name(&self, strtab: &Section, off: usize) -> Result<&'a [u8], String> {
+        let names = self.content(strtab)?;
+        let name = names
+            .get(off..)
+            .ok_or("section name outside of the table")?;
+        Ok(name.split(|&b| b == 0).next().unwrap_or_default())
+    }
+
+    /// Little endian integer of size bytes at off.
+    fn read(&self, off: usize, size: usize) -> Result<usize, String> {
+        let bytes = self.data.get(off..off + size).ok_or("truncated ELF file")?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &b| (value << 8) | b as usize))
+    }
+}
@@ -0,0 +1,201 @@
+// Format the log arguments like core::fmt, from the format string of the log section.
+// See documentation in `Documentation/kernel/logs.md`
+
+/// Argument of a binary log frame.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Unsigned(u64),
+    Signed(i64),
+    Bool(bool),
+    Char(char),
+    Str(String),
+    // Formatted by the kernel, printed as it is.
+    Text(String),
+}
+
+/// Placeholder options, `{:[[fill]align][+][#][0][width][.precision][type]}`.
+#[derive(Default)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<char>,
+    plus: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    kind: String,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Spec {
+        let mut parsed = Spec::default();
+        let mut chars: Vec<char> = spec.chars().collect();
+        if chars.len() >= 2 && matches!(chars[1], '<' | '>' | '^') {
+            parsed.fill = Some(chars[0]);
+            parsed.align = Some(chars[1]);
+            chars.drain(..2);
+        } else if !chars.is_empty() && matches!(chars[0], '<' | '>' | '^') {
+            parsed.align = Some(chars[0]);
+            chars.remove(0);
+        }
+        let mut rest = chars.into_iter().peekable();
+        if rest.peek() == Some(&'+') {
+            parsed.plus = true;
+            rest.next();
+        }
+        if rest.peek() == Some(&'#') {
+            parsed.alternate = true;
+            rest.next();
+        }
+        if rest.peek() == Some(&'0') {
+            parsed.zero = true;
+            rest.next();
+        }
+        let digits = |rest: &mut std::iter::Peekable<std::vec::IntoIter<char>>| {
+            let mut n = 0;
+            while let Some(d) = rest.peek().and_then(|c| c.to_digit(10)) {
+                n = n * 10 + d as usize;
+                rest.next();
+            }
+            n
+        };
+        parsed.width = digits(&mut rest);
+        if rest.peek() == Some(&'.') {
+            rest.next();
+            parsed.precision = Some(digits(&mut rest));
+        }
+        parsed.kind = rest.collect();
+        parsed
+    }
+}
+
+/// Format the message, the placeholders are replaced by the arguments in order. A missing
+/// argument, dropped by the kernel in a full frame, is printed `<?>`.
+pub fn format(fmt: &str, args: &[Value]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let placeholder: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                let spec = Spec::parse(placeholder.strip_prefix(':').unwrap_or(""));
+                match args.next() {
+                    Some(value) => out.push_str(&format_value(value, &spec)),
+                    None => out.push_str("<?>"),
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn format_value(value: &Value, spec: &Spec) -> String {
+    let debug = spec.kind.contains('?');
+    let (sign, digits) = match value {
+        Value::Unsigned(n) => (if spec.plus { "+" } else { "" }, format_radix(*n, spec)),
+        Value::Signed(n) if *n < 0 => ("-", format_radix(n.unsigned_abs(), spec)),
+        Value::Signed(n) => (
+            if spec.plus { "+" } else { "" },
+            format_radix(*n as u64, spec),
+        ),
+        Value::Bool(b) => return pad(&b.to_string(), spec, '<'),
+        Value::Char(c) if debug => return pad(&format!("{c:?}"), spec, '<'),
+        Value::Char(c) => return pad(&c.to_string(), spec, '<'),
+        Value::Str(s) if debug => return pad(&format!("{s:?}"), spec, '<'),
+        Value::Str(s) => {
+            let s: String = match spec.precision {
+                Some(n) => s.chars().take(n).collect(),
+                None => s.clone(),
+            };
+            return pad(&s, spec, '<');
+        }
+        Value::Text(s) => return s.clone(),
+    };
+    if spec.zero {
+        // The zeros go between the sign or prefix and the digits.
+        let (prefix, digits) = split_prefix(&digits);
+        let len = sign.len() + prefix.len() + digits.len();
+        let zeros = "0".repeat(spec.width.saturating_sub(len));
+        return format!("{sign}{prefix}{zeros}{digits}");
+    }
+    pad(&format!("{sign}{digits}"), spec, '>')
+}
+
+fn format_radix(n: u64, spec: &Spec) -> String {
+    let alternate = spec.alternate;
+    match spec.kind.trim_end_matches('?') {
+        "x" if alternate => format!("{n:#x}"),
+        "x" => format!("{n:x}"),
+        "X" if alternate => format!("{n:#X}"),
+        "X" => format!("{n:X}"),
+        "b" if alternate => format!("{n:#b}"),
+        "b" => format!("{n:b}"),
+        "o" if alternate => format!("{n:#o}"),
+        "o" => format!("{n:o}"),
+        _ => n.to_string(),
+    }
+}
+
+fn split_prefix(digits: &str) -> (&str, &str) {
+    for prefix in ["0x", "0b", "0o"] {
+        if let Some(rest) = digits.strip_prefix(prefix) {
+            return (prefix, rest);
+        }
+    }
+    ("", digits)
+}
+
+/// Pad to the width, default_align is used without alignment in the spec.
+fn pad(s: &str, spec: &Spec, default_align: char) -> String {
+    let len = s.chars().count();
+    if len >= spec.width {
+        return s.to_string();
+    }
+    let fill = spec.fill.unwrap_or(' ').to_string();
+    let missing = spec.width - len;
+    let (left, right) = match spec.align.unwrap_or(default_align) {
+        '<' => (0, missing),
+        '^' => (missing / 2, missing - missing / 2),
+        _ => (missing, 0),
+    };
+    format!("{}{s}{}", fill.repeat(left), fill.repeat(right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_integers() {
+        let args = [Value::Unsigned(255), Value::Signed(-3), Value::Unsigned(7)];
+        assert_eq!(format("{:#x} {} {:04}", &args), "0xff -3 0007");
+        assert_eq!(format("{:#010x}", &[Value::Unsigned(255)]), "0x000000ff");
+        assert_eq!(format("{:>4}|{:<3}|", &args[..2]), " 255|-3 |");
+    }
+
+    #[test]
+    fn format_strings() {
+        let args = [
+            Value::Str("pid".into()),
+            Value::Str("a\"b".into()),
+            Value::Text("NotFound".into()),
+        ];
+        assert_eq!(format("{} {:?} {:?}", &args), "pid \"a\\\"b\" NotFound");
+        assert_eq!(format("{{{}}}", &args[..1]), "{pid}");
+    }
+
+    #[test]
+    fn format_missing_argument() {
+        assert_eq!(format("{} and {}", &[Value::Bool(true)]), "true and <?>");
+    }
+}
@@ -0,0 +1,67 @@
+// Host decoder of the LrnRTOS binary logs, built with the `binlog` feature.
+// See documentation in `Documentation/kernel/logs.md`
+//
+// Usage: lrnrtos-log <kernel ELF file> [captured output]
+// Without captured output, read the kernel output on stdin, like a serial console piped to it:
+// qemu-system-riscv32 ... -nographic | cargo logdecode target/riscv32imc-unknown-none-elf/debug/lrnrtos
+
+mod decode;
+mod elf;
+mod format;
+
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    process::ExitCode,
+};
+
+use decode::Decoder;
+use elf::Elf;
+
+// Section of the format strings, see linkers/linker.ld.
+const LOG_SECTION: &str = ".lrnrtos_log";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("Usage: {} <kernel ELF file> [captured output]", args[0]);
+        return ExitCode::FAILURE;
+    }
+    match run(&args[1], args.get(2).map(String::as_str)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("lrnrtos-log: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(elf_path: &str, input_path: Option<&str>) -> Result<(), String> {
+    let elf = fs::read(elf_path).map_err(|e| format!("{elf_path}: {e}"))?;
+    let strings = Elf::parse(&elf)
+        .and_then(|elf| elf.section(LOG_SECTION))
+        .map_err(|e| format!("{elf_path}: {e}, is the kernel built with the binlog feature?"))?;
+    let mut input: Box<dyn Read> = match input_path {
+        Some(path) => Box::new(fs::File::open(path).map_err(|e| format!("{path}: {e}"))?),
+        None => Box::new(io::stdin()),
+    };
+    let mut decoder = Decoder::new(strings);
+    let mut stdout = io::stdout().lock();
+    let mut buf = [0; 4096];
+    let mut out = Vec::new();
+    loop {
+        // Decode what's available, the output of a running kernel is printed as it comes.
+        let len = match input.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.to_string()),
+        };
+        out.clear();
+        decoder.feed(&buf[..len], &mut out);
+        stdout
+            .write_all(&out)
+            .and_then(|()| stdout.flush())
+            .map_err(|e| e.to_string())?;
+    }
+}